@@ -77,6 +77,33 @@ fn benchmarks(c: &mut Criterion) {
         })
     });
 
+    let grid_settings = GridSettingsBuilder::new_2d(256, 256).chunk_size(32).build();
+
+    let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&grid_settings);
+    grid.build();
+
+    group.bench_function("raw_pathfind_256x256", |b| {
+        b.iter(|| {
+            grid.pathfind_astar(
+                UVec3::new(0, 0, 0),
+                UVec3::new(255, 200, 0),
+                &HashMap::new(),
+                false,
+            )
+        })
+    });
+
+    group.bench_function("jps_pathfind_256x256", |b| {
+        b.iter(|| {
+            grid.pathfind_jps(
+                UVec3::new(0, 0, 0),
+                UVec3::new(255, 200, 0),
+                &HashMap::new(),
+                false,
+            )
+        })
+    });
+
     let grid_settings = GridSettingsBuilder::new_3d(128, 128, 4)
         .chunk_size(16)
         .build();
@@ -209,10 +209,7 @@ fn spawn_minions(
                 ..Default::default()
             })
             .insert(Name::new(format!("{color:?}")))
-            .insert(DebugPath {
-                color,
-                draw_unrefined: false,
-            })
+            .insert(DebugPath::new(color))
             .insert(AgentOfGrid(grid_entity))
             .insert(Blocking)
             .insert(Transform::from_translation(transform))
@@ -283,13 +280,8 @@ fn set_new_goal(
     for entity in minions.iter_mut() {
         let new_goal = walkable.tiles.choose(&mut rand::rng()).unwrap();
 
-        let mut pathfind = Pathfind::new_2d((new_goal.x / 8.0) as u32, (new_goal.y / 8.0) as u32);
-
-        match config.mode {
-            PathfindMode::AStar => pathfind = pathfind.mode(PathfindMode::AStar),
-            PathfindMode::Coarse => pathfind = pathfind.mode(PathfindMode::Coarse),
-            PathfindMode::Refined => pathfind = pathfind.mode(PathfindMode::Refined),
-        }
+        let pathfind = Pathfind::new_2d((new_goal.x / 8.0) as u32, (new_goal.y / 8.0) as u32)
+            .mode(config.mode);
 
         commands.entity(entity).insert(pathfind);
     }
@@ -308,13 +300,8 @@ fn handle_pathfinding_failed(
         log::info!("Pathfinding failed for entity {entity:?}, setting new goal.");
         let new_goal = walkable.tiles.choose(&mut rand::rng()).unwrap();
 
-        let mut pathfind = Pathfind::new_2d((new_goal.x / 8.0) as u32, (new_goal.y / 8.0) as u32);
-
-        match config.mode {
-            PathfindMode::AStar => pathfind = pathfind.mode(PathfindMode::AStar),
-            PathfindMode::Coarse => pathfind = pathfind.mode(PathfindMode::Coarse),
-            PathfindMode::Refined => pathfind = pathfind.mode(PathfindMode::Refined),
-        }
+        let pathfind = Pathfind::new_2d((new_goal.x / 8.0) as u32, (new_goal.y / 8.0) as u32)
+            .mode(config.mode);
 
         commands
             .entity(entity)
@@ -216,13 +216,8 @@ fn spawn_minions(
             rand::random::<f32>(),
         );
 
-        let mut pathfind = Pathfind::new_2d((goal.x / 8.0) as u32, (goal.y / 8.0) as u32);
-
-        match config.mode {
-            PathfindMode::AStar => pathfind = pathfind.mode(PathfindMode::AStar),
-            PathfindMode::Coarse => pathfind = pathfind.mode(PathfindMode::Coarse),
-            PathfindMode::Refined => pathfind = pathfind.mode(PathfindMode::Refined),
-        }
+        let pathfind =
+            Pathfind::new_2d((goal.x / 8.0) as u32, (goal.y / 8.0) as u32).mode(config.mode);
 
         commands
             .spawn(Sprite {
@@ -302,13 +297,8 @@ fn set_new_goal(
     for entity in minions.iter_mut() {
         let new_goal = walkable.tiles.choose(&mut rand::rng()).unwrap();
 
-        let mut pathfind = Pathfind::new_2d((new_goal.x / 8.0) as u32, (new_goal.y / 8.0) as u32);
-
-        match config.mode {
-            PathfindMode::AStar => pathfind = pathfind.mode(PathfindMode::AStar),
-            PathfindMode::Coarse => pathfind = pathfind.mode(PathfindMode::Coarse),
-            PathfindMode::Refined => pathfind = pathfind.mode(PathfindMode::Refined),
-        }
+        let pathfind = Pathfind::new_2d((new_goal.x / 8.0) as u32, (new_goal.y / 8.0) as u32)
+            .mode(config.mode);
 
         commands.entity(entity).insert(pathfind);
     }
@@ -327,13 +317,8 @@ fn handle_pathfinding_failed(
         //log::info!("Pathfinding failed for entity {entity:?}, setting new goal.");
         let new_goal = walkable.tiles.choose(&mut rand::rng()).unwrap();
 
-        let mut pathfind = Pathfind::new_2d((new_goal.x / 8.0) as u32, (new_goal.y / 8.0) as u32);
-
-        match config.mode {
-            PathfindMode::AStar => pathfind = pathfind.mode(PathfindMode::AStar),
-            PathfindMode::Coarse => pathfind = pathfind.mode(PathfindMode::Coarse),
-            PathfindMode::Refined => pathfind = pathfind.mode(PathfindMode::Refined),
-        }
+        let pathfind = Pathfind::new_2d((new_goal.x / 8.0) as u32, (new_goal.y / 8.0) as u32)
+            .mode(config.mode);
 
         commands
             .entity(entity)
@@ -194,6 +194,9 @@ pub fn update_pathfind_type_text(
             PathfindMode::AStar => "A*".to_string(),
             PathfindMode::Coarse => "HPA* Coarse".to_string(),
             PathfindMode::Refined => "HPA*".to_string(),
+            PathfindMode::Jps => "JPS".to_string(),
+            PathfindMode::FlowField => "Flow Field".to_string(),
+            PathfindMode::Theta => "Theta*".to_string(),
         };
     }
 }
@@ -251,7 +254,10 @@ pub fn input<N: Neighborhood + 'static>(
         if keyboard_input.just_pressed(KeyCode::KeyP) {
             // Cycle through pathfinding modes
             config.mode = match config.mode {
-                PathfindMode::AStar => PathfindMode::Refined,
+                PathfindMode::AStar => PathfindMode::Jps,
+                PathfindMode::Jps => PathfindMode::FlowField,
+                PathfindMode::FlowField => PathfindMode::Theta,
+                PathfindMode::Theta => PathfindMode::Refined,
                 PathfindMode::Coarse => PathfindMode::AStar,
                 PathfindMode::Refined => PathfindMode::Coarse,
             };
@@ -1,14 +1,156 @@
 //! A* algorithms used by the crate.
-use bevy::{log, math::UVec3, platform::collections::HashMap, prelude::Entity};
+use bevy::{
+    log,
+    math::{IVec3, UVec2, UVec3},
+    platform::collections::HashMap,
+    prelude::Entity,
+};
 use indexmap::map::Entry::{Occupied, Vacant};
 use ndarray::ArrayView3;
-use std::collections::BinaryHeap;
+use std::{
+    cell::{Cell, RefCell},
+    collections::BinaryHeap,
+};
 
 use crate::{
-    graph::Graph, in_bounds_3d, nav::NavCell, neighbor::Neighborhood, path::Path, FxIndexMap,
-    SmallestCostHolder,
+    components::Corridor,
+    goal::SearchGoal,
+    graph::Graph,
+    in_bounds_3d,
+    nav::{NavCell, NavLayers, ALL_NAV_LAYERS},
+    neighbor::Neighborhood,
+    path::Path,
+    trace::{SearchTrace, TracedNode},
+    FxIndexMap, MovementCost, SmallestCostHolder,
 };
 
+/// Per request bookkeeping of the node expansions of grid searches.
+/// Caps how many nodes may be expanded before giving up and optionally records every expansion.
+/// Shared between the searches of a single request so retries count towards the same budget.
+#[derive(Default)]
+pub(crate) struct SearchContext<'a> {
+    max: Option<usize>,
+    count: Cell<usize>,
+    reached: Cell<bool>,
+    trace: Option<RefCell<SearchTrace>>,
+    prefer_straight: bool,
+    weight: f32,
+    corridor: Option<Corridor>,
+    corridor_width: Cell<u32>,
+    filter: Option<&'a dyn Fn(UVec3) -> bool>,
+}
+
+impl<'a> SearchContext<'a> {
+    /// Creates a limit of `max` expansions, `None` never stops a search.
+    pub(crate) fn new(max: Option<usize>) -> Self {
+        Self {
+            max,
+            ..Default::default()
+        }
+    }
+
+    /// Breaks ties between nodes of equal estimated cost in favor of the node closest to the straight line
+    /// from the start to the goal, see [`straight_line_bias`].
+    pub(crate) fn prefer_straight(mut self, value: bool) -> Self {
+        self.prefer_straight = value;
+        self
+    }
+
+    /// Inflates the heuristic by `weight` to find paths faster at the cost of optimality,
+    /// see [`crate::components::Pathfind::weight`]. Weights of 1.0 or less leave the heuristic as is.
+    pub(crate) fn weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Returns the heuristic `h` inflated by the weight, used to order the open set.
+    pub(crate) fn weighted(&self, h: u32) -> u32 {
+        if self.weight > 1.0 {
+            // Leave room for the path cost it's added to
+            ((h as f32 * self.weight) as u32).min(u32::MAX / 2)
+        } else {
+            h
+        }
+    }
+
+    /// Only expands cells inside the `corridor`, see [`crate::components::Pathfind::corridor`].
+    pub(crate) fn corridor(mut self, corridor: Option<Corridor>) -> Self {
+        self.corridor_width = Cell::new(corridor.as_ref().map_or(0, |corridor| corridor.width));
+        self.corridor = corridor;
+        self
+    }
+
+    /// Only expands cells `filter` returns `true` for, see [`crate::grid::Grid::find_path_filtered`].
+    pub(crate) fn filter(mut self, filter: &'a dyn Fn(UVec3) -> bool) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Returns `true` if the search is restricted to a corridor or by a filter.
+    pub(crate) fn is_restricted(&self) -> bool {
+        self.corridor.is_some() || self.filter.is_some()
+    }
+
+    /// Returns `true` if the search may expand `pos`.
+    pub(crate) fn allows(&self, pos: UVec3) -> bool {
+        self.corridor
+            .as_ref()
+            .is_none_or(|corridor| corridor.contains_within(pos, self.corridor_width.get()))
+            && self.filter.is_none_or(|filter| filter(pos))
+    }
+
+    /// Doubles the width of a widening corridor that is still narrower than `limit`.
+    /// Returns `false` if the corridor can't be widened any further.
+    pub(crate) fn widen_corridor(&self, limit: u32) -> bool {
+        let width = self.corridor_width.get();
+        if !self
+            .corridor
+            .as_ref()
+            .is_some_and(|corridor| corridor.widen)
+            || width >= limit
+        {
+            return false;
+        }
+
+        self.corridor_width.set(width.saturating_mul(2).max(1));
+        true
+    }
+
+    /// Records every expansion into a [`SearchTrace`], see [`SearchContext::into_trace`].
+    pub(crate) fn traced(mut self) -> Self {
+        self.trace = Some(RefCell::default());
+        self
+    }
+
+    /// Counts the expansion of `pos` with its `g` and `h` scores. Returns `false` once the limit has been used up.
+    pub(crate) fn expand(&self, pos: UVec3, g: MovementCost, h: u32) -> bool {
+        if let Some(max) = self.max {
+            if self.count.get() >= max {
+                self.reached.set(true);
+                return false;
+            }
+
+            self.count.set(self.count.get() + 1);
+        }
+
+        if let Some(trace) = &self.trace {
+            trace.borrow_mut().push(TracedNode { pos, g, h });
+        }
+
+        true
+    }
+
+    /// Returns `true` if a search was stopped by the limit.
+    pub(crate) fn reached(&self) -> bool {
+        self.reached.get()
+    }
+
+    /// Returns the recorded expansions, `None` unless created with [`SearchContext::traced`].
+    pub(crate) fn into_trace(self) -> Option<SearchTrace> {
+        self.trace.map(RefCell::into_inner)
+    }
+}
+
 /// A* search algorithm for a [`crate::grid::Grid`] of [`crate::nav::NavCell`]s.
 ///
 /// # Arguments
@@ -19,9 +161,11 @@ use crate::{
 /// * `size_hint` - A hint for the size of the binary heap.
 /// * `partial` - If `true`, the algorithm will return the closest node if the goal is not reachable.
 /// * `blocking` - Pass [`crate::plugin::BlockingMap`] or a new `HashMap<UVec3, Entity>` to indicate which positions are blocked by entities.
+/// * `cost_overrides` - Extra movement cost added on top of the [`NavCell`] cost when entering a position.
 ///
 /// # Returns
 /// * [`Option<Path>`] - An optional path object. If a path is found, it returns `Some(Path)`, otherwise it returns `None`.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn astar_grid<N: Neighborhood>(
     neighborhood: &N,
     grid: &ArrayView3<NavCell>,
@@ -30,28 +174,137 @@ pub(crate) fn astar_grid<N: Neighborhood>(
     size_hint: usize,
     partial: bool,
     blocking: &HashMap<UVec3, Entity>,
+    cost_overrides: &HashMap<UVec3, MovementCost>,
 ) -> Option<Path> {
+    astar_grid_to(
+        neighborhood,
+        grid,
+        start,
+        &SearchGoal::Position(goal),
+        size_hint,
+        partial,
+        blocking,
+        cost_overrides,
+        ALL_NAV_LAYERS,
+        UVec2::ONE,
+        &SearchContext::default(),
+    )
+}
+
+/// Returns true if an agent with a `footprint` anchored at `anchor` only covers cells that are in bounds,
+/// passable for `layers` and not blocked. The footprint extends towards positive x and y on the same depth.
+/// Cells covered by the footprint anchored at `start` belong to the agent itself and never block it.
+pub(crate) fn footprint_fits(
+    grid: &ArrayView3<NavCell>,
+    anchor: UVec3,
+    start: UVec3,
+    footprint: UVec2,
+    layers: NavLayers,
+    blocking: &HashMap<UVec3, Entity>,
+) -> bool {
+    let shape = grid.shape();
+    let footprint = footprint.max(UVec2::ONE);
+
+    let own = |cell: UVec3| {
+        cell.z == start.z
+            && cell.x >= start.x
+            && cell.y >= start.y
+            && cell.x < start.x + footprint.x
+            && cell.y < start.y + footprint.y
+    };
+
+    (0..footprint.x).all(|dx| {
+        (0..footprint.y).all(|dy| {
+            let cell = anchor + UVec3::new(dx, dy, 0);
+
+            (cell.x as usize) < shape[0]
+                && (cell.y as usize) < shape[1]
+                && (cell.z as usize) < shape[2]
+                && grid[[cell.x as usize, cell.y as usize, cell.z as usize]].is_passable_for(layers)
+                && (own(cell) || !blocking.contains_key(&cell))
+        })
+    })
+}
+
+// A diagonal step also sweeps the footprint across both cells it cuts past, so a large agent can't clip a corner.
+fn footprint_step_fits(
+    grid: &ArrayView3<NavCell>,
+    from: UVec3,
+    to: UVec3,
+    start: UVec3,
+    footprint: UVec2,
+    layers: NavLayers,
+    blocking: &HashMap<UVec3, Entity>,
+) -> bool {
+    let fits = |anchor| footprint_fits(grid, anchor, start, footprint, layers, blocking);
+
+    fits(to)
+        && (from.x == to.x
+            || from.y == to.y
+            || (fits(UVec3::new(to.x, from.y, from.z)) && fits(UVec3::new(from.x, to.y, from.z))))
+}
+
+/// How far `pos` strays from the straight line between `start` and `goal`, the magnitude of their cross product.
+/// Only used to order nodes with the same estimated cost, so the search stays admissible.
+#[inline(always)]
+pub(crate) fn straight_line_bias(pos: UVec3, start: UVec3, goal: UVec3) -> u32 {
+    let to_pos = pos.as_ivec3() - goal.as_ivec3();
+    let to_start = start.as_ivec3() - goal.as_ivec3();
+
+    let cross: IVec3 = to_pos.cross(to_start);
+    cross.abs().element_sum() as u32
+}
+
+/// A* search algorithm for a [`crate::grid::Grid`] towards a [`SearchGoal`].
+/// See [`astar_grid`] for details, the heuristic and goal test are provided by `goal`.
+/// Cells whose layers don't intersect `layers` are treated as impassable.
+/// Agents with a `footprint` larger than one cell only move where every cell they cover fits, see [`footprint_fits`].
+/// The search stops once `context` runs out of expansions, returning the closest node found so far if `partial` is set.
+/// Ties are broken towards the straight line to a single goal position if `context` prefers straight paths.
+/// The heuristic is inflated by the weight of `context`, see [`SearchContext::weight`].
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn astar_grid_to<N: Neighborhood>(
+    neighborhood: &N,
+    grid: &ArrayView3<NavCell>,
+    start: UVec3,
+    goal: &SearchGoal,
+    size_hint: usize,
+    partial: bool,
+    blocking: &HashMap<UVec3, Entity>,
+    cost_overrides: &HashMap<UVec3, MovementCost>,
+    layers: NavLayers,
+    footprint: UVec2,
+    context: &SearchContext,
+) -> Option<Path> {
+    let tie_break = |pos: UVec3| match goal {
+        SearchGoal::Position(goal) if context.prefer_straight => {
+            straight_line_bias(pos, start, *goal)
+        }
+        _ => 0,
+    };
+
     let mut to_visit = BinaryHeap::with_capacity(size_hint / 2);
     to_visit.push(SmallestCostHolder {
         estimated_cost: 0,
         cost: 0,
         index: 0,
+        tie_break: 0,
     });
 
     let mut visited: FxIndexMap<UVec3, (usize, u32)> = FxIndexMap::default();
     visited.insert(start, (usize::MAX, 0));
 
     let mut closest_node = start;
-    let mut closest_distance = neighborhood.heuristic(start, goal);
+    let mut closest_distance = goal.heuristic(neighborhood, start);
 
     let shape = grid.shape();
     let min = UVec3::new(0, 0, 0);
     let max = UVec3::new(shape[0] as u32, shape[1] as u32, shape[2] as u32);
 
     while let Some(SmallestCostHolder { cost, index, .. }) = to_visit.pop() {
-        let neighbors = {
+        let (current, cell, neighbors) = {
             let (current_pos, &(_, current_cost)) = visited.get_index(index).unwrap();
-            let current_distance = neighborhood.heuristic(*current_pos, goal);
+            let current_distance = goal.heuristic(neighborhood, *current_pos);
 
             // Update the closest node if this node is closer
             if current_distance < closest_distance {
@@ -59,7 +312,7 @@ pub(crate) fn astar_grid<N: Neighborhood>(
                 closest_distance = current_distance;
             }
 
-            if *current_pos == goal {
+            if goal.is_goal(*current_pos) {
                 let mut current = index;
                 let mut steps = vec![];
 
@@ -77,17 +330,21 @@ pub(crate) fn astar_grid<N: Neighborhood>(
                 continue;
             }
 
+            if !context.expand(*current_pos, current_cost, current_distance) {
+                break;
+            }
+
             let cell = &grid[[
                 current_pos.x as usize,
                 current_pos.y as usize,
                 current_pos.z as usize,
             ]];
 
-            cell.neighbor_iter(*current_pos)
+            (*current_pos, cell, cell.neighbor_iter(*current_pos))
         };
 
         for neighbor in neighbors {
-            if !in_bounds_3d(neighbor, min, max) {
+            if !in_bounds_3d(neighbor, min, max) || !context.allows(neighbor) {
                 continue;
             }
 
@@ -97,26 +354,39 @@ pub(crate) fn astar_grid<N: Neighborhood>(
                 neighbor.z as usize,
             ]];
 
-            if neighbor_cell.is_impassable() {
+            if !neighbor_cell.is_passable_for(layers) {
                 continue;
             }
 
-            if blocking.contains_key(&neighbor) {
+            if footprint == UVec2::ONE {
+                if blocking.contains_key(&neighbor) {
+                    continue;
+                }
+            } else if !footprint_step_fits(
+                grid, current, neighbor, start, footprint, layers, blocking,
+            ) {
                 continue;
             }
 
-            let new_cost = cost + neighbor_cell.cost;
+            // Links added by a `NeighborProvider` and vertical links carry their own traversal cost
+            let step_cost = neighborhood.step_cost(
+                current,
+                neighbor,
+                cell.move_cost(current, neighbor, neighbor_cell),
+            );
+            let extra_cost = cost_overrides.get(&neighbor).copied().unwrap_or(0);
+            let new_cost = cost.saturating_add(step_cost).saturating_add(extra_cost);
             let h;
             let n;
             match visited.entry(neighbor) {
                 Vacant(e) => {
-                    h = neighborhood.heuristic(neighbor, goal);
+                    h = goal.heuristic(neighborhood, neighbor);
                     n = e.index();
                     e.insert((index, new_cost));
                 }
                 Occupied(mut e) => {
                     if e.get().1 > new_cost {
-                        h = neighborhood.heuristic(neighbor, goal);
+                        h = goal.heuristic(neighborhood, neighbor);
                         n = e.index();
                         e.insert((index, new_cost));
                     } else {
@@ -126,9 +396,10 @@ pub(crate) fn astar_grid<N: Neighborhood>(
             }
 
             to_visit.push(SmallestCostHolder {
-                estimated_cost: h,
+                estimated_cost: context.weighted(h),
                 cost: new_cost,
                 index: n,
+                tie_break: tie_break(neighbor),
             });
         }
     }
@@ -186,6 +457,7 @@ pub(crate) fn astar_graph<N: Neighborhood>(
         estimated_cost: 0,
         cost: 0,
         index: 0,
+        tie_break: 0,
     });
 
     let mut visited: FxIndexMap<UVec3, (usize, u32)> = FxIndexMap::default();
@@ -249,6 +521,7 @@ pub(crate) fn astar_graph<N: Neighborhood>(
                 estimated_cost: h,
                 cost: new_cost,
                 index: n,
+                tie_break: 0,
             });
         }
     }
@@ -262,7 +535,7 @@ mod tests {
     use crate::chunk::Chunk;
     use crate::grid::{Grid, GridSettingsBuilder};
     use crate::nav::Nav;
-    use crate::neighbor::OrdinalNeighborhood3d;
+    use crate::neighbor::{CardinalNeighborhood, OrdinalNeighborhood3d};
     use crate::node::Node;
 
     #[test]
@@ -278,6 +551,8 @@ mod tests {
         let path = astar_grid(
             &OrdinalNeighborhood3d {
                 filters: Vec::new(),
+                heuristic: None,
+                diagonal_cost: None,
             },
             &grid.view(),
             start,
@@ -285,6 +560,7 @@ mod tests {
             64,
             false,
             &HashMap::new(),
+            &HashMap::new(),
         )
         .unwrap();
 
@@ -312,6 +588,8 @@ mod tests {
         let path = astar_grid(
             &OrdinalNeighborhood3d {
                 filters: Vec::new(),
+                heuristic: None,
+                diagonal_cost: None,
             },
             &grid.view(),
             start,
@@ -319,6 +597,7 @@ mod tests {
             64,
             false,
             &HashMap::new(),
+            &HashMap::new(),
         )
         .unwrap();
 
@@ -372,6 +651,8 @@ mod tests {
         let path = astar_grid(
             &OrdinalNeighborhood3d {
                 filters: Vec::new(),
+                heuristic: None,
+                diagonal_cost: None,
             },
             &grid.view(),
             start,
@@ -379,6 +660,7 @@ mod tests {
             64,
             false,
             &HashMap::new(),
+            &HashMap::new(),
         )
         .unwrap();
 
@@ -408,6 +690,8 @@ mod tests {
         let path = astar_grid(
             &OrdinalNeighborhood3d {
                 filters: Vec::new(),
+                heuristic: None,
+                diagonal_cost: None,
             },
             &grid.view(),
             start,
@@ -415,6 +699,7 @@ mod tests {
             16,
             false,
             &HashMap::new(),
+            &HashMap::new(),
         )
         .unwrap();
 
@@ -469,6 +754,8 @@ mod tests {
         let path = astar_graph(
             &OrdinalNeighborhood3d {
                 filters: Vec::new(),
+                heuristic: None,
+                diagonal_cost: None,
             },
             &graph,
             UVec3::new(0, 0, 0),
@@ -484,4 +771,87 @@ mod tests {
         // Ensure the last position is the goal position
         assert_eq!(path.path()[2], UVec3::new(2, 2, 2));
     }
+
+    #[test]
+    fn test_prefer_straight() {
+        let grid_settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid = Grid::<CardinalNeighborhood>::new(&grid_settings);
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(12, 6, 0);
+
+        let search = |context: &SearchContext| {
+            astar_grid_to(
+                &CardinalNeighborhood::default(),
+                &grid.view(),
+                start,
+                &SearchGoal::Position(goal),
+                64,
+                false,
+                &HashMap::new(),
+                &HashMap::new(),
+                ALL_NAV_LAYERS,
+                UVec2::ONE,
+                context,
+            )
+            .unwrap()
+        };
+
+        // The largest distance from the straight line, scaled by the line's length
+        let deviation = |path: &Path| {
+            path.path()
+                .iter()
+                .map(|pos| straight_line_bias(*pos, start, goal))
+                .max()
+                .unwrap()
+        };
+
+        let plain = search(&SearchContext::default());
+        let straight = search(&SearchContext::default().prefer_straight(true));
+
+        // Breaking ties doesn't change the cost, only which of the equally short paths is taken
+        assert_eq!(plain.cost(), straight.cost());
+        assert!(deviation(&straight) < deviation(&plain));
+
+        // Every step stays within a cell of the line
+        assert!(deviation(&straight) <= 12);
+    }
+
+    #[test]
+    fn test_weighted_search() {
+        let grid_settings = GridSettingsBuilder::new_2d(32, 32).chunk_size(8).build();
+        let mut grid = Grid::<CardinalNeighborhood>::new(&grid_settings);
+        // A wall between the start and goal with a gap at the top
+        for y in 0..30 {
+            grid.set_nav(UVec3::new(16, y, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let search = |weight: f32| {
+            let context = SearchContext::default().weight(weight).traced();
+            let path = astar_grid_to(
+                &CardinalNeighborhood::default(),
+                &grid.view(),
+                UVec3::new(2, 16, 0),
+                &SearchGoal::Position(UVec3::new(29, 16, 0)),
+                64,
+                false,
+                &HashMap::new(),
+                &HashMap::new(),
+                ALL_NAV_LAYERS,
+                UVec2::ONE,
+                &context,
+            )
+            .unwrap();
+            (path, context.into_trace().unwrap().len())
+        };
+
+        let (optimal, optimal_expansions) = search(1.0);
+        assert_eq!(optimal.cost(), 55);
+
+        let (weighted, weighted_expansions) = search(3.0);
+        assert!(weighted_expansions < optimal_expansions);
+        assert!(weighted.cost() >= optimal.cost() && weighted.cost() <= 3 * optimal.cost());
+    }
 }
@@ -15,6 +15,8 @@ pub(crate) struct Chunk {
     max: UVec3,
     /// Flags which indicate which edges are dirty.
     dirty_edges: [bool; 26],
+    /// Incremented every time the chunk is rebuilt.
+    version: u32,
 }
 
 impl PartialEq for Chunk {
@@ -33,6 +35,7 @@ impl Chunk {
             min,
             max,
             dirty_edges: [true; 26],
+            version: 0,
         }
     }
 
@@ -80,6 +83,19 @@ impl Chunk {
         self.dirty_edges.fill(false);
     }
 
+    /// Returns how many times the chunk has been rebuilt.
+    pub(crate) fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub(crate) fn set_version(&mut self, version: u32) {
+        self.version = version;
+    }
+
+    pub(crate) fn bump_version(&mut self) {
+        self.version = self.version.wrapping_add(1);
+    }
+
     // Adjusts a position to the local coordinates of the chunk.
     pub(crate) fn to_local(&self, pos: &UVec3) -> UVec3 {
         UVec3::new(
@@ -1,22 +1,55 @@
 //! Components for pathfinding, collision, and debugging.
+use std::time::Duration;
+
 use bevy::{
-    color::palettes::css,
     ecs::entity::Entity,
-    math::{UVec3, Vec2, Vec3},
-    platform::collections::HashMap,
-    prelude::{Color, Component},
+    math::{UVec2, UVec3, Vec3},
+    platform::collections::HashSet,
+    prelude::{Component, ReflectComponent},
     reflect::Reflect,
-    transform::components::Transform,
 };
 
-#[cfg(feature = "gui-debug")]
-use crate::debug::DebugTilemapType;
+use crate::{
+    dir::Dir,
+    goal::SearchGoal,
+    nav::{NavLayers, ALL_NAV_LAYERS},
+    neighbor::HeuristicKind,
+};
 
 /// An entities position on the pathfinding [`crate::grid::Grid`].
 /// You'll need to maintain this position if you use the plugin pathfinding systems.
-#[derive(Component, Default, Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Component, Default, Debug, Clone, Eq, PartialEq, Hash, Reflect)]
+#[reflect(Component)]
 pub struct AgentPos(pub UVec3);
 
+/// The size in cells of an agent that covers more than one cell, such as a 2x2 vehicle. Agents without it cover a single cell.
+///
+/// [`AgentPos`] is the corner of the footprint with the lowest x and y, the footprint extends towards positive x and y on the same depth.
+/// Paths only pass where the whole footprint fits, and [`Blocking`] agents block every cell they cover.
+/// Footprints larger than one cell always pathfind with A* since the HPA* hierarchy is built for single cells.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct AgentSize(pub UVec2);
+
+impl Default for AgentSize {
+    fn default() -> Self {
+        Self(UVec2::ONE)
+    }
+}
+
+impl AgentSize {
+    /// Iterates over the cells covered by an agent of this size at `pos`.
+    pub fn cells(&self, pos: UVec3) -> impl Iterator<Item = UVec3> {
+        let size = self.0.max(UVec2::ONE);
+        (0..size.x).flat_map(move |x| (0..size.y).map(move |y| pos + UVec3::new(x, y, 0)))
+    }
+
+    /// Returns true if the agent covers more than one cell.
+    pub fn is_multi_cell(&self) -> bool {
+        self.0.x > 1 || self.0.y > 1
+    }
+}
+
 /****************************************
     PATHFINDING COMPONENTS
 *****************************************/
@@ -32,20 +65,134 @@ pub enum PathfindMode {
     /// Full-grid A* pathfinding without hierarchy.
     /// Useful for small grids or a turn based pathfinding path where movement cost needs to be the most accurate and cpu usage isn't a concern.
     AStar,
+    /// Full-grid Jump Point Search without hierarchy.
+    /// Returns the same paths as [`PathfindMode::AStar`] but is much faster on large open grids.
+    /// Requires uniform movement costs, falls back to [`PathfindMode::AStar`] if the grid has varying costs.
+    Jps,
+    /// Follow a [`crate::flow_field::FlowField`] towards the goal.
+    /// The plugin caches one field per goal so many agents heading to the same goal share a single search.
+    /// Great for tower defense or swarm style games where agents greatly outnumber distinct goals.
+    /// Partial paths aren't supported in this mode.
+    FlowField,
+    /// Full-grid Theta* any-angle pathfinding without hierarchy.
+    /// The returned [`crate::path::Path`] only contains the waypoints where the path changes direction,
+    /// so consecutive positions are not adjacent and you must interpolate between them when moving.
+    /// Line of sight is only traced within the agent's current z-plane, changing levels is taken a cell at a time.
+    /// Useful for flying units or RTS style movement where grid aligned zig-zags look unnatural.
+    Theta,
+}
+
+/// A band around a polyline that a path has to stay within, see [`Pathfind::corridor()`].
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct Corridor {
+    /// The points of the reference line, in order.
+    pub points: Vec<UVec3>,
+    /// How far from the line, in cells, the path may stray.
+    pub width: u32,
+    /// Widen the corridor until a path is found instead of failing, see [`Pathfind::widen_corridor()`].
+    pub widen: bool,
+}
+
+impl Corridor {
+    /// Returns true if `pos` is within the width of the corridor's line.
+    /// A corridor without points contains every position.
+    pub fn contains(&self, pos: UVec3) -> bool {
+        self.contains_within(pos, self.width)
+    }
+
+    // Returns true if `pos` is within `width` cells of the line.
+    pub(crate) fn contains_within(&self, pos: UVec3, width: u32) -> bool {
+        let pos = pos.as_vec3();
+        let width = width as f32;
+
+        match self.points.as_slice() {
+            [] => true,
+            [point] => point.as_vec3().distance(pos) <= width,
+            points => points.windows(2).any(|segment| {
+                let (a, b) = (segment[0].as_vec3(), segment[1].as_vec3());
+                let along = b - a;
+                let t = if along == Vec3::ZERO {
+                    0.0
+                } else {
+                    ((pos - a).dot(along) / along.length_squared()).clamp(0.0, 1.0)
+                };
+                (a + along * t).distance(pos) <= width
+            }),
+        }
+    }
 }
 
 /// Insert [`Pathfind`] on an entity to pathfind to a goal.
 /// Once the plugin systems have found a path, [`NextPos`] will be inserted.
-#[derive(Component, Default, Debug, Reflect)]
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
 pub struct Pathfind {
     /// The goal to pathfind to.
+    /// When pathfinding to multiple goals this is the first goal, see [`Pathfind::goals()`].
     pub goal: UVec3,
+    /// Candidate goals when pathfinding to whichever goal is cheapest to reach.
+    /// Empty when pathfinding to the single [`Pathfind::goal`].
+    pub goals: Vec<UVec3>,
+    /// The inclusive `(min, max)` corners of a region goal, see [`Pathfind::new_region()`].
+    /// When set, reaching any cell inside the region completes the path and [`Pathfind::goals`] is ignored.
+    pub region: Option<(UVec3, UVec3)>,
     /// Will attempt to return the best path if full route isn't found.
     pub partial: bool,
+    /// Stops next to goals that are blocked instead of failing, see [`Pathfind::stop_adjacent()`].
+    pub stop_adjacent: bool,
+    /// Completes the request within this many steps of the goal, see [`Pathfind::within()`].
+    pub within: Option<u32>,
 
     /// The [`PathfindMode`] to use for pathfinding.
     /// Defaults to [`PathfindMode::Refined`] which is hierarchical pathfinding with full refinement.
     pub mode: PathfindMode,
+
+    /// Overrides the grid's heuristic for this request, see [`Pathfind::heuristic()`].
+    pub heuristic: Option<HeuristicKind>,
+
+    /// The [`NavLayers`] the agent can move on, see [`Pathfind::layers()`].
+    /// Defaults to [`ALL_NAV_LAYERS`].
+    pub layers: NavLayers,
+
+    /// Breaks ties between equally short paths towards the straight line to the goal, see [`Pathfind::prefer_straight()`].
+    pub prefer_straight: bool,
+
+    /// Caps how many nodes the search may expand, see [`Pathfind::max_expansions()`].
+    /// `None` lets the search run until it finishes.
+    pub max_expansions: Option<usize>,
+
+    /// Inflates the heuristic of the search, see [`Pathfind::weight()`]. 1.0 searches for the cheapest path.
+    pub weight: f32,
+
+    /// Keeps the path within a band around a reference line, see [`Pathfind::corridor()`].
+    pub corridor: Option<Corridor>,
+
+    /// The entity whose [`AgentPos`] is used as the goal, see [`Pathfind::target_entity()`].
+    pub target: Option<Entity>,
+    /// How many cells the [`Pathfind::target`] can move away from the goal before a new path is requested.
+    pub repath_threshold: u32,
+}
+
+impl Default for Pathfind {
+    fn default() -> Self {
+        Pathfind {
+            goal: UVec3::ZERO,
+            goals: Vec::new(),
+            region: None,
+            partial: false,
+            stop_adjacent: false,
+            within: None,
+            mode: PathfindMode::default(),
+            heuristic: None,
+            layers: ALL_NAV_LAYERS,
+            prefer_straight: false,
+            max_expansions: None,
+            weight: 1.0,
+            corridor: None,
+            target: None,
+            repath_threshold: 0,
+        }
+    }
 }
 
 impl Pathfind {
@@ -87,12 +234,176 @@ impl Pathfind {
         }
     }
 
+    /// Creates a new [`Pathfind`] component that will pathfind to whichever of the `goals` is cheapest to reach.
+    /// The resulting [`crate::path::Path`] and [`NextPos`] work exactly like they do for a single goal.
+    ///
+    /// Give the goals in order of preference, when several goals cost the same to reach the first one given wins.
+    /// [`crate::path::Path::goal_index`] returns the index of the goal the path leads to.
+    /// # Example
+    /// ```rust,no_run
+    /// use bevy::math::UVec3;
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let pathfind = Pathfind::new_multi(vec![UVec3::new(5, 5, 0), UVec3::new(20, 3, 0)]);
+    /// ```
+    ///
+    pub fn new_multi(goals: Vec<UVec3>) -> Self {
+        Pathfind {
+            goal: goals.first().copied().unwrap_or_default(),
+            goals,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new [`Pathfind`] component that will pathfind to the closest reachable cell inside the box
+    /// between `min` and `max` (inclusive). Useful for behavior like retreating to a base area.
+    /// Combined with [`Pathfind::partial()`] the best approach to the region is returned if it can't be reached.
+    /// # Example
+    /// ```rust,no_run
+    /// use bevy::math::UVec3;
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let pathfind = Pathfind::new_region(UVec3::new(0, 0, 0), UVec3::new(8, 8, 0));
+    /// ```
+    ///
+    pub fn new_region(min: UVec3, max: UVec3) -> Self {
+        let (min, max) = (min.min(max), min.max(max));
+
+        Pathfind {
+            goal: min,
+            region: Some((min, max)),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new [`Pathfind`] component that chases the `target` entity.
+    /// The plugin systems use the target's [`AgentPos`] as the goal and request a new path whenever the target
+    /// moves further than the [`Pathfind::repath_threshold()`] from the goal the current path leads to.
+    ///
+    /// If the target despawns or loses its [`AgentPos`], the [`Pathfind`] and [`crate::path::Path`] are removed,
+    /// [`PathfindingFailed`] is inserted and [`crate::events::PathfindingFailed`] is triggered with [`PathError::TargetLost`].
+    ///
+    /// A target with the [`Blocking`] component blocks its own cell, combine with [`Pathfind::stop_adjacent()`]
+    /// to path right up to it.
+    /// # Example
+    /// ```rust,no_run
+    /// use bevy::prelude::*;
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// fn chase(mut commands: Commands, hunter: Entity, prey: Entity) {
+    ///     commands
+    ///         .entity(hunter)
+    ///         .insert(Pathfind::target_entity(prey).repath_threshold(2).stop_adjacent());
+    /// }
+    /// ```
+    ///
+    pub fn target_entity(target: Entity) -> Self {
+        Pathfind {
+            target: Some(target),
+            ..Default::default()
+        }
+    }
+
+    /// Sets how many cells the [`Pathfind::target`] can move away from the goal before a new path is requested.
+    /// Distance is measured as the largest difference along any axis. Defaults to 0, repathing whenever the target moves.
+    pub fn repath_threshold(mut self, threshold: u32) -> Self {
+        self.repath_threshold = threshold;
+        self
+    }
+
+    /// Returns every goal the pathfinding request can be satisfied by.
+    /// Empty for region goals, see [`Pathfind::region`].
+    pub fn goals(&self) -> &[UVec3] {
+        if self.region.is_some() {
+            &[]
+        } else if self.goals.is_empty() {
+            std::slice::from_ref(&self.goal)
+        } else {
+            &self.goals
+        }
+    }
+
+    /// Returns true if `pos` is one of the goals or inside the goal region.
+    pub fn is_goal(&self, pos: UVec3) -> bool {
+        self.search_goal().is_goal(pos)
+    }
+
+    // The goal in the form the search algorithms take.
+    pub(crate) fn search_goal(&self) -> SearchGoal<'_> {
+        match self.region {
+            Some((min, max)) => SearchGoal::Region { min, max },
+            None => SearchGoal::from_slice(self.goals()),
+        }
+    }
+
     /// Sets the pathfinding mode. See [`PathfindMode`] for options.
     pub fn mode(mut self, mode: PathfindMode) -> Self {
         self.mode = mode;
         self
     }
 
+    /// Sets the [`HeuristicKind`] used for this request instead of the grid's heuristic.
+    /// See [`HeuristicKind`] for which heuristics are admissible for each neighborhood.
+    pub fn heuristic(mut self, heuristic: HeuristicKind) -> Self {
+        self.heuristic = Some(heuristic);
+        self
+    }
+
+    /// Restricts the agent to cells whose [`NavLayers`] intersect `layers`,
+    /// set the layers of the cells with [`crate::grid::Grid::set_nav_layers`].
+    /// Lets a single grid serve unit types that traverse different terrain, such as infantry and boats.
+    ///
+    /// The HPA* hierarchy ignores layers, so requests restricted to some layers are searched with
+    /// [`PathfindMode::AStar`] regardless of the [`Pathfind::mode`].
+    pub fn layers(mut self, layers: NavLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Prefer the path closest to the straight line from the start to the goal when several paths cost the same.
+    ///
+    /// On open ground many paths share the lowest cost and the search returns whichever it reaches first,
+    /// which often hugs a wall or takes all of one direction before the other. This breaks the ties between
+    /// nodes of equal estimated cost with the cross product of their offset from the straight line,
+    /// so the path stays centered along the line. The path cost is unaffected.
+    ///
+    /// Only applies to [`PathfindMode::AStar`] searches towards a single goal, along with requests that fall back to A*.
+    pub fn prefer_straight(mut self) -> Self {
+        self.prefer_straight = true;
+        self
+    }
+
+    /// Keeps the path within `width` cells of the polyline through `points`, for guided or patrol routes that
+    /// follow a designer drawn line but still route around obstacles. The start and goal should lie inside the corridor.
+    ///
+    /// The request is searched with A* on the grid, only expanding cells inside the corridor.
+    /// If there's no path inside the corridor the request fails, use [`Pathfind::widen_corridor()`] to widen it instead.
+    /// # Example
+    /// ```rust,no_run
+    /// use bevy::math::UVec3;
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let pathfind = Pathfind::new(UVec3::new(30, 10, 0))
+    ///     .corridor(vec![UVec3::new(0, 0, 0), UVec3::new(15, 0, 0), UVec3::new(30, 10, 0)], 2);
+    /// ```
+    pub fn corridor(mut self, points: Vec<UVec3>, width: u32) -> Self {
+        self.corridor = Some(Corridor {
+            points,
+            width,
+            widen: false,
+        });
+        self
+    }
+
+    /// Doubles the width of the [`Pathfind::corridor()`] until a path is found instead of failing.
+    /// Once the corridor covers the whole grid the search is no longer restricted.
+    pub fn widen_corridor(mut self) -> Self {
+        if let Some(corridor) = &mut self.corridor {
+            corridor.widen = true;
+        }
+        self
+    }
+
     /// Allow partial paths.
     /// The pathfinding system will return the best path it can find
     /// even if it can't find a full route to the goal.
@@ -100,16 +411,170 @@ impl Pathfind {
         self.partial = true;
         self
     }
+
+    /// When the goal is impassable or occupied by a [`Blocking`] agent, path to the passable cell next to it
+    /// that is cheapest to reach instead of failing. This is the common "walk up to the target and attack it" case.
+    ///
+    /// Adjacency follows the grid's [`crate::neighbor::Neighborhood`], see [`crate::grid::Grid::adjacent_passable`]:
+    /// only the 4 sides count with a cardinal neighborhood while an ordinal neighborhood also counts the corners.
+    /// The request completes once the agent stands on one of those cells. Goals that are free are pathed to as usual.
+    ///
+    /// Works with every [`PathfindMode`], [`PathfindMode::FlowField`] requests are searched with A* since the goal moves off the field.
+    /// If every cell around the goal is blocked the goal itself is searched for, combine with [`Pathfind::partial()`]
+    /// to get as close as possible.
+    pub fn stop_adjacent(mut self) -> Self {
+        self.stop_adjacent = true;
+        self
+    }
+
+    /// Completes the request as soon as the agent reaches a passable cell within `radius` steps of the goal,
+    /// such as a ranged unit that only needs to get into attack range. With several goals any goal counts.
+    ///
+    /// The distance is counted in steps of the grid's [`crate::neighbor::Neighborhood`] ignoring walls,
+    /// see [`crate::grid::Grid::step_distance`]. Cells occupied by [`Blocking`] agents don't count,
+    /// so a radius of 1 or more already stops next to a blocked goal like [`Pathfind::stop_adjacent()`].
+    /// Combined with [`Pathfind::partial()`] the best approach is returned if no cell in range can be reached.
+    /// [`Pathfind::new_region()`] requests ignore the radius.
+    ///
+    /// Works with every [`PathfindMode`], [`PathfindMode::FlowField`] requests are searched with A* instead.
+    /// # Example
+    /// ```rust,no_run
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let pathfind = Pathfind::new_2d(20, 20).within(3);
+    /// ```
+    pub fn within(mut self, radius: u32) -> Self {
+        self.within = Some(radius);
+        self
+    }
+
+    /// Gives up the search after `max` node expansions so a request for an unreachable or distant goal
+    /// can't stall a frame on a large open map.
+    ///
+    /// If the cap is hit the request fails with [`PathError::ExpansionLimitReached`],
+    /// or with [`Pathfind::partial()`] the path to the closest node found so far is returned instead.
+    ///
+    /// Only [`PathfindMode::AStar`], [`PathfindMode::Jps`] and [`PathfindMode::Theta`] searches are capped,
+    /// along with requests that fall back to A*. The HPA* modes are already bounded by the chunk hierarchy.
+    pub fn max_expansions(mut self, max: usize) -> Self {
+        self.max_expansions = Some(max);
+        self
+    }
+
+    /// Runs a weighted A* search, multiplying the heuristic by `weight` so the search heads for the goal more greedily
+    /// and expands fewer nodes, at the cost of a possibly more expensive path. Useful for distant goals where
+    /// an exact path doesn't matter but a refined one is still wanted, without switching to [`PathfindMode::Coarse`].
+    ///
+    /// The path found costs at most `weight` times the cheapest path. The bound relies on the heuristic never
+    /// overestimating, which holds for the built-in neighborhoods unless a heuristic that overestimates their moves
+    /// is chosen, like [`HeuristicKind::Manhattan`] with diagonal moves. A weight of 1.0, the default, is a regular search
+    /// and values below 1.0 are treated as 1.0.
+    ///
+    /// Only [`PathfindMode::AStar`], [`PathfindMode::Jps`] and [`PathfindMode::Theta`] searches are weighted,
+    /// along with requests that fall back to A*. [`PathfindMode::Theta`] doesn't find the cheapest path
+    /// to begin with, so the bound doesn't apply to it.
+    /// # Example
+    /// ```rust,no_run
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// // Accept paths up to 50% more expensive than the cheapest one
+    /// let pathfind = Pathfind::new_2d(500, 500).mode(PathfindMode::AStar).weight(1.5);
+    /// ```
+    pub fn weight(mut self, weight: f32) -> Self {
+        self.weight = weight.max(1.0);
+        self
+    }
+}
+
+/// Marker component for agents whose [`Pathfind`] request is waiting in the queue to be serviced.
+/// Only inserted if [`crate::plugin::PathfindingBudget::mark_pending`] is enabled.
+#[derive(Component, Default, Debug, Reflect)]
+#[component(storage = "SparseSet")]
+#[reflect(Component)]
+pub struct PathPending;
+
+/// Runs the [`Pathfind`] requests of the entity on the [`bevy::tasks::AsyncComputeTaskPool`] instead of within the frame.
+/// Useful for very long searches on huge maps that could take longer than a frame.
+///
+/// [`PathfindingInProgress`] is inserted while the search runs and the [`crate::path::Path`] is inserted when it completes,
+/// usually a few frames later. Changing or removing the [`Pathfind`] component cancels the search.
+/// The search runs on a snapshot of the grid, so changes made to the grid while it runs are not seen.
+#[derive(Component, Default, Debug, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct AsyncPathfind {
+    /// Fails the request with [`PathError::TimedOut`] if the search takes longer than this.
+    /// `None` waits for as long as the search takes.
+    pub timeout: Option<Duration>,
+}
+
+impl AsyncPathfind {
+    /// Runs the searches asynchronously and fails them if they take longer than `timeout`.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+        }
+    }
 }
 
+/// Marker component inserted while the search of an [`AsyncPathfind`] agent is running.
+#[derive(Component, Default, Debug, Reflect)]
+#[component(storage = "SparseSet")]
+#[reflect(Component)]
+pub struct PathfindingInProgress;
+
 /// The next position in the path inserted into an entity by the pathfinding system.
 /// The `pathfind` system in [`crate::plugin::NorthstarPlugin`] will insert this.
 /// Remove [`NextPos`] after you've moved the entity to the next position and
 /// a new [`NextPos`] will be inserted on the next frame.
 #[derive(Component, Default, Debug, Reflect)]
 #[component(storage = "SparseSet")]
+#[reflect(Component)]
 pub struct NextPos(pub UVec3);
 
+/// The direction of the move from the agent's [`AgentPos`] to its [`NextPos`], such as [`Dir::NorthEast`]
+/// for a diagonal move. Inserted by the [`crate::plugin::NorthstarPlugin`] whenever a new [`NextPos`] is inserted.
+///
+/// Use it to face sprites along their movement. It isn't updated when the agent waits in place
+/// and it's kept after the agent reaches its goal, so the agent keeps facing the way it last moved.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct NextDir(pub Dir);
+
+/// Marker component to move the agent along its [`crate::path::Path`] yourself instead of following [`NextPos`].
+///
+/// The plugin still finds, reroutes and completes the path, but it won't insert [`NextPos`] or pop positions from the path.
+/// Read the whole remaining path with [`crate::path::Path::remaining`] to interpolate across several cells,
+/// and call [`crate::path::Path::advance`] as the agent reaches each cell.
+/// Keep [`AgentPos`] updated so the agent's cell is known for blocking and for completing the path.
+///
+/// Agents with this component are never asked to step aside or wait for other agents.
+#[derive(Component, Default, Debug, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct ManualAdvance;
+
+/// Marker component to hold the agent in place without losing its [`Pathfind`] or [`crate::path::Path`],
+/// for example while it's stunned or in a cutscene.
+///
+/// The plugin won't insert [`NextPos`], avoid other agents, or reroute for the agent while the component is present.
+/// The agent still blocks its cells and other agents path around it. Remove the component to resume following the path.
+/// A [`NextPos`] inserted before the pause is kept, your movement system decides whether to finish the step.
+#[derive(Component, Default, Debug, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct PathfindingPaused;
+
+/// How many cells per second the agent moves along its [`crate::path::Path`].
+///
+/// Without the component the [`crate::plugin::NorthstarPlugin`] inserts a [`NextPos`] one cell ahead every time
+/// the previous one is removed. With it, the agent builds up progress while it has no [`NextPos`] and only gets one
+/// once it has enough for a whole cell. A fast agent can get a [`NextPos`] several cells down the path in a single frame,
+/// the cells in between are skipped. Fractional progress carries over to the next step.
+///
+/// Collision checks apply to the cell the agent ends up on, an agent in the way stops the agent short of it.
+/// The speed is ignored while the [`crate::plugin::CooperativeConfig`] planner is moving the agents.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct AgentSpeed(pub f32);
+
 // See src/path.rs for the Path component
 
 /****************************************
@@ -123,58 +588,100 @@ pub struct NextPos(pub UVec3);
 ///
 /// **Do not** use this component for static obstacles such as walls or terrain.
 /// Static geometry should be handled separately with [`crate::grid::Grid::set_nav()`] in [`crate::grid::Grid`].
-#[derive(Component, Default)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct Blocking;
 
-// I want to switch to this in the future on the next Bevy major release.
-/*#[derive(Component, Debug)]
+/// Optional priority of an agent when contesting cells with other agents, higher values win. Agents without it have a priority of 0.
+///
+/// With collision enabled, higher priority agents claim their next cell first each frame and keep their [`NextPos`],
+/// while lower priority agents wait or reroute around them. In a head-on swap the lower priority agent steps aside.
+/// Ties are broken on the entity for determinism. The cooperative planner also plans higher priority agents first.
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+#[reflect(Component)]
+pub struct PathPriority(pub u32);
+
+/// Optional set of [`Blocking`] entities an agent ignores, such as a friendly unit it's moving to merge or swap with.
+///
+/// The cells of these entities are treated as free when the agent's path is found, avoided and rerouted,
+/// and the agent may move into them. Other agents are unaffected. Agents without it ignore no entities.
+///
+/// ```rust,ignore
+/// commands.entity(agent).insert((
+///     Pathfind::new_2d(8, 8),
+///     IgnoreBlocking::from_iter([leader]),
+/// ));
+/// ```
+#[derive(Component, Default, Debug, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct IgnoreBlocking(pub HashSet<Entity>);
+
+impl FromIterator<Entity> for IgnoreBlocking {
+    fn from_iter<T: IntoIterator<Item = Entity>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// The reason carried by the [`crate::events::PathfindingFailed`] event,
+/// and returned by [`crate::grid::Grid::try_find_path`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, thiserror::Error)]
 pub enum PathError {
     /// Unable to find a path to the goal.
+    #[error("no path found")]
     NoPathFound,
+    /// The start or every goal is outside of the grid.
+    #[error("position {0} is out of bounds")]
+    OutOfBounds(UVec3),
+    /// The start position is impassable.
+    #[error("the start position is impassable")]
+    StartBlocked,
+    /// Every goal is impassable, like a goal inside a wall.
+    #[error("the goal is impassable")]
+    GoalBlocked,
+    /// Every goal is walled off from the start, see [`crate::grid::Grid::same_component`].
+    #[error("the goal can't be reached from the start")]
+    Disconnected,
     /// The next position in the path is now impassable due to dynamic changes to the grid.
+    #[error("the next position in the path is impassable")]
     PathInvalidated,
     /// The pathfinding system failed to reroute the entity around an obstacle with `Blocking`.
     /// `NorthstarPlugin` reroute_path system will attempt to deeper reroute. You can also handle this yourself by running your system before [`crate::prelude::PathingSet`].
+    #[error("failed to avoid a blocking agent")]
     AvoidanceFailed,
-    /// The pathfinding system failed to reroute the entity to its goal after all avoidance options were exhausted.
-    /// This means the entity cannot reach its goal and you will need to handle this failure in your own system.
-    /// Examples would be to set a new goal or wait for a certain amount of time before trying to reroute again.
-    /// **You will need to handle this failure in your own system before the entity can be pathed again**.
-    RerouteFailed,
+    /// The search of an [`AsyncPathfind`] agent took longer than its timeout.
+    #[error("the search timed out")]
+    TimedOut,
+    /// The search gave up after expanding [`Pathfind::max_expansions`] nodes without reaching the goal.
+    #[error("the search ran out of node expansions")]
+    ExpansionLimitReached,
+    /// The entity chased with [`Pathfind::target_entity()`] despawned or no longer has an [`AgentPos`].
+    #[error("the target entity is gone")]
+    TargetLost,
 }
 
-impl PartialEq for PathError {
-    fn eq(&self, other: &Self) -> bool {
-        matches!(
-            (self, other),
-            (PathError::NoPathFound, PathError::NoPathFound)
-                | (PathError::PathInvalidated, PathError::PathInvalidated)
-                | (PathError::AvoidanceFailed, PathError::AvoidanceFailed)
-                | (PathError::RerouteFailed, PathError::RerouteFailed)
-        )
-    }
-}*/
-
 /// Marker component that is inserted on an entity when local avoidance fails.
 /// Currently this marker is handled by the [`crate::plugin::NorthstarPlugin`] `reroute_path` system and can be ignored
 /// unless the desire is to handle the failure in a custom way.
-#[derive(Component, Default, Debug)]
+#[derive(Component, Default, Debug, Reflect)]
 #[component(storage = "SparseSet")]
+#[reflect(Component)]
 pub struct AvoidanceFailed;
 
 /// Marker component that is inserted on an entity when a collision is detected.
 /// The built-in pathfinding system will try to pathfind for this entity every frame unless
 /// you handle the failure in a custom way.
-#[derive(Component, Default, Debug)]
+#[derive(Component, Default, Debug, Reflect)]
 #[component(storage = "SparseSet")]
+#[reflect(Component)]
 pub struct PathfindingFailed;
 
 /// Marker component that is inserted on an entity when path rerouting in [`crate::plugin::NorthstarPlugin`] `reroute_path` fails.
 /// This happens well all avoidance options have been exhausted and the entity cannot be rerouted to its goal.
 /// **You will need to handle this failure in your own system before the entity can be pathed again**.
 /// Examples would be to set a new goal or wait for a certain amount of time before trying to reroute again.
-#[derive(Component, Default, Debug)]
+#[derive(Component, Default, Debug, Reflect)]
 #[component(storage = "SparseSet")]
+#[reflect(Component)]
 pub struct RerouteFailed;
 
 /****************************************
@@ -193,12 +700,14 @@ pub mod debug_components;
 /// pathfinding systems and debugging know which grid to use.
 #[derive(Component, Reflect)]
 #[relationship(relationship_target = GridAgents)]
+#[reflect(Component)]
 pub struct AgentOfGrid(pub Entity);
 
 /// The [`GridAgents`] component is used to store a list of entities that are agents in a grid.
 /// See [`AgentOfGrid`] for more information on how to associate an entity with a grid.
 #[derive(Component, Reflect)]
 #[relationship_target(relationship = AgentOfGrid, linked_spawn)]
+#[reflect(Component)]
 pub struct GridAgents(Vec<Entity>);
 
 impl GridAgents {
@@ -1,10 +1,9 @@
 //! Components for pathfinding, collision, and debugging.
 use bevy::{
     color::palettes::css,
-    ecs::entity::Entity,
     math::{UVec3, Vec2, Vec3},
     platform::collections::HashMap,
-    prelude::{Color, Component},
+    prelude::{Color, Component, ReflectComponent},
     reflect::Reflect,
     transform::components::Transform,
 };
@@ -18,17 +17,20 @@ use crate::debug::DebugTilemapType;
 /// Add this component to the same entity as [`DebugPath`] to offset the debug gizmos.
 /// Useful for aligning the gizmos with your tilemap rendering offset.
 #[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct DebugOffset(pub Vec3);
 
 /// You can add DebugDepthOffsets to your DebugGrid entity and the debug gizmo's y position
 /// will be offset by the depth (z-coordinate) of the grid/path position.
 #[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct DebugDepthYOffsets(pub HashMap<u32, f32>);
 
 /// Add [`DebugCursor`] to your DebugGrid entity and provide it with the current position
 /// of your mouse cursor.
 /// This will allow [`DebugGrid::set_show_connections_on_hover()`] to only draw connections graph node under the cursor.
 #[derive(Component, Debug, Default, Reflect)]
+#[reflect(Component)]
 pub struct DebugCursor(pub Option<Vec2>);
 
 // Internal component to hold which cell the mouse is hovering over.
@@ -37,12 +39,23 @@ pub(crate) struct DebugNode(pub(crate) Option<UVec3>);
 
 /// Component for debugging an entity's [`crate::path::Path`].
 #[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct DebugPath {
     /// The [`Color`] of the path gizmo.
     pub color: Color,
     /// Draw the HPA* high level graph path between chunk entrances.
     /// This is useful for debugging the HPA* algorithm.
     pub draw_unrefined: bool,
+    /// The [`Color`] of the unrefined HPA* path. `None` uses the inverse of [`DebugPath::color`].
+    pub unrefined_color: Option<Color>,
+    /// Offset in world units applied to the unrefined HPA* path so it doesn't overlap the refined path.
+    pub unrefined_offset: Vec2,
+    /// Draw the unrefined HPA* path as a dashed line.
+    pub unrefined_dashed: bool,
+    /// The width of the path polyline in world units. Defaults to 1.0, the regular gizmo line.
+    pub line_width: f32,
+    /// Draw an arrowhead pointing at the goal at the end of the path.
+    pub draw_arrow: bool,
 }
 
 impl DebugPath {
@@ -51,7 +64,7 @@ impl DebugPath {
     pub fn new(color: Color) -> Self {
         DebugPath {
             color,
-            draw_unrefined: false,
+            ..Default::default()
         }
     }
 }
@@ -61,14 +74,29 @@ impl Default for DebugPath {
         DebugPath {
             color: bevy::prelude::Color::Srgba(css::RED),
             draw_unrefined: false,
+            unrefined_color: None,
+            unrefined_offset: Vec2::ZERO,
+            unrefined_dashed: false,
+            line_width: 1.0,
+            draw_arrow: false,
         }
     }
 }
 
+/// Insert [`DebugSearch`] on an agent to record the cells its next search expands.
+/// The recorded search is inserted on the agent as a [`crate::trace::SearchTrace`] and drawn as a heatmap
+/// by a [`DebugGrid`] with [`DebugGrid::draw_search`] enabled.
+///
+/// Recording is only meant for debugging a single agent at a time, every expansion is stored.
+#[derive(Component, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct DebugSearch;
+
 /// Component for debugging [`crate::grid::Grid`].
 /// You need to insert [`DebugGrid`] as a child of your map.
 #[derive(Reflect, Component)]
 #[require(Transform, DebugOffset, DebugDepthYOffsets, DebugCursor, DebugNode)]
+#[reflect(Component)]
 pub struct DebugGrid {
     /// The width of your tiles in pixels.
     pub tile_width: u32,
@@ -82,12 +110,22 @@ pub struct DebugGrid {
     pub draw_chunks: bool,
     /// Will draw the [`crate::nav::NavCell`]s in your grid.
     pub draw_cells: bool,
+    /// Will shade each cell by its movement cost, from green for the cheapest to red for the most expensive.
+    pub draw_cost_heatmap: bool,
     /// Will draw the HPA* graph entrance nodes in each chunk.
     pub draw_entrances: bool,
     /// Will draw the internal cached paths between the entrances.
     pub draw_cached_paths: bool,
     /// Will show the connections between nodes only when hovering over them.
     pub show_connections_on_hover: bool,
+    /// Will highlight the cells occupied by [`crate::components::Blocking`] agents in the [`crate::plugin::BlockingMap`].
+    pub draw_blocking: bool,
+    /// Will draw an arrow from each [`crate::nav::Portal`] cell to the cell it leads to.
+    pub draw_portals: bool,
+    /// Will draw the cells expanded by the searches of [`DebugSearch`] agents, colored by their g-score.
+    pub draw_search: bool,
+    /// Limits drawing to the cells between the two corners, inclusive. `None` draws the whole grid.
+    pub draw_region: Option<(UVec3, UVec3)>,
 }
 
 impl DebugGrid {
@@ -139,6 +177,19 @@ impl DebugGrid {
         self
     }
 
+    /// Shades each cell by its movement cost, from green for the cheapest passable cells to red for the most expensive.
+    /// Impassable cells are drawn dark gray. The colors are scaled to the costs inside the draw region.
+    pub fn set_draw_cost_heatmap(&mut self, value: bool) -> &Self {
+        self.draw_cost_heatmap = value;
+        self
+    }
+
+    /// Toggle draw_cost_heatmap.
+    pub fn toggle_cost_heatmap(&mut self) -> &Self {
+        self.draw_cost_heatmap = !self.draw_cost_heatmap;
+        self
+    }
+
     /// The entrances are the cells in each chunk that connect to other chunks.
     /// This will draw the entrances calculated by the HPA* algorithm.
     /// This is very useful for debugging the HPA* algorithm and understanding how chunks are connected to build the hierarchy.
@@ -179,6 +230,62 @@ impl DebugGrid {
         self.show_connections_on_hover = !self.show_connections_on_hover;
         self
     }
+
+    /// Highlights every cell in the [`crate::plugin::BlockingMap`], the cells collision avoidance treats as occupied by
+    /// [`crate::components::Blocking`] agents. Useful for seeing why an agent waited or rerouted.
+    pub fn set_draw_blocking(&mut self, value: bool) -> &Self {
+        self.draw_blocking = value;
+        self
+    }
+
+    /// Toggle draw_blocking.
+    pub fn toggle_blocking(&mut self) -> &Self {
+        self.draw_blocking = !self.draw_blocking;
+        self
+    }
+
+    /// Draws an arrow from every [`crate::nav::Portal`] cell to its target, a link added with
+    /// [`crate::grid::Grid::add_portal`] shows up as an arrow in each direction.
+    pub fn set_draw_portals(&mut self, value: bool) -> &Self {
+        self.draw_portals = value;
+        self
+    }
+
+    /// Toggle draw_portals.
+    pub fn toggle_portals(&mut self) -> &Self {
+        self.draw_portals = !self.draw_portals;
+        self
+    }
+
+    /// Draws the cells expanded by the last search of each [`DebugSearch`] agent as a heatmap.
+    /// Cells are colored from blue to red as their g-score, the cost from the start, increases.
+    pub fn set_draw_search(&mut self, value: bool) -> &Self {
+        self.draw_search = value;
+        self
+    }
+
+    /// Toggle draw_search.
+    pub fn toggle_search(&mut self) -> &Self {
+        self.draw_search = !self.draw_search;
+        self
+    }
+
+    /// Limits drawing to the rectangle of cells between `min` and `max`, inclusive. Pass `None` to draw the whole grid.
+    /// Cells, the cost heatmap, entrances, cached paths, blocking cells, portals and searches outside the region are skipped entirely,
+    /// which keeps the debug drawing usable on large grids. Move the region along with your camera to follow the view.
+    ///
+    /// Only the x and y of the corners are used, the drawn depth is set with [`DebugGrid::set_depth`].
+    pub fn set_draw_region(&mut self, region: Option<(UVec3, UVec3)>) -> &Self {
+        self.draw_region = region.map(|(min, max)| (min.min(max), min.max(max)));
+        self
+    }
+
+    /// Returns true if `pos` is inside the [`DebugGrid::draw_region`], or if there is no region set.
+    pub fn in_draw_region(&self, pos: UVec3) -> bool {
+        self.draw_region.is_none_or(|(min, max)| {
+            pos.x >= min.x && pos.x <= max.x && pos.y >= min.y && pos.y <= max.y
+        })
+    }
 }
 
 /// Builder for [`DebugGrid`].
@@ -191,9 +298,14 @@ pub struct DebugGridBuilder {
     tilemap_type: DebugTilemapType,
     draw_chunks: bool,
     draw_cells: bool,
+    draw_cost_heatmap: bool,
     draw_entrances: bool,
     draw_cached_paths: bool,
     show_connections_on_hover: bool,
+    draw_blocking: bool,
+    draw_portals: bool,
+    draw_search: bool,
+    draw_region: Option<(UVec3, UVec3)>,
 }
 
 impl DebugGridBuilder {
@@ -206,9 +318,14 @@ impl DebugGridBuilder {
             tilemap_type: DebugTilemapType::Square,
             draw_chunks: false,
             draw_cells: false,
+            draw_cost_heatmap: false,
             draw_entrances: false,
             draw_cached_paths: false,
             show_connections_on_hover: false,
+            draw_blocking: false,
+            draw_portals: false,
+            draw_search: false,
+            draw_region: None,
         }
     }
 
@@ -232,6 +349,12 @@ impl DebugGridBuilder {
         self
     }
 
+    /// Utility function to set the [`DebugGrid`] to draw pointy-top hex tiles, for use with [`crate::neighbor::HexNeighborhood`].
+    pub fn hex(mut self) -> Self {
+        self.tilemap_type = DebugTilemapType::Hex;
+        self
+    }
+
     /// Enables drawing the outline of chunks the grid is divided into.
     pub fn enable_chunks(mut self) -> Self {
         self.draw_chunks = true;
@@ -245,6 +368,13 @@ impl DebugGridBuilder {
         self
     }
 
+    /// Enables shading each cell by its movement cost, from green for cheap to red for expensive.
+    /// Useful for checking that your terrain weights ended up where you expect them.
+    pub fn enable_cost_heatmap(mut self) -> Self {
+        self.draw_cost_heatmap = true;
+        self
+    }
+
     /// Enables drawing the chunk entrances calculated by the HPA* algorithm.
     /// This is useful for debugging how chunks are connected and how the HPA* algorithm builds its hierarchy.
     pub fn enable_entrances(mut self) -> Self {
@@ -267,6 +397,34 @@ impl DebugGridBuilder {
         self
     }
 
+    /// Enables highlighting the cells occupied by [`crate::components::Blocking`] agents.
+    /// This is the same set of cells collision avoidance reads from the [`crate::plugin::BlockingMap`].
+    pub fn enable_blocking(mut self) -> Self {
+        self.draw_blocking = true;
+        self
+    }
+
+    /// Enables drawing an arrow from each [`crate::nav::Portal`] cell to the cell it leads to.
+    /// Portals can link cells far apart, this makes it easy to check they connect the cells you expect.
+    pub fn enable_portals(mut self) -> Self {
+        self.draw_portals = true;
+        self
+    }
+
+    /// Enables drawing the cells expanded by the searches of [`DebugSearch`] agents.
+    /// Useful for seeing how much of the grid a search explores and where it spent its effort.
+    pub fn enable_search(mut self) -> Self {
+        self.draw_search = true;
+        self
+    }
+
+    /// Limits drawing to the rectangle of cells between `min` and `max`, inclusive.
+    /// Useful on large grids where drawing every cell would tank the frame rate. See [`DebugGrid::set_draw_region`].
+    pub fn draw_region(mut self, min: UVec3, max: UVec3) -> Self {
+        self.draw_region = Some((min.min(max), min.max(max)));
+        self
+    }
+
     /// Builds the final [`DebugGrid`] component with the configured settings to be inserted into your map entity.
     /// You need to call this methdod to finalize the builder and create the component.
     pub fn build(self) -> DebugGrid {
@@ -277,9 +435,14 @@ impl DebugGridBuilder {
             map_type: self.tilemap_type,
             draw_chunks: self.draw_chunks,
             draw_cells: self.draw_cells,
+            draw_cost_heatmap: self.draw_cost_heatmap,
             draw_entrances: self.draw_entrances,
             draw_cached_paths: self.draw_cached_paths,
             show_connections_on_hover: self.show_connections_on_hover,
+            draw_blocking: self.draw_blocking,
+            draw_portals: self.draw_portals,
+            draw_search: self.draw_search,
+            draw_region: self.draw_region,
         }
     }
 }
@@ -0,0 +1,246 @@
+//! Space-time reservations and windowed search used by the cooperative mode of the [`crate::plugin::NorthstarPlugin`].
+//! See [`crate::plugin::CooperativeConfig`].
+use bevy::{ecs::entity::Entity, math::UVec3, platform::collections::HashMap};
+use indexmap::map::Entry::{Occupied, Vacant};
+use std::collections::BinaryHeap;
+
+use crate::{
+    grid::Grid,
+    nav::NavLayers,
+    neighbor::{HeuristicKind, Neighborhood},
+    FxIndexMap, SmallestCostHolder,
+};
+
+/// Cells reserved by agents at each timestep of the planning window.
+/// Timestep 0 is the current frame and every step after is one move.
+pub(crate) struct ReservationTable {
+    window: u32,
+    cells: HashMap<(UVec3, u32), Entity>,
+}
+
+impl ReservationTable {
+    pub(crate) fn new(window: u32) -> Self {
+        Self {
+            window,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn window(&self) -> u32 {
+        self.window
+    }
+
+    /// Reserves `pos` at `time` for `entity`. The first reservation of a slot wins.
+    pub(crate) fn reserve(&mut self, pos: UVec3, time: u32, entity: Entity) {
+        if time <= self.window {
+            self.cells.entry((pos, time)).or_insert(entity);
+        }
+    }
+
+    /// Reserves `pos` from `time` until the end of the window.
+    pub(crate) fn reserve_from(&mut self, pos: UVec3, time: u32, entity: Entity) {
+        for time in time..=self.window {
+            self.reserve(pos, time, entity);
+        }
+    }
+
+    /// Returns true if `entity` can move from `from` at `time` to `to` at `time + 1`
+    /// without entering a reserved cell or swapping places with another agent.
+    pub(crate) fn can_move(&self, entity: Entity, from: UVec3, to: UVec3, time: u32) -> bool {
+        let free = |pos: UVec3, time: u32| {
+            self.cells
+                .get(&(pos, time))
+                .is_none_or(|reserved| *reserved == entity)
+        };
+
+        if !free(to, time + 1) {
+            return false;
+        }
+
+        // Two agents can't pass through each other
+        match (
+            self.cells.get(&(to, time)),
+            self.cells.get(&(from, time + 1)),
+        ) {
+            (Some(a), Some(b)) => a != b || *a == entity,
+            _ => true,
+        }
+    }
+}
+
+/// Space-time A* from `start` to `target` over the reservation window.
+/// Waiting in place is allowed at every step. Cells that can't be entered on `layers` are impassable.
+///
+/// Returns the position of `entity` at every timestep after `start` until `target` is reached,
+/// a wait repeats the previous position. Returns `None` if `target` can't be reached within the window.
+pub(crate) fn plan_window<N: Neighborhood>(
+    grid: &Grid<N>,
+    reservations: &ReservationTable,
+    entity: Entity,
+    start: UVec3,
+    target: UVec3,
+    layers: NavLayers,
+) -> Option<Vec<UVec3>> {
+    // The neighborhood heuristics may overestimate, the reservations need an admissible one
+    // for the waits to be planned properly.
+    let heuristic = if grid.neighborhood().is_ordinal() {
+        HeuristicKind::Chebyshev
+    } else {
+        HeuristicKind::Manhattan
+    };
+
+    let mut to_visit = BinaryHeap::new();
+    to_visit.push(SmallestCostHolder {
+        estimated_cost: heuristic.distance(start, target),
+        cost: 0,
+        index: 0,
+        tie_break: 0,
+    });
+
+    let mut visited: FxIndexMap<(UVec3, u32), (usize, u32)> = FxIndexMap::default();
+    visited.insert((start, 0), (usize::MAX, 0));
+
+    while let Some(SmallestCostHolder { cost, index, .. }) = to_visit.pop() {
+        let (&(pos, time), &(_, current_cost)) = visited.get_index(index).unwrap();
+
+        if pos == target {
+            let mut steps = Vec::with_capacity(time as usize);
+            let mut current = index;
+
+            while let Some((&(pos, _), &(parent, _))) = visited.get_index(current) {
+                if parent == usize::MAX {
+                    break;
+                }
+
+                steps.push(pos);
+                current = parent;
+            }
+
+            steps.reverse();
+            return Some(steps);
+        }
+
+        if cost > current_cost || time >= reservations.window() {
+            continue;
+        }
+
+        let moves = grid
+            .navcell(pos)
+            .neighbor_iter(pos)
+            .filter(|neighbor| {
+                grid.in_bounds(*neighbor) && grid.navcell(*neighbor).is_passable_for(layers)
+            })
+            .map(|neighbor| {
+                let cell = grid.navcell(neighbor);
+                let cost = cell.cost.saturating_add(grid.navcell(pos).climb_cost(cell));
+                (neighbor, grid.neighborhood().step_cost(pos, neighbor, cost))
+            })
+            .chain(std::iter::once((
+                pos,
+                grid.neighborhood().step_cost(pos, pos, 1),
+            )));
+
+        for (next, step_cost) in moves {
+            if !reservations.can_move(entity, pos, next, time) {
+                continue;
+            }
+
+            let new_cost = cost.saturating_add(step_cost);
+            let h;
+            let n;
+            match visited.entry((next, time + 1)) {
+                Vacant(e) => {
+                    h = heuristic.distance(next, target);
+                    n = e.index();
+                    e.insert((index, new_cost));
+                }
+                Occupied(mut e) => {
+                    if e.get().1 > new_cost {
+                        h = heuristic.distance(next, target);
+                        n = e.index();
+                        e.insert((index, new_cost));
+                    } else {
+                        continue;
+                    }
+                }
+            }
+
+            to_visit.push(SmallestCostHolder {
+                estimated_cost: h,
+                cost: new_cost,
+                index: n,
+                tie_break: 0,
+            });
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::entity::Entity, math::UVec3};
+
+    use crate::{
+        cooperative::{plan_window, ReservationTable},
+        grid::{Grid, GridSettingsBuilder},
+        nav::{Nav, ALL_NAV_LAYERS},
+        neighbor::CardinalNeighborhood,
+    };
+
+    // A 1-wide corridor along y = 1
+    fn corridor() -> Grid<CardinalNeighborhood> {
+        let settings = GridSettingsBuilder::new_2d(6, 3).chunk_size(3).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for x in 0..6 {
+            grid.set_nav(UVec3::new(x, 0, 0), Nav::Impassable);
+            grid.set_nav(UVec3::new(x, 2, 0), Nav::Impassable);
+        }
+        grid.build();
+        grid
+    }
+
+    #[test]
+    fn test_plan_window_waits_for_reserved_cell() {
+        let grid = corridor();
+        let agent = Entity::from_raw(1);
+        let other = Entity::from_raw(2);
+
+        let mut reservations = ReservationTable::new(8);
+        for time in 0..=2 {
+            reservations.reserve(UVec3::new(2, 1, 0), time, other);
+        }
+
+        let start = UVec3::new(0, 1, 0);
+        let target = UVec3::new(4, 1, 0);
+        let plan = plan_window(&grid, &reservations, agent, start, target, ALL_NAV_LAYERS).unwrap();
+
+        // One wait before entering the reserved cell once it's free
+        assert_eq!(plan.len(), 5);
+        assert_eq!(plan[2], UVec3::new(2, 1, 0));
+        assert_eq!(plan.last(), Some(&target));
+
+        // The target can't be reached if the cell is reserved for the whole window
+        reservations.reserve_from(UVec3::new(2, 1, 0), 0, other);
+        assert!(plan_window(&grid, &reservations, agent, start, target, ALL_NAV_LAYERS).is_none());
+    }
+
+    #[test]
+    fn test_reservations_prevent_swapping() {
+        let agent = Entity::from_raw(1);
+        let other = Entity::from_raw(2);
+
+        let a = UVec3::new(0, 1, 0);
+        let b = UVec3::new(1, 1, 0);
+
+        // The other agent moves from b to a
+        let mut reservations = ReservationTable::new(4);
+        reservations.reserve(b, 0, other);
+        reservations.reserve(a, 1, other);
+
+        assert!(!reservations.can_move(agent, a, b, 0));
+        assert!(!reservations.can_move(agent, b, a, 0));
+        assert!(reservations.can_move(other, b, a, 0));
+        assert!(reservations.can_move(agent, a, b, 1));
+    }
+}
@@ -4,14 +4,16 @@ use std::fmt::Debug;
 use bevy::{color::palettes::css, math::Vec2, platform::collections::HashMap, prelude::*};
 
 use crate::{
-    components::debug_components::{DebugCursor, DebugGrid, DebugNode, DebugPath},
-    grid::Grid,
+    components::debug_components::{DebugCursor, DebugGrid, DebugNode, DebugPath, DebugSearch},
+    grid::{isometric_to_cell, isometric_to_world, square_to_cell, square_to_world, Grid},
     neighbor::Neighborhood,
     path::Path,
+    plugin::BlockingMap,
     prelude::{
         debug_components::{DebugDepthYOffsets, DebugOffset},
         AgentOfGrid,
     },
+    trace::SearchTrace,
 };
 
 /// Required to calculate how to draw the debug gizmos
@@ -22,6 +24,8 @@ pub enum DebugTilemapType {
     Square,
     /// Isometric tilemap, where each tile is a diamond.
     Isometric,
+    /// Pointy-top hex tilemap using axial coordinates, see [`crate::neighbor::HexNeighborhood`].
+    Hex,
 }
 
 /// Debug plugin for the Northstar pathfinding library.
@@ -71,6 +75,7 @@ impl<N: Neighborhood + 'static> Plugin for NorthstarDebugPlugin<N> {
             (
                 draw_debug_map::<N>,
                 draw_debug_paths::<N>,
+                draw_debug_searches::<N>,
                 update_debug_node::<N>,
             ),
         )
@@ -78,6 +83,8 @@ impl<N: Neighborhood + 'static> Plugin for NorthstarDebugPlugin<N> {
         .register_type::<DebugTilemapType>()
         .register_type::<DebugOffset>()
         .register_type::<DebugPath>()
+        .register_type::<DebugSearch>()
+        .register_type::<DebugCursor>()
         .register_type::<DebugDepthYOffsets>();
     }
 }
@@ -91,6 +98,7 @@ fn draw_debug_map<N: Neighborhood + 'static>(
         Option<&DebugDepthYOffsets>,
     )>,
     grid: Query<&Grid<N>>,
+    blocking: Option<Res<BlockingMap>>,
     mut gizmos: Gizmos,
 ) {
     let grid = if let Ok(grid) = grid.single() {
@@ -120,28 +128,37 @@ fn draw_debug_map<N: Neighborhood + 'static>(
         if debug_grid.draw_chunks {
             // Draw chunk boundaries
             let chunk_size = grid.chunk_size();
-            let chunk_width = grid.width() / chunk_size;
-            let chunk_height = grid.height() / chunk_size;
+            let chunk_count = grid.chunk_count();
 
             match debug_grid.map_type {
                 DebugTilemapType::Square => {
-                    for x in 0..chunk_width {
-                        for y in 0..chunk_height {
+                    for x in 0..chunk_count.x {
+                        for y in 0..chunk_count.y {
+                            // Edge chunks are clamped to the grid when the size doesn't divide the grid evenly
+                            let min_x = x * chunk_size.x;
+                            let min_y = y * chunk_size.y;
+                            let max_x = ((x + 1) * chunk_size.x).min(grid.width());
+                            let max_y = ((y + 1) * chunk_size.y).min(grid.height());
+
+                            if !chunk_in_region(debug_grid, min_x, min_y, max_x, max_y) {
+                                continue;
+                            }
+
                             let bottom_left = Vec2::new(
-                                (x * chunk_size) as f32 * debug_grid.tile_width as f32,
-                                (y * chunk_size) as f32 * debug_grid.tile_height as f32,
+                                min_x as f32 * debug_grid.tile_width as f32,
+                                min_y as f32 * debug_grid.tile_height as f32,
                             );
                             let bottom_right = Vec2::new(
-                                ((x + 1) * chunk_size) as f32 * debug_grid.tile_width as f32,
-                                (y * chunk_size) as f32 * debug_grid.tile_height as f32,
+                                max_x as f32 * debug_grid.tile_width as f32,
+                                min_y as f32 * debug_grid.tile_height as f32,
                             );
                             let top_left = Vec2::new(
-                                (x * chunk_size) as f32 * debug_grid.tile_width as f32,
-                                ((y + 1) * chunk_size) as f32 * debug_grid.tile_height as f32,
+                                min_x as f32 * debug_grid.tile_width as f32,
+                                max_y as f32 * debug_grid.tile_height as f32,
                             );
                             let top_right = Vec2::new(
-                                ((x + 1) * chunk_size) as f32 * debug_grid.tile_width as f32,
-                                ((y + 1) * chunk_size) as f32 * debug_grid.tile_height as f32,
+                                max_x as f32 * debug_grid.tile_width as f32,
+                                max_y as f32 * debug_grid.tile_height as f32,
                             );
 
                             let bottom_left = bottom_left + offset - half_tile_width;
@@ -157,22 +174,37 @@ fn draw_debug_map<N: Neighborhood + 'static>(
                     }
                 }
                 DebugTilemapType::Isometric => {
-                    for x in 1..chunk_width {
-                        for y in 1..chunk_height {
+                    let chunk_width = chunk_size.x as f32;
+                    let chunk_height = chunk_size.y as f32;
+
+                    for x in 1..chunk_count.x {
+                        for y in 1..chunk_count.y {
+                            if !chunk_in_region(
+                                debug_grid,
+                                (x - 1) * chunk_size.x,
+                                (y - 1) * chunk_size.y,
+                                ((x + 1) * chunk_size.x).min(grid.width()),
+                                ((y + 1) * chunk_size.y).min(grid.height()),
+                            ) {
+                                continue;
+                            }
+
                             let position = Vec2::new(
-                                (y as f32 + x as f32) * half_tile_width * chunk_size as f32,
-                                (y as f32 - x as f32) * half_tile_height * chunk_size as f32
+                                y as f32 * half_tile_width * chunk_height
+                                    + x as f32 * half_tile_width * chunk_width,
+                                y as f32 * half_tile_height * chunk_height
+                                    - x as f32 * half_tile_height * chunk_width
                                     + y_offset,
                             );
 
                             let top = position
-                                + Vec2::new(0.0, debug_grid.tile_height as f32 * chunk_size as f32);
+                                + Vec2::new(0.0, debug_grid.tile_height as f32 * chunk_height);
                             let right = position
-                                + Vec2::new(debug_grid.tile_width as f32 * chunk_size as f32, 0.0);
+                                + Vec2::new(debug_grid.tile_width as f32 * chunk_width, 0.0);
                             let bottom = position
-                                - Vec2::new(0.0, debug_grid.tile_height as f32 * chunk_size as f32);
+                                - Vec2::new(0.0, debug_grid.tile_height as f32 * chunk_height);
                             let left = position
-                                - Vec2::new(debug_grid.tile_width as f32 * chunk_size as f32, 0.0);
+                                - Vec2::new(debug_grid.tile_width as f32 * chunk_width, 0.0);
 
                             gizmos.line_2d(top + offset_center, right + offset_center, css::WHITE);
                             gizmos.line_2d(
@@ -189,6 +221,82 @@ fn draw_debug_map<N: Neighborhood + 'static>(
                         }
                     }
                 }
+                DebugTilemapType::Hex => {
+                    for x in 0..chunk_count.x {
+                        for y in 0..chunk_count.y {
+                            if !chunk_in_region(
+                                debug_grid,
+                                x * chunk_size.x,
+                                y * chunk_size.y,
+                                ((x + 1) * chunk_size.x).min(grid.width()),
+                                ((y + 1) * chunk_size.y).min(grid.height()),
+                            ) {
+                                continue;
+                            }
+
+                            // Chunks are parallelograms in axial coordinates, outline them through the tile edges
+                            let min_x = (x * chunk_size.x) as f32 - 0.5;
+                            let min_y = (y * chunk_size.y) as f32 - 0.5;
+                            let max_x = ((x + 1) * chunk_size.x).min(grid.width()) as f32 - 0.5;
+                            let max_y = ((y + 1) * chunk_size.y).min(grid.height()) as f32 - 0.5;
+
+                            let corner = |cx: f32, cy: f32| {
+                                hex_position(cx, cy, debug_grid.tile_width, debug_grid.tile_height)
+                                    + offset
+                                    + Vec2::new(0.0, y_offset)
+                            };
+
+                            let bottom_left = corner(min_x, min_y);
+                            let bottom_right = corner(max_x, min_y);
+                            let top_right = corner(max_x, max_y);
+                            let top_left = corner(min_x, max_y);
+
+                            gizmos.line_2d(bottom_left, bottom_right, css::WHITE);
+                            gizmos.line_2d(bottom_right, top_right, css::WHITE);
+                            gizmos.line_2d(top_right, top_left, css::WHITE);
+                            gizmos.line_2d(top_left, bottom_left, css::WHITE);
+                        }
+                    }
+                }
+            }
+        }
+
+        if debug_grid.draw_cost_heatmap && debug_grid.depth < grid.depth() {
+            let (min, max) = debug_grid.draw_region.unwrap_or((UVec3::ZERO, UVec3::MAX));
+            let max = max.min(UVec3::new(
+                grid.width().saturating_sub(1),
+                grid.height().saturating_sub(1),
+                debug_grid.depth,
+            ));
+            let depth = debug_grid.depth;
+            let cells =
+                (min.x..=max.x).flat_map(|x| (min.y..=max.y).map(move |y| UVec3::new(x, y, depth)));
+
+            // Scale the colors to the range of costs being drawn
+            let (min_cost, max_cost) = cells
+                .clone()
+                .map(|pos| grid.navcell(pos))
+                .filter(|cell| cell.is_passable())
+                .fold((u32::MAX, 0), |(low, high), cell| {
+                    (low.min(cell.cost), high.max(cell.cost))
+                });
+            let range = max_cost.saturating_sub(min_cost).max(1) as f32;
+            let size = Vec2::new(half_tile_width, half_tile_height);
+
+            for pos in cells {
+                let cell = grid.navcell(pos);
+                let color = if cell.is_impassable() {
+                    Color::from(css::DARK_SLATE_GRAY)
+                } else {
+                    let t = (cell.cost - min_cost) as f32 / range;
+                    Color::srgb(t, 1.0 - t, 0.0)
+                };
+
+                gizmos.rect_2d(
+                    cell_position(debug_grid, pos, y_offset) + offset,
+                    size,
+                    color,
+                );
             }
         }
 
@@ -197,9 +305,14 @@ fn draw_debug_map<N: Neighborhood + 'static>(
                 continue;
             }
 
+            // Only visit the cells inside the draw region
+            let (min, max) = debug_grid.draw_region.unwrap_or((UVec3::ZERO, UVec3::MAX));
+            let max_x = max.x.min(grid.width().saturating_sub(1));
+            let max_y = max.y.min(grid.height().saturating_sub(1));
+
             // Draw cell gizmos
-            for x in 0..grid.width() {
-                for y in 0..grid.height() {
+            for x in min.x..=max_x {
+                for y in min.y..=max_y {
                     let cell = grid.navcell(UVec3::new(x, y, debug_grid.depth));
                     let color = if cell.is_impassable() {
                         css::RED
@@ -216,6 +329,14 @@ fn draw_debug_map<N: Neighborhood + 'static>(
                             (y as f32 + x as f32) * half_tile_width,
                             (y as f32 - x as f32) * half_tile_height - half_tile_height + y_offset,
                         ),
+                        DebugTilemapType::Hex => {
+                            hex_position(
+                                x as f32,
+                                y as f32,
+                                debug_grid.tile_width,
+                                debug_grid.tile_height,
+                            ) + Vec2::new(0.0, y_offset)
+                        }
                     };
 
                     gizmos.circle_2d(position + offset, 2.0, color);
@@ -226,6 +347,10 @@ fn draw_debug_map<N: Neighborhood + 'static>(
         if debug_grid.draw_entrances {
             // Draw graph nodes
             for node in grid.graph().nodes() {
+                if !debug_grid.in_draw_region(node.pos) {
+                    continue;
+                }
+
                 let pos_offset = *debug_depth_offsets.get(&node.pos.z).unwrap_or(&0.0);
 
                 let position = match debug_grid.map_type {
@@ -239,6 +364,14 @@ fn draw_debug_map<N: Neighborhood + 'static>(
                             - half_tile_height
                             + pos_offset,
                     ),
+                    DebugTilemapType::Hex => {
+                        hex_position(
+                            node.pos.x as f32,
+                            node.pos.y as f32,
+                            debug_grid.tile_width,
+                            debug_grid.tile_height,
+                        ) + Vec2::new(0.0, pos_offset)
+                    }
                 };
 
                 let color = if node.pos.z == debug_grid.depth {
@@ -276,6 +409,14 @@ fn draw_debug_map<N: Neighborhood + 'static>(
                                         - half_tile_height
                                         + neighbor_offset,
                                 ),
+                                DebugTilemapType::Hex => {
+                                    hex_position(
+                                        neighbor.pos.x as f32,
+                                        neighbor.pos.y as f32,
+                                        debug_grid.tile_width,
+                                        debug_grid.tile_height,
+                                    ) + Vec2::new(0.0, neighbor_offset)
+                                }
                             };
 
                             gizmos.line_2d(
@@ -289,6 +430,53 @@ fn draw_debug_map<N: Neighborhood + 'static>(
             }
         }
 
+        if debug_grid.draw_blocking {
+            if let Some(blocking) = &blocking {
+                let radius = half_tile_width.min(half_tile_height) * 0.6;
+
+                for pos in blocking
+                    .0
+                    .keys()
+                    .filter(|pos| debug_grid.in_draw_region(**pos))
+                {
+                    let pos_offset = *debug_depth_offsets.get(&pos.z).unwrap_or(&0.0);
+                    let color = if pos.z == debug_grid.depth {
+                        css::ORANGE
+                    } else {
+                        css::DARK_ORANGE
+                    };
+
+                    gizmos.circle_2d(
+                        cell_position(debug_grid, *pos, pos_offset) + offset,
+                        radius,
+                        color,
+                    );
+                }
+            }
+        }
+
+        if debug_grid.draw_portals {
+            for (pos, portal) in grid.portals() {
+                if !debug_grid.in_draw_region(pos) && !debug_grid.in_draw_region(portal.target) {
+                    continue;
+                }
+
+                let pos_offset = *debug_depth_offsets.get(&pos.z).unwrap_or(&0.0);
+                let target_offset = *debug_depth_offsets.get(&portal.target.z).unwrap_or(&0.0);
+                let color = if pos.z == debug_grid.depth {
+                    css::AQUA
+                } else {
+                    css::TEAL
+                };
+
+                gizmos.arrow_2d(
+                    cell_position(debug_grid, pos, pos_offset) + offset,
+                    cell_position(debug_grid, portal.target, target_offset) + offset,
+                    color,
+                );
+            }
+        }
+
         if debug_grid.draw_cached_paths {
             let path_colors = [
                 css::RED,
@@ -319,6 +507,10 @@ fn draw_debug_map<N: Neighborhood + 'static>(
                     continue;
                 }
 
+                if !debug_grid.in_draw_region(*prev) {
+                    continue;
+                }
+
                 for next in iter {
                     let prev_offset = *debug_depth_offsets.get(&prev.z).unwrap_or(&0.0);
 
@@ -333,6 +525,14 @@ fn draw_debug_map<N: Neighborhood + 'static>(
                                 + prev_offset
                                 - half_tile_height,
                         ),
+                        DebugTilemapType::Hex => {
+                            hex_position(
+                                prev.x as f32,
+                                prev.y as f32,
+                                debug_grid.tile_width,
+                                debug_grid.tile_height,
+                            ) + Vec2::new(0.0, prev_offset)
+                        }
                     };
 
                     let next_offset = *debug_depth_offsets.get(&next.z).unwrap_or(&0.0);
@@ -347,6 +547,14 @@ fn draw_debug_map<N: Neighborhood + 'static>(
                                 + next_offset
                                 - half_tile_height,
                         ),
+                        DebugTilemapType::Hex => {
+                            hex_position(
+                                next.x as f32,
+                                next.y as f32,
+                                debug_grid.tile_width,
+                                debug_grid.tile_height,
+                            ) + Vec2::new(0.0, next_offset)
+                        }
                     };
 
                     let mut color = css::BLUE;
@@ -406,105 +614,245 @@ fn draw_debug_paths<N: Neighborhood + 'static>(
             let half_tile_width = debug_grid.tile_width as f32 * 0.5;
             let half_tile_height = debug_grid.tile_height as f32 * 0.5;
 
-            // Iterate over path.path() drawing a line from one cell to the next cell until completed
-            let mut iter = path.path().iter();
-            let mut prev = iter.next().unwrap();
-
-            for next in iter {
-                let prev_y_offset = if let Some(depth_offsets) = debug_depth_offsets {
-                    depth_offsets.0.get(&prev.z).cloned().unwrap_or_default()
-                } else {
-                    0.0
-                };
-
-                let next_y_offset = if let Some(depth_offsets) = debug_depth_offsets {
-                    depth_offsets.0.get(&next.z).cloned().unwrap_or_default()
+            // Projects a cell to the center of its tile
+            let project = |pos: &UVec3| {
+                let y_offset = if let Some(depth_offsets) = debug_depth_offsets {
+                    depth_offsets.0.get(&pos.z).cloned().unwrap_or_default()
                 } else {
                     0.0
                 };
 
-                let prev_position = match debug_grid.map_type {
+                let position = match debug_grid.map_type {
                     DebugTilemapType::Square => Vec2::new(
-                        (prev.x * debug_grid.tile_width) as f32,
-                        (prev.y * debug_grid.tile_height) as f32 + prev_y_offset,
+                        (pos.x * debug_grid.tile_width) as f32,
+                        (pos.y * debug_grid.tile_height) as f32 + y_offset,
                     ),
                     DebugTilemapType::Isometric => Vec2::new(
-                        (prev.y as f32 + prev.x as f32) * half_tile_width,
-                        (prev.y as f32 - prev.x as f32) * half_tile_height - half_tile_height
-                            + prev_y_offset,
+                        (pos.y as f32 + pos.x as f32) * half_tile_width,
+                        (pos.y as f32 - pos.x as f32) * half_tile_height - half_tile_height
+                            + y_offset,
                     ),
+                    DebugTilemapType::Hex => {
+                        hex_position(
+                            pos.x as f32,
+                            pos.y as f32,
+                            debug_grid.tile_width,
+                            debug_grid.tile_height,
+                        ) + Vec2::new(0.0, y_offset)
+                    }
                 };
 
-                let next_position = match debug_grid.map_type {
-                    DebugTilemapType::Square => Vec2::new(
-                        (next.x * debug_grid.tile_width) as f32,
-                        (next.y * debug_grid.tile_height) as f32 + next_y_offset,
-                    ),
-                    DebugTilemapType::Isometric => Vec2::new(
-                        (next.y as f32 + next.x as f32) * half_tile_width,
-                        (next.y as f32 - next.x as f32) * half_tile_height - half_tile_height
-                            + next_y_offset,
-                    ),
-                };
+                position + center_offset
+            };
 
-                let x = prev_position + center_offset;
-                let y = next_position + center_offset;
+            // Project every cell in the path to build a continuous polyline
+            let points = path.path().iter().map(project).collect::<Vec<_>>();
 
-                gizmos.line_2d(x, y, debug_path.color);
+            // Gizmo lines have a fixed width, thicker lines are drawn as parallel strokes a unit apart
+            let strokes = debug_path.line_width.max(1.0).round() as usize;
+            for stroke in 0..strokes {
+                let distance = stroke as f32 - (strokes - 1) as f32 * 0.5;
+                gizmos.linestrip_2d(offset_polyline(&points, distance), debug_path.color);
+            }
+
+            if debug_path.draw_arrow && points.len() > 1 {
+                let end = points[points.len() - 1];
+                let start = points[points.len() - 2];
+                let tip_length =
+                    (half_tile_width.min(half_tile_height) * 0.75).max(debug_path.line_width * 3.0);
 
-                prev = next;
+                gizmos
+                    .arrow_2d(start, end, debug_path.color)
+                    .with_tip_length(tip_length);
             }
 
             if debug_path.draw_unrefined {
-                let mut iter = path.graph_path.iter();
-                let mut prev = if let Some(p) = iter.next() {
-                    p
+                let color = debug_path.unrefined_color.unwrap_or_else(|| {
+                    let color = debug_path.color.to_srgba();
+                    Color::srgba(
+                        1.0 - color.red,
+                        1.0 - color.green,
+                        1.0 - color.blue,
+                        color.alpha,
+                    )
+                });
+
+                let unrefined = path
+                    .graph_path
+                    .iter()
+                    .map(|pos| project(pos) + debug_path.unrefined_offset)
+                    .collect::<Vec<_>>();
+
+                if debug_path.unrefined_dashed {
+                    let dash = half_tile_width.min(half_tile_height).max(1.0);
+                    for segment in unrefined.windows(2) {
+                        draw_dashed_line_2d(&mut gizmos, segment[0], segment[1], dash, color);
+                    }
                 } else {
-                    continue;
-                };
+                    gizmos.linestrip_2d(unrefined, color);
+                }
+            }
+        }
+    }
+}
 
-                let inverted_color = debug_path.color.to_srgba();
-                let inverted_color = Color::srgba(
-                    1.0 - inverted_color.red,
-                    1.0 - inverted_color.green,
-                    1.0 - inverted_color.blue,
-                    inverted_color.alpha,
-                );
+// Draws a line from `start` to `end` as dashes of `dash` length separated by gaps of the same length.
+fn draw_dashed_line_2d(gizmos: &mut Gizmos, start: Vec2, end: Vec2, dash: f32, color: Color) {
+    let length = start.distance(end);
+    let direction = (end - start).normalize_or_zero();
+
+    let mut distance = 0.0;
+    while distance < length {
+        let dash_end = (distance + dash).min(length);
+        gizmos.line_2d(
+            start + direction * distance,
+            start + direction * dash_end,
+            color,
+        );
+        distance += dash * 2.0;
+    }
+}
 
-                for next in iter {
-                    let prev_position = match debug_grid.map_type {
-                        DebugTilemapType::Square => Vec2::new(
-                            (prev.x * debug_grid.tile_width) as f32,
-                            (prev.y * debug_grid.tile_height) as f32,
-                        ),
-                        DebugTilemapType::Isometric => Vec2::new(
-                            (prev.y as f32 + prev.x as f32) * (debug_grid.tile_width as f32 * 0.5),
-                            (prev.y as f32 - prev.x as f32) * (debug_grid.tile_height as f32 * 0.5),
-                        ),
-                    };
+// Draws the cells expanded by traced searches on the debug grid's depth, colored from blue to red by g-score.
+fn draw_debug_searches<N: Neighborhood + 'static>(
+    grid_children: Query<(Entity, &Children), With<Grid<N>>>,
+    debug_grid: Query<(&DebugGrid, &DebugOffset, Option<&DebugDepthYOffsets>)>,
+    traces: Query<(&SearchTrace, &AgentOfGrid)>,
+    mut gizmos: Gizmos,
+) {
+    for (grid_entity, child) in grid_children {
+        let Some((debug_grid, debug_offset, debug_depth_offsets)) = child
+            .iter()
+            .find_map(|child_entity| debug_grid.get(child_entity).ok())
+        else {
+            continue;
+        };
 
-                    let next_position = match debug_grid.map_type {
-                        DebugTilemapType::Square => Vec2::new(
-                            (next.x * debug_grid.tile_width) as f32,
-                            (next.y * debug_grid.tile_height) as f32,
-                        ),
-                        DebugTilemapType::Isometric => Vec2::new(
-                            (next.y as f32 + next.x as f32) * (debug_grid.tile_width as f32 * 0.5),
-                            (next.y as f32 - next.x as f32) * (debug_grid.tile_height as f32 * 0.5),
-                        ),
-                    };
+        if !debug_grid.draw_search {
+            continue;
+        }
 
-                    let x = prev_position + center_offset;
-                    let y = next_position + center_offset;
+        let offset = debug_offset.0.truncate();
+        let size = Vec2::new(debug_grid.tile_width as f32, debug_grid.tile_height as f32) * 0.5;
 
-                    gizmos.line_2d(x, y, inverted_color);
+        for (trace, parent_grid) in &traces {
+            if parent_grid.0 != grid_entity {
+                continue;
+            }
 
-                    prev = next;
+            let max_g = trace.max_g().max(1) as f32;
+
+            for node in trace.nodes() {
+                if node.pos.z != debug_grid.depth || !debug_grid.in_draw_region(node.pos) {
+                    continue;
                 }
+
+                let y_offset = debug_depth_offsets
+                    .and_then(|offsets| offsets.0.get(&node.pos.z).copied())
+                    .unwrap_or_default();
+
+                let t = node.g as f32 / max_g;
+
+                gizmos.rect_2d(
+                    cell_position(debug_grid, node.pos, y_offset) + offset,
+                    size,
+                    Color::srgb(t, 0.2, 1.0 - t),
+                );
             }
         }
     }
 }
+
+// Returns true if the chunk spanning `min` to `max`, exclusive, overlaps the draw region of the debug grid.
+fn chunk_in_region(debug_grid: &DebugGrid, min_x: u32, min_y: u32, max_x: u32, max_y: u32) -> bool {
+    debug_grid
+        .draw_region
+        .is_none_or(|(min, max)| min_x <= max.x && max_x > min.x && min_y <= max.y && max_y > min.y)
+}
+
+// Converts a cell position to its position on the tilemap, before the debug offset is applied.
+fn cell_position(debug_grid: &DebugGrid, pos: UVec3, y_offset: f32) -> Vec2 {
+    let position = match debug_grid.map_type {
+        DebugTilemapType::Square => square_to_world(pos, tile_size(debug_grid)),
+        DebugTilemapType::Isometric => isometric_to_world(pos, tile_size(debug_grid)),
+        DebugTilemapType::Hex => hex_position(
+            pos.x as f32,
+            pos.y as f32,
+            debug_grid.tile_width,
+            debug_grid.tile_height,
+        ),
+    };
+
+    position + Vec2::new(0.0, y_offset)
+}
+
+// The width and height of a tile of the debug grid.
+fn tile_size(debug_grid: &DebugGrid) -> Vec2 {
+    Vec2::new(debug_grid.tile_width as f32, debug_grid.tile_height as f32)
+}
+
+// Converts an axial hex coordinate to the center of its tile, rows are offset by half a tile.
+fn hex_position(x: f32, y: f32, tile_width: u32, tile_height: u32) -> Vec2 {
+    Vec2::new(
+        (x + y * 0.5) * tile_width as f32,
+        y * tile_height as f32 * 0.75,
+    )
+}
+
+// Rounds fractional axial coordinates to the hex tile containing them.
+fn hex_round(q: f32, r: f32) -> (i32, i32) {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+
+    (rq as i32, rr as i32)
+}
+
+// Offsets every point of the polyline sideways by `distance`, mitering the corners so the strokes stay parallel.
+fn offset_polyline(points: &[Vec2], distance: f32) -> Vec<Vec2> {
+    if distance == 0.0 || points.len() < 2 {
+        return points.to_vec();
+    }
+
+    let direction = |a: Vec2, b: Vec2| (b - a).normalize_or_zero();
+
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, point)| {
+            let incoming = if i > 0 {
+                direction(points[i - 1], *point)
+            } else {
+                Vec2::ZERO
+            };
+            let outgoing = if i + 1 < points.len() {
+                direction(*point, points[i + 1])
+            } else {
+                Vec2::ZERO
+            };
+
+            let normal = (incoming + outgoing).normalize_or_zero().perp();
+
+            // Lengthen the offset on corners so the stroke keeps its distance from both edges,
+            // capped so switchbacks don't spike out
+            let edge_normal = if incoming != Vec2::ZERO {
+                incoming.perp()
+            } else {
+                outgoing.perp()
+            };
+            let miter = 1.0 / normal.dot(edge_normal).max(0.5);
+
+            *point + normal * distance * miter
+        })
+        .collect()
+}
+
 fn update_debug_node<N: Neighborhood + 'static>(
     mut query: Query<(
         &mut DebugNode,
@@ -541,18 +889,22 @@ fn update_debug_node<N: Neighborhood + 'static>(
 
             let (x, y) = match debug_grid.map_type {
                 DebugTilemapType::Square => {
-                    let x = (adjusted_cursor.x / debug_grid.tile_width as f32).floor() as u32;
-                    let y = (adjusted_cursor.y / debug_grid.tile_height as f32).floor() as u32;
-                    (x, y)
+                    let cell =
+                        square_to_cell(adjusted_cursor - tile_half_size, tile_size(debug_grid));
+                    (cell.x.max(0) as u32, cell.y.max(0) as u32)
                 }
                 DebugTilemapType::Isometric => {
-                    let screen_x = adjusted_cursor.x;
-                    let screen_y = adjusted_cursor.y;
-                    let x = ((screen_x / tile_half_size.x - screen_y / tile_half_size.y) / 2.0)
-                        .floor() as u32;
-                    let y = ((screen_x / tile_half_size.x + screen_y / tile_half_size.y) / 2.0)
-                        .floor() as u32;
-                    (x, y)
+                    let cell =
+                        isometric_to_cell(adjusted_cursor - tile_half_size, tile_size(debug_grid));
+                    (cell.x.max(0) as u32, cell.y.max(0) as u32)
+                }
+                DebugTilemapType::Hex => {
+                    // Undo the half tile shift applied for square tiles, hex positions are tile centers
+                    let local = adjusted_cursor - tile_half_size;
+                    let r = local.y / (debug_grid.tile_height as f32 * 0.75);
+                    let q = local.x / debug_grid.tile_width as f32 - r * 0.5;
+                    let (q, r) = hex_round(q, r);
+                    (q.max(0) as u32, r.max(0) as u32)
                 }
             };
 
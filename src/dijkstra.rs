@@ -9,7 +9,10 @@ use indexmap::map::Entry::{Occupied, Vacant};
 use ndarray::ArrayView3;
 use std::collections::BinaryHeap;
 
-use crate::{graph::Graph, in_bounds_3d, nav::NavCell, path::Path, FxIndexMap, SmallestCostHolder};
+use crate::{
+    graph::Graph, in_bounds_3d, nav::NavCell, neighbor::Neighborhood, path::Path, FxIndexMap,
+    MovementCost, SmallestCostHolder,
+};
 
 /// Dijkstra's algorithm for pathfinding in a grid.
 ///
@@ -24,7 +27,8 @@ use crate::{graph::Graph, in_bounds_3d, nav::NavCell, path::Path, FxIndexMap, Sm
 ///
 /// ## Returns
 /// A `HashMap` of `UVec3` goal positions with their respective `Path`s.
-pub(crate) fn dijkstra_grid(
+pub(crate) fn dijkstra_grid<N: Neighborhood>(
+    neighborhood: &N,
     grid: &ArrayView3<NavCell>,
     start: UVec3,
     goals: &[UVec3],
@@ -37,6 +41,7 @@ pub(crate) fn dijkstra_grid(
         estimated_cost: 0,
         cost: 0,
         index: 0,
+        tie_break: 0,
     });
 
     let mut visited: FxIndexMap<UVec3, (usize, u32)> = FxIndexMap::default();
@@ -51,10 +56,10 @@ pub(crate) fn dijkstra_grid(
 
     while let Some(SmallestCostHolder { cost, index, .. }) = to_visit.pop() {
         let neighbors = {
-            let (current_pos, &(_, current_cost)) = visited.get_index(index).unwrap();
+            let (&current_pos, &(_, current_cost)) = visited.get_index(index).unwrap();
 
-            if remaining_goals.remove(current_pos) {
-                goal_costs.insert(*current_pos, current_cost);
+            if remaining_goals.remove(&current_pos) {
+                goal_costs.insert(current_pos, current_cost);
                 if only_closest_goal || remaining_goals.is_empty() {
                     break;
                 }
@@ -66,10 +71,11 @@ pub(crate) fn dijkstra_grid(
                 current_pos.z as usize,
             ]];
 
-            cell.neighbor_iter(*current_pos)
+            cell.neighbor_iter(current_pos)
+                .map(move |neighbor| (neighbor, cell, current_pos))
         };
 
-        for neighbor in neighbors {
+        for (neighbor, cell, current_pos) in neighbors {
             if !in_bounds_3d(neighbor, min, max) {
                 continue;
             }
@@ -88,7 +94,12 @@ pub(crate) fn dijkstra_grid(
                 continue;
             }
 
-            let new_cost = cost + neighbor_cell.cost;
+            let new_cost = cost
+                + neighborhood.step_cost(
+                    current_pos,
+                    neighbor,
+                    cell.move_cost(current_pos, neighbor, neighbor_cell),
+                );
             let n;
 
             match visited.entry(neighbor) {
@@ -110,6 +121,7 @@ pub(crate) fn dijkstra_grid(
                 estimated_cost: new_cost,
                 cost: new_cost,
                 index: n,
+                tie_break: 0,
             });
         }
     }
@@ -136,6 +148,109 @@ pub(crate) fn dijkstra_grid(
     goal_data
 }
 
+/// Dijkstra expansion from `start` that stops at cells costing more than `budget` to reach.
+///
+/// ## Arguments
+/// * `neighborhood` - The `Neighborhood` to use for finding neighbors.
+/// * `grid` - The `Grid` to search.
+/// * `start` - The starting position.
+/// * `budget` - The maximum movement cost to expand to.
+/// * `blocking` - A map of blocking entities.
+/// * `cost_overrides` - Extra movement cost added on top of the [`NavCell`] cost when entering a position.
+///
+/// ## Returns
+/// A `HashMap` of every reachable position with the cost of reaching it, including `start` at 0.
+pub(crate) fn dijkstra_range<N: Neighborhood>(
+    neighborhood: &N,
+    grid: &ArrayView3<NavCell>,
+    start: UVec3,
+    budget: MovementCost,
+    blocking: &HashMap<UVec3, Entity>,
+    cost_overrides: &HashMap<UVec3, MovementCost>,
+) -> HashMap<UVec3, MovementCost> {
+    let mut to_visit = BinaryHeap::new();
+    to_visit.push(SmallestCostHolder {
+        estimated_cost: 0,
+        cost: 0,
+        index: 0,
+        tie_break: 0,
+    });
+
+    let mut visited: FxIndexMap<UVec3, MovementCost> = FxIndexMap::default();
+    visited.insert(start, 0);
+
+    let shape = grid.shape();
+    let min = UVec3::new(0, 0, 0);
+    let max = UVec3::new(shape[0] as u32, shape[1] as u32, shape[2] as u32);
+
+    while let Some(SmallestCostHolder { cost, index, .. }) = to_visit.pop() {
+        let (&current_pos, &current_cost) = visited.get_index(index).unwrap();
+
+        // A cheaper route to this cell was already expanded
+        if cost > current_cost {
+            continue;
+        }
+
+        let cell = &grid[[
+            current_pos.x as usize,
+            current_pos.y as usize,
+            current_pos.z as usize,
+        ]];
+
+        for neighbor in cell.neighbor_iter(current_pos) {
+            if !in_bounds_3d(neighbor, min, max) || blocking.contains_key(&neighbor) {
+                continue;
+            }
+
+            let neighbor_cell = &grid[[
+                neighbor.x as usize,
+                neighbor.y as usize,
+                neighbor.z as usize,
+            ]];
+
+            if neighbor_cell.is_impassable() {
+                continue;
+            }
+
+            let new_cost = cost
+                .saturating_add(neighborhood.step_cost(
+                    current_pos,
+                    neighbor,
+                    cell.move_cost(current_pos, neighbor, neighbor_cell),
+                ))
+                .saturating_add(cost_overrides.get(&neighbor).copied().unwrap_or(0));
+
+            if new_cost > budget {
+                continue;
+            }
+
+            let n = match visited.entry(neighbor) {
+                Vacant(e) => {
+                    let n = e.index();
+                    e.insert(new_cost);
+                    n
+                }
+                Occupied(mut e) => {
+                    if *e.get() <= new_cost {
+                        continue;
+                    }
+                    e.insert(new_cost);
+                    e.index()
+                }
+            };
+
+            to_visit.push(SmallestCostHolder {
+                estimated_cost: 0,
+                cost: new_cost,
+                index: n,
+                tie_break: 0,
+            });
+        }
+    }
+
+    visited.into_iter().collect()
+}
+
 /// Dijkstra's algorithm for pathfinding in a graph.
 ///
 /// ## Arguments
@@ -160,6 +275,7 @@ pub fn dijkstra_graph(
         estimated_cost: 0,
         cost: 0,
         index: 0,
+        tie_break: 0,
     });
 
     let mut visited: FxIndexMap<UVec3, (usize, u32)> = FxIndexMap::default();
@@ -206,6 +322,7 @@ pub fn dijkstra_graph(
                 estimated_cost: new_cost,
                 cost: new_cost,
                 index: n,
+                tie_break: 0,
             });
         }
     }
@@ -258,6 +375,7 @@ mod tests {
         ];
 
         let paths = dijkstra_grid(
+            grid.neighborhood(),
             &grid.view(),
             start,
             &goals,
@@ -1,13 +1,18 @@
 //! This module defines the `Dir` enum, which represents various directions in 3D space.
 
-use bevy::math::{IVec3, Vec3};
+use bevy::{
+    math::{IVec3, UVec3, Vec3},
+    reflect::Reflect,
+};
 use std::ops::Neg;
 
+use crate::neighbor::offset_index;
+
 pub use self::Dir::*;
 
 /// Enum that represents the 26 directions in 3D space.
 #[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
 #[repr(u8)]
 pub enum Dir {
     NorthWestDown = 0,
@@ -116,6 +121,13 @@ impl Dir {
         }
     }
 
+    /// Returns the direction of a move from `from` to `to`, or `None` if they're the same position.
+    /// Moves longer than one cell, like the any-angle steps of [`crate::prelude::PathfindMode::Theta`] or portals,
+    /// are snapped to the closest of the 26 directions by the sign of each axis.
+    pub fn between(from: UVec3, to: UVec3) -> Option<Self> {
+        Self::from_offset((to.as_ivec3() - from.as_ivec3()).signum())
+    }
+
     /// All direction variants as an iterator.
     pub fn all() -> impl Iterator<Item = Dir> {
         (0..26).filter_map(Dir::from_bit_index)
@@ -214,6 +226,12 @@ impl Dir {
         Dir::from_offset(vec).expect("Not a valid direction")
     }
 
+    /// Returns the bit of this direction in the neighbor masks of a [`crate::nav::NavCell`],
+    /// see [`crate::grid::Grid::set_neighbor_bits`].
+    pub fn bit(self) -> u32 {
+        1 << offset_index(self.offset())
+    }
+
     /// Returns the opposite direction.
     pub fn opposite(self) -> Dir {
         self.offset()
@@ -0,0 +1,41 @@
+//! Observer events triggered by the [`crate::plugin::NorthstarPlugin`] systems.
+//!
+//! The events are triggered with the agent entity as the target, so they can be observed globally
+//! with `app.add_observer` or on a single agent with `commands.entity(agent).observe(...)`.
+//!
+//! The marker components in [`crate::components`] are still inserted alongside these events.
+use bevy::{
+    ecs::{entity::Entity, event::Event},
+    math::UVec3,
+};
+
+use crate::components::PathError;
+
+/// Triggered when an agent reaches its goal and the plugin removes its [`crate::path::Path`] and [`crate::components::Pathfind`].
+#[derive(Event, Debug, Clone)]
+pub struct PathCompleted {
+    /// The agent that reached its goal.
+    pub entity: Entity,
+    /// The goal that was reached.
+    pub goal: UVec3,
+}
+
+/// Triggered when the plugin fails to find or follow a path for an agent.
+/// The [`crate::components::PathfindingFailed`] marker is inserted on the agent at the same time unless
+/// the reason is [`PathError::AvoidanceFailed`]. That reason is only triggered once rerouting around the blocking
+/// agents fails as well, together with [`RerouteFailed`] and the [`crate::components::RerouteFailed`] marker.
+#[derive(Event, Debug, Clone)]
+pub struct PathfindingFailed {
+    /// The agent that failed to pathfind.
+    pub entity: Entity,
+    /// Why pathfinding failed.
+    pub reason: PathError,
+}
+
+/// Triggered when the `reroute_path` system fails to reroute an agent to its goal.
+/// The [`crate::components::RerouteFailed`] marker is inserted on the agent at the same time.
+#[derive(Event, Debug, Clone)]
+pub struct RerouteFailed {
+    /// The agent that failed to reroute.
+    pub entity: Entity,
+}
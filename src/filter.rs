@@ -6,7 +6,7 @@ use bevy::{
 use ndarray::ArrayView3;
 use std::fmt::Debug;
 
-use crate::{nav::NavCell, prelude::ORDINAL_3D_OFFSETS};
+use crate::{nav::NavCell, prelude::ORDINAL_3D_OFFSETS, MovementCost};
 
 /// [`NeighborFilter`] trait to add custom filtering logic to the neighbors returned by the [`crate::neighbor::Neighborhood::neighbors()`] method.
 /// Add a filter to the grid settings with [`crate::grid::GridSettingsBuilder::add_neighbor_filter()`].
@@ -15,6 +15,25 @@ pub trait NeighborFilter {
     fn filter(&self, pos: UVec3, mask: u32, grid: &ArrayView3<NavCell>) -> u32;
 }
 
+/// [`NeighborProvider`] trait to add or remove the moves out of a cell, for connectivity a regular grid neighborhood can't express
+/// such as portal links or one-way ledges.
+/// Set a provider in the grid settings with [`crate::grid::GridSettingsBuilder::neighbor_provider()`].
+///
+/// The provider is consulted for every cell when the grid is built, after the [`crate::neighbor::Neighborhood`] and its [`NeighborFilter`]s.
+/// The HPA* hierarchy doesn't know about the extra links so [`crate::grid::Grid::find_path`] and the plugin
+/// search a grid with a provider using A*, regardless of the requested [`crate::components::PathfindMode`].
+pub trait NeighborProvider {
+    /// Edits the moves out of `pos`. `neighbors` holds the positions the neighborhood allows moving to
+    /// with the cost of entering each one. Remove entries to disallow moves, or push a position with its
+    /// traversal cost to add a link to any cell in the grid. Links can be one-way.
+    fn neighbors(
+        &self,
+        pos: UVec3,
+        grid: &ArrayView3<NavCell>,
+        neighbors: &mut Vec<(UVec3, MovementCost)>,
+    );
+}
+
 /// Disallow diagonal movement if both adjacent cardinals in the direction of movement are solid.
 /// Prevents agents from clipping through wall corners like this where is x is a wall:
 /// |x|/|
@@ -206,6 +225,28 @@ impl NeighborFilter for DisallowDiagonalZMovement {
     }
 }
 
+/// Disallow all diagonal movement, only cardinal moves remain.
+/// Useful to restrict an ordinal neighborhood per map without changing the grid type.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoDiagonalMovement;
+
+impl NeighborFilter for NoDiagonalMovement {
+    fn filter(&self, _pos: UVec3, mut mask: u32, _grid: &ArrayView3<NavCell>) -> u32 {
+        for (i, &offset) in ORDINAL_3D_OFFSETS.iter().enumerate() {
+            if (mask >> i) & 1 == 0 {
+                continue;
+            }
+
+            // Disallow any movement along more than one axis
+            if offset.abs().element_sum() > 1 {
+                mask &= !(1 << i);
+            }
+        }
+
+        mask
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::nav::Nav;
@@ -295,4 +336,21 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_no_diagonal_movement() {
+        let grid = Array3::<NavCell>::default((3, 3, 3));
+        let pos = UVec3::new(1, 1, 1);
+
+        let filtered_mask = NoDiagonalMovement.filter(pos, u32::MAX, &grid.view());
+
+        for (i, offset) in ORDINAL_3D_OFFSETS.iter().enumerate() {
+            let bit_set = (filtered_mask >> i) & 1 == 1;
+            assert_eq!(
+                bit_set,
+                offset.abs().element_sum() == 1,
+                "Direction {offset:?} has the wrong state"
+            );
+        }
+    }
 }
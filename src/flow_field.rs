@@ -0,0 +1,429 @@
+//! Flow fields for moving many agents towards a single goal, and distance fields to a set of sources.
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{
+    math::{IVec3, UVec3},
+    platform::collections::{HashMap, HashSet},
+};
+use ndarray::Array3;
+
+use crate::{
+    grid::Grid,
+    neighbor::{offset_index, Neighborhood, ORDINAL_3D_OFFSETS},
+    path::Path,
+    MovementCost,
+};
+
+/// A flow field (also known as a Dijkstra map) pointing every reachable cell in the grid towards a single goal.
+///
+/// The field is generated once with [`Grid::compute_flow_field`] and can then be shared by any number of agents
+/// heading to the same goal. Use [`FlowField::next`] to get the next position to move to from any cell.
+///
+/// A field becomes stale when chunks it covers are rebuilt, check [`FlowField::is_valid`] before reusing it.
+/// Dynamic [`crate::components::Blocking`] entities are not taken into account.
+#[derive(Debug, Clone)]
+pub struct FlowField {
+    goal: UVec3,
+    // The next position to move to for every cell. `None` if the cell can't reach the goal.
+    next: Array3<Option<UVec3>>,
+    // The accumulated movement cost to the goal for every cell.
+    costs: Array3<MovementCost>,
+    // Versions of the chunks the field covers, used to detect grid changes.
+    chunk_versions: HashMap<(usize, usize, usize), u32>,
+}
+
+impl FlowField {
+    /// Returns the goal the field flows towards.
+    pub fn goal(&self) -> UVec3 {
+        self.goal
+    }
+
+    /// Returns the next position to move to from `pos` to get closer to the goal.
+    /// Returns `None` if `pos` is the goal, out of bounds, or can't reach the goal.
+    pub fn next(&self, pos: UVec3) -> Option<UVec3> {
+        self.next
+            .get([pos.x as usize, pos.y as usize, pos.z as usize])
+            .copied()
+            .flatten()
+    }
+
+    /// Returns the total movement cost from `pos` to the goal.
+    /// Returns `None` if `pos` is out of bounds or can't reach the goal.
+    pub fn cost(&self, pos: UVec3) -> Option<MovementCost> {
+        self.costs
+            .get([pos.x as usize, pos.y as usize, pos.z as usize])
+            .copied()
+            .filter(|cost| *cost != MovementCost::MAX)
+    }
+
+    /// Returns true if the goal can be reached from `pos`.
+    pub fn is_reachable(&self, pos: UVec3) -> bool {
+        self.cost(pos).is_some()
+    }
+
+    /// Follows the field from `start` to the goal and returns it as a [`Path`].
+    /// Like the other pathfinding methods, the returned path doesn't include `start`.
+    /// Returns `None` if the goal can't be reached from `start`.
+    pub fn path_from(&self, start: UVec3) -> Option<Path> {
+        let cost = self.cost(start)?;
+
+        let mut steps = Vec::new();
+        let mut current = start;
+
+        while current != self.goal {
+            current = self.next(current)?;
+            steps.push(current);
+        }
+
+        Some(Path::new(steps, cost))
+    }
+
+    /// Returns false if any chunk the field covers, or borders, has been rebuilt since the field was computed.
+    pub fn is_valid<N: Neighborhood>(&self, grid: &Grid<N>) -> bool {
+        !self.chunk_versions.is_empty()
+            && self
+                .chunk_versions
+                .iter()
+                .all(|(index, version)| grid.chunk_version(*index) == Some(*version))
+    }
+}
+
+/// A distance field holding the movement cost from every cell in the grid to the nearest of a set of sources.
+///
+/// Generated with [`Grid::distance_field`] using the same expansion as [`FlowField`],
+/// but only keeps the scalar costs. Useful for influence and threat maps, for example the cost for every cell
+/// to reach the nearest enemy.
+///
+/// Like flow fields, a distance field becomes stale when chunks it covers are rebuilt, see [`DistanceField::is_valid`].
+#[derive(Debug, Clone)]
+pub struct DistanceField {
+    sources: Vec<UVec3>,
+    // The accumulated movement cost to the nearest source for every cell.
+    costs: Array3<MovementCost>,
+    // Versions of the chunks the field covers, used to detect grid changes.
+    chunk_versions: HashMap<(usize, usize, usize), u32>,
+}
+
+impl DistanceField {
+    /// Returns the sources the distances are measured to.
+    pub fn sources(&self) -> &[UVec3] {
+        &self.sources
+    }
+
+    /// Returns the movement cost from `pos` to the nearest source.
+    /// Returns `None` if `pos` is out of bounds, can't reach a source, or is further than the `max_cost` of the field.
+    pub fn get(&self, pos: UVec3) -> Option<MovementCost> {
+        self.costs
+            .get([pos.x as usize, pos.y as usize, pos.z as usize])
+            .copied()
+            .filter(|cost| *cost != MovementCost::MAX)
+    }
+
+    /// Iterates over every cell that can reach a source and its movement cost to the nearest source.
+    pub fn iter(&self) -> impl Iterator<Item = (UVec3, MovementCost)> + '_ {
+        self.costs
+            .indexed_iter()
+            .filter(|(_, cost)| **cost != MovementCost::MAX)
+            .map(|((x, y, z), cost)| (UVec3::new(x as u32, y as u32, z as u32), *cost))
+    }
+
+    /// Returns false if any chunk the field covers, or borders, has been rebuilt since the field was computed.
+    pub fn is_valid<N: Neighborhood>(&self, grid: &Grid<N>) -> bool {
+        !self.chunk_versions.is_empty()
+            && self
+                .chunk_versions
+                .iter()
+                .all(|(index, version)| grid.chunk_version(*index) == Some(*version))
+    }
+}
+
+/// Dijkstra expansion outwards from the goal following the precomputed neighbors in reverse.
+pub(crate) fn compute_flow_field<N: Neighborhood>(grid: &Grid<N>, goal: UVec3) -> FlowField {
+    let Expansion {
+        next,
+        costs,
+        chunk_versions,
+    } = expand(grid, &[goal], MovementCost::MAX);
+
+    FlowField {
+        goal,
+        next,
+        costs,
+        chunk_versions,
+    }
+}
+
+/// Multi-source Dijkstra expansion, skipping the cells that cost more than `max_cost` to reach a source.
+pub(crate) fn compute_distance_field<N: Neighborhood>(
+    grid: &Grid<N>,
+    sources: &[UVec3],
+    max_cost: MovementCost,
+) -> DistanceField {
+    let Expansion {
+        costs,
+        chunk_versions,
+        ..
+    } = expand(grid, sources, max_cost);
+
+    DistanceField {
+        sources: sources.to_vec(),
+        costs,
+        chunk_versions,
+    }
+}
+
+struct Expansion {
+    next: Array3<Option<UVec3>>,
+    costs: Array3<MovementCost>,
+    chunk_versions: HashMap<(usize, usize, usize), u32>,
+}
+
+// Expands outwards from the passable `sources` following the precomputed neighbors in reverse,
+// so the costs are the cost of moving from each cell to the nearest source.
+fn expand<N: Neighborhood>(grid: &Grid<N>, sources: &[UVec3], max_cost: MovementCost) -> Expansion {
+    let view = grid.view();
+    let shape = view.raw_dim();
+
+    let mut next: Array3<Option<UVec3>> = Array3::from_elem(shape, None);
+    let mut costs: Array3<MovementCost> = Array3::from_elem(shape, MovementCost::MAX);
+
+    let sources = sources
+        .iter()
+        .copied()
+        .filter(|source| grid.in_bounds(*source) && grid.is_passable(*source))
+        .collect::<Vec<_>>();
+
+    if grid.needs_build() || sources.is_empty() {
+        return Expansion {
+            next,
+            costs,
+            chunk_versions: HashMap::new(),
+        };
+    }
+
+    // Portals only store their outgoing target, so map targets back to their sources.
+    let mut portal_sources: HashMap<UVec3, Vec<UVec3>> = HashMap::new();
+    for ((x, y, z), cell) in view.indexed_iter() {
+        for target in &cell.special_neighbors {
+            portal_sources
+                .entry(*target)
+                .or_default()
+                .push(UVec3::new(x as u32, y as u32, z as u32));
+        }
+    }
+
+    let mut reached_chunks = HashSet::new();
+    let mut to_visit = BinaryHeap::new();
+
+    for source in sources {
+        costs[[source.x as usize, source.y as usize, source.z as usize]] = 0;
+        to_visit.push(Reverse((0, source.x, source.y, source.z)));
+    }
+
+    while let Some(Reverse((cost, x, y, z))) = to_visit.pop() {
+        let current = UVec3::new(x, y, z);
+
+        if cost > costs[[x as usize, y as usize, z as usize]] {
+            continue;
+        }
+
+        reached_chunks.insert(grid.chunk_index_of(current));
+
+        let adjacent = ORDINAL_3D_OFFSETS.iter().filter_map(|offset| {
+            let source = current.as_ivec3() + *offset;
+            if source.cmplt(IVec3::ZERO).any() || !grid.in_bounds(source.as_uvec3()) {
+                return None;
+            }
+
+            let source = source.as_uvec3();
+            // Can the source cell step into the current cell?
+            // Impassable cells still store their passable neighbors, but nothing can stand on them
+            let bit = offset_index(-*offset);
+            let cell = grid.navcell(source);
+            if cell.is_impassable() || cell.neighbor_bits & (1 << bit) == 0 {
+                return None;
+            }
+
+            Some(source)
+        });
+
+        let portals = portal_sources.get(&current).into_iter().flatten().copied();
+
+        for source in adjacent.chain(portals) {
+            // Moving into the current cell costs the current cell's cost, unless the source links to it with its own cost
+            let step_cost = grid.neighborhood().step_cost(
+                source,
+                current,
+                grid.navcell(source)
+                    .move_cost(source, current, grid.navcell(current)),
+            );
+            let new_cost = cost.saturating_add(step_cost);
+            if new_cost > max_cost {
+                continue;
+            }
+
+            let index = [source.x as usize, source.y as usize, source.z as usize];
+
+            if new_cost < costs[index] {
+                costs[index] = new_cost;
+                next[index] = Some(current);
+                to_visit.push(Reverse((new_cost, source.x, source.y, source.z)));
+            }
+        }
+    }
+
+    // Track the reached chunks and the chunks bordering them
+    let mut chunk_versions = HashMap::new();
+    for (cx, cy, cz) in reached_chunks {
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let index = (cx as isize + dx, cy as isize + dy, cz as isize + dz);
+                    if !grid.chunk_in_bounds(index.0, index.1, index.2) {
+                        continue;
+                    }
+
+                    let index = (index.0 as usize, index.1 as usize, index.2 as usize);
+                    if let Some(version) = grid.chunk_version(index) {
+                        chunk_versions.insert(index, version);
+                    }
+                }
+            }
+        }
+    }
+
+    Expansion {
+        next,
+        costs,
+        chunk_versions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::UVec3;
+
+    use crate::{
+        grid::{Grid, GridSettingsBuilder},
+        nav::Nav,
+        neighbor::{CardinalNeighborhood, OrdinalNeighborhood},
+    };
+
+    #[test]
+    fn test_flow_field_points_to_goal() {
+        let grid_settings = GridSettingsBuilder::new_2d(12, 12).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&grid_settings);
+
+        for y in 0..10 {
+            grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let goal = UVec3::new(11, 0, 0);
+        let field = grid.compute_flow_field(goal);
+
+        assert_eq!(field.goal(), goal);
+        assert_eq!(field.cost(goal), Some(0));
+        assert_eq!(field.next(goal), None);
+        assert!(!field.is_reachable(UVec3::new(6, 0, 0)));
+
+        let start = UVec3::new(0, 0, 0);
+        let path = field.path_from(start).unwrap();
+        let astar = grid
+            .pathfind_astar(start, goal, &Default::default(), false)
+            .unwrap();
+
+        assert_eq!(path.path().last(), Some(&goal));
+        assert_eq!(path.cost(), astar.cost());
+        assert_eq!(path.len(), astar.len());
+    }
+
+    #[test]
+    fn test_flow_field_unreachable() {
+        let grid_settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&grid_settings);
+
+        for y in 0..8 {
+            grid.set_nav(UVec3::new(4, y, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let field = grid.compute_flow_field(UVec3::new(7, 7, 0));
+
+        assert!(field.path_from(UVec3::new(0, 0, 0)).is_none());
+        assert!(field.next(UVec3::new(0, 0, 0)).is_none());
+        assert!(field.next(UVec3::new(5, 5, 0)).is_some());
+    }
+
+    #[test]
+    fn test_distance_field() {
+        let grid_settings = GridSettingsBuilder::new_2d(12, 12).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&grid_settings);
+
+        for y in 0..12 {
+            grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
+        }
+        grid.set_nav(UVec3::new(2, 0, 0), Nav::Passable(5));
+        grid.build();
+
+        let sources = [UVec3::new(0, 0, 0), UVec3::new(0, 11, 0)];
+        let field = grid.distance_field(&sources, None);
+
+        assert_eq!(field.sources(), &sources);
+        assert_eq!(field.get(UVec3::new(0, 0, 0)), Some(0));
+        assert_eq!(field.get(UVec3::new(0, 11, 0)), Some(0));
+        assert_eq!(field.get(UVec3::new(0, 9, 0)), Some(2));
+        assert_eq!(field.get(UVec3::new(3, 0, 0)), Some(5));
+        assert_eq!(field.get(UVec3::new(6, 0, 0)), None);
+        assert_eq!(field.get(UVec3::new(7, 0, 0)), None);
+        assert_eq!(field.get(UVec3::new(12, 0, 0)), None);
+        assert_eq!(field.iter().count(), 6 * 12);
+
+        // Every cell costs the same as a path to the nearest source
+        for (pos, cost) in field.iter() {
+            if sources.contains(&pos) {
+                continue;
+            }
+
+            let nearest = sources
+                .iter()
+                .filter_map(|source| {
+                    grid.pathfind_astar(pos, *source, &Default::default(), false)
+                        .map(|path| path.cost())
+                })
+                .min()
+                .unwrap();
+            assert_eq!(cost, nearest, "{pos}");
+        }
+
+        let local = grid.distance_field(&sources, Some(3));
+        assert!(local.iter().all(|(_, cost)| cost <= 3));
+        assert_eq!(local.get(UVec3::new(0, 3, 0)), Some(3));
+        assert_eq!(local.get(UVec3::new(0, 4, 0)), None);
+        assert!(local.is_valid(&grid));
+    }
+
+    #[test]
+    fn test_flow_field_invalidation() {
+        let grid_settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&grid_settings);
+
+        // Wall off the left half of the map so the field only covers the right side.
+        for y in 0..16 {
+            grid.set_nav(UVec3::new(7, y, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let field = grid.compute_flow_field(UVec3::new(15, 15, 0));
+        assert!(field.is_valid(&grid));
+
+        // Changing a chunk far away from the reached area doesn't invalidate the field.
+        grid.set_nav(UVec3::new(0, 0, 0), Nav::Passable(2));
+        grid.build();
+        assert!(field.is_valid(&grid));
+
+        grid.set_nav(UVec3::new(12, 12, 0), Nav::Impassable);
+        grid.build();
+        assert!(!field.is_valid(&grid));
+    }
+}
@@ -0,0 +1,161 @@
+//! Goal descriptions shared by the grid search algorithms.
+use bevy::math::UVec3;
+
+use crate::neighbor::{chebyshev_distance, hex_distance, manhattan_distance, Neighborhood};
+
+/// What a grid search is trying to reach.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum SearchGoal<'a> {
+    /// A single goal position.
+    Position(UVec3),
+    /// Reaching any of the positions completes the search.
+    Any(&'a [UVec3]),
+    /// Reaching any cell inside the box between `min` and `max` (inclusive) completes the search.
+    Region {
+        /// The minimum corner of the region.
+        min: UVec3,
+        /// The maximum corner of the region.
+        max: UVec3,
+    },
+    /// Reaching any cell within `radius` steps of one of the centers completes the search.
+    Within {
+        /// The positions the distance is measured from.
+        centers: &'a [UVec3],
+        /// The maximum number of steps from a center.
+        radius: u32,
+        /// The step distance of the neighborhood, see [`SearchGoal::within`].
+        distance: fn(UVec3, UVec3) -> u32,
+    },
+}
+
+impl<'a> SearchGoal<'a> {
+    /// Creates a goal from a slice, using [`SearchGoal::Position`] when there's only one.
+    pub(crate) fn from_slice(goals: &'a [UVec3]) -> Self {
+        match goals {
+            [goal] => SearchGoal::Position(*goal),
+            _ => SearchGoal::Any(goals),
+        }
+    }
+
+    /// Creates a goal reached within `radius` steps of any of the `centers`,
+    /// counting steps the same way as [`Neighborhood::step_distance`].
+    pub(crate) fn within<N: Neighborhood>(
+        neighborhood: &N,
+        centers: &'a [UVec3],
+        radius: u32,
+    ) -> Self {
+        let distance: fn(UVec3, UVec3) -> u32 = if neighborhood.is_hex() {
+            hex_distance
+        } else if neighborhood.is_ordinal() {
+            chebyshev_distance
+        } else {
+            manhattan_distance
+        };
+
+        SearchGoal::Within {
+            centers,
+            radius,
+            distance,
+        }
+    }
+
+    /// Returns the goal positions, empty for [`SearchGoal::Region`] and [`SearchGoal::Within`].
+    /// The centers of a [`SearchGoal::Within`] don't have to be reached, or even be passable.
+    pub(crate) fn positions(&self) -> &[UVec3] {
+        match self {
+            SearchGoal::Position(goal) => std::slice::from_ref(goal),
+            SearchGoal::Any(goals) => goals,
+            SearchGoal::Region { .. } | SearchGoal::Within { .. } => &[],
+        }
+    }
+
+    /// Returns true if `pos` satisfies the goal.
+    #[inline(always)]
+    pub(crate) fn is_goal(&self, pos: UVec3) -> bool {
+        match self {
+            SearchGoal::Position(goal) => *goal == pos,
+            SearchGoal::Any(goals) => goals.contains(&pos),
+            SearchGoal::Region { min, max } => pos.cmpge(*min).all() && pos.cmple(*max).all(),
+            SearchGoal::Within {
+                centers,
+                radius,
+                distance,
+            } => centers
+                .iter()
+                .any(|center| distance(*center, pos) <= *radius),
+        }
+    }
+
+    /// Returns the heuristic from `pos` to the goal.
+    /// For multiple goals this is the minimum heuristic over all of them.
+    #[inline(always)]
+    pub(crate) fn heuristic<N: Neighborhood>(&self, neighborhood: &N, pos: UVec3) -> u32 {
+        match self {
+            SearchGoal::Position(goal) => neighborhood.heuristic(pos, *goal),
+            SearchGoal::Any(goals) => goals
+                .iter()
+                .map(|goal| neighborhood.heuristic(pos, *goal))
+                .min()
+                .unwrap_or(0),
+            // Distance to the closest cell of the region
+            SearchGoal::Region { min, max } => neighborhood.heuristic(pos, pos.clamp(*min, *max)),
+            // Distance to the closest center, less the steps the radius saves
+            SearchGoal::Within {
+                centers, radius, ..
+            } => centers
+                .iter()
+                .map(|center| neighborhood.heuristic(pos, *center).saturating_sub(*radius))
+                .min()
+                .unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighbor::{CardinalNeighborhood, OrdinalNeighborhood};
+
+    #[test]
+    fn test_multi_goal_heuristic() {
+        let neighborhood = CardinalNeighborhood::default();
+        let goals = [UVec3::new(10, 0, 0), UVec3::new(2, 2, 0)];
+        let goal = SearchGoal::Any(&goals);
+
+        assert_eq!(goal.heuristic(&neighborhood, UVec3::new(0, 0, 0)), 4);
+        assert!(goal.is_goal(UVec3::new(2, 2, 0)));
+        assert!(!goal.is_goal(UVec3::new(0, 0, 0)));
+    }
+
+    #[test]
+    fn test_region_goal_heuristic() {
+        let neighborhood = CardinalNeighborhood::default();
+        let goal = SearchGoal::Region {
+            min: UVec3::new(4, 4, 0),
+            max: UVec3::new(8, 6, 0),
+        };
+
+        assert_eq!(goal.heuristic(&neighborhood, UVec3::new(0, 5, 0)), 4);
+        assert_eq!(goal.heuristic(&neighborhood, UVec3::new(10, 10, 0)), 6);
+        assert_eq!(goal.heuristic(&neighborhood, UVec3::new(6, 5, 0)), 0);
+        assert!(goal.is_goal(UVec3::new(8, 4, 0)));
+        assert!(!goal.is_goal(UVec3::new(9, 4, 0)));
+    }
+
+    #[test]
+    fn test_within_goal_heuristic() {
+        let neighborhood = CardinalNeighborhood::default();
+        let centers = [UVec3::new(10, 10, 0)];
+        let goal = SearchGoal::within(&neighborhood, &centers, 3);
+
+        assert_eq!(goal.heuristic(&neighborhood, UVec3::new(0, 10, 0)), 7);
+        assert_eq!(goal.heuristic(&neighborhood, UVec3::new(9, 9, 0)), 0);
+        assert!(goal.is_goal(UVec3::new(8, 11, 0)));
+        assert!(!goal.is_goal(UVec3::new(8, 12, 0)));
+
+        // Diagonal steps count as one
+        let neighborhood = OrdinalNeighborhood::default();
+        let goal = SearchGoal::within(&neighborhood, &centers, 3);
+        assert!(goal.is_goal(UVec3::new(7, 13, 0)));
+    }
+}
@@ -4,6 +4,7 @@ use bevy::{log, math::UVec3, platform::collections::HashMap};
 use crate::{chunk::Chunk, dir::Dir, node::Node, path::Path, NodeId};
 
 /// A graph structure that holds nodes and their connections (edges).
+#[derive(Clone)]
 pub(crate) struct Graph {
     /// `Node` storage.
     nodes: slab::Slab<Node>,
@@ -135,6 +136,25 @@ impl Graph {
         }
     }*/
 
+    /// Remove every `Node` in the `Chunk` and the edges pointing to them.
+    pub(crate) fn remove_nodes_in_chunk(&mut self, chunk: &Chunk) {
+        let positions: Vec<UVec3> = self
+            .nodes_in_chunk(chunk)
+            .iter()
+            .map(|node| node.pos)
+            .collect();
+
+        for pos in &positions {
+            if let Some(id) = self.node_ids.remove(pos) {
+                self.nodes.remove(id);
+            }
+        }
+
+        for node in self.nodes.iter_mut() {
+            node.1.remove_edges_to_positions(&positions);
+        }
+    }
+
     pub(crate) fn remove_edges_for_chunk(&mut self, chunk: &Chunk) {
         // Get all the ndoes in the chunk
         let nodes_in_chunk = self.nodes_in_chunk(chunk);
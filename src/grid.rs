@@ -1,39 +1,124 @@
 //! This module contains the `Grid` component which is the main component for the crate.
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc, time::Duration};
 
 use bevy::{
     log,
-    math::{IVec3, UVec3},
+    math::{BVec3, I64Vec3, IVec3, UVec2, UVec3},
     platform::collections::{HashMap, HashSet},
     prelude::{Component, Entity},
+    tasks::{AsyncComputeTaskPool, Task, TaskPool},
 };
 use ndarray::{s, Array2, Array3, ArrayView1, ArrayView2, ArrayView3, Zip};
+use rand::RngCore;
+use smallvec::SmallVec;
 
 use crate::{
+    astar::SearchContext,
     chunk::Chunk,
+    components::{PathError, PathfindMode},
     dijkstra::*,
     dir::*,
-    filter::NeighborFilter,
+    filter::{NeighborFilter, NeighborProvider},
     flood_fill::flood_fill_bool_mask,
+    flow_field::{compute_distance_field, compute_flow_field, DistanceField, FlowField},
+    goal::SearchGoal,
     graph::Graph,
-    nav::{Nav, NavCell, Portal},
-    neighbor::Neighborhood,
+    nav::{Nav, NavCell, NavLayers, Portal, VerticalLinks, ALL_NAV_LAYERS},
+    neighbor::{
+        offset_index, DiagonalCost, HeightHeuristic, HeuristicKind, HeuristicOverride,
+        Neighborhood, WrappedHeuristic, ALL_EXITS, ORDINAL_3D_OFFSETS,
+    },
     node::Node,
     path::Path,
-    pathfind::{pathfind, pathfind_astar, reroute_path},
-    position_in_cubic_window, timed, MovementCost,
+    pathfind::{
+        pathfind, pathfind_astar, pathfind_astar_goals, pathfind_goals, pathfind_jps,
+        pathfind_region, pathfind_theta, pathfind_within, repair_path, reroute_path,
+    },
+    position_in_cubic_window,
+    raycast::bresenham_walk,
+    timed,
+    trace::SearchTrace,
+    MovementCost,
 };
 
+mod build_task;
+mod builder;
+mod cache;
+mod connectivity;
+mod entrance;
+#[cfg(feature = "explain")]
+mod explain;
+mod import;
+mod projection;
+mod route_cache;
+#[cfg(feature = "serde")]
+mod serialize;
+mod snapshot;
+#[cfg(feature = "svg")]
+mod svg;
+mod validate;
+
+pub use build_task::GridBuildTask;
+pub use builder::GridBuilder;
+pub use cache::CacheStats;
+use connectivity::Connectivity;
+pub use entrance::Entrance;
+#[cfg(feature = "explain")]
+pub use explain::{ExplainedStep, PathExplanation, RejectReason, RejectedNeighbor};
+#[cfg(feature = "gui-debug")]
+pub(crate) use projection::{
+    isometric_to_cell, isometric_to_world, square_to_cell, square_to_world,
+};
+use route_cache::RouteCache;
+pub use snapshot::GridSnapshot;
+#[cfg(feature = "svg")]
+pub use svg::SvgProjection;
+pub use validate::GridInconsistency;
+
+/// Errors returned by fallible [`Grid`] operations.
+#[derive(Debug, thiserror::Error)]
+pub enum GridError {
+    /// The data doesn't start with a serialized grid header.
+    #[error("data is not a serialized grid")]
+    InvalidHeader,
+    /// The data was serialized by an incompatible version of the crate.
+    #[error("unsupported grid format version {found}, expected version {expected}")]
+    UnsupportedVersion {
+        /// The format version found in the header.
+        found: u32,
+        /// The format version this version of the crate reads.
+        expected: u32,
+    },
+    /// The data is truncated or corrupted.
+    #[error("failed to decode grid: {0}")]
+    Corrupted(String),
+    /// The serialized grid doesn't match the provided settings.
+    #[error("serialized grid doesn't match the settings: {0}")]
+    SettingsMismatch(String),
+    /// The position is outside of the grid.
+    #[error("position {0} is out of bounds")]
+    OutOfBounds(UVec3),
+    /// The options of a [`GridBuilder`] don't make a valid grid.
+    #[error("invalid grid settings: {0}")]
+    InvalidSettings(String),
+    /// The cell data passed to a constructor such as [`Grid::from_costs`] doesn't match the grid dimensions in the settings.
+    #[error("cell data doesn't match the grid dimensions: {0}")]
+    DimensionMismatch(String),
+    /// The image passed to [`Grid::from_image`] couldn't be read.
+    #[cfg(feature = "image")]
+    #[error("failed to read image: {0}")]
+    Image(String),
+}
+
 /// Settings for how the grid is divided into chunks.
 #[derive(Copy, Clone, Debug)]
 pub struct ChunkSettings {
-    /// The square size of each chunk in the grid.
-    /// Needs to be at least 3.
-    pub size: u32,
-    /// The depth of each chunk in the grid when using 3D grids.
-    pub depth: u32,
+    /// The width (x), height (y) and depth (z) of each chunk in the grid.
+    /// The width and height need to be at least 3 and the depth at least 1.
+    /// If the grid dimensions aren't divisible by the size, the chunks along the far edges are smaller.
+    pub size: UVec3,
     /// If true, allows corner connections between chunks.
     /// This will increase the time it takes to build the grid.
     /// It generally isn't recommended as the path refinement step should handle corners already unless you have a noisy tilemap.
@@ -43,8 +128,7 @@ pub struct ChunkSettings {
 impl Default for ChunkSettings {
     fn default() -> Self {
         ChunkSettings {
-            size: 16,
-            depth: 1,
+            size: UVec3::new(16, 16, 1),
             diagonal_connections: false,
         }
     }
@@ -87,12 +171,61 @@ impl Default for CollisionSettings {
     }
 }
 
+/// The movement cost of each unit of height difference between two cells, set with [`GridSettingsBuilder::height_cost()`].
+///
+/// For 2.5D maps such as isometric terrain, where every cell sits on a single layer but has an elevation
+/// set with [`Grid::set_cell_height`]. Stepping between cells costs the height difference times this cost on top of
+/// the cost of the cell being entered, and the heuristic adds the height difference to the goal the same way,
+/// so paths walk around hills and ridges rather than climbing over them. The default of 0 ignores heights.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeightCost(pub MovementCost);
+
+/// How agents are allowed to move diagonally, set with [`GridSettingsBuilder::diagonal_movement()`].
+///
+/// Diagonal movement also requires an ordinal [`Neighborhood`] such as [`crate::neighbor::OrdinalNeighborhood`].
+/// The policy is applied as a [`NeighborFilter`] so it's respected by every pathfinding mode,
+/// HPA* path refinement, and chunk entrance connections.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DiagonalMovement {
+    /// Diagonal moves are allowed whenever the [`Neighborhood`] allows them.
+    #[default]
+    Always,
+    /// Only cardinal moves are allowed. See [`crate::filter::NoDiagonalMovement`].
+    Never,
+    /// Diagonal moves are disallowed when both adjacent cardinal cells are solid,
+    /// agents can't squeeze between two diagonal walls. See [`crate::filter::NoCornerClipping`].
+    NoCornerClipping,
+    /// Diagonal moves are disallowed when any adjacent cardinal cell is solid,
+    /// agents can't cut around wall corners. See [`crate::filter::NoCornerCutting`].
+    NoCornerCutting,
+}
+
+impl DiagonalMovement {
+    // The filter applying the policy, `None` for `Always`.
+    pub(crate) fn filter(&self) -> Option<Arc<dyn NeighborFilter + Send + Sync + 'static>> {
+        match self {
+            DiagonalMovement::Always => None,
+            DiagonalMovement::Never => Some(Arc::new(crate::filter::NoDiagonalMovement)),
+            DiagonalMovement::NoCornerClipping => Some(Arc::new(crate::filter::NoCornerClipping)),
+            DiagonalMovement::NoCornerCutting => Some(Arc::new(crate::filter::NoCornerCutting)),
+        }
+    }
+}
+
 /// Settings for filtering determined neighbors.
 #[derive(Clone, Default)]
 pub struct NeighborhoodSettings {
     /// Provide a `Vec` of [`NeighborFilter`]s to apply custom filtering logic.
     /// The filters will be chained in order added.
     pub filters: Vec<Arc<dyn NeighborFilter + Send + Sync + 'static>>,
+    /// The [`HeuristicKind`] used by the built-in neighborhoods.
+    /// `None` uses the neighborhood's own heuristic.
+    pub heuristic: Option<HeuristicKind>,
+    /// Weighted straight and diagonal step costs used by the ordinal neighborhoods.
+    /// `None` makes every step cost the same.
+    pub diagonal_cost: Option<DiagonalCost>,
+    /// Optional [`NeighborProvider`] to add or remove links after the neighbors are computed.
+    pub provider: Option<Arc<dyn NeighborProvider + Send + Sync + 'static>>,
 }
 
 /// Holder for internal crate settings.
@@ -122,6 +255,9 @@ pub struct GridSettingsBuilder {
     cost_settings: NavSettings,
     collision_settings: CollisionSettings,
     neighborhood_settings: NeighborhoodSettings,
+    wrap: BVec3,
+    height_cost: HeightCost,
+    diagonal_movement: DiagonalMovement,
 }
 
 impl Default for GridSettingsBuilder {
@@ -132,6 +268,9 @@ impl Default for GridSettingsBuilder {
             cost_settings: NavSettings::default(),
             collision_settings: CollisionSettings::default(),
             neighborhood_settings: NeighborhoodSettings::default(),
+            wrap: BVec3::FALSE,
+            height_cost: HeightCost::default(),
+            diagonal_movement: DiagonalMovement::default(),
         }
     }
 }
@@ -148,7 +287,7 @@ impl GridSettingsBuilder {
             ..Default::default()
         };
 
-        grid_settings.chunk_settings.depth = 1;
+        grid_settings.chunk_settings.size.z = 1;
 
         grid_settings
     }
@@ -171,26 +310,46 @@ impl GridSettingsBuilder {
 
     /// Size of each square chunk the grid is divided into.
     /// Must be at least 3.
+    ///
+    /// Smaller chunks have more entrances, which gives more accurate HPA* paths at the cost of
+    /// more memory and a longer build. Larger chunks build faster and use less memory but the coarse paths are less accurate.
     pub fn chunk_size(mut self, chunk_size: u32) -> Self {
         if chunk_size < 3 {
             panic!("Chunk size must be at least 3");
         }
 
-        self.chunk_settings.size = chunk_size;
+        self.chunk_settings.size.x = chunk_size;
+        self.chunk_settings.size.y = chunk_size;
         self
     }
 
     /// Depth (Z) of each chunk in the grid when using 3D grids.
-    /// Must be at least 1 and the grid's depth must be divisible by the chunk depth.
+    /// Must be at least 1.
     pub fn chunk_depth(mut self, chunk_depth: u32) -> Self {
         if chunk_depth < 1 {
             panic!("Chunk depth must be at least 1");
         }
 
-        self.chunk_settings.depth = chunk_depth;
+        self.chunk_settings.size.z = chunk_depth;
         self
     }
 
+    /// Width (x), height (y) and depth (z) of each chunk the grid is divided into.
+    /// The width and height must be at least 3 and the depth at least 1.
+    ///
+    /// The grid dimensions don't need to be divisible by the chunk size, the chunks along the far edges will be smaller.
+    /// See [`GridSettingsBuilder::chunk_size()`] for the tradeoffs of the chunk size.
+    pub fn chunk_dimensions(self, chunk_size: UVec3) -> Self {
+        if chunk_size.x < 3 || chunk_size.y < 3 {
+            panic!("Chunk width and height must be at least 3");
+        }
+
+        let mut settings = self.chunk_depth(chunk_size.z);
+        settings.chunk_settings.size.x = chunk_size.x;
+        settings.chunk_settings.size.y = chunk_size.y;
+        settings
+    }
+
     /// Enabling this will create extra HPA connections in the corners of the chunks if passable.
     /// This can create better paths in some cases but at the cost of performance.
     /// If you're only using refined HPA* (default) paths then this likely isn't needed.
@@ -235,11 +394,75 @@ impl GridSettingsBuilder {
         self
     }
 
+    /// Sets a [`NeighborProvider`] to add links such as portals, or remove moves such as one-way ledges,
+    /// for connectivity the grid neighborhood can't express.
+    /// [`Grid::find_path`] and the [`crate::plugin::NorthstarPlugin`] use A* for every [`PathfindMode`] while a provider is set,
+    /// since the HPA* hierarchy and the other algorithms don't know about the links.
+    pub fn neighbor_provider<P>(mut self, provider: P) -> Self
+    where
+        P: NeighborProvider + Send + Sync + 'static,
+    {
+        self.neighborhood_settings.provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Sets the [`HeuristicKind`] the grid's built-in neighborhood uses to estimate the remaining cost to a goal.
+    /// By default each neighborhood picks a heuristic that suits its movement, see [`HeuristicKind`] for which are admissible.
+    /// Individual requests can override this with [`crate::components::Pathfind::heuristic()`].
+    pub fn heuristic(mut self, heuristic: HeuristicKind) -> Self {
+        self.neighborhood_settings.heuristic = Some(heuristic);
+        self
+    }
+
+    /// Weighs straight steps by `straight` and diagonal steps by `diagonal` for the ordinal neighborhoods.
+    /// By default every step costs the same, which leaves many equally cheap paths that zigzag between
+    /// diagonal and straight steps. Use [`DiagonalCost::default()`]'s 10 and 14 to approximate √2
+    /// so paths take their diagonal steps in one run, the way a straight line would.
+    ///
+    /// Applies to every search and to the cached HPA* paths, path costs are scaled by `straight`.
+    pub fn diagonal_cost(mut self, straight: MovementCost, diagonal: MovementCost) -> Self {
+        self.neighborhood_settings.diagonal_cost = Some(DiagonalCost { straight, diagonal });
+        self
+    }
+
+    /// Sets how agents are allowed to move diagonally on this grid. See [`DiagonalMovement`] for options.
+    /// Defaults to [`DiagonalMovement::Always`].
+    pub fn diagonal_movement(mut self, diagonal_movement: DiagonalMovement) -> Self {
+        self.diagonal_movement = diagonal_movement;
+        if let Some(filter) = diagonal_movement.filter() {
+            self.neighborhood_settings.filters.push(filter);
+        }
+        self
+    }
+
+    /// Sets which axes of the grid wrap around, like the map of a strategy game that wraps at its east and west edges.
+    /// Stepping off a wrapped edge leads to the cell on the opposite edge, and the heuristics measure the shorter
+    /// of the wrapped and unwrapped distance so every search takes the shorter way around.
+    ///
+    /// Only straight steps cross the seam, diagonal moves step across it one axis at a time.
+    /// [`PathfindMode::Jps`] and [`PathfindMode::Theta`] searches use A* on wrapped grids.
+    /// Wrapping isn't supported on hex grids.
+    pub fn wrap(mut self, wrap: BVec3) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Sets the [`HeightCost`] of climbing or descending between cells of different heights, see [`Grid::set_cell_height`].
+    ///
+    /// ```rust
+    /// # use bevy_northstar::prelude::*;
+    /// // Each unit of elevation between two cells costs as much as 2 flat steps
+    /// let settings = GridSettingsBuilder::new_2d(32, 32).height_cost(HeightCost(2)).build();
+    /// ```
+    pub fn height_cost(mut self, height_cost: HeightCost) -> Self {
+        self.height_cost = height_cost;
+        self
+    }
+
     /// Pass in a [`ChunkSettings`] to configure the grid's chunking behavior.
     /// Or use the individual methods [`GridSettingsBuilder::chunk_size()`] and [`GridSettingsBuilder::chunk_depth()`] to set the chunk size and depth individually.
     pub fn chunk_settings(mut self, chunk_settings: ChunkSettings) -> Self {
-        self = self.chunk_size(chunk_settings.size);
-        self = self.chunk_depth(chunk_settings.depth);
+        self = self.chunk_dimensions(chunk_settings.size);
 
         if chunk_settings.diagonal_connections {
             self = self.enable_diagonal_connections();
@@ -284,6 +507,9 @@ impl GridSettingsBuilder {
             cost_settings: self.cost_settings,
             collision_settings: self.collision_settings,
             neighborhood_settings: self.neighborhood_settings,
+            wrap: self.wrap,
+            height_cost: self.height_cost,
+            diagonal_movement: self.diagonal_movement,
         })
     }
 }
@@ -295,6 +521,9 @@ pub(crate) struct GridInternalSettings {
     pub(crate) cost_settings: NavSettings,
     pub(crate) collision_settings: CollisionSettings,
     pub(crate) neighborhood_settings: NeighborhoodSettings,
+    pub(crate) wrap: BVec3,
+    pub(crate) height_cost: HeightCost,
+    pub(crate) diagonal_movement: DiagonalMovement,
 }
 
 impl Default for GridInternalSettings {
@@ -339,13 +568,22 @@ impl Default for GridInternalSettings {
 ///    commands.spawn(grid);
 /// }
 /// ```
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct Grid<N: Neighborhood> {
     pub(crate) neighborhood: N,
 
     dimensions: UVec3,
     chunk_settings: ChunkSettings,
     collision_settings: CollisionSettings,
+    // Axes whose edges wrap around to the opposite edge.
+    wrap: BVec3,
+    // Cost of each unit of height difference between neighboring cells.
+    height_cost: HeightCost,
+    // The policy the diagonal movement filter was added for, if any.
+    diagonal_movement: DiagonalMovement,
+    // The neighborhood settings the neighborhood was created from, kept so they can be serialized.
+    heuristic: Option<HeuristicKind>,
+    diagonal_cost: Option<DiagonalCost>,
 
     grid: Array3<NavCell>,
     chunks: Array3<Chunk>,
@@ -355,6 +593,31 @@ pub struct Grid<N: Neighborhood> {
     dirty: bool,
     built: bool,
     dirty_chunks: HashSet<(usize, usize, usize)>,
+
+    // Set during build if every passable cell shares the same movement cost.
+    uniform_cost: Option<MovementCost>,
+
+    // Extra movement cost layered on top of the cell costs. Doesn't affect the HPA* graph.
+    cost_overrides: HashMap<UVec3, MovementCost>,
+
+    // Decaying extra cost left behind by agents, see `Grid::set_trail_costs`.
+    trail_costs: HashMap<UVec3, MovementCost>,
+
+    // `cost_overrides` and `trail_costs` added together, kept up to date so searches only read one map.
+    extra_costs: HashMap<UVec3, MovementCost>,
+
+    // Cells treated as blocked by searches until their remaining time runs out. Doesn't affect the HPA* graph.
+    temporary_blocks: HashMap<UVec3, Duration>,
+
+    // Set once any cell has a blocked direction, path refinement then only shortcuts along allowed moves.
+    one_way: bool,
+
+    connectivity: Connectivity,
+
+    // Coarse routes towards recent goal chunks, see `Grid::set_route_cache_capacity`.
+    route_cache: RouteCache,
+
+    neighbor_provider: Option<Arc<dyn NeighborProvider + Send + Sync + 'static>>,
 }
 
 impl<N: Neighborhood + Default> Grid<N> {
@@ -367,13 +630,18 @@ impl<N: Neighborhood + Default> Grid<N> {
             cost_settings,
             collision_settings,
             neighborhood_settings: _,
+            wrap,
+            height_cost,
+            diagonal_movement,
         } = settings.0;
 
         let UVec3 { x, y, z } = dimensions;
 
-        let x_chunks = x / chunk_settings.size;
-        let y_chunks = y / chunk_settings.size;
-        let z_chunks = z / chunk_settings.depth;
+        // Round up so the far edges get partial chunks when the dimensions aren't divisible by the chunk size
+        let size = chunk_settings.size;
+        let x_chunks = x.div_ceil(size.x);
+        let y_chunks = y.div_ceil(size.y);
+        let z_chunks = z.div_ceil(size.z);
 
         let default_navcell = if cost_settings.default_impassible {
             NavCell::new(Nav::Impassable)
@@ -386,18 +654,10 @@ impl<N: Neighborhood + Default> Grid<N> {
         let chunks = Array3::from_shape_fn(
             (x_chunks as usize, y_chunks as usize, z_chunks as usize),
             |(x, y, z)| {
-                let min_x = x as u32 * chunk_settings.size;
-                let max_x = min_x + chunk_settings.size;
-                let min_y = y as u32 * chunk_settings.size;
-                let max_y = min_y + chunk_settings.size;
-                let min_z = z as u32 * chunk_settings.depth;
-                let max_z = min_z + chunk_settings.depth;
-
-                Chunk::new(
-                    (x, y, z),
-                    UVec3::new(min_x, min_y, min_z),
-                    UVec3::new(max_x, max_y, max_z),
-                )
+                let min = UVec3::new(x as u32, y as u32, z as u32) * size;
+                let max = (min + size).min(dimensions);
+
+                Chunk::new((x, y, z), min, max)
             },
         );
 
@@ -409,11 +669,18 @@ impl<N: Neighborhood + Default> Grid<N> {
             })
             .collect::<HashSet<_>>();
 
+        let connectivity = Connectivity::new(dimensions, chunks.dim());
+
         Self {
             neighborhood: N::from_settings(&settings.0.neighborhood_settings),
             dimensions,
             chunk_settings,
             collision_settings,
+            wrap,
+            height_cost,
+            diagonal_movement,
+            heuristic: settings.0.neighborhood_settings.heuristic,
+            diagonal_cost: settings.0.neighborhood_settings.diagonal_cost,
 
             grid,
             chunks,
@@ -423,6 +690,21 @@ impl<N: Neighborhood + Default> Grid<N> {
             dirty: true,
             built: false,
             dirty_chunks,
+
+            uniform_cost: None,
+
+            cost_overrides: HashMap::new(),
+            trail_costs: HashMap::new(),
+            extra_costs: HashMap::new(),
+            temporary_blocks: HashMap::new(),
+
+            one_way: false,
+
+            connectivity,
+
+            route_cache: RouteCache::default(),
+
+            neighbor_provider: settings.0.neighborhood_settings.provider.clone(),
         }
     }
 
@@ -432,13 +714,70 @@ impl<N: Neighborhood + Default> Grid<N> {
         &self.neighborhood
     }
 
+    /// Returns the axes whose edges wrap around to the opposite edge, see [`GridSettingsBuilder::wrap`].
+    pub fn wrap(&self) -> BVec3 {
+        self.wrap
+    }
+
+    /// Returns the cost of each unit of height difference between cells, see [`GridSettingsBuilder::height_cost`].
+    pub fn height_cost(&self) -> HeightCost {
+        self.height_cost
+    }
+
+    /// Returns how agents are allowed to move diagonally, see [`GridSettingsBuilder::diagonal_movement`].
+    pub fn diagonal_movement(&self) -> DiagonalMovement {
+        self.diagonal_movement
+    }
+
+    /// Returns the heuristic set with [`GridSettingsBuilder::heuristic`], `None` if the neighborhood uses its own.
+    pub fn heuristic(&self) -> Option<HeuristicKind> {
+        self.heuristic
+    }
+
+    /// Returns the diagonal step weights set with [`GridSettingsBuilder::diagonal_cost`], if any.
+    pub fn diagonal_cost(&self) -> Option<DiagonalCost> {
+        self.diagonal_cost
+    }
+
+    /// Returns the heuristic distance from `a` to `b` the searches on this grid use,
+    /// including the [`GridSettingsBuilder::heuristic`] and [`GridSettingsBuilder::diagonal_cost`] settings.
+    /// On grids that [`GridSettingsBuilder::wrap`] the distance is measured the shorter way around,
+    /// and on grids with a [`HeightCost`] the cost of the height difference is added.
+    ///
+    /// With an admissible heuristic this never exceeds the cost of the path between them, and matches it on open
+    /// terrain with a movement cost of 1. The default heuristic of [`crate::prelude::OrdinalNeighborhood`]
+    /// is scaled to break ties and doesn't match path costs, set a [`HeuristicKind`] or a diagonal cost if you need it to.
+    /// See [`crate::neighbor::manhattan_distance`], [`crate::neighbor::chebyshev_distance`] and
+    /// [`crate::neighbor::octile_distance`] for the raw distances.
+    pub fn heuristic_distance(&self, a: UVec3, b: UVec3) -> u32 {
+        let distance = if self.wrap.any() {
+            let wrapped = WrappedHeuristic {
+                neighborhood: self.neighborhood.clone(),
+                dimensions: self.dimensions,
+                wrap: self.wrap,
+            };
+            wrapped.heuristic(a, b)
+        } else {
+            self.neighborhood.heuristic(a, b)
+        };
+
+        let climb = |pos: UVec3| self.nav_cell(pos).map_or(0, |cell| cell.climb);
+        distance.saturating_add(climb(a).abs_diff(climb(b)))
+    }
+
+    /// Returns the number of steps from `a` to `b` on open terrain with the grid's neighborhood,
+    /// ignoring movement costs and walls, see [`Neighborhood::step_distance`].
+    pub fn step_distance(&self, a: UVec3, b: UVec3) -> u32 {
+        self.neighborhood.step_distance(a, b)
+    }
+
     /// Returns an [`ndarray::ArrayView3<NavCell>`] for read-only access to the grid data.
-    pub fn view(&self) -> ArrayView3<NavCell> {
+    pub fn view(&self) -> ArrayView3<'_, NavCell> {
         self.grid.view()
     }
 
     /// Returns an [`ndarray::ArrayView3<NavCell>`] for read-only access to the data within a given [`Chunk`].
-    pub(crate) fn chunk_view(&self, chunk: &Chunk) -> ArrayView3<NavCell> {
+    pub(crate) fn chunk_view(&self, chunk: &Chunk) -> ArrayView3<'_, NavCell> {
         chunk.view(&self.grid)
     }
 
@@ -456,6 +795,233 @@ impl<N: Neighborhood + Default> Grid<N> {
         self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].is_passable()
     }
 
+    /// Returns the passable cell closest to `pos`, or `pos` itself if it is passable.
+    ///
+    /// Searches outward from `pos` one ring at a time using the grid's [`Neighborhood`] directions, so the distance is
+    /// counted in steps of the neighborhood and 3D neighborhoods also search the layers above and below.
+    /// Impassable cells are searched through, which makes this useful for snapping a clicked position inside a wall.
+    /// Returns `None` if `pos` is out of bounds or no passable cell is within `max_radius` steps.
+    pub fn nearest_passable(&self, pos: UVec3, max_radius: u32) -> Option<UVec3> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(pos);
+        queue.push_back((pos, 0));
+
+        while let Some((current, distance)) = queue.pop_front() {
+            if self.is_passable(current) {
+                return Some(current);
+            }
+
+            if distance == max_radius {
+                continue;
+            }
+
+            for &(dx, dy, dz) in self.neighborhood.directions() {
+                let next = current.as_ivec3() + IVec3::new(dx, dy, dz);
+                if next.cmplt(IVec3::ZERO).any() {
+                    continue;
+                }
+
+                let next = next.as_uvec3();
+                if self.in_bounds(next) && visited.insert(next) {
+                    queue.push_back((next, distance + 1));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the passable cells adjacent to `pos`, whether `pos` itself is passable or not.
+    ///
+    /// Adjacency follows the directions of the grid's [`Neighborhood`]: the 4 sides with a cardinal neighborhood,
+    /// the 4 sides and 4 corners with an ordinal neighborhood, the 6 sides of a hex, and the cells above and below in 3D.
+    /// Neighbor filters aren't applied since `pos` may be a wall or an occupied cell that can't be moved through.
+    pub fn adjacent_passable(&self, pos: UVec3) -> SmallVec<[UVec3; 8]> {
+        if !self.in_bounds(pos) {
+            return SmallVec::new();
+        }
+
+        self.neighborhood
+            .directions()
+            .iter()
+            .filter_map(|&(dx, dy, dz)| {
+                let next = pos.as_ivec3() + IVec3::new(dx, dy, dz);
+                (next.cmpge(IVec3::ZERO).all() && self.is_passable(next.as_uvec3()))
+                    .then(|| next.as_uvec3())
+            })
+            .collect()
+    }
+
+    /// Returns the passable cells within `radius` steps of `center`, including `center` itself,
+    /// in order of increasing [`Grid::step_distance`]. Walls don't block the distance, use [`Grid::flood_fill`]
+    /// for the cells that can be walked to. Used for the goals of [`crate::components::Pathfind::within`].
+    pub fn passable_within(&self, center: UVec3, radius: u32) -> Vec<UVec3> {
+        if !self.in_bounds(center) {
+            return Vec::new();
+        }
+
+        let min = center.saturating_sub(UVec3::splat(radius));
+        let max = center
+            .saturating_add(UVec3::splat(radius))
+            .min(self.dimensions - UVec3::ONE);
+
+        let mut cells = Vec::new();
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let pos = UVec3::new(x, y, z);
+                    let distance = self.step_distance(center, pos);
+                    if distance <= radius && self.is_passable(pos) {
+                        cells.push((distance, pos));
+                    }
+                }
+            }
+        }
+
+        cells.sort_by_key(|(distance, _)| *distance);
+        cells.into_iter().map(|(_, pos)| pos).collect()
+    }
+
+    /// Returns every cell reachable from `origin` within `max_steps` moves, including `origin` itself.
+    /// Useful for quick gameplay range checks such as area of effect, patrol ranges, or revealing fog of war.
+    ///
+    /// Unlike [`Grid::distance_field`] movement costs are ignored, every move counts as a single step.
+    /// Moves follow the precomputed neighbors of the grid, so the [`Neighborhood`] rules, portals,
+    /// and vertical links all apply. Dynamic [`crate::components::Blocking`] entities are ignored.
+    /// See [`Grid::flood_fill_steps`] to get the step distance of each cell.
+    ///
+    /// Returns an empty list if `origin` isn't passable or the grid needs to be built.
+    pub fn flood_fill(&self, origin: UVec3, max_steps: u32) -> Vec<UVec3> {
+        self.flood_fill_steps(origin, max_steps)
+            .into_iter()
+            .map(|(pos, _)| pos)
+            .collect()
+    }
+
+    /// Like [`Grid::flood_fill`] but also returns the number of steps from `origin` to each cell.
+    /// Cells are returned in order of increasing step distance, starting with `origin` at 0 steps.
+    pub fn flood_fill_steps(&self, origin: UVec3, max_steps: u32) -> Vec<(UVec3, u32)> {
+        if !self.is_passable(origin) || self.needs_build() {
+            return Vec::new();
+        }
+
+        let mut visited = HashSet::new();
+        let mut cells = vec![(origin, 0)];
+        visited.insert(origin);
+
+        // The cells are visited in step order, so the result doubles as the queue
+        let mut index = 0;
+        while let Some(&(current, steps)) = cells.get(index) {
+            index += 1;
+
+            if steps == max_steps {
+                continue;
+            }
+
+            for neighbor in self.navcell(current).neighbor_iter(current) {
+                if self.is_passable(neighbor) && visited.insert(neighbor) {
+                    cells.push((neighbor, steps + 1));
+                }
+            }
+        }
+
+        cells
+    }
+
+    /// Returns a random passable cell, chosen uniformly from every passable cell in the grid.
+    /// Returns `None` if the grid has no passable cells.
+    pub fn random_passable_cell<R: RngCore + ?Sized>(&self, rng: &mut R) -> Option<UVec3> {
+        // Sampling is fast on mostly open grids, fall back to counting the passable cells on crowded ones
+        for _ in 0..32 {
+            let pos = UVec3::new(
+                random_below(rng, self.dimensions.x as u64) as u32,
+                random_below(rng, self.dimensions.y as u64) as u32,
+                random_below(rng, self.dimensions.z as u64) as u32,
+            );
+
+            if self.is_passable(pos) {
+                return Some(pos);
+            }
+        }
+
+        let count = self.grid.iter().filter(|cell| cell.is_passable()).count();
+        if count == 0 {
+            return None;
+        }
+
+        let index = random_below(rng, count as u64) as usize;
+        self.grid
+            .indexed_iter()
+            .filter(|(_, cell)| cell.is_passable())
+            .nth(index)
+            .map(|((x, y, z), _)| UVec3::new(x as u32, y as u32, z as u32))
+    }
+
+    /// Returns the passable positions that can be moved to from `pos` in a single step.
+    ///
+    /// The neighbors follow the same rules the pathfinding uses: the grid's [`Neighborhood`], its [`NeighborFilter`]s
+    /// such as the [`DiagonalMovement`] corner rules, vertical movement in 3D grids, and [`crate::nav::Portal`]s.
+    /// Neighbors are precomputed when the grid is built, so the result reflects the last call to [`Grid::build`].
+    /// Returns an empty list if `pos` is out of bounds.
+    ///
+    /// This is cheap enough to call in a hot loop, useful for writing your own movement or search algorithms on top of the grid.
+    pub fn neighbor_positions(&self, pos: UVec3) -> SmallVec<[UVec3; 8]> {
+        if !self.in_bounds(pos) {
+            return SmallVec::new();
+        }
+
+        self.navcell(pos)
+            .neighbor_iter(pos)
+            .filter(|neighbor| self.is_passable(*neighbor))
+            .collect()
+    }
+
+    /// Returns true if a single step from `from` to the adjacent cell `to` is a legal move.
+    ///
+    /// This is the check the searches make for every step, it follows the same rules as [`Grid::neighbor_positions`]:
+    /// the [`DiagonalMovement`] corner rules and other [`NeighborFilter`]s, directions blocked with
+    /// [`Grid::block_direction`], and vertical movement in 3D grids. Dynamic [`crate::components::Blocking`] agents aren't checked.
+    /// Returns false if either position is out of bounds or the cells aren't adjacent, including cells linked by a [`crate::nav::Portal`].
+    pub fn is_edge_passable(&self, from: UVec3, to: UVec3) -> bool {
+        if !self.in_bounds(from) || !self.in_bounds(to) || from == to {
+            return false;
+        }
+
+        if (from.as_ivec3() - to.as_ivec3()).abs().max_element() > 1 {
+            return false;
+        }
+
+        self.is_passable(to) && self.navcell(from).neighbor_iter(from).any(|pos| pos == to)
+    }
+
+    /// Returns true if the straight line from `from` to `to` doesn't hit any impassable cells.
+    /// Useful for AI visibility checks. See [`Grid::raycast`] for details on how the line is traced.
+    pub fn line_of_sight(&self, from: UVec3, to: UVec3) -> bool {
+        self.raycast(from, to).is_none()
+    }
+
+    /// Traces the straight line from `from` to `to` and returns the first impassable cell it hits,
+    /// or `None` if the line is clear. Both ends of the line are checked.
+    ///
+    /// The line is traced with the same Bresenham stepping that refines HPA* paths,
+    /// so a clear line is also a line an agent could follow with the grid's [`Neighborhood`].
+    /// Lines walk the z-axis in 3D grids. Out of bounds cells are treated as blocked.
+    pub fn raycast(&self, from: UVec3, to: UVec3) -> Option<UVec3> {
+        bresenham_walk(
+            &self.grid.view(),
+            from,
+            to,
+            self.neighborhood.is_ordinal(),
+            |pos| self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].is_passable(),
+        )
+        .err()
+    }
+
     /// Test if a grid cell is a portal to a target [`bevy::math::UVec3`] cell.
     pub fn is_portal(&self, pos: UVec3) -> bool {
         if !self.in_bounds(pos) {
@@ -465,7 +1031,85 @@ impl<N: Neighborhood + Default> Grid<N> {
         self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].is_portal()
     }
 
+    /// Links `a` and `b` with a [`Portal`] in both directions, such as a pair of teleporters.
+    /// Both cells become portals that keep their movement layers, moving through one costs `cost`.
+    ///
+    /// Portals are long-distance moves for every search, and become edges of the HPA* graph once
+    /// the grid is rebuilt with [`Grid::build`], so hierarchical searches route through them as well.
+    ///
+    /// # Errors
+    /// Returns [`GridError::OutOfBounds`] if either cell is outside of the grid.
+    pub fn add_portal(&mut self, a: UVec3, b: UVec3, cost: MovementCost) -> Result<(), GridError> {
+        self.try_set_nav(a, Nav::Portal(Portal::to(b, cost, false)))
+    }
+
+    /// Like [`Grid::add_portal`] but only links `a` to `b`, `b` is left as it is.
+    ///
+    /// # Errors
+    /// Returns [`GridError::OutOfBounds`] if either cell is outside of the grid.
+    pub fn add_one_way_portal(
+        &mut self,
+        a: UVec3,
+        b: UVec3,
+        cost: MovementCost,
+    ) -> Result<(), GridError> {
+        self.try_set_nav(a, Nav::Portal(Portal::to(b, cost, true)))
+    }
+
+    /// Removes the portal at `pos` and returns it, `None` if the cell isn't a portal.
+    /// The reverse portal at its target is removed as well when it leads back to `pos`.
+    /// Both cells are left passable with the portal's movement cost.
+    pub fn remove_portal(&mut self, pos: UVec3) -> Option<Portal> {
+        let Some(Nav::Portal(portal)) = self.nav(pos) else {
+            return None;
+        };
+
+        self.set_nav(pos, Nav::Passable(portal.cost));
+
+        if let Some(Nav::Portal(reverse)) = self.nav(portal.target) {
+            if reverse.target == pos {
+                self.set_nav(portal.target, Nav::Passable(reverse.cost));
+            }
+        }
+
+        Some(portal)
+    }
+
+    /// Returns every portal cell in the grid with its [`Portal`].
+    /// A link added with [`Grid::add_portal`] shows up once from each end.
+    pub fn portals(&self) -> Vec<(UVec3, Portal)> {
+        self.grid
+            .indexed_iter()
+            .filter_map(|((x, y, z), cell)| match cell.nav {
+                Nav::Portal(portal) => Some((UVec3::new(x as u32, y as u32, z as u32), portal)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Like [`Grid::set_nav`] but returns [`GridError::OutOfBounds`] instead of panicking
+    /// if `pos` or the target of a [`Nav::Portal`] is outside of the grid.
+    pub fn try_set_nav(&mut self, pos: UVec3, nav: Nav) -> Result<(), GridError> {
+        if !self.in_bounds(pos) {
+            return Err(GridError::OutOfBounds(pos));
+        }
+
+        if let Nav::Portal(portal) = &nav {
+            if !self.in_bounds(portal.target) {
+                return Err(GridError::OutOfBounds(portal.target));
+            }
+        }
+
+        self.set_nav(pos, nav);
+        Ok(())
+    }
+
     /// Set the [`Nav`] settings at a given [`bevy::math::UVec3`] position in the grid.
+    /// Use [`Nav::Passable`] with a higher movement cost for terrain that should be avoided, such as swamps,
+    /// and a lower cost for preferred terrain, such as roads. A movement cost of 0 is clamped to 1.
+    ///
+    /// # Panics
+    /// Panics if `pos` or the target of a [`Nav::Portal`] is out of bounds, see [`Grid::try_set_nav`].
     pub fn set_nav(&mut self, pos: UVec3, nav: Nav) {
         if !self.in_bounds(pos) {
             panic!("Attempted to set nav at out-of-bounds position at {pos}");
@@ -496,67 +1140,640 @@ impl<N: Neighborhood + Default> Grid<N> {
             }
         }
 
-        let navcell = NavCell::new(nav);
-        self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]] = navcell;
+        // Keep the movement layers, vertical links, authored exits and height of the cell
+        let cell = &mut self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]];
+        let (layers, vertical, blocked_exits, added_exits, height, climb) = (
+            cell.layers,
+            cell.vertical,
+            cell.blocked_exits,
+            cell.added_exits,
+            cell.height,
+            cell.climb,
+        );
+        *cell = NavCell::new(nav);
+        cell.layers = layers;
+        cell.vertical = vertical;
+        cell.blocked_exits = blocked_exits;
+        cell.added_exits = added_exits;
+        cell.height = height;
+        cell.climb = climb;
     }
 
-    /// Gets the [`Nav`] settings at a given [`bevy::math::UVec3`] position in the grid.
-    pub fn nav(&self, pos: UVec3) -> Option<Nav> {
-        if self.in_bounds(pos) {
-            Some(self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].nav())
-        } else {
-            None
-        }
+    /// Sets many cells at once and rebuilds the grid a single time afterwards.
+    /// Each cell is applied like [`Grid::set_nav`] with the movement layers of the given [`NavCell`].
+    ///
+    /// The chunks touched by the changes are collected while applying them,
+    /// so chunks and entrances shared by many changes are only rebuilt once.
+    /// Useful for carving caves or stamping buildings during procedural generation.
+    pub fn set_nav_batch(&mut self, cells: impl IntoIterator<Item = (UVec3, NavCell)>) {
+        self.with_deferred_rebuild(|grid| {
+            for (pos, cell) in cells {
+                grid.set_nav(pos, cell.nav);
+                grid.set_nav_layers(pos, cell.layers);
+            }
+        });
     }
 
-    /// Gets the [`NavCell`] at a given [`bevy::math::UVec3`] position in the grid.
-    pub(crate) fn navcell(&self, pos: UVec3) -> &NavCell {
-        &self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]]
+    /// Sets every cell on the straight line from `a` to `b` to `nav` and rebuilds the grid a single time afterwards.
+    ///
+    /// The line only steps along one axis at a time, so a wall drawn with it can't be slipped through diagonally
+    /// and a corridor carved with it is connected for every [`Neighborhood`].
+    /// Cells outside of the grid are skipped instead of panicking.
+    pub fn set_nav_line(&mut self, a: UVec3, b: UVec3, nav: Nav) {
+        let cells = line_cells(a, b);
+        self.set_nav_clipped(cells, nav);
     }
 
-    /// Returns the dimensions of the grid.
-    pub fn dimensions(&self) -> UVec3 {
-        self.dimensions
+    /// Sets every cell in the box between the `min` and `max` corners, inclusive, to `nav`
+    /// and rebuilds the grid a single time afterwards. Cells outside of the grid are skipped instead of panicking.
+    pub fn set_nav_rect(&mut self, min: UVec3, max: UVec3, nav: Nav) {
+        let max = max.min(self.dimensions().saturating_sub(UVec3::ONE));
+        let cells = (min.z..=max.z).flat_map(move |z| {
+            (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| UVec3::new(x, y, z)))
+        });
+        self.set_nav_clipped(cells, nav);
     }
 
-    /// Returns the width of the grid.
-    pub fn width(&self) -> u32 {
-        self.dimensions.x
+    /// Sets every cell within `radius` of `center` on the layer of `center` to `nav`
+    /// and rebuilds the grid a single time afterwards. Cells outside of the grid are skipped instead of panicking.
+    pub fn set_nav_filled_circle(&mut self, center: UVec3, radius: u32, nav: Nav) {
+        let min = center.saturating_sub(UVec3::new(radius, radius, 0));
+        let max = center.saturating_add(UVec3::new(radius, radius, 0));
+        let radius_squared = radius as u64 * radius as u64;
+
+        let cells = (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| UVec3::new(x, y, center.z)))
+            .filter(move |pos| {
+                let offset = (pos.as_i64vec3() - center.as_i64vec3()).abs().as_u64vec3();
+                offset.x * offset.x + offset.y * offset.y <= radius_squared
+            });
+        self.set_nav_clipped(cells, nav);
     }
 
-    /// Returns the height of the grid.
-    pub fn height(&self) -> u32 {
-        self.dimensions.y
+    // Sets the in bounds `cells` to `nav` with a single rebuild, the shape helpers clip to the grid with this.
+    fn set_nav_clipped(&mut self, cells: impl IntoIterator<Item = UVec3>, nav: Nav) {
+        self.with_deferred_rebuild(|grid| {
+            for pos in cells {
+                if grid.in_bounds(pos) {
+                    grid.set_nav(pos, nav);
+                }
+            }
+        });
     }
 
-    /// Returns the depth of the grid.
-    pub fn depth(&self) -> u32 {
-        self.dimensions.z
-    }
+    /// Runs `f` and then rebuilds the dirty chunks once, returning the result of `f`.
+    /// Any number of [`Grid::set_nav`] calls can be made inside the closure without calling [`Grid::build`] yourself.
+    ///
+    /// ```rust,no_run
+    /// # use bevy::math::UVec3;
+    /// # use bevy_northstar::prelude::*;
+    /// # let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GridSettingsBuilder::new_2d(64, 64).build());
+    /// grid.with_deferred_rebuild(|grid| {
+    ///     for x in 10..20 {
+    ///         grid.set_nav(UVec3::new(x, 10, 0), Nav::Impassable);
+    ///     }
+    /// });
+    /// ```
+    pub fn with_deferred_rebuild<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let result = f(self);
+
+        if self.dirty || !self.built {
+            self.build();
+        }
 
-    /// Returns the square width/height dimensions of the chunks in the grid.
-    pub fn chunk_size(&self) -> u32 {
-        self.chunk_settings.size
+        result
     }
 
-    /// Returns the depth of the chunks in the grid.
-    pub fn chunk_depth(&self) -> u32 {
-        self.chunk_settings.depth
-    }
+    /// Sets the [`NavLayers`] that can enter the cell at `pos`, cells allow every layer by default.
+    /// Agents only enter cells whose layers intersect the layers set with [`crate::components::Pathfind::layers`],
+    /// for example mark water cells with a water bit so only boats with the water bit can cross them.
+    ///
+    /// The HPA* hierarchy ignores layers so this doesn't require a rebuild,
+    /// requests restricted to some layers are searched with A* instead.
+    pub fn set_nav_layers(&mut self, pos: UVec3, layers: NavLayers) {
+        if !self.in_bounds(pos) {
+            panic!("Attempted to set nav layers at out-of-bounds position at {pos}");
+        }
 
-    /// Returns the chunk settings of the grid.
-    pub fn chunk_settings(&self) -> ChunkSettings {
-        self.chunk_settings
+        self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].layers = layers;
     }
 
-    /// Test if collision is enabled.
-    pub fn collision(&self) -> bool {
-        self.collision_settings.enabled
-    }
+    /// Sets the elevation of the cell at `pos` on a 2.5D map, where z is a height rather than a separate floor.
+    /// Cells start at a height of 0.
+    ///
+    /// With a [`HeightCost`] set, moving between neighboring cells costs their height difference times the
+    /// height cost on top of the cost of the cell being entered, so paths avoid climbing when there is a cheap enough way around.
+    /// Provider links and [`VerticalLinks`] keep their own cost. Heights are ignored without a height cost.
+    ///
+    /// ```rust
+    /// # use bevy::math::UVec3;
+    /// # use bevy_northstar::prelude::*;
+    /// let settings = GridSettingsBuilder::new_2d(16, 16).height_cost(HeightCost(2)).build();
+    /// let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+    ///
+    /// // A plateau 3 units up, stepping onto it costs 1 + 3 * 2
+    /// grid.set_cell_height(UVec3::new(8, 8, 0), 3);
+    /// grid.build();
+    /// assert_eq!(grid.cell_height(UVec3::new(8, 8, 0)), Some(3));
+    /// ```
+    pub fn set_cell_height(&mut self, pos: UVec3, height: u32) {
+        if !self.in_bounds(pos) {
+            panic!("Attempted to set height at out-of-bounds position at {pos}");
+        }
 
-    /// Set the collision settings for the grid.
-    pub fn set_collision(&mut self, collision: bool) {
-        self.collision_settings.enabled = collision;
+        // The cached paths between entrances include the climbing costs
+        if self.built {
+            self.dirty = true;
+            self.mark_dirty_for_pos(pos);
+        }
+
+        let cell = &mut self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]];
+        cell.height = height;
+        cell.climb = height.saturating_mul(self.height_cost.0);
+    }
+
+    /// Connects the cell at `pos` to the cells directly above and below it with [`VerticalLinks`],
+    /// such as a staircase between the floors of a building. Moving along a link costs the link's cost
+    /// instead of the cost of the cell being entered. Pass [`VerticalLinks::default()`] to remove the links.
+    ///
+    /// Use a planar neighborhood like [`crate::prelude::CardinalNeighborhood`] on a multi-floor grid
+    /// so the floors are only joined where cells have vertical links.
+    /// The HPA* hierarchy creates entrances at the links between chunks stacked in z.
+    ///
+    /// ```rust,no_run
+    /// # use bevy::math::UVec3;
+    /// # use bevy_northstar::prelude::*;
+    /// # let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GridSettingsBuilder::new_3d(16, 16, 2).build());
+    /// // Stairs at (4, 4) climb to the second floor at a cost of 3 and descend at a cost of 2
+    /// grid.set_vertical_links(UVec3::new(4, 4, 0), VerticalLinks::up(3));
+    /// grid.set_vertical_links(UVec3::new(4, 4, 1), VerticalLinks::down(2));
+    /// grid.build();
+    /// ```
+    pub fn set_vertical_links(&mut self, pos: UVec3, links: VerticalLinks) {
+        if !self.in_bounds(pos) {
+            panic!("Attempted to set vertical links at out-of-bounds position at {pos}");
+        }
+
+        if self.built {
+            self.dirty = true;
+            self.mark_dirty_for_pos(pos);
+
+            // The entrances between stacked chunks are found from both sides
+            for z in [pos.z.checked_sub(1), pos.z.checked_add(1)]
+                .into_iter()
+                .flatten()
+            {
+                let other = UVec3::new(pos.x, pos.y, z);
+                if self.in_bounds(other) {
+                    self.mark_dirty_for_pos(other);
+                }
+            }
+        }
+
+        self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].vertical = links;
+        // Path shortcuts mustn't cut between floors away from the links
+        if links != VerticalLinks::default() {
+            self.one_way = true;
+        }
+    }
+
+    /// Blocks agents from moving out of the cell at `pos` in `dir` while still allowing the opposite move into it,
+    /// such as a ledge that can be dropped down but not climbed, or a conveyor belt.
+    /// The move is removed from the cell's neighbors, so every pathfinding mode, path refinement and the
+    /// HPA* entrances and cached paths treat it as one-way. The grid needs to be rebuilt like after [`Grid::set_nav`].
+    ///
+    /// ```rust,no_run
+    /// # use bevy::math::UVec3;
+    /// # use bevy_northstar::prelude::*;
+    /// # let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GridSettingsBuilder::new_2d(16, 16).build());
+    /// // Agents can drop down from (4, 5) to (4, 4) but can't climb back up
+    /// grid.block_direction(UVec3::new(4, 4, 0), Dir::North);
+    /// grid.build();
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `pos` is out of bounds.
+    pub fn block_direction(&mut self, pos: UVec3, dir: Dir) {
+        self.set_direction_blocked(pos, dir, true);
+    }
+
+    /// Allows moving out of the cell at `pos` in `dir` again after [`Grid::block_direction`].
+    ///
+    /// # Panics
+    /// Panics if `pos` is out of bounds.
+    pub fn unblock_direction(&mut self, pos: UVec3, dir: Dir) {
+        self.set_direction_blocked(pos, dir, false);
+    }
+
+    /// Makes the move from `from` to the adjacent cell `to` one-way by blocking the move back from `to` to `from`.
+    /// Shorthand for [`Grid::block_direction`].
+    ///
+    /// # Panics
+    /// Panics if either position is out of bounds or the cells aren't adjacent.
+    pub fn set_one_way(&mut self, from: UVec3, to: UVec3) {
+        let offset = from.as_ivec3() - to.as_ivec3();
+        let Some(dir) = Dir::from_offset(offset) else {
+            panic!("Attempted to set a one-way move between {from} and {to} which aren't adjacent");
+        };
+
+        if !self.in_bounds(from) {
+            panic!("Attempted to set a one-way move from out-of-bounds position at {from}");
+        }
+
+        self.block_direction(to, dir);
+    }
+
+    /// Returns the directions agents can't move out of the cell at `pos` in, see [`Grid::block_direction`].
+    /// Empty if `pos` is out of bounds.
+    pub fn blocked_directions(&self, pos: UVec3) -> Vec<Dir> {
+        if !self.in_bounds(pos) {
+            return Vec::new();
+        }
+
+        self.navcell(pos).blocked_directions()
+    }
+
+    /// Allows agents to move out of the cell at `pos` in `dir` whenever the cell in that direction is passable,
+    /// even where the grid's neighborhood wouldn't, such as a diagonal step on a
+    /// [`crate::prelude::CardinalNeighborhood`] grid or a ramp up to the next z-layer.
+    /// Clears a [`Grid::block_direction`] in the same direction. The grid needs to be rebuilt like after [`Grid::set_nav`].
+    ///
+    /// # Panics
+    /// Panics if `pos` is out of bounds.
+    pub fn allow_direction(&mut self, pos: UVec3, dir: Dir) {
+        let (added, blocked) = self.exits(pos);
+        self.set_exits(pos, added | dir.bit(), blocked & !dir.bit());
+    }
+
+    /// Sets the exact moves out of the cell at `pos` as a mask of [`Dir::bit`]s, replacing the moves of the neighborhood.
+    /// Directions in the mask are allowed like [`Grid::allow_direction`] and every other direction is blocked
+    /// like [`Grid::block_direction`], so map editors can author one-way edges, ramps, and custom connectivity.
+    /// The mask is kept when the cell's [`Nav`] changes, use [`Grid::reset_neighbor_bits`] to go back to the neighborhood.
+    ///
+    /// The cached neighbors read with [`NavCell::neighbor_bits`] are only updated by [`Grid::build`],
+    /// which only rebuilds the chunk of `pos` and the HPA* entrances on its borders:
+    /// * Every change to a cell's moves needs a rebuild before searching, for the grid searches
+    ///   ([`crate::prelude::PathfindMode::AStar`], `Jps`, `Theta`) as well as the HPA* hierarchy.
+    /// * [`Grid::set_nav_layers`], [`Grid::set_cost_override`] and [`Grid::block_temporarily`] don't change
+    ///   the cached neighbors and never need a rebuild.
+    ///
+    /// ```rust,no_run
+    /// # use bevy::math::UVec3;
+    /// # use bevy_northstar::prelude::*;
+    /// # let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GridSettingsBuilder::new_2d(16, 16).build());
+    /// // The cell at (4, 4) can only be left to the east or north east
+    /// grid.set_neighbor_bits(UVec3::new(4, 4, 0), Dir::East.bit() | Dir::NorthEast.bit());
+    /// grid.build();
+    /// assert!(grid.nav_cell(UVec3::new(4, 4, 0)).unwrap().has_exit(Dir::NorthEast));
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `pos` is out of bounds.
+    pub fn set_neighbor_bits(&mut self, pos: UVec3, bits: u32) {
+        self.set_exits(pos, bits & ALL_EXITS, !bits & ALL_EXITS);
+    }
+
+    /// Removes every [`Grid::allow_direction`], [`Grid::block_direction`] and [`Grid::set_neighbor_bits`]
+    /// from the cell at `pos` so its moves follow the neighborhood again.
+    /// The grid needs to be rebuilt like after [`Grid::set_nav`].
+    ///
+    /// # Panics
+    /// Panics if `pos` is out of bounds.
+    pub fn reset_neighbor_bits(&mut self, pos: UVec3) {
+        self.set_exits(pos, 0, 0);
+    }
+
+    fn set_direction_blocked(&mut self, pos: UVec3, dir: Dir, blocked: bool) {
+        let (added, blocked_exits) = self.exits(pos);
+        if blocked {
+            self.set_exits(pos, added & !dir.bit(), blocked_exits | dir.bit());
+        } else {
+            self.set_exits(pos, added, blocked_exits & !dir.bit());
+        }
+    }
+
+    // The added and blocked exits of the cell at `pos`.
+    fn exits(&self, pos: UVec3) -> (u32, u32) {
+        if !self.in_bounds(pos) {
+            panic!("Attempted to change the neighbors of out-of-bounds position at {pos}");
+        }
+
+        let cell = self.navcell(pos);
+        (cell.added_exits, cell.blocked_exits)
+    }
+
+    // Replaces the added and blocked exits of the cell at `pos` and flags its chunk for a rebuild.
+    fn set_exits(&mut self, pos: UVec3, added: u32, blocked: u32) {
+        if self.built {
+            self.dirty = true;
+            self.mark_dirty_for_pos(pos);
+        }
+
+        let cell = &mut self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]];
+        cell.added_exits = added;
+        cell.blocked_exits = blocked;
+        if added != 0 || blocked != 0 {
+            self.one_way = true;
+        }
+    }
+
+    // True if any cell has had a direction blocked or allowed or has vertical links,
+    // path shortcuts then need to follow the precomputed neighbors.
+    pub(crate) fn has_one_way_moves(&self) -> bool {
+        self.one_way
+    }
+
+    /// Gets the [`VerticalLinks`] of the cell at `pos`, `None` if it's out of bounds.
+    pub fn vertical_links(&self, pos: UVec3) -> Option<VerticalLinks> {
+        if self.in_bounds(pos) {
+            Some(self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].vertical)
+        } else {
+            None
+        }
+    }
+
+    /// Gets the [`NavLayers`] of the cell at `pos`, `None` if it's out of bounds.
+    pub fn nav_layers(&self, pos: UVec3) -> Option<NavLayers> {
+        if self.in_bounds(pos) {
+            Some(self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].layers)
+        } else {
+            None
+        }
+    }
+
+    /// Gets the elevation of the cell at `pos` set with [`Grid::set_cell_height`], `None` if it's out of bounds.
+    pub fn cell_height(&self, pos: UVec3) -> Option<u32> {
+        self.nav_cell(pos).map(NavCell::height)
+    }
+
+    /// Gets the [`Nav`] settings at a given [`bevy::math::UVec3`] position in the grid.
+    pub fn nav(&self, pos: UVec3) -> Option<Nav> {
+        if self.in_bounds(pos) {
+            Some(self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].nav())
+        } else {
+            None
+        }
+    }
+
+    /// Gets the [`NavCell`] at a given [`bevy::math::UVec3`] position in the grid, `None` if it's out of bounds.
+    /// Useful for editing tools that need the exact moves out of a cell, see [`NavCell::neighbor_bits`].
+    /// Change them with [`Grid::set_neighbor_bits`].
+    pub fn nav_cell(&self, pos: UVec3) -> Option<&NavCell> {
+        if self.in_bounds(pos) {
+            Some(self.navcell(pos))
+        } else {
+            None
+        }
+    }
+
+    /// Gets the movement cost of entering the cell at the given position.
+    /// Returns `None` if the position is out of bounds or impassable.
+    pub fn nav_cost(&self, pos: UVec3) -> Option<MovementCost> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+
+        let cell = &self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]];
+        if cell.is_impassable() {
+            return None;
+        }
+
+        Some(cell.cost)
+    }
+
+    /// Adds `extra_cost` on top of the movement cost of entering the cell at `pos`.
+    /// Useful for temporary hazards like fire or acid that agents should route around when a cheaper route exists,
+    /// without blocking the cell outright. A cell with a huge extra cost is still used if it's the only way through.
+    ///
+    /// Unlike [`Grid::set_nav`] this doesn't require the grid to be rebuilt. The override is taken into account by
+    /// [`PathfindMode::AStar`], [`PathfindMode::Jps`] (which falls back to A* while any overrides are set),
+    /// [`PathfindMode::Theta`] and HPA* path refinement, but the cached HPA* graph and coarse paths are not affected.
+    /// Cost overrides are not serialized.
+    ///
+    /// Setting an override on a position replaces any previous override.
+    pub fn set_cost_override(&mut self, pos: UVec3, extra_cost: MovementCost) {
+        if !self.in_bounds(pos) {
+            panic!("Attempted to set cost override at out-of-bounds position at {pos}");
+        }
+
+        if extra_cost == 0 {
+            self.cost_overrides.remove(&pos);
+        } else {
+            self.cost_overrides.insert(pos, extra_cost);
+        }
+        self.update_extra_cost(pos);
+    }
+
+    /// Removes the cost override set with [`Grid::set_cost_override`] at `pos`.
+    pub fn clear_cost_override(&mut self, pos: UVec3) {
+        self.cost_overrides.remove(&pos);
+        self.update_extra_cost(pos);
+    }
+
+    /// Removes every cost override set with [`Grid::set_cost_override`].
+    pub fn clear_cost_overrides(&mut self) {
+        self.cost_overrides.clear();
+        self.extra_costs = self.trail_costs.clone();
+    }
+
+    /// Returns the extra movement cost set with [`Grid::set_cost_override`] at `pos`, if any.
+    pub fn cost_override(&self, pos: UVec3) -> Option<MovementCost> {
+        self.cost_overrides.get(&pos).copied()
+    }
+
+    /// Replaces the trail costs, extra movement cost left behind by agents that searches add on top of the cell cost
+    /// and any [`Grid::set_cost_override`] cost. Agents route around busy cells when a cheaper route exists,
+    /// which spreads crowds out into lanes.
+    ///
+    /// The [`crate::plugin::NorthstarPlugin`] sets them every frame from the [`crate::plugin::TrailField`]
+    /// while the [`crate::plugin::TrailConfig`] resource is inserted. Like cost overrides they don't require the grid
+    /// to be rebuilt and aren't taken into account by [`PathfindMode::FlowField`] or the cached HPA* graph.
+    /// Positions outside the grid and zero costs are ignored. Returns `true` if any trail cost changed.
+    pub fn set_trail_costs(
+        &mut self,
+        costs: impl IntoIterator<Item = (UVec3, MovementCost)>,
+    ) -> bool {
+        let trail_costs = costs
+            .into_iter()
+            .filter(|(pos, cost)| *cost > 0 && self.in_bounds(*pos))
+            .collect::<HashMap<_, _>>();
+        if trail_costs == self.trail_costs {
+            return false;
+        }
+        let previous = std::mem::replace(&mut self.trail_costs, trail_costs);
+
+        let changed = previous
+            .keys()
+            .chain(self.trail_costs.keys())
+            .copied()
+            .collect::<Vec<_>>();
+        for pos in changed {
+            self.update_extra_cost(pos);
+        }
+        true
+    }
+
+    /// Returns the trail cost at `pos` set with [`Grid::set_trail_costs`], if any.
+    pub fn trail_cost(&self, pos: UVec3) -> Option<MovementCost> {
+        self.trail_costs.get(&pos).copied()
+    }
+
+    // Recomputes the extra cost searches add at `pos` after its cost override or trail cost changed.
+    fn update_extra_cost(&mut self, pos: UVec3) {
+        let extra_cost = self
+            .cost_overrides
+            .get(&pos)
+            .copied()
+            .unwrap_or_default()
+            .saturating_add(self.trail_costs.get(&pos).copied().unwrap_or_default());
+
+        if extra_cost == 0 {
+            self.extra_costs.remove(&pos);
+        } else {
+            self.extra_costs.insert(pos, extra_cost);
+        }
+    }
+
+    /// Blocks `pos` for `duration`, like a collapsed bridge that reopens later.
+    ///
+    /// [`Grid::find_path`] and the other `find_path` searches treat the cell like one occupied by a
+    /// [`crate::components::Blocking`] agent until the time runs out. [`crate::plugin::NorthstarPlugin`] also adds the cell
+    /// to the [`crate::plugin::BlockingMap`] and reroutes agents whose next step is blocked.
+    /// The cached HPA* graph isn't touched so this doesn't require the grid to be rebuilt, but like
+    /// [`crate::components::Blocking`] agents the HPA* modes only route around blocks near the start and goal,
+    /// the rest are avoided as agents reach them.
+    ///
+    /// [`crate::plugin::NorthstarPlugin`] counts the time down every frame, without the plugin call [`Grid::tick_temporary_blocks`].
+    /// Blocking a position again replaces the remaining time. Temporary blocks are not serialized.
+    pub fn block_temporarily(&mut self, pos: UVec3, duration: Duration) {
+        if !self.in_bounds(pos) {
+            panic!("Attempted to block out-of-bounds position at {pos}");
+        }
+
+        self.temporary_blocks.insert(pos, duration);
+    }
+
+    /// Removes the block set with [`Grid::block_temporarily`] at `pos` before its time runs out.
+    pub fn clear_temporary_block(&mut self, pos: UVec3) {
+        self.temporary_blocks.remove(&pos);
+    }
+
+    /// Returns the time left on the block set with [`Grid::block_temporarily`] at `pos`, if any.
+    pub fn temporary_block(&self, pos: UVec3) -> Option<Duration> {
+        self.temporary_blocks.get(&pos).copied()
+    }
+
+    /// Returns true if `pos` is blocked with [`Grid::block_temporarily`].
+    pub fn is_temporarily_blocked(&self, pos: UVec3) -> bool {
+        self.temporary_blocks.contains_key(&pos)
+    }
+
+    /// Returns an iterator over the positions blocked with [`Grid::block_temporarily`] and their remaining time.
+    pub fn temporary_blocks(&self) -> impl Iterator<Item = (UVec3, Duration)> + '_ {
+        self.temporary_blocks
+            .iter()
+            .map(|(pos, remaining)| (*pos, *remaining))
+    }
+
+    /// Counts the temporary blocks down by `delta` and clears the ones that ran out.
+    /// Returns the positions that were cleared.
+    pub fn tick_temporary_blocks(&mut self, delta: Duration) -> Vec<UVec3> {
+        let mut expired = Vec::new();
+
+        self.temporary_blocks.retain(|pos, remaining| {
+            *remaining = remaining.saturating_sub(delta);
+            if remaining.is_zero() {
+                expired.push(*pos);
+            }
+            !remaining.is_zero()
+        });
+
+        expired
+    }
+
+    pub(crate) fn has_neighbor_provider(&self) -> bool {
+        self.neighbor_provider.is_some()
+    }
+
+    // Returns `blocking` with the temporarily blocked cells added, see `Grid::block_temporarily`.
+    fn blocking_with_temporary(&self, blocking: &HashMap<UVec3, Entity>) -> HashMap<UVec3, Entity> {
+        let mut merged = blocking.clone();
+        for pos in self.temporary_blocks.keys() {
+            merged.entry(*pos).or_insert(Entity::PLACEHOLDER);
+        }
+        merged
+    }
+
+    // The cost overrides and trail costs added together, the extra costs the searches add on top of the cell costs.
+    pub(crate) fn extra_costs(&self) -> &HashMap<UVec3, MovementCost> {
+        &self.extra_costs
+    }
+
+    // The extra cost of entering `pos` on top of its cell cost, see `Grid::extra_costs`.
+    pub(crate) fn extra_cost(&self, pos: UVec3) -> Option<MovementCost> {
+        self.extra_costs.get(&pos).copied()
+    }
+
+    pub(crate) fn navcell(&self, pos: UVec3) -> &NavCell {
+        &self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]]
+    }
+
+    /// Returns the dimensions of the grid.
+    pub fn dimensions(&self) -> UVec3 {
+        self.dimensions
+    }
+
+    /// Returns the width of the grid.
+    pub fn width(&self) -> u32 {
+        self.dimensions.x
+    }
+
+    /// Returns the height of the grid.
+    pub fn height(&self) -> u32 {
+        self.dimensions.y
+    }
+
+    /// Returns the depth of the grid.
+    pub fn depth(&self) -> u32 {
+        self.dimensions.z
+    }
+
+    /// Returns the width (x), height (y) and depth (z) of the chunks in the grid.
+    /// Chunks along the far edges of the grid may be smaller if the grid dimensions aren't divisible by the chunk size.
+    pub fn chunk_size(&self) -> UVec3 {
+        self.chunk_settings.size
+    }
+
+    /// Returns the depth of the chunks in the grid.
+    pub fn chunk_depth(&self) -> u32 {
+        self.chunk_settings.size.z
+    }
+
+    /// Returns the coordinates of the chunk containing `pos` in the chunk grid.
+    /// See [`Grid::chunk_count()`] for the size of the chunk grid.
+    pub fn chunk_at(&self, pos: UVec3) -> UVec3 {
+        pos / self.chunk_settings.size
+    }
+
+    /// Returns the number of chunks along each axis, including partial chunks along the far edges.
+    pub fn chunk_count(&self) -> UVec3 {
+        let (x, y, z) = self.chunks.dim();
+        UVec3::new(x as u32, y as u32, z as u32)
+    }
+
+    /// Returns the chunk settings of the grid.
+    pub fn chunk_settings(&self) -> ChunkSettings {
+        self.chunk_settings
+    }
+
+    /// Test if collision is enabled.
+    pub fn collision(&self) -> bool {
+        self.collision_settings.enabled
+    }
+
+    /// Set the collision settings for the grid.
+    pub fn set_collision(&mut self, collision: bool) {
+        self.collision_settings.enabled = collision;
     }
 
     /// Returns the avoidance distance for collision avoidance
@@ -586,11 +1803,7 @@ impl<N: Neighborhood + Default> Grid<N> {
     }
 
     pub(crate) fn chunk_in_bounds(&self, chunk_x: isize, chunk_y: isize, chunk_z: isize) -> bool {
-        let chunk_size = self.chunk_settings.size as usize;
-        let chunk_depth = self.chunk_settings.depth as usize;
-        let x_chunks = self.dimensions.x as usize / chunk_size;
-        let y_chunks = self.dimensions.y as usize / chunk_size;
-        let z_chunks = self.dimensions.z as usize / chunk_depth;
+        let (x_chunks, y_chunks, z_chunks) = self.chunks.dim();
 
         chunk_x >= 0
             && chunk_x < x_chunks as isize
@@ -600,6 +1813,17 @@ impl<N: Neighborhood + Default> Grid<N> {
             && chunk_z < z_chunks as isize
     }
 
+    /// Returns the index of the chunk containing `pos`.
+    pub(crate) fn chunk_index_of(&self, pos: UVec3) -> (usize, usize, usize) {
+        let index = self.chunk_at(pos);
+        (index.x as usize, index.y as usize, index.z as usize)
+    }
+
+    /// Returns how many times the chunk at `index` has been rebuilt.
+    pub(crate) fn chunk_version(&self, index: (usize, usize, usize)) -> Option<u32> {
+        self.chunks.get(index).map(|chunk| chunk.version())
+    }
+
     pub(crate) fn needs_build(&self) -> bool {
         if self.dirty {
             log::error!("Grid is dirty! You must call `build()` after modifying the grid.");
@@ -617,12 +1841,7 @@ impl<N: Neighborhood + Default> Grid<N> {
     /// Marks the chunk containing the given position as dirty, marks all its edges as dirty,
     /// and marks the relevant edges and cells of adjacent chunks as dirty as well.
     fn mark_dirty_for_pos(&mut self, pos: UVec3) {
-        let chunk_size = self.chunk_settings.size as usize;
-        let chunk_depth = self.chunk_settings.depth as usize;
-
-        let chunk_x = pos.x as usize / chunk_size;
-        let chunk_y = pos.y as usize / chunk_size;
-        let chunk_z = pos.z as usize / chunk_depth;
+        let (chunk_x, chunk_y, chunk_z) = self.chunk_index_of(pos);
 
         // Get a reference to the chunk
         let Some(chunk) = self.chunks.get((chunk_x, chunk_y, chunk_z)) else {
@@ -656,14 +1875,7 @@ impl<N: Neighborhood + Default> Grid<N> {
 
         // Dirty adjacent chunks and mark their opposite edges
         for dir in dirs_to_dirty {
-            let offset = dir.offset();
-            let (dx, dy, dz) = (offset.x, offset.y, offset.z);
-            let nx = chunk_x as isize + dx as isize;
-            let ny = chunk_y as isize + dy as isize;
-            let nz = chunk_z as isize + dz as isize;
-
-            if self.chunk_in_bounds(nx, ny, nz) {
-                let n_coords = (nx as usize, ny as usize, nz as usize);
+            if let Some(n_coords) = self.adjacent_chunk_index((chunk_x, chunk_y, chunk_z), dir) {
                 self.dirty_chunks.insert(n_coords);
 
                 if let Some(neighbor_chunk) = self.chunks.get_mut(n_coords) {
@@ -673,30 +1885,209 @@ impl<N: Neighborhood + Default> Grid<N> {
         }
     }
 
-    /// Builds the entire grid. This includes precomputing neighbors, creating nodes for each edge of each chunk,
-    /// caching paths between internal nodes within each chunk, and connecting adjacent nodes between chunks.
-    /// This method needs to be called after the grid has been initialized.
-    pub fn build(&mut self) {
-        #[cfg(feature = "stats")]
-        let num_dirty_chunks = self.dirty_chunks.len();
-        #[cfg(feature = "stats")]
-        let build_start = std::time::Instant::now();
+    /// Returns the index of the chunk next to the chunk at `index` in direction `dir`,
+    /// wrapping around the edges of the grid set to wrap. `None` if there's no such chunk.
+    fn adjacent_chunk_index(
+        &self,
+        index: (usize, usize, usize),
+        dir: Dir,
+    ) -> Option<(usize, usize, usize)> {
+        let (x, y, z) = self.chunks.dim();
+        let counts = UVec3::new(x as u32, y as u32, z as u32);
+        let index = UVec3::new(index.0 as u32, index.1 as u32, index.2 as u32);
+
+        let adjacent = wrap_position(index.as_ivec3() + dir.offset(), counts, self.wrap)?;
+        Some((
+            adjacent.x as usize,
+            adjacent.y as usize,
+            adjacent.z as usize,
+        ))
+    }
 
-        timed!("Precomputed neighbors", { self.precompute_neighbors() });
-        timed!("Built nodes", { self.build_nodes() });
-        timed!("Create portal nodes", {
-            self.create_portal_nodes();
-        });
-        timed!("Connected internal chunk nodes", {
-            self.connect_internal_chunk_nodes()
-        });
-        timed!("Connected adjacent chunk nodes", {
-            self.connect_adjacent_chunk_nodes()
+    /// Resizes the grid to `new_size`, keeping the [`NavCell`]s where the old and new bounds overlap
+    /// and filling the new cells with `fill`. Useful for streaming in new regions of an open world.
+    ///
+    /// Only the chunks that gained or lost cells, and the chunks bordering them, are marked dirty.
+    /// The HPA* nodes and cached paths of every other chunk are kept, so paths through them stay valid.
+    /// Call [`Grid::build`] afterwards to rebuild the dirty chunks.
+    ///
+    /// When shrinking, cost overrides, links, and portals that lead outside the new bounds are removed,
+    /// portal cells are left passable with the portal's movement cost.
+    /// Agents standing outside the new bounds aren't moved, their pathfinding requests fail
+    /// until they're placed back inside the grid.
+    pub fn resize(&mut self, new_size: UVec3, fill: NavCell) {
+        if new_size == self.dimensions {
+            return;
+        }
+
+        let old_size = self.dimensions;
+
+        // Copy over the cells where the old and new grid overlap
+        let mut grid = Array3::from_elem(
+            (
+                new_size.x as usize,
+                new_size.y as usize,
+                new_size.z as usize,
+            ),
+            fill,
+        );
+        let overlap = old_size.min(new_size);
+        let overlap = s![
+            ..overlap.x as usize,
+            ..overlap.y as usize,
+            ..overlap.z as usize
+        ];
+        grid.slice_mut(overlap).assign(&self.grid.slice(overlap));
+
+        // Keep the chunks whose bounds didn't change
+        let size = self.chunk_settings.size;
+        let chunk_count = (
+            new_size.x.div_ceil(size.x) as usize,
+            new_size.y.div_ceil(size.y) as usize,
+            new_size.z.div_ceil(size.z) as usize,
+        );
+
+        let mut changed = Vec::new();
+        let chunks = Array3::from_shape_fn(chunk_count, |(x, y, z)| {
+            let min = UVec3::new(x as u32, y as u32, z as u32) * size;
+            let max = (min + size).min(new_size);
+
+            match self.chunks.get((x, y, z)) {
+                Some(old) if old.min() == min && old.max() == max => old.clone(),
+                old => {
+                    changed.push((x, y, z));
+
+                    // Keep counting from the old version so cached paths through it are invalidated
+                    let mut chunk = Chunk::new((x, y, z), min, max);
+                    if let Some(old) = old {
+                        chunk.set_version(old.version());
+                    }
+                    chunk
+                }
+            }
         });
 
-        for (_, chunk) in self.chunks.indexed_iter_mut() {
-            chunk.clean();
-        }
+        // Remove the nodes of the chunks that changed or no longer exist
+        for (index, old) in self.chunks.indexed_iter() {
+            if chunks.get(index) != Some(old) {
+                self.graph.remove_nodes_in_chunk(old);
+
+                if !changed.contains(&index) {
+                    changed.push(index);
+                }
+            }
+        }
+
+        self.grid = grid;
+        self.chunks = chunks;
+        self.dimensions = new_size;
+        self.connectivity.resize(new_size, chunk_count);
+
+        self.dirty = true;
+        self.dirty_chunks
+            .retain(|&(x, y, z)| x < chunk_count.0 && y < chunk_count.1 && z < chunk_count.2);
+
+        // Dirty the changed chunks and the edges of the chunks bordering them
+        for (x, y, z) in changed {
+            if self.chunks.get((x, y, z)).is_some() {
+                self.dirty_chunks.insert((x, y, z));
+            }
+
+            for dir in Dir::all() {
+                if let Some(n_coords) = self.adjacent_chunk_index((x, y, z), dir) {
+                    self.dirty_chunks.insert(n_coords);
+                    self.chunks[n_coords].set_dirty_edge(dir.opposite(), true);
+                }
+            }
+        }
+
+        if new_size.cmplt(old_size).any() {
+            self.cost_overrides
+                .retain(|pos, _| pos.x < new_size.x && pos.y < new_size.y && pos.z < new_size.z);
+            self.trail_costs
+                .retain(|pos, _| pos.x < new_size.x && pos.y < new_size.y && pos.z < new_size.z);
+            self.extra_costs
+                .retain(|pos, _| pos.x < new_size.x && pos.y < new_size.y && pos.z < new_size.z);
+            self.temporary_blocks
+                .retain(|pos, _| pos.x < new_size.x && pos.y < new_size.y && pos.z < new_size.z);
+
+            // Remove links and portals leading outside of the grid
+            let in_bounds =
+                |pos: UVec3| pos.x < new_size.x && pos.y < new_size.y && pos.z < new_size.z;
+
+            let mut severed = Vec::new();
+            for ((x, y, z), cell) in self.grid.indexed_iter_mut() {
+                let pos = UVec3::new(x as u32, y as u32, z as u32);
+
+                let links = cell.links.len();
+                cell.links.retain(|(target, _)| in_bounds(*target));
+
+                if let Nav::Portal(portal) = cell.nav {
+                    if !in_bounds(portal.target) {
+                        let layers = cell.layers;
+                        *cell = NavCell::new(Nav::Passable(portal.cost));
+                        cell.layers = layers;
+                        severed.push(pos);
+                        continue;
+                    }
+                }
+
+                if cell.links.len() != links {
+                    severed.push(pos);
+                }
+            }
+
+            for pos in severed {
+                self.mark_dirty_for_pos(pos);
+            }
+        }
+    }
+
+    /// Builds the entire grid. This includes precomputing neighbors, creating nodes for each edge of each chunk,
+    /// caching paths between internal nodes within each chunk, and connecting adjacent nodes between chunks.
+    /// This method needs to be called after the grid has been initialized.
+    /// Use [`Grid::build_async`] to build a large grid without blocking the main thread.
+    pub fn build(&mut self) {
+        self.build_with_progress(|_| {});
+    }
+
+    // Builds the grid, calling `progress` with the completed fraction of the build after each step.
+    // The weights roughly follow how long each step takes, caching the paths inside the chunks dominates.
+    pub(crate) fn build_with_progress(&mut self, progress: impl Fn(f32)) {
+        #[cfg(feature = "stats")]
+        let num_dirty_chunks = self.dirty_chunks.len();
+        #[cfg(feature = "stats")]
+        let build_start = std::time::Instant::now();
+
+        timed!("Precomputed neighbors", { self.precompute_neighbors() });
+        progress(0.1);
+        timed!("Labeled connected components", {
+            self.update_connectivity()
+        });
+        progress(0.15);
+        timed!("Built nodes", { self.build_nodes() });
+        progress(0.25);
+        timed!("Create portal nodes", {
+            self.create_portal_nodes();
+        });
+        progress(0.3);
+        timed!("Connected internal chunk nodes", {
+            self.connect_internal_chunk_nodes()
+        });
+        progress(0.9);
+        timed!("Connected adjacent chunk nodes", {
+            self.connect_adjacent_chunk_nodes()
+        });
+
+        for (_, chunk) in self.chunks.indexed_iter_mut() {
+            chunk.clean();
+
+            if self.dirty_chunks.contains(&chunk.index()) {
+                chunk.bump_version();
+            }
+        }
+
+        self.uniform_cost = self.compute_uniform_cost();
 
         #[cfg(feature = "stats")]
         {
@@ -716,6 +2107,22 @@ impl<N: Neighborhood + Default> Grid<N> {
         self.dirty = false;
         self.dirty_chunks.clear();
         self.built = true;
+        progress(1.0);
+    }
+
+    // Returns the shared movement cost if every passable cell costs the same and sits at the same height.
+    fn compute_uniform_cost(&self) -> Option<MovementCost> {
+        let mut cost = None;
+
+        for cell in self.grid.iter().filter(|cell| cell.is_passable()) {
+            match cost {
+                None => cost = Some((cell.cost, cell.climb)),
+                Some(cost) if cost != (cell.cost, cell.climb) => return None,
+                _ => {}
+            }
+        }
+
+        cost.map(|(cost, _)| cost)
     }
 
     fn precompute_neighbors(&mut self) {
@@ -735,6 +2142,7 @@ impl<N: Neighborhood + Default> Grid<N> {
         let mut updates = Vec::new();
         let grid_view = self.grid.view();
         let neighborhood = &self.neighborhood;
+        let provider = self.neighbor_provider.as_deref();
 
         for (_, chunk) in self.chunks.indexed_iter_mut() {
             if !self.dirty_chunks.contains(&chunk.index()) {
@@ -742,15 +2150,22 @@ impl<N: Neighborhood + Default> Grid<N> {
             }
 
             for pos in chunk.bounds() {
-                let (pos, bits, special) = compute_cell_neighbors(neighborhood, &grid_view, pos);
-                updates.push((pos, bits, special));
+                updates.push(compute_cell_neighbors(
+                    neighborhood,
+                    provider,
+                    &grid_view,
+                    pos,
+                    self.wrap,
+                ));
             }
         }
 
         // Apply updates after view is dropped
-        for (pos, bits, special) in updates {
-            self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].neighbor_bits = bits;
-            self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].special_neighbors = special;
+        for (pos, bits, special, links) in updates {
+            let cell = &mut self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]];
+            cell.neighbor_bits = bits;
+            cell.special_neighbors = special;
+            cell.links = links;
         }
     }
 
@@ -759,8 +2174,10 @@ impl<N: Neighborhood + Default> Grid<N> {
     fn precompute_neighbors_parallel(&mut self) {
         let grid_view = self.grid.view();
         let neighborhood = &self.neighborhood;
+        let provider = self.neighbor_provider.as_deref();
+        let wrap = self.wrap;
 
-        let updates: Vec<(UVec3, u32, Vec<UVec3>)> = self
+        let updates: Vec<CellNeighbors> = self
             .chunks
             .indexed_iter()
             .par_bridge() // rayon parallel iterator over non-par types
@@ -772,9 +2189,7 @@ impl<N: Neighborhood + Default> Grid<N> {
                 let updates = chunk
                     .bounds()
                     .map(|pos| {
-                        let (pos, bits, special) =
-                            compute_cell_neighbors(neighborhood, &grid_view, pos);
-                        (pos, bits, special)
+                        compute_cell_neighbors(neighborhood, provider, &grid_view, pos, wrap)
                     })
                     .collect::<Vec<_>>();
 
@@ -786,9 +2201,11 @@ impl<N: Neighborhood + Default> Grid<N> {
             .collect();
 
         // Now apply updates
-        for (pos, bits, special) in updates {
-            self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].neighbor_bits = bits;
-            self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]].special_neighbors = special;
+        for (pos, bits, special, links) in updates {
+            let cell = &mut self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]];
+            cell.neighbor_bits = bits;
+            cell.special_neighbors = special;
+            cell.links = links;
         }
     }
 
@@ -806,11 +2223,7 @@ impl<N: Neighborhood + Default> Grid<N> {
     /// Shared logic for building nodes for a single chunk at (x, y, z).
     /// Returns (nodes_to_add, cleaned_edges).
     fn build_nodes_for_chunk(&self, x: usize, y: usize, z: usize) -> Option<(Vec<Node>, Vec<Dir>)> {
-        let chunk_size = self.chunk_settings.size as usize;
-        let chunk_depth = self.chunk_settings.depth as usize;
-        let x_chunks = self.dimensions.x as usize / chunk_size;
-        let y_chunks = self.dimensions.y as usize / chunk_size;
-        let z_chunks = self.dimensions.z as usize / chunk_depth;
+        let (x_chunks, y_chunks, z_chunks) = self.chunks.dim();
 
         let chunk = &self.chunks[[x, y, z]];
 
@@ -837,16 +2250,12 @@ impl<N: Neighborhood + Default> Grid<N> {
                 continue;
             }
 
-            let dir_vec = dir.offset();
-            let nx = x as i32 + dir_vec.x;
-            let ny = y as i32 + dir_vec.y;
-            let nz = z as i32 + dir_vec.z;
-
-            if !in_bounds(nx, ny, nz) {
+            // Faces on a wrapped edge of the grid get entrances to the chunk on the opposite edge
+            let Some(neighbor_index) = self.adjacent_chunk_index((x, y, z), dir) else {
                 continue;
-            }
+            };
 
-            let neighbor_chunk = &self.chunks[[nx as usize, ny as usize, nz as usize]];
+            let neighbor_chunk = &self.chunks[neighbor_index];
 
             let current_edge = chunk.face(&self.grid, dir);
             let neighbor_edge = neighbor_chunk.face(&self.grid, dir.opposite());
@@ -923,6 +2332,11 @@ impl<N: Neighborhood + Default> Grid<N> {
                     continue;
                 }
 
+                // Respect the neighborhood rules, such as corner clipping filters
+                if current_corner.neighbor_bits & (1 << offset_index(dir_vec)) == 0 {
+                    continue;
+                }
+
                 let pos = chunk.corner_pos(dir);
 
                 nodes_to_add.push(Node::new(pos, chunk.clone(), Some(dir)));
@@ -1006,6 +2420,22 @@ impl<N: Neighborhood + Default> Grid<N> {
             }
         }
 
+        // Stacked chunks are only joined where a cell can move straight up or down,
+        // planar neighborhoods only do so at cells with vertical links.
+        let vertical = matches!(dir, Dir::Up | Dir::Down);
+        if vertical {
+            let bit = 1 << offset_index(dir.offset());
+            let opposite_bit = 1 << offset_index(dir.opposite().offset());
+
+            Zip::from(&mut start_mask)
+                .and(&start_face)
+                .and(&end_face)
+                .for_each(|mask, start, end| {
+                    *mask &=
+                        start.neighbor_bits & bit != 0 || end.neighbor_bits & opposite_bit != 0;
+                });
+        }
+
         // AND the masks and get the intersection
         let mut intersection = Array2::from_elem(start_face.raw_dim(), false);
         Zip::from(&mut intersection)
@@ -1030,7 +2460,8 @@ impl<N: Neighborhood + Default> Grid<N> {
             nodes.push(Node::new(pos, chunk.clone(), Some(dir)));
         }
         // If no nodes found and ordinal movement is allowed, try checking adjacent edge cells
-        if nodes.is_empty() && self.neighborhood.is_ordinal() {
+        let planar = self.neighborhood.directions().iter().all(|dir| dir.2 == 0);
+        if nodes.is_empty() && self.neighborhood.is_ordinal() && !(vertical && planar) {
             let mut ordinal_nodes = Vec::new();
             for ((x, y), start_cell) in start_face.indexed_iter() {
                 if start_cell.is_impassable() {
@@ -1208,7 +2639,15 @@ impl<N: Neighborhood + Default> Grid<N> {
                     .map(|other| other.pos - chunk.min())
                     .collect();
 
-                let paths = dijkstra_grid(&chunk_grid, start, &goals, false, 100, &HashMap::new());
+                let paths = dijkstra_grid(
+                    &self.neighborhood,
+                    &chunk_grid,
+                    start,
+                    &goals,
+                    false,
+                    100,
+                    &HashMap::new(),
+                );
 
                 for (goal_pos, path) in paths.into_iter() {
                     let world_start = node.pos;
@@ -1222,7 +2661,7 @@ impl<N: Neighborhood + Default> Grid<N> {
                     all_connections.push((
                         world_start,
                         world_goal,
-                        Path::new(path_vec.clone(), path_vec.len() as u32),
+                        Path::new(path_vec, path.cost()),
                     ));
                 }
             }
@@ -1247,6 +2686,7 @@ impl<N: Neighborhood + Default> Grid<N> {
             self.graph.remove_edges_for_chunk(chunk);
 
             let nodes = self.graph.nodes_in_chunk(chunk);
+            let neighborhood = &self.neighborhood;
 
             let all_connections: Vec<_> = nodes
                 .par_iter()
@@ -1258,8 +2698,15 @@ impl<N: Neighborhood + Default> Grid<N> {
                         .map(|other| other.pos - chunk.min())
                         .collect::<Vec<_>>();
 
-                    let paths =
-                        dijkstra_grid(&chunk_grid, start, &goals, false, 100, &HashMap::new());
+                    let paths = dijkstra_grid(
+                        neighborhood,
+                        &chunk_grid,
+                        start,
+                        &goals,
+                        false,
+                        100,
+                        &HashMap::new(),
+                    );
 
                     paths.into_iter().map(move |(goal_pos, path)| {
                         let world_start = node.pos;
@@ -1269,11 +2716,7 @@ impl<N: Neighborhood + Default> Grid<N> {
                             .iter()
                             .map(|p| *p + chunk.min())
                             .collect::<Vec<_>>();
-                        (
-                            world_start,
-                            world_goal,
-                            Path::new(path_vec.clone(), path_vec.len() as u32),
-                        )
+                        (world_start, world_goal, Path::new(path_vec, path.cost()))
                     })
                 })
                 .collect();
@@ -1319,18 +2762,34 @@ impl<N: Neighborhood + Default> Grid<N> {
 
             for dir in directions {
                 let dir_vec = dir.offset();
+                let target = node.pos.as_ivec3() + dir_vec;
 
-                let nx = node.pos.x as i32 + dir_vec.x;
-                let ny = node.pos.y as i32 + dir_vec.y;
-                let nz = node.pos.z as i32 + dir_vec.z;
+                // Positions off a wrapped edge are the entrances on the opposite edge
+                let Some(target_pos) = wrap_position(target, self.dimensions, self.wrap) else {
+                    continue;
+                };
 
-                if let Some(neighbor) = self
-                    .graph
-                    .node_at(UVec3::new(nx as u32, ny as u32, nz as u32))
-                {
-                    // Check if neighbor is in a different chunk
-                    if node.chunk_index != neighbor.chunk_index {
-                        let path = Path::from_slice(&[node.pos, neighbor.pos], 1);
+                if let Some(neighbor) = self.graph.node_at(target_pos) {
+                    // Check if neighbor is in a different chunk and the move is allowed by the neighborhood
+                    let reachable = if target_pos.as_ivec3() == target {
+                        self.navcell(node.pos).neighbor_bits & (1 << offset_index(dir_vec)) != 0
+                    } else {
+                        self.navcell(node.pos)
+                            .special_neighbors
+                            .contains(&target_pos)
+                    };
+
+                    if node.chunk_index != neighbor.chunk_index && reachable {
+                        let cost = self.neighborhood.step_cost(
+                            node.pos,
+                            neighbor.pos,
+                            self.navcell(node.pos).move_cost(
+                                node.pos,
+                                neighbor.pos,
+                                self.navcell(neighbor.pos),
+                            ),
+                        );
+                        let path = Path::from_slice(&[node.pos, neighbor.pos], cost);
 
                         connections.push((node.pos, neighbor.pos, path));
                     }
@@ -1400,6 +2859,63 @@ impl<N: Neighborhood + Default> Grid<N> {
         reroute_path(self, path, start, goal, blocking, refined)
     }
 
+    /// Repairs `old_path` for an agent that has moved to `current` after the grid or `blocking` changed.
+    ///
+    /// Only the stretch of the path that's no longer walkable is replanned, the path rejoins the still valid
+    /// rest of `old_path` as soon as it can so the route stays stable. Falls back to [`Grid::reroute_path`]
+    /// and then to a full search when the path can't be repaired. Returns `old_path` unchanged if it's still walkable.
+    ///
+    /// # Arguments
+    /// * `current` - The current position of the agent.
+    /// * `goal` - The goal position of the path.
+    /// * `old_path` - The remaining [`Path`] from `current` to `goal`, `current` excluded.
+    /// * `blocking` - A map of positions to entities that are blocking the path. Pass `&HashMap::new()` if you're not concerned with collision.
+    ///
+    /// # Returns
+    /// The repaired [`Path`], or `None` if `goal` can't be reached from `current`.
+    ///
+    /// # Example
+    /// ```
+    /// use bevy::{math::UVec3, platform::collections::HashMap};
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GridSettingsBuilder::new_2d(16, 16).build());
+    /// grid.build();
+    ///
+    /// let path = grid.pathfind(UVec3::new(0, 4, 0), UVec3::new(15, 4, 0), &HashMap::new(), false).unwrap();
+    ///
+    /// grid.set_nav(UVec3::new(8, 4, 0), Nav::Impassable);
+    /// grid.build();
+    ///
+    /// let repaired = grid.repath_from(UVec3::new(0, 4, 0), UVec3::new(15, 4, 0), &path, &HashMap::new()).unwrap();
+    /// assert!(!repaired.path().contains(&UVec3::new(8, 4, 0)));
+    /// ```
+    pub fn repath_from(
+        &self,
+        current: UVec3,
+        goal: UVec3,
+        old_path: &Path,
+        blocking: &HashMap<UVec3, Entity>,
+    ) -> Option<Path> {
+        if self.needs_build() {
+            return None;
+        }
+
+        repair_path(self, old_path, current, goal, blocking)
+            .or_else(|| reroute_path(self, old_path, current, goal, blocking, true))
+            .or_else(|| {
+                pathfind(
+                    self,
+                    &self.neighborhood,
+                    current,
+                    goal,
+                    blocking,
+                    false,
+                    true,
+                )
+            })
+    }
+
     /// Checks if a path exists from `start` to `goal` using the fastest algorithm.
     /// Ignores any blocking entities.
     ///
@@ -1414,683 +2930,3596 @@ impl<N: Neighborhood + Default> Grid<N> {
             return false;
         }
 
-        pathfind(self, start, goal, &HashMap::new(), false, false).is_some()
+        pathfind(
+            self,
+            &self.neighborhood,
+            start,
+            goal,
+            &HashMap::new(),
+            false,
+            false,
+        )
+        .is_some()
     }
 
-    /// Generate an HPA* path from `start` to `goal`.
+    /// Find a path from `start` to `goal` immediately using the given [`PathfindMode`].
+    ///
+    /// This doesn't require the [`crate::plugin::NorthstarPlugin`] systems, doesn't mutate the grid,
+    /// and is safe to call from parallel systems through `&Grid`. Useful for turn based games,
+    /// UI previews, AI planning, and tests.
+    ///
+    /// **Note:** Dynamic collision avoidance with [`crate::components::Blocking`] entities is not applied
+    /// since this call has no access to agent entities. Use the mode specific methods such as [`Grid::pathfind`]
+    /// if you need to pass a blocking map.
     ///
     /// # Arguments
     /// * `start` - The starting position in the grid.
     /// * `goal` - The goal position in the grid.
-    /// * `blocking` - A map of positions to entities that are blocking the path. Pass `&HashMap::new()` if you're not concerned with collision.
-    ///   Pass `&HasMap::new()` if you're not concerned with collision. If using [`crate::plugin::NorthstarPlugin`] you can pass it the [`crate::plugin::BlockingMap`] resource.
-    ///   If not, build a [`HashMap<UVec3, Entity>`] with the positions of entities that should be blocking paths.
+    /// * `mode` - The [`PathfindMode`] algorithm to use.
     /// * `partial` - Whether to allow partial paths (i.e., if the goal is unreachable, return the closest reachable point).
     /// # Returns
     /// A [`Path`] if successful, or `None` if no viable path could be found.
     ///
-    pub fn pathfind(
+    /// # Example
+    /// ```
+    /// use bevy::math::UVec3;
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let grid_settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+    /// let mut grid: Grid<CardinalNeighborhood> = Grid::new(&grid_settings);
+    /// grid.build();
+    ///
+    /// let path = grid.find_path(UVec3::new(0, 0, 0), UVec3::new(15, 15, 0), PathfindMode::AStar, false);
+    /// assert!(path.is_some());
+    /// ```
+    pub fn find_path(
         &self,
         start: UVec3,
         goal: UVec3,
-        blocking: &HashMap<UVec3, Entity>,
+        mode: PathfindMode,
         partial: bool,
     ) -> Option<Path> {
-        if self.needs_build() {
-            return None;
-        }
-
-        pathfind(self, start, goal, blocking, partial, true)
+        self.find_path_with_blocking(
+            start,
+            &SearchGoal::Position(goal),
+            mode,
+            &HashMap::new(),
+            partial,
+            None,
+            ALL_NAV_LAYERS,
+            UVec2::ONE,
+            &SearchContext::default(),
+        )
     }
 
-    /// Generate a coarse (unrefined) HPA* path from `start` to `goal`.
+    /// Like [`Grid::find_path`] but returns why no path was found, so gameplay code can tell
+    /// a goal inside a wall ([`PathError::GoalBlocked`]) from a goal that can't be reached ([`PathError::Disconnected`]).
     ///
-    /// This method is useful for generating paths when the shortest viable path is not required.
+    /// # Example
+    /// ```rust,no_run
+    /// use bevy::math::UVec3;
+    /// use bevy_northstar::prelude::*;
     ///
-    /// # Arguments
-    /// * `start` - The starting position in the grid.
-    /// * `goal` - The goal position in the grid.
-    /// * `blocking` - A map of positions to entities that are blocking the path. Pass `&HashMap::new()` if you're not concerned with collision.
-    ///   Pass `&HasMap::new()` if you're not concerned with collision. If using [`crate::plugin::NorthstarPlugin`] you can pass it the [`crate::plugin::BlockingMap`] resource.
-    ///   If not, build a [`HashMap<UVec3, Entity>`] with the positions of entities that should be blocking paths.
-    /// * `partial` - Whether to allow partial paths (i.e., if the goal is unreachable, return the closest reachable point).
-    /// # Returns
-    /// A [`Path`] if successful, or `None` if no viable path could be found.
+    /// let grid_settings = GridSettingsBuilder::new_2d(64, 64).chunk_size(16).build();
+    /// let mut grid: Grid<CardinalNeighborhood> = Grid::new(&grid_settings);
+    /// grid.build();
     ///
-    pub fn pathfind_coarse(
+    /// match grid.try_find_path(UVec3::new(0, 0, 0), UVec3::new(63, 63, 0), PathfindMode::Refined, false) {
+    ///     Ok(path) => println!("Found a path costing {}", path.cost()),
+    ///     Err(PathError::GoalBlocked) => println!("You can't move into a wall"),
+    ///     Err(error) => println!("No path: {error}"),
+    /// }
+    /// ```
+    pub fn try_find_path(
         &self,
         start: UVec3,
         goal: UVec3,
-        blocking: &HashMap<UVec3, Entity>,
+        mode: PathfindMode,
         partial: bool,
-    ) -> Option<Path> {
-        if self.needs_build() {
-            return None;
+    ) -> Result<Path, PathError> {
+        self.find_path(start, goal, mode, partial)
+            .ok_or_else(|| self.path_error(start, &SearchGoal::Position(goal), partial))
+    }
+
+    // Works out why a search from `start` towards `goal` found no path.
+    // Goals only count as blocked or disconnected if every goal is, and only when no partial path was requested.
+    pub(crate) fn path_error(&self, start: UVec3, goal: &SearchGoal, partial: bool) -> PathError {
+        if !self.in_bounds(start) {
+            return PathError::OutOfBounds(start);
+        }
+
+        let goals = goal.positions();
+
+        if let Some(goal) = goals.first() {
+            if goals.iter().all(|goal| !self.in_bounds(*goal)) {
+                return PathError::OutOfBounds(*goal);
+            }
+        }
+
+        if !self.is_passable(start) {
+            return PathError::StartBlocked;
+        }
+
+        if partial || goals.is_empty() || self.needs_build() {
+            return PathError::NoPathFound;
+        }
+
+        if goals.iter().all(|goal| !self.is_passable(*goal)) {
+            return PathError::GoalBlocked;
+        }
+
+        if goals.iter().all(|goal| !self.same_component(start, *goal)) {
+            return PathError::Disconnected;
         }
 
-        pathfind(self, start, goal, blocking, partial, false)
+        PathError::NoPathFound
     }
 
-    /// Generate a traditional A* path from `start` to `goal`.
-    /// This method is useful for generating paths that require precise navigation and CPU cost isn't a concern.
-    /// Great for a turn based game where movment cost is important.
+    /// Like [`Grid::find_path`] but runs the search on the [`AsyncComputeTaskPool`] so long searches don't stall the frame.
+    /// The grid is shared with the task through the [`Arc`], so it can't be modified while the search runs,
+    /// clone the grid into a new [`Arc`] before making changes if you need to keep searching in the background.
     ///
-    /// # Arguments
-    /// * `start` - The starting position in the grid.
-    /// * `goal` - The goal position in the grid.
-    /// * `blocking` - A map of positions to entities that are blocking the path. Pass `&HashMap::new()` if you're not concerned with collision.
-    ///   Pass `&HasMap::new()` if you're not concerned with collision. If using [`crate::plugin::NorthstarPlugin`] you can pass it the [`crate::plugin::BlockingMap`] resource.
-    ///   If not, build a [`HashMap<UVec3, Entity>`] with the positions of entities that should be blocking paths.
-    /// * `partial` - Whether to allow partial paths (i.e., if the goal is unreachable, return the closest reachable point).
-    /// # Returns
-    /// A [`Path`] if successful, or `None` if no viable path could be found.
+    /// Poll the returned [`Task`] every frame, or `.await` it. Dropping the task cancels the search.
+    /// With [`crate::plugin::NorthstarPlugin`] add the [`crate::components::AsyncPathfind`] component to an agent instead.
     ///
-    pub fn pathfind_astar(
+    /// # Example
+    /// ```rust,no_run
+    /// use std::sync::Arc;
+    /// use bevy::{math::UVec3, tasks::{block_on, futures_lite::future}};
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let grid_settings = GridSettingsBuilder::new_2d(512, 512).chunk_size(16).build();
+    /// let mut grid: Grid<CardinalNeighborhood> = Grid::new(&grid_settings);
+    /// grid.build();
+    ///
+    /// let grid = Arc::new(grid);
+    /// let mut task = grid.find_path_async(UVec3::new(0, 0, 0), UVec3::new(511, 511, 0), PathfindMode::AStar, false);
+    ///
+    /// // Check on the task once per frame
+    /// if let Some(path) = block_on(future::poll_once(&mut task)) {
+    ///     // The search has completed
+    /// }
+    /// ```
+    pub fn find_path_async(
+        self: &Arc<Self>,
+        start: UVec3,
+        goal: UVec3,
+        mode: PathfindMode,
+        partial: bool,
+    ) -> Task<Option<Path>>
+    where
+        N: 'static,
+    {
+        let grid = self.clone();
+
+        AsyncComputeTaskPool::get_or_init(TaskPool::default)
+            .spawn(async move { grid.find_path(start, goal, mode, partial) })
+    }
+
+    /// Like [`Grid::find_path`] but also records every cell the search expands along with its g and h scores.
+    /// Useful for visualizing how a search behaves, see [`SearchTrace`].
+    ///
+    /// Only the grid searches of [`PathfindMode::AStar`], [`PathfindMode::Jps`], and [`PathfindMode::Theta`] are recorded,
+    /// the trace of the HPA* modes is empty.
+    pub fn find_path_traced(
         &self,
         start: UVec3,
         goal: UVec3,
-        blocking: &HashMap<UVec3, Entity>,
+        mode: PathfindMode,
         partial: bool,
-    ) -> Option<Path> {
-        if self.needs_build() {
-            return None;
-        }
+    ) -> (Option<Path>, SearchTrace) {
+        let context = SearchContext::default().traced();
 
-        pathfind_astar(
-            &self.neighborhood,
-            &self.grid.view(),
+        let path = self.find_path_with_blocking(
             start,
-            goal,
-            blocking,
+            &SearchGoal::Position(goal),
+            mode,
+            &HashMap::new(),
             partial,
-        )
+            None,
+            ALL_NAV_LAYERS,
+            UVec2::ONE,
+            &context,
+        );
+
+        (path, context.into_trace().unwrap_or_default())
     }
 
-    /// Generate an A* path within a cubic radius around the `start` position.
-    /// This can be used to limit an A* search to a confined search area.
-    /// You'll want to ensure your radius at least covers the distance to the goal.
+    /// Find a path from `start` to whichever of the `goals` is cheapest to reach using the given [`PathfindMode`].
+    ///
+    /// A* based modes search for all goals at once using the minimum heuristic over all goals.
+    /// The HPA* modes skip goals walled off from `start` and only search the hierarchy towards the nearest
+    /// remaining goal by heuristic, which isn't always the cheapest one to reach around obstacles.
+    /// Goals that cost the same to reach are preferred in the order given, see [`Path::goal_index`].
+    /// [`PathfindMode::FlowField`] falls back to A* for multiple goals.
+    ///
+    /// If `partial` is set and none of the goals are reachable, a partial path towards the nearest goal is returned.
+    /// Like [`Grid::find_path`], dynamic collision avoidance with [`crate::components::Blocking`] entities is not applied.
     ///
     /// # Arguments
     /// * `start` - The starting position in the grid.
-    /// * `goal` - The goal position in the grid.
-    /// * `radius` - The radius around the start position to search for a path.
-    /// * `blocking` - A map of positions to entities that are blocking the path.
-    ///   Pass `&HasMap::new()` if you're not concerned with collision. If using [`crate::plugin::NorthstarPlugin`] you can pass it the [`crate::plugin::BlockingMap`] resource.
-    ///   If not, build a [`HashMap<UVec3, Entity>`] with the positions of entities that should be blocking paths.
-    /// * `partial` - Whether to allow partial paths (i.e., if the goal is unreachable, return the closest reachable point).
+    /// * `goals` - The candidate goal positions.
+    /// * `mode` - The [`PathfindMode`] algorithm to use.
+    /// * `partial` - Whether to allow partial paths.
     /// # Returns
-    /// A [`Path`] if successful, or `None` if no viable path could be found.
+    /// A [`Path`] to the cheapest goal if successful, or `None` if no viable path could be found.
     ///
-    pub fn pathfind_astar_radius(
+    pub fn find_path_multi(
         &self,
         start: UVec3,
-        goal: UVec3,
-        radius: u32,
-        blocking: &HashMap<UVec3, Entity>,
+        goals: &[UVec3],
+        mode: PathfindMode,
         partial: bool,
     ) -> Option<Path> {
-        if self.needs_build() {
-            return None;
+        self.find_path_with_blocking(
+            start,
+            &SearchGoal::Any(goals),
+            mode,
+            &HashMap::new(),
+            partial,
+            None,
+            ALL_NAV_LAYERS,
+            UVec2::ONE,
+            &SearchContext::default(),
+        )
+    }
+
+    /// Finds the cheapest path from `start` to each of the `goals` with a single Dijkstra expansion.
+    /// Cheaper than calling [`Grid::find_path`] for each goal when previewing many destinations at once,
+    /// such as every cell a unit could move to, since the goals share the expansion.
+    ///
+    /// Unreachable goals are left out of the map, the search stops once every reachable goal is found.
+    /// Cells blocked with [`Grid::block_temporarily`] are avoided but cost overrides aren't added to the paths.
+    /// Returns an empty map if `start` isn't passable or the grid needs to be built.
+    pub fn paths_from(&self, start: UVec3, goals: &[UVec3]) -> HashMap<UVec3, Path> {
+        if !self.is_passable(start) || self.needs_build() {
+            return HashMap::new();
         }
 
-        let min = start.as_ivec3().saturating_sub(IVec3::splat(radius as i32));
-        let max = start
-            .as_ivec3()
-            .saturating_add(IVec3::splat(radius as i32) + IVec3::ONE);
+        // Goals walled off from the start would make the search expand every cell it can reach
+        let goals = goals
+            .iter()
+            .copied()
+            .filter(|goal| self.same_component(start, *goal))
+            .collect::<Vec<_>>();
+        if goals.is_empty() {
+            return HashMap::new();
+        }
 
-        let grid_shape = self.grid.shape();
-        let min = min.max(IVec3::ZERO);
-        let max = max.min(IVec3::new(
-            grid_shape[0] as i32,
-            grid_shape[1] as i32,
-            grid_shape[2] as i32,
-        ));
+        dijkstra_grid(
+            &self.neighborhood,
+            &self.grid.view(),
+            start,
+            &goals,
+            false,
+            self.grid.len(),
+            &self.blocking_with_temporary(&HashMap::new()),
+        )
+    }
 
-        let grid_shape_vec = IVec3::new(
-            grid_shape[0] as i32,
-            grid_shape[1] as i32,
-            grid_shape[2] as i32,
-        );
-        if !position_in_cubic_window(goal, start.as_ivec3(), radius as i32, grid_shape_vec) {
-            return None;
+    /// Returns every cell reachable from `start` within a movement `budget`, mapped to the cost of reaching it.
+    /// This is the classic tactics movement range highlight, subtract the cost from the budget to color cells
+    /// by the movement left over.
+    ///
+    /// Cell costs, cost overrides and the grid's neighborhood are honored. Cells blocked with
+    /// [`Grid::block_temporarily`] are avoided. `start` is included with a cost of 0.
+    /// Returns an empty map if `start` isn't passable or the grid needs to be built.
+    ///
+    /// # Example
+    /// ```
+    /// use bevy::math::UVec3;
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build());
+    /// grid.build();
+    ///
+    /// let range = grid.movement_range(UVec3::new(4, 4, 0), 2);
+    /// assert_eq!(range.get(&UVec3::new(4, 6, 0)), Some(&2));
+    /// assert!(!range.contains_key(&UVec3::new(5, 6, 0)));
+    /// ```
+    pub fn movement_range(
+        &self,
+        start: UVec3,
+        budget: MovementCost,
+    ) -> HashMap<UVec3, MovementCost> {
+        if !self.is_passable(start) || self.needs_build() {
+            return HashMap::new();
         }
 
-        // Create a subview of the grid
-        let view = self.grid.slice(s![
-            min.x as usize..max.x as usize,
-            min.y as usize..max.y as usize,
-            min.z as usize..max.z as usize
-        ]);
-
-        // Remap start/goal into local view
-        let start_local = (start.as_ivec3() - min).as_uvec3();
+        dijkstra_range(
+            &self.neighborhood,
+            &self.grid.view(),
+            start,
+            budget,
+            &self.blocking_with_temporary(&HashMap::new()),
+            &self.extra_costs,
+        )
+    }
+
+    /// Find a path from `start` to `goal` that only enters cells `filter` returns `true` for,
+    /// on top of the grid's own [`Nav`]. Useful for one-off constraints such as avoiding a quadrant
+    /// or an area a scripted AI believes is dangerous, without mutating the grid.
+    ///
+    /// Arbitrary filters break the assumptions of the HPA* hierarchy and the other modes,
+    /// so every `mode` searches the grid with [`PathfindMode::AStar`]. The start position is never filtered.
+    ///
+    /// # Example
+    /// ```
+    /// use bevy::math::UVec3;
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let grid_settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+    /// let mut grid: Grid<CardinalNeighborhood> = Grid::new(&grid_settings);
+    /// grid.build();
+    ///
+    /// // Stay out of the upper right quadrant
+    /// let path = grid
+    ///     .find_path_filtered(UVec3::new(0, 15, 0), UVec3::new(15, 0, 0), PathfindMode::AStar, |pos| {
+    ///         pos.x < 8 || pos.y < 8
+    ///     })
+    ///     .unwrap();
+    /// assert!(path.path().iter().all(|pos| pos.x < 8 || pos.y < 8));
+    /// ```
+    pub fn find_path_filtered(
+        &self,
+        start: UVec3,
+        goal: UVec3,
+        mode: PathfindMode,
+        filter: impl Fn(UVec3) -> bool,
+    ) -> Option<Path> {
+        self.find_path_with_blocking(
+            start,
+            &SearchGoal::Position(goal),
+            mode,
+            &HashMap::new(),
+            false,
+            None,
+            ALL_NAV_LAYERS,
+            UVec2::ONE,
+            &SearchContext::default().filter(&filter),
+        )
+    }
+
+    /// Find a path from `start` to `goal` for an agent covering `size` cells, such as a 2x2 vehicle.
+    ///
+    /// The agent's footprint extends from its position towards positive x and y on the same depth,
+    /// see [`crate::components::AgentSize`]. A step is only taken if every cell the footprint covers is passable,
+    /// and diagonal steps also need room for the cells swept past, so a large agent won't squeeze through
+    /// gaps narrower than itself or clip wall corners.
+    ///
+    /// The HPA* entrances and cached paths are built for single cells, so footprints larger than one cell always
+    /// search the grid with A*. A `size` of 1x1 is the same as [`Grid::find_path`] with the given `mode`.
+    ///
+    /// # Arguments
+    /// * `start` - The position of the agent, the corner of its footprint with the lowest x and y.
+    /// * `goal` - The position to move the agent to.
+    /// * `size` - The width and height of the agent in cells.
+    /// * `mode` - The [`PathfindMode`] algorithm to use for single cell agents.
+    /// * `partial` - Whether to allow partial paths.
+    pub fn find_path_sized(
+        &self,
+        start: UVec3,
+        goal: UVec3,
+        size: UVec2,
+        mode: PathfindMode,
+        partial: bool,
+    ) -> Option<Path> {
+        self.find_path_with_blocking(
+            start,
+            &SearchGoal::Position(goal),
+            mode,
+            &HashMap::new(),
+            partial,
+            None,
+            ALL_NAV_LAYERS,
+            size.max(UVec2::ONE),
+            &SearchContext::default(),
+        )
+    }
+
+    /// Find a path from `start` to the closest reachable cell inside the box between `min` and `max` (inclusive)
+    /// using the given [`PathfindMode`].
+    ///
+    /// A* based modes stop as soon as any cell inside the region is reached, using the distance to the region as the heuristic.
+    /// The HPA* modes treat every chunk overlapping the region as a potential goal chunk.
+    /// [`PathfindMode::FlowField`] falls back to A* for regions.
+    ///
+    /// If `partial` is set and the region can't be reached, a partial path towards the region is returned.
+    /// Like [`Grid::find_path`], dynamic collision avoidance with [`crate::components::Blocking`] entities is not applied.
+    ///
+    /// # Arguments
+    /// * `start` - The starting position in the grid.
+    /// * `min` - The minimum corner of the region.
+    /// * `max` - The maximum corner of the region.
+    /// * `mode` - The [`PathfindMode`] algorithm to use.
+    /// * `partial` - Whether to allow partial paths.
+    /// # Returns
+    /// A [`Path`] ending inside the region if successful, or `None` if no viable path could be found.
+    ///
+    pub fn find_path_region(
+        &self,
+        start: UVec3,
+        min: UVec3,
+        max: UVec3,
+        mode: PathfindMode,
+        partial: bool,
+    ) -> Option<Path> {
+        self.find_path_with_blocking(
+            start,
+            &SearchGoal::Region { min, max },
+            mode,
+            &HashMap::new(),
+            partial,
+            None,
+            ALL_NAV_LAYERS,
+            UVec2::ONE,
+            &SearchContext::default(),
+        )
+    }
+
+    // Dispatches to the pathfinding method matching the `PathfindMode`.
+    // `heuristic` replaces the neighborhood's heuristic for this search only.
+    // Searches restricted to some `layers`, or for agents with a `footprint` larger than one cell,
+    // always use A* since the hierarchy ignores layers and is built for single cells.
+    // `context` caps and traces the node expansions of the A*, JPS and Theta* searches, the HPA* modes are bounded by the hierarchy.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn find_path_with_blocking(
+        &self,
+        start: UVec3,
+        goal: &SearchGoal,
+        mode: PathfindMode,
+        blocking: &HashMap<UVec3, Entity>,
+        partial: bool,
+        heuristic: Option<HeuristicKind>,
+        layers: NavLayers,
+        footprint: UVec2,
+        context: &SearchContext,
+    ) -> Option<Path> {
+        // Temporarily blocked cells are searched around like cells occupied by blocking agents
+        let merged;
+        let blocking = if self.temporary_blocks.is_empty() {
+            blocking
+        } else {
+            merged = self.blocking_with_temporary(blocking);
+            &merged
+        };
+
+        match heuristic {
+            Some(kind) => {
+                let neighborhood = HeuristicOverride {
+                    neighborhood: self.neighborhood.clone(),
+                    kind,
+                };
+                self.search_wrapped(
+                    &neighborhood,
+                    start,
+                    goal,
+                    mode,
+                    blocking,
+                    partial,
+                    layers,
+                    footprint,
+                    context,
+                )
+            }
+            None => self.search_wrapped(
+                &self.neighborhood,
+                start,
+                goal,
+                mode,
+                blocking,
+                partial,
+                layers,
+                footprint,
+                context,
+            ),
+        }
+    }
+
+    // The pathfinding methods for a single algorithm go through the dispatch on wrapped grids and grids with a height cost,
+    // which measures the heuristic around the wrapped edges and over the height difference and runs JPS and Theta* as A* when wrapped.
+    fn find_path_wrapped(
+        &self,
+        start: UVec3,
+        goal: UVec3,
+        mode: PathfindMode,
+        blocking: &HashMap<UVec3, Entity>,
+        partial: bool,
+    ) -> Option<Path> {
+        self.find_path_with_blocking(
+            start,
+            &SearchGoal::Position(goal),
+            mode,
+            blocking,
+            partial,
+            None,
+            ALL_NAV_LAYERS,
+            UVec2::ONE,
+            &SearchContext::default(),
+        )
+    }
+
+    // Searches with the heuristic measuring the shorter way around the wrapped edges, if the grid wraps.
+    #[allow(clippy::too_many_arguments)]
+    fn search_wrapped<H: Neighborhood>(
+        &self,
+        neighborhood: &H,
+        start: UVec3,
+        goal: &SearchGoal,
+        mode: PathfindMode,
+        blocking: &HashMap<UVec3, Entity>,
+        partial: bool,
+        layers: NavLayers,
+        footprint: UVec2,
+        context: &SearchContext,
+    ) -> Option<Path> {
+        if !self.wrap.any() {
+            return self.search_climbing(
+                neighborhood,
+                start,
+                goal,
+                mode,
+                blocking,
+                partial,
+                layers,
+                footprint,
+                context,
+            );
+        }
+
+        let wrapped = WrappedHeuristic {
+            neighborhood: neighborhood.clone(),
+            dimensions: self.dimensions,
+            wrap: self.wrap,
+        };
+        self.search_climbing(
+            &wrapped, start, goal, mode, blocking, partial, layers, footprint, context,
+        )
+    }
+
+    // Searches with the heuristic adding the cost of the height difference, if the grid has a height cost.
+    #[allow(clippy::too_many_arguments)]
+    fn search_climbing<H: Neighborhood>(
+        &self,
+        neighborhood: &H,
+        start: UVec3,
+        goal: &SearchGoal,
+        mode: PathfindMode,
+        blocking: &HashMap<UVec3, Entity>,
+        partial: bool,
+        layers: NavLayers,
+        footprint: UVec2,
+        context: &SearchContext,
+    ) -> Option<Path> {
+        if self.height_cost.0 == 0 {
+            return self.search(
+                neighborhood,
+                start,
+                goal,
+                mode,
+                blocking,
+                partial,
+                layers,
+                footprint,
+                context,
+            );
+        }
+
+        let climbing = HeightHeuristic {
+            neighborhood: neighborhood.clone(),
+            cells: Some(self.grid.view()),
+        };
+        self.search(
+            &climbing, start, goal, mode, blocking, partial, layers, footprint, context,
+        )
+    }
+
+    // Whether searches need the adjusted heuristic of `search_wrapped`.
+    fn adjusts_heuristic(&self) -> bool {
+        self.wrap.any() || self.height_cost.0 > 0
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn search<H: Neighborhood>(
+        &self,
+        neighborhood: &H,
+        start: UVec3,
+        goal: &SearchGoal,
+        mode: PathfindMode,
+        blocking: &HashMap<UVec3, Entity>,
+        partial: bool,
+        layers: NavLayers,
+        footprint: UVec2,
+        context: &SearchContext,
+    ) -> Option<Path> {
+        if self.needs_build() {
+            return None;
+        }
+
+        let goal = match *goal {
+            SearchGoal::Any(&[]) => return None,
+            SearchGoal::Any(&[goal]) => SearchGoal::Position(goal),
+            goal => goal,
+        };
+
+        // Nothing to search for if every goal is walled off from the start
+        if !partial {
+            let reachable = match goal {
+                SearchGoal::Position(goal) => self.same_component(start, goal),
+                SearchGoal::Any(goals) => {
+                    goals.iter().any(|goal| self.same_component(start, *goal))
+                }
+                SearchGoal::Region { .. } | SearchGoal::Within { .. } => true,
+            };
+
+            if !reachable {
+                return None;
+            }
+        }
+
+        let hierarchical = |refined: bool| match goal {
+            SearchGoal::Position(goal) => {
+                pathfind(self, neighborhood, start, goal, blocking, partial, refined)
+            }
+            SearchGoal::Any(goals) => {
+                pathfind_goals(self, neighborhood, start, goals, blocking, partial, refined)
+            }
+            SearchGoal::Region { min, max } => pathfind_region(
+                self,
+                neighborhood,
+                start,
+                min,
+                max,
+                blocking,
+                partial,
+                refined,
+            ),
+            SearchGoal::Within { .. } => {
+                pathfind_within(self, neighborhood, start, &goal, blocking, partial, refined)
+            }
+        };
+
+        let astar = || {
+            pathfind_astar_goals(
+                neighborhood,
+                &self.grid.view(),
+                start,
+                &goal,
+                blocking,
+                partial,
+                &self.extra_costs,
+                layers,
+                footprint,
+                context,
+            )
+        };
+
+        // The hierarchy is built for single cells, its entrances and cached paths would route large agents through narrow gaps
+        // Corridors and filters restrict which cells A* expands
+        let grid_only = layers != ALL_NAV_LAYERS
+            || self.has_neighbor_provider()
+            || footprint != UVec2::ONE
+            || context.is_restricted();
+
+        let path = match mode {
+            _ if grid_only => astar(),
+            PathfindMode::Refined => hierarchical(true),
+            PathfindMode::Coarse => hierarchical(false),
+            PathfindMode::AStar => astar(),
+            PathfindMode::FlowField => match goal {
+                SearchGoal::Position(goal) => self.compute_flow_field(goal).path_from(start),
+                // Flow fields only flow towards a single goal
+                _ => astar(),
+            },
+            // JPS and Theta* assume square tiles that end at the grid edges
+            PathfindMode::Jps | PathfindMode::Theta if neighborhood.is_hex() || self.wrap.any() => {
+                astar()
+            }
+            PathfindMode::Jps => pathfind_jps(
+                neighborhood,
+                &self.grid.view(),
+                start,
+                &goal,
+                blocking,
+                partial,
+                self.uniform_cost.is_some(),
+                &self.extra_costs,
+                self.one_way,
+                context,
+            ),
+            PathfindMode::Theta => pathfind_theta(
+                neighborhood,
+                &self.grid.view(),
+                start,
+                &goal,
+                blocking,
+                partial,
+                &self.extra_costs,
+                context,
+            ),
+        };
+
+        let Some(mut path) = path else {
+            let size = self.width().max(self.height()).max(self.depth());
+            if context.widen_corridor(size) {
+                return self.search(
+                    neighborhood,
+                    start,
+                    &goal,
+                    mode,
+                    blocking,
+                    partial,
+                    layers,
+                    footprint,
+                    context,
+                );
+            }
+
+            return None;
+        };
+
+        let goals = goal.positions();
+        let goal_index = path
+            .path()
+            .last()
+            .and_then(|last| goals.iter().position(|goal| goal == last));
+
+        // The grid searches stop at the first goal reached, check that no goal given earlier ties on cost.
+        // The HPA* modes already search the goals in order.
+        let in_order = !grid_only && matches!(mode, PathfindMode::Refined | PathfindMode::Coarse);
+        if let Some(index) = goal_index.filter(|index| *index > 0 && !in_order) {
+            if let Some(earlier) = self.search(
+                neighborhood,
+                start,
+                &SearchGoal::Any(&goals[..index]),
+                mode,
+                blocking,
+                false,
+                layers,
+                footprint,
+                context,
+            ) {
+                if earlier.cost() <= path.cost() {
+                    return Some(earlier);
+                }
+            }
+        }
+
+        path.set_goal_index(goal_index);
+        Some(path)
+    }
+
+    /// Generate an HPA* path from `start` to `goal`.
+    ///
+    /// # Arguments
+    /// * `start` - The starting position in the grid.
+    /// * `goal` - The goal position in the grid.
+    /// * `blocking` - A map of positions to entities that are blocking the path. Pass `&HashMap::new()` if you're not concerned with collision.
+    ///   Pass `&HasMap::new()` if you're not concerned with collision. If using [`crate::plugin::NorthstarPlugin`] you can pass it the [`crate::plugin::BlockingMap`] resource.
+    ///   If not, build a [`HashMap<UVec3, Entity>`] with the positions of entities that should be blocking paths.
+    /// * `partial` - Whether to allow partial paths (i.e., if the goal is unreachable, return the closest reachable point).
+    /// # Returns
+    /// A [`Path`] if successful, or `None` if no viable path could be found.
+    ///
+    pub fn pathfind(
+        &self,
+        start: UVec3,
+        goal: UVec3,
+        blocking: &HashMap<UVec3, Entity>,
+        partial: bool,
+    ) -> Option<Path> {
+        if self.needs_build() {
+            return None;
+        }
+
+        if self.adjusts_heuristic() {
+            return self.find_path_wrapped(start, goal, PathfindMode::Refined, blocking, partial);
+        }
+
+        pathfind(
+            self,
+            &self.neighborhood,
+            start,
+            goal,
+            blocking,
+            partial,
+            true,
+        )
+    }
+
+    /// Generate a coarse (unrefined) HPA* path from `start` to `goal`.
+    ///
+    /// This method is useful for generating paths when the shortest viable path is not required.
+    ///
+    /// # Arguments
+    /// * `start` - The starting position in the grid.
+    /// * `goal` - The goal position in the grid.
+    /// * `blocking` - A map of positions to entities that are blocking the path. Pass `&HashMap::new()` if you're not concerned with collision.
+    ///   Pass `&HasMap::new()` if you're not concerned with collision. If using [`crate::plugin::NorthstarPlugin`] you can pass it the [`crate::plugin::BlockingMap`] resource.
+    ///   If not, build a [`HashMap<UVec3, Entity>`] with the positions of entities that should be blocking paths.
+    /// * `partial` - Whether to allow partial paths (i.e., if the goal is unreachable, return the closest reachable point).
+    /// # Returns
+    /// A [`Path`] if successful, or `None` if no viable path could be found.
+    ///
+    pub fn pathfind_coarse(
+        &self,
+        start: UVec3,
+        goal: UVec3,
+        blocking: &HashMap<UVec3, Entity>,
+        partial: bool,
+    ) -> Option<Path> {
+        if self.needs_build() {
+            return None;
+        }
+
+        if self.adjusts_heuristic() {
+            return self.find_path_wrapped(start, goal, PathfindMode::Coarse, blocking, partial);
+        }
+
+        pathfind(
+            self,
+            &self.neighborhood,
+            start,
+            goal,
+            blocking,
+            partial,
+            false,
+        )
+    }
+
+    /// Generate a traditional A* path from `start` to `goal`.
+    /// This method is useful for generating paths that require precise navigation and CPU cost isn't a concern.
+    /// Great for a turn based game where movment cost is important.
+    ///
+    /// # Arguments
+    /// * `start` - The starting position in the grid.
+    /// * `goal` - The goal position in the grid.
+    /// * `blocking` - A map of positions to entities that are blocking the path. Pass `&HashMap::new()` if you're not concerned with collision.
+    ///   Pass `&HasMap::new()` if you're not concerned with collision. If using [`crate::plugin::NorthstarPlugin`] you can pass it the [`crate::plugin::BlockingMap`] resource.
+    ///   If not, build a [`HashMap<UVec3, Entity>`] with the positions of entities that should be blocking paths.
+    /// * `partial` - Whether to allow partial paths (i.e., if the goal is unreachable, return the closest reachable point).
+    /// # Returns
+    /// A [`Path`] if successful, or `None` if no viable path could be found.
+    ///
+    pub fn pathfind_astar(
+        &self,
+        start: UVec3,
+        goal: UVec3,
+        blocking: &HashMap<UVec3, Entity>,
+        partial: bool,
+    ) -> Option<Path> {
+        if self.needs_build() {
+            return None;
+        }
+
+        if self.adjusts_heuristic() {
+            return self.find_path_wrapped(start, goal, PathfindMode::AStar, blocking, partial);
+        }
+
+        pathfind_astar(
+            &self.neighborhood,
+            &self.grid.view(),
+            start,
+            goal,
+            blocking,
+            partial,
+            &self.extra_costs,
+        )
+    }
+
+    /// Generate a Jump Point Search path from `start` to `goal`.
+    /// JPS returns the same optimal paths as [`Grid::pathfind_astar`] but skips over large open areas,
+    /// which makes it considerably faster on open maps.
+    ///
+    /// JPS requires uniform movement costs. If the grid has cells with different movement costs or
+    /// the neighborhood has [`NeighborFilter`]s applied, this will fall back to [`Grid::pathfind_astar`].
+    ///
+    /// # Arguments
+    /// * `start` - The starting position in the grid.
+    /// * `goal` - The goal position in the grid.
+    /// * `blocking` - A map of positions to entities that are blocking the path. Pass `&HashMap::new()` if you're not concerned with collision.
+    ///   If using [`crate::plugin::NorthstarPlugin`] you can pass it the [`crate::plugin::BlockingMap`] resource.
+    ///   If not, build a [`HashMap<UVec3, Entity>`] with the positions of entities that should be blocking paths.
+    /// * `partial` - Whether to allow partial paths (i.e., if the goal is unreachable, return the closest reachable point).
+    /// # Returns
+    /// A [`Path`] if successful, or `None` if no viable path could be found.
+    ///
+    pub fn pathfind_jps(
+        &self,
+        start: UVec3,
+        goal: UVec3,
+        blocking: &HashMap<UVec3, Entity>,
+        partial: bool,
+    ) -> Option<Path> {
+        if self.needs_build() {
+            return None;
+        }
+
+        if self.adjusts_heuristic() {
+            return self.find_path_wrapped(start, goal, PathfindMode::Jps, blocking, partial);
+        }
+
+        pathfind_jps(
+            &self.neighborhood,
+            &self.grid.view(),
+            start,
+            &SearchGoal::Position(goal),
+            blocking,
+            partial,
+            self.uniform_cost.is_some(),
+            &self.extra_costs,
+            self.one_way,
+            &SearchContext::default(),
+        )
+    }
+
+    /// Generate a Theta* any-angle path from `start` to `goal`.
+    /// Theta* searches like [`Grid::pathfind_astar`] but links each cell directly to any earlier cell it has line of sight to,
+    /// so the returned path is made of fewer, longer straight segments.
+    ///
+    /// The returned [`Path`] only contains the waypoints where the path changes direction.
+    /// Consecutive positions are not adjacent, you'll need to interpolate between them when moving an agent.
+    /// Line of sight is only traced within a single z-plane.
+    ///
+    /// # Arguments
+    /// * `start` - The starting position in the grid.
+    /// * `goal` - The goal position in the grid.
+    /// * `blocking` - A map of positions to entities that are blocking the path. Pass `&HashMap::new()` if you're not concerned with collision.
+    ///   If using [`crate::plugin::NorthstarPlugin`] you can pass it the [`crate::plugin::BlockingMap`] resource.
+    ///   If not, build a [`HashMap<UVec3, Entity>`] with the positions of entities that should be blocking paths.
+    /// * `partial` - Whether to allow partial paths (i.e., if the goal is unreachable, return the closest reachable point).
+    /// # Returns
+    /// A [`Path`] if successful, or `None` if no viable path could be found.
+    ///
+    pub fn pathfind_theta(
+        &self,
+        start: UVec3,
+        goal: UVec3,
+        blocking: &HashMap<UVec3, Entity>,
+        partial: bool,
+    ) -> Option<Path> {
+        if self.needs_build() {
+            return None;
+        }
+
+        if self.adjusts_heuristic() {
+            return self.find_path_wrapped(start, goal, PathfindMode::Theta, blocking, partial);
+        }
+
+        pathfind_theta(
+            &self.neighborhood,
+            &self.grid.view(),
+            start,
+            &SearchGoal::Position(goal),
+            blocking,
+            partial,
+            &self.extra_costs,
+            &SearchContext::default(),
+        )
+    }
+
+    /// Computes a [`FlowField`] towards `goal` with a single Dijkstra expansion over the whole grid.
+    ///
+    /// Useful when many agents share the same goal, such as in a tower defense game.
+    /// Each agent can look up [`FlowField::next`] from its position instead of searching for its own path.
+    /// The field ignores dynamic [`crate::components::Blocking`] entities.
+    ///
+    /// The returned field will be empty if the grid needs to be built or `goal` is impassable.
+    ///
+    /// # Arguments
+    /// * `goal` - The goal position every cell in the field will flow towards.
+    pub fn compute_flow_field(&self, goal: UVec3) -> FlowField {
+        compute_flow_field(self, goal)
+    }
+
+    /// Computes a [`DistanceField`] holding the movement cost from every cell to the nearest of the `sources`
+    /// with a single multi-source Dijkstra expansion. Useful for influence and threat maps.
+    ///
+    /// Pass a `max_cost` to stop expanding once the cost exceeds it, cells further away are left unreachable,
+    /// which keeps local fields cheap to compute. The field ignores dynamic [`crate::components::Blocking`] entities.
+    ///
+    /// Impassable or out of bounds sources are skipped. The returned field will be empty if the grid needs to be built.
+    ///
+    /// # Arguments
+    /// * `sources` - The positions distances are measured to.
+    /// * `max_cost` - The maximum movement cost to expand to, `None` to cover the whole grid.
+    pub fn distance_field(
+        &self,
+        sources: &[UVec3],
+        max_cost: Option<MovementCost>,
+    ) -> DistanceField {
+        compute_distance_field(self, sources, max_cost.unwrap_or(MovementCost::MAX))
+    }
+
+    /// Generate an A* path within a cubic radius around the `start` position.
+    /// This can be used to limit an A* search to a confined search area.
+    /// You'll want to ensure your radius at least covers the distance to the goal.
+    ///
+    /// # Arguments
+    /// * `start` - The starting position in the grid.
+    /// * `goal` - The goal position in the grid.
+    /// * `radius` - The radius around the start position to search for a path.
+    /// * `blocking` - A map of positions to entities that are blocking the path.
+    ///   Pass `&HasMap::new()` if you're not concerned with collision. If using [`crate::plugin::NorthstarPlugin`] you can pass it the [`crate::plugin::BlockingMap`] resource.
+    ///   If not, build a [`HashMap<UVec3, Entity>`] with the positions of entities that should be blocking paths.
+    /// * `partial` - Whether to allow partial paths (i.e., if the goal is unreachable, return the closest reachable point).
+    /// # Returns
+    /// A [`Path`] if successful, or `None` if no viable path could be found.
+    ///
+    pub fn pathfind_astar_radius(
+        &self,
+        start: UVec3,
+        goal: UVec3,
+        radius: u32,
+        blocking: &HashMap<UVec3, Entity>,
+        partial: bool,
+    ) -> Option<Path> {
+        if self.needs_build() {
+            return None;
+        }
+
+        let min = start.as_ivec3().saturating_sub(IVec3::splat(radius as i32));
+        let max = start
+            .as_ivec3()
+            .saturating_add(IVec3::splat(radius as i32) + IVec3::ONE);
+
+        let grid_shape = self.grid.shape();
+        let min = min.max(IVec3::ZERO);
+        let max = max.min(IVec3::new(
+            grid_shape[0] as i32,
+            grid_shape[1] as i32,
+            grid_shape[2] as i32,
+        ));
+
+        let grid_shape_vec = IVec3::new(
+            grid_shape[0] as i32,
+            grid_shape[1] as i32,
+            grid_shape[2] as i32,
+        );
+        if !position_in_cubic_window(goal, start.as_ivec3(), radius as i32, grid_shape_vec) {
+            return None;
+        }
+
+        // Create a subview of the grid
+        let view = self.grid.slice(s![
+            min.x as usize..max.x as usize,
+            min.y as usize..max.y as usize,
+            min.z as usize..max.z as usize
+        ]);
+
+        // Remap start/goal into local view
+        let start_local = (start.as_ivec3() - min).as_uvec3();
         let goal_local = (goal.as_ivec3() - min).as_uvec3();
 
-        // Remap blocking positions into local view
-        let blocking_local: HashMap<UVec3, Entity> = blocking
-            .iter()
-            .filter_map(|(pos, &ent)| {
-                let pos_i = pos.as_ivec3();
-                if pos_i.cmplt(min).any() || pos_i.cmpge(max).any() {
-                    return None;
+        // Remap blocking positions into local view
+        let blocking_local: HashMap<UVec3, Entity> = blocking
+            .iter()
+            .filter_map(|(pos, &ent)| {
+                let pos_i = pos.as_ivec3();
+                if pos_i.cmplt(min).any() || pos_i.cmpge(max).any() {
+                    return None;
+                }
+                Some(((pos_i - min).as_uvec3(), ent))
+            })
+            .collect();
+
+        let cost_overrides_local: HashMap<UVec3, MovementCost> = self
+            .extra_costs
+            .iter()
+            .filter_map(|(pos, &cost)| {
+                let pos_i = pos.as_ivec3();
+                if pos_i.cmplt(min).any() || pos_i.cmpge(max).any() {
+                    return None;
+                }
+                Some(((pos_i - min).as_uvec3(), cost))
+            })
+            .collect();
+
+        // Run pathfinding on the subview
+        let result = pathfind_astar(
+            &self.neighborhood,
+            &view,
+            start_local,
+            goal_local,
+            &blocking_local,
+            partial,
+            &cost_overrides_local,
+        );
+
+        // Convert path result back to global positions
+        result.map(|mut path| {
+            path.translate_by(min.as_uvec3());
+            path
+        })
+    }
+}
+
+// The neighbor bits of the cells directly above and below `pos` that `vertical` connects it to.
+fn vertical_bits(grid_view: &ArrayView3<NavCell>, pos: UVec3, vertical: VerticalLinks) -> u32 {
+    let passable = |z: u32| {
+        (z as usize) < grid_view.shape()[2]
+            && grid_view[[pos.x as usize, pos.y as usize, z as usize]].is_passable()
+    };
+
+    let mut bits = 0;
+    if vertical.up.is_some() && passable(pos.z + 1) {
+        bits |= 1 << offset_index(IVec3::Z);
+    }
+    if vertical.down.is_some() && pos.z > 0 && passable(pos.z - 1) {
+        bits |= 1 << offset_index(IVec3::NEG_Z);
+    }
+
+    bits
+}
+
+// The moves allowed with `Grid::allow_direction` out of the cell at `pos` that lead to a passable cell.
+fn added_bits(grid_view: &ArrayView3<NavCell>, pos: UVec3, added_exits: u32) -> u32 {
+    let shape = grid_view.shape();
+    ORDINAL_3D_OFFSETS
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| (added_exits >> i) & 1 != 0)
+        .filter(|(_, offset)| {
+            let target = pos.as_ivec3() + **offset;
+            target.cmpge(IVec3::ZERO).all()
+                && (target.x as usize) < shape[0]
+                && (target.y as usize) < shape[1]
+                && (target.z as usize) < shape[2]
+                && grid_view[[target.x as usize, target.y as usize, target.z as usize]]
+                    .is_passable()
+        })
+        .fold(0, |bits, (i, _)| bits | 1 << i)
+}
+
+// The cells on the line from `a` to `b`, including both ends, stepping one axis at a time.
+// The next step is taken along the axis whose next cell boundary the line crosses first.
+fn line_cells(a: UVec3, b: UVec3) -> Vec<UVec3> {
+    let delta = (b.as_i64vec3() - a.as_i64vec3()).abs();
+    let step = (b.as_ivec3() - a.as_ivec3()).signum();
+
+    let mut cells = Vec::with_capacity((delta.element_sum() + 1) as usize);
+    let mut current = a.as_ivec3();
+    let mut taken = I64Vec3::ZERO;
+    cells.push(a);
+
+    for _ in 0..delta.element_sum() {
+        // The line crosses the next boundary of an axis at (2 * taken + 1) / (2 * delta),
+        // compare the crossings without dividing
+        let crossing = |axis: usize| {
+            if taken[axis] >= delta[axis] {
+                return None;
+            }
+            Some((axis, 2 * taken[axis] + 1, delta[axis]))
+        };
+
+        let (axis, ..) = (0..3)
+            .filter_map(crossing)
+            .reduce(|best, next| {
+                if next.1 * best.2 < best.1 * next.2 {
+                    next
+                } else {
+                    best
+                }
+            })
+            .unwrap();
+
+        taken[axis] += 1;
+        current[axis] += step[axis];
+        cells.push(current.as_uvec3());
+    }
+
+    cells
+}
+
+// Wraps `pos` around the axes set in `wrap`, `None` if it's outside `dimensions` along an axis that doesn't wrap.
+fn wrap_position(pos: IVec3, dimensions: UVec3, wrap: BVec3) -> Option<UVec3> {
+    let dimensions = dimensions.as_ivec3();
+    let outside = pos.cmplt(IVec3::ZERO) | pos.cmpge(dimensions);
+    if (outside & !wrap).any() {
+        return None;
+    }
+
+    Some(pos.rem_euclid(dimensions).as_uvec3())
+}
+
+// The passable cells reached by a straight step off a wrapped edge of the grid onto the opposite edge.
+// Diagonal steps don't cross the seam so seam steps always cost a straight step.
+// Steps that wrap back onto an adjacent cell, on axes only a cell or two wide, are left to the regular neighbors.
+fn wrapped_neighbors<N: Neighborhood>(
+    neighborhood: &N,
+    grid_view: &ArrayView3<NavCell>,
+    pos: UVec3,
+    wrap: BVec3,
+) -> Vec<UVec3> {
+    let shape = grid_view.shape();
+    let dimensions = UVec3::new(shape[0] as u32, shape[1] as u32, shape[2] as u32);
+
+    neighborhood
+        .directions()
+        .iter()
+        .filter(|&&(dx, dy, dz)| dx.abs() + dy.abs() + dz.abs() == 1)
+        .filter_map(|&(dx, dy, dz)| {
+            let target = pos.as_ivec3() + IVec3::new(dx, dy, dz);
+            if target.cmpge(IVec3::ZERO).all() && target.as_uvec3().cmplt(dimensions).all() {
+                return None;
+            }
+
+            let wrapped = wrap_position(target, dimensions, wrap)?;
+            let adjacent = (wrapped.as_ivec3() - pos.as_ivec3()).abs().max_element() <= 1;
+            let passable = grid_view[[wrapped.x as usize, wrapped.y as usize, wrapped.z as usize]]
+                .is_passable();
+
+            (!adjacent && passable).then_some(wrapped)
+        })
+        .collect()
+}
+
+// The position, neighbor bits, special neighbors, and provider links of a cell.
+type CellNeighbors = (UVec3, u32, Vec<UVec3>, Vec<(UVec3, MovementCost)>);
+
+fn compute_cell_neighbors<N: Neighborhood>(
+    neighborhood: &N,
+    provider: Option<&(dyn NeighborProvider + Send + Sync + 'static)>,
+    grid_view: &ArrayView3<NavCell>,
+    pos: UVec3,
+    wrap: BVec3,
+) -> CellNeighbors {
+    let cell = &grid_view[[pos.x as usize, pos.y as usize, pos.z as usize]];
+    let bits = (neighborhood.neighbors(grid_view, pos)
+        | vertical_bits(grid_view, pos, cell.vertical)
+        | added_bits(grid_view, pos, cell.added_exits))
+        & !cell.blocked_exits;
+    let nav = cell.nav();
+
+    let mut special = match nav {
+        Nav::Portal(p) => vec![p.target],
+        _ => Vec::new(),
+    };
+
+    if wrap.any() && cell.is_passable() {
+        for neighbor in wrapped_neighbors(neighborhood, grid_view, pos, wrap) {
+            if !special.contains(&neighbor) {
+                special.push(neighbor);
+            }
+        }
+    }
+
+    let Some(provider) = provider else {
+        return (pos, bits, special, Vec::new());
+    };
+
+    let shape = grid_view.shape();
+    let in_bounds = |p: UVec3| {
+        (p.x as usize) < shape[0] && (p.y as usize) < shape[1] && (p.z as usize) < shape[2]
+    };
+    let cell_cost = |p: UVec3| grid_view[[p.x as usize, p.y as usize, p.z as usize]].cost;
+
+    let mut neighbors = ORDINAL_3D_OFFSETS
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| (bits >> i) & 1 != 0)
+        .map(|(_, offset)| (pos.as_ivec3() + *offset).as_uvec3())
+        .chain(special.iter().copied())
+        .map(|neighbor| (neighbor, cell_cost(neighbor)))
+        .collect::<Vec<_>>();
+
+    provider.neighbors(pos, grid_view, &mut neighbors);
+
+    // Moves that still cost the cell cost are stored as regular neighbors, anything else becomes a link
+    let mut provided_bits = 0;
+    let mut provided_special = Vec::new();
+    let mut links: Vec<(UVec3, MovementCost)> = Vec::new();
+    for (neighbor, cost) in neighbors {
+        if !in_bounds(neighbor) || neighbor == pos {
+            continue;
+        }
+
+        let offset = neighbor.as_ivec3() - pos.as_ivec3();
+        if cost == cell_cost(neighbor) {
+            if offset.abs().max_element() <= 1 {
+                provided_bits |= 1 << offset_index(offset);
+                continue;
+            }
+
+            if special.contains(&neighbor) {
+                provided_special.push(neighbor);
+                continue;
+            }
+        }
+
+        if !links.iter().any(|(link, _)| *link == neighbor) {
+            links.push((neighbor, cost.max(1)));
+        }
+    }
+
+    (
+        pos,
+        (provided_bits | added_bits(grid_view, pos, cell.added_exits)) & !cell.blocked_exits,
+        provided_special,
+        links,
+    )
+}
+
+// Uniform random number below `bound`, which must be above 0.
+// The multiply and shift maps the full range onto `bound`, rejecting the few low products that would bias it.
+fn random_below<R: RngCore + ?Sized>(rng: &mut R, bound: u64) -> u64 {
+    let threshold = bound.wrapping_neg() % bound;
+    loop {
+        let product = rng.next_u64() as u128 * bound as u128;
+        if product as u64 >= threshold {
+            return (product >> 64) as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{
+        ecs::entity::Entity,
+        math::{BVec3, UVec2, UVec3},
+        platform::collections::HashMap,
+    };
+    use ndarray::ArrayView3;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{
+        astar::SearchContext,
+        components::{PathError, PathfindMode},
+        dir::Dir,
+        filter::NeighborProvider,
+        goal::SearchGoal,
+        grid::{
+            ChunkSettings, CollisionSettings, DiagonalMovement, Grid, GridError, GridInconsistency,
+            GridInternalSettings, GridSettings, GridSettingsBuilder, HeightCost, NavCell,
+            NavSettings, NeighborhoodSettings,
+        },
+        nav::{Nav, Portal, VerticalLinks, ALL_NAV_LAYERS},
+        neighbor::{
+            DiagonalCost, HeuristicKind, HexNeighborhood, Neighborhood, OrdinalNeighborhood3d,
+        },
+        path::Path,
+        prelude::{CardinalNeighborhood, OrdinalNeighborhood},
+        MovementCost,
+    };
+
+    const GRID_SETTINGS: GridSettings = GridSettings(GridInternalSettings {
+        dimensions: UVec3::new(12, 12, 1),
+        chunk_settings: ChunkSettings {
+            size: UVec3::new(4, 4, 1),
+            diagonal_connections: false,
+        },
+        cost_settings: NavSettings {
+            default_movement_cost: 1,
+            default_impassible: false,
+        },
+        collision_settings: CollisionSettings {
+            enabled: false,
+            avoidance_distance: 4,
+        },
+        neighborhood_settings: NeighborhoodSettings {
+            filters: Vec::new(),
+            heuristic: None,
+            diagonal_cost: None,
+            provider: None,
+        },
+        wrap: BVec3::FALSE,
+        height_cost: HeightCost(0),
+        diagonal_movement: DiagonalMovement::Always,
+    });
+
+    const GRID_SETTINGS_3D: GridSettings = GridSettings(GridInternalSettings {
+        dimensions: UVec3::new(12, 12, 12),
+        chunk_settings: ChunkSettings {
+            size: UVec3::new(4, 4, 4),
+            diagonal_connections: false,
+        },
+        cost_settings: NavSettings {
+            default_movement_cost: 1,
+            default_impassible: false,
+        },
+        collision_settings: CollisionSettings {
+            enabled: false,
+            avoidance_distance: 4,
+        },
+        neighborhood_settings: NeighborhoodSettings {
+            filters: Vec::new(),
+            heuristic: None,
+            diagonal_cost: None,
+            provider: None,
+        },
+        wrap: BVec3::FALSE,
+        height_cost: HeightCost(0),
+        diagonal_movement: DiagonalMovement::Always,
+    });
+
+    #[test]
+    pub fn test_new() {
+        let grid = Grid::<OrdinalNeighborhood3d>::new(&GRID_SETTINGS);
+        assert_eq!(grid.grid.shape(), [12, 12, 1]);
+    }
+
+    #[test]
+    pub fn test_build() {
+        let mut grid = Grid::<OrdinalNeighborhood3d>::new(&GRID_SETTINGS);
+        grid.build();
+
+        assert!(!grid.needs_build());
+        assert_eq!(grid.grid.shape(), [12, 12, 1]);
+        assert_eq!(grid.chunks.len(), 9); // 3x3 chunks of size 4
+
+        // Test 3d
+        let mut grid_3d = Grid::<OrdinalNeighborhood3d>::new(&GRID_SETTINGS_3D);
+        grid_3d.build();
+
+        assert!(!grid_3d.needs_build());
+        assert_eq!(grid_3d.grid.shape(), [12, 12, 12]);
+        assert_eq!(grid_3d.chunks.len(), 27); // 3x3x3 chunks of size 4
+    }
+
+    #[test]
+    pub fn test_faces() {
+        let grid_settings = GridSettingsBuilder::new_2d(4, 4)
+            .chunk_size(4)
+            .enable_diagonal_connections()
+            .build();
+
+        let mut grid = Grid::<OrdinalNeighborhood>::new(&grid_settings);
+
+        // Fill grid edges with walls
+        for x in 0..4 {
+            for y in 0..4 {
+                if x == 0 || x == 3 || y == 0 || y == 3 {
+                    grid.grid[[x, y, 0]] = NavCell::new(Nav::Impassable);
+                }
+            }
+        }
+
+        let chunk = grid.chunks[[0, 0, 0]].clone();
+
+        let mut faces = Vec::new();
+
+        faces.push(chunk.face(&grid.grid, Dir::North));
+        faces.push(chunk.face(&grid.grid, Dir::East));
+        faces.push(chunk.face(&grid.grid, Dir::South));
+        faces.push(chunk.face(&grid.grid, Dir::West));
+
+        for face in faces {
+            for cell in face.iter() {
+                assert!(cell.is_impassable());
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_calculate_face_nodes() {
+        let grid = Grid::<OrdinalNeighborhood3d>::new(&GRID_SETTINGS);
+
+        let chunk = grid.chunks.iter().next().unwrap().clone();
+        let neighbor_chunk = grid.chunks.iter().nth(1).unwrap().clone();
+
+        let edges = grid.calculate_face_nodes(
+            chunk.face(&grid.grid, Dir::North),
+            neighbor_chunk.face(&grid.grid, Dir::South),
+            chunk.clone(),
+            Dir::North,
+        );
+
+        assert_eq!(edges.len(), 1);
+
+        // 3D
+        let grid_settings = GridSettingsBuilder::new_3d(12, 12, 12)
+            .chunk_size(4)
+            .chunk_depth(4)
+            .enable_diagonal_connections()
+            .build();
+
+        let grid_3d = Grid::<OrdinalNeighborhood3d>::new(&grid_settings);
+        // Center chunk
+        let chunk_3d = grid_3d.chunks[[1, 1, 1]].clone();
+        // Neighbor chunk
+        let neighbor_chunk_3d = grid_3d.chunks[[1, 1, 2]].clone();
+
+        let edges_3d = grid_3d.calculate_face_nodes(
+            chunk_3d.face(&grid_3d.grid, Dir::North),
+            neighbor_chunk_3d.face(&grid_3d.grid, Dir::South),
+            chunk_3d.clone(),
+            Dir::North,
+        );
+
+        assert_eq!(edges_3d.len(), 1);
+        assert_eq!(edges_3d[0].pos, UVec3::new(2, 2, 0));
+        assert_eq!(edges_3d[0].dir, Some(Dir::North));
+    }
+
+    #[test]
+    pub fn test_build_nodes() {
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
+
+        grid.build_nodes();
+
+        let nodes = grid.graph.nodes();
+
+        let expected = vec![
+            UVec3::new(2, 3, 0),
+            UVec3::new(3, 2, 0),
+            UVec3::new(2, 7, 0),
+            UVec3::new(3, 6, 0),
+            UVec3::new(2, 4, 0),
+            UVec3::new(3, 10, 0),
+            UVec3::new(2, 8, 0),
+        ];
+        for pos in expected {
+            assert!(
+                nodes.iter().any(|n| n.pos == pos),
+                "Missing node at {pos:?}"
+            );
+        }
+
+        assert_eq!(grid.graph.node_count(), 24);
+
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
+
+        let chunk_size = GRID_SETTINGS.0.chunk_settings.size.x as usize;
+        let half_chunk_size = chunk_size / 2;
+
+        for x in 0..(GRID_SETTINGS.0.dimensions.x as usize) {
+            for y in 0..(GRID_SETTINGS.0.dimensions.y as usize) {
+                if x % chunk_size == 0 && y % half_chunk_size == 0 {
+                    grid.grid[[x, y, 0]] = NavCell::new(Nav::Impassable);
+                } else {
+                    grid.grid[[x, y, 0]] = NavCell::new(Nav::Passable(1));
+                }
+            }
+        }
+
+        grid.build_nodes();
+
+        assert_eq!(grid.graph.node_count(), 36);
+
+        // Check that there's no nodes with duplicate positions
+        let mut positions = std::collections::HashSet::new();
+        for node in grid.graph.nodes() {
+            assert!(
+                positions.insert(node.pos),
+                "Duplicate node at {:?}",
+                node.pos
+            );
+        }
+
+        let grid_settings = GridSettingsBuilder::new_2d(12, 12)
+            .chunk_size(4)
+            .enable_diagonal_connections()
+            .build();
+
+        // Test ordinal
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings);
+
+        grid.precompute_neighbors();
+        grid.build_nodes();
+
+        assert_eq!(grid.graph.node_count(), 40);
+    }
+
+    #[test]
+    pub fn test_build_nodes_3d() {
+        // Test 3d
+        let grid_settings = GridSettingsBuilder::new_3d(48, 48, 48)
+            .chunk_size(16)
+            .chunk_depth(16)
+            .build();
+
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings);
+
+        grid.precompute_neighbors();
+        grid.build_nodes();
+
+        // Get the center nodes of the center chunk
+        let center_chunk = grid.chunks[[1, 1, 1]].clone();
+        let center_nodes = grid.graph.nodes_in_chunk(&center_chunk);
+
+        // Print all nodes and their directions
+        for node in center_nodes.clone() {
+            println!("Node at {:?} with direction {:?}", node.pos, node.dir);
+        }
+
+        assert_eq!(center_nodes.len(), 14);
+    }
+
+    #[test]
+    pub fn test_connect_internal_nodes() {
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
+
+        grid.precompute_neighbors();
+        grid.build_nodes();
+        grid.connect_internal_chunk_nodes();
+
+        assert_eq!(grid.graph.node_count(), 24);
+        assert_eq!(grid.graph.edge_count(), 44);
+    }
+
+    #[test]
+    pub fn test_connect_adjacent_nodes() {
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
+
+        grid.precompute_neighbors();
+        grid.build_nodes();
+        grid.connect_adjacent_chunk_nodes();
+
+        let edges = grid
+            .graph
+            .node_at(UVec3::new(2, 3, 0))
+            .unwrap()
+            .edges
+            .clone();
+
+        assert!(edges.contains_key(&UVec3::new(2, 4, 0)));
+
+        assert_eq!(edges[&UVec3::new(2, 4, 0)].path().len(), 2);
+        assert_eq!(edges[&UVec3::new(2, 4, 0)].cost(), 1);
+
+        assert_eq!(grid.graph.edge_count(), 24);
+    }
+
+    #[test]
+    pub fn test_get_chunk_for_position() {
+        let grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
+        let chunk = grid.chunk_at_position(UVec3::new(0, 0, 0)).unwrap();
+
+        assert_eq!(chunk.min(), UVec3::new(0, 0, 0));
+        assert_eq!(chunk.max(), GRID_SETTINGS.0.chunk_settings.size);
+    }
+
+    #[test]
+    fn test_partial_edge_chunks() {
+        let settings = GridSettingsBuilder::new_2d(10, 10).chunk_size(4).build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+
+        assert_eq!(grid.chunk_size(), UVec3::new(4, 4, 1));
+        assert_eq!(grid.chunk_count(), UVec3::new(3, 3, 1));
+        assert_eq!(grid.chunk_at(UVec3::new(9, 9, 0)), UVec3::new(2, 2, 0));
+        assert_eq!(grid.chunk_at(UVec3::new(3, 4, 0)), UVec3::new(0, 1, 0));
+
+        // The far edge chunks are clamped to the grid dimensions
+        let chunk = grid.chunk_at_position(UVec3::new(9, 9, 0)).unwrap();
+        assert_eq!(chunk.min(), UVec3::new(8, 8, 0));
+        assert_eq!(chunk.max(), UVec3::new(10, 10, 1));
+
+        grid.build();
+
+        for mode in [PathfindMode::Refined, PathfindMode::Coarse] {
+            let path = grid
+                .find_path(UVec3::new(0, 0, 0), UVec3::new(9, 9, 0), mode, false)
+                .unwrap();
+            assert_eq!(path.path().last(), Some(&UVec3::new(9, 9, 0)));
+        }
+    }
+
+    #[test]
+    fn test_chunk_dimensions() {
+        let settings = GridSettingsBuilder::new_3d(12, 8, 4)
+            .chunk_dimensions(UVec3::new(6, 4, 2))
+            .build();
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&settings);
+
+        assert_eq!(grid.chunk_size(), UVec3::new(6, 4, 2));
+        assert_eq!(grid.chunk_depth(), 2);
+        assert_eq!(grid.chunk_count(), UVec3::new(2, 2, 2));
+        assert_eq!(grid.chunk_at(UVec3::new(11, 3, 2)), UVec3::new(1, 0, 1));
+
+        grid.build();
+
+        let path = grid.pathfind(
+            UVec3::new(0, 0, 0),
+            UVec3::new(11, 7, 3),
+            &HashMap::new(),
+            false,
+        );
+        assert!(path.is_some());
+    }
+
+    #[test]
+    pub fn test_get_all_nodes_in_chunk() {
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
+        grid.build_nodes();
+
+        let nodes = grid.graph.nodes_in_chunk(&grid.chunks[[0, 0, 0]]);
+
+        assert_eq!(nodes.len(), 2);
+    }
+
+    #[test]
+    pub fn test_get_path() {
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
+
+        grid.build();
+
+        let path = grid.pathfind(
+            UVec3::new(10, 10, 0),
+            UVec3::new(4, 4, 0),
+            &HashMap::new(),
+            false,
+        );
+        let raw_path = grid.pathfind_astar(
+            UVec3::new(10, 10, 0),
+            UVec3::new(4, 4, 0),
+            &HashMap::new(),
+            false,
+        );
+
+        assert!(path.is_some());
+        // Ensure start cell is the first cell in the path
+        assert_ne!(path.clone().unwrap().path()[0], UVec3::new(10, 10, 0));
+        // Ensure end cell is the last cell in the path
+        assert_eq!(
+            path.clone().unwrap().path().last().unwrap(),
+            &UVec3::new(4, 4, 0)
+        );
+
+        assert_eq!(path.clone().unwrap().len(), raw_path.unwrap().len());
+        assert_eq!(path.unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_calculate_face_nodes_returns_center() {
+        //2D
+        let grid_settings = GridSettingsBuilder::new_2d(64, 64).chunk_size(32).build();
+
+        let grid: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings);
+
+        let start_edge = grid.chunks[[0, 0, 0]].face(&grid.grid, Dir::North);
+        let end_edge = grid.chunks[[0, 1, 0]].face(&grid.grid, Dir::South);
+
+        let nodes = grid.calculate_face_nodes(
+            start_edge,
+            end_edge,
+            grid.chunks[[0, 0, 0]].clone(),
+            Dir::North,
+        );
+
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].pos, UVec3::new(16, 0, 0));
+
+        //3D
+        let grid_settings_3d = GridSettingsBuilder::new_3d(64, 64, 64)
+            .chunk_size(32)
+            .chunk_depth(32)
+            .build();
+
+        let grid_3d: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings_3d);
+
+        let start_edge = grid_3d.chunks[[0, 0, 0]].face(&grid_3d.grid, Dir::North);
+        let end_edge = grid_3d.chunks[[0, 1, 0]].face(&grid_3d.grid, Dir::South);
+        let nodes_3d = grid_3d.calculate_face_nodes(
+            start_edge,
+            end_edge,
+            grid_3d.chunks[[0, 0, 0]].clone(),
+            Dir::North,
+        );
+
+        assert_eq!(nodes_3d.len(), 1);
+        assert_eq!(nodes_3d[0].pos, UVec3::new(16, 16, 0));
+        assert_eq!(nodes_3d[0].dir, Some(Dir::North));
+    }
+
+    #[test]
+    fn test_calculate_edge_nodes_returns_center() {
+        let grid_settings = GridSettingsBuilder::new_2d(64, 64).chunk_size(32).build();
+
+        let grid: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings);
+
+        let start_edge = grid.chunks[[0, 0, 0]].edge(&grid.grid, Dir::NorthUp);
+        let end_edge = grid.chunks[[0, 1, 0]].edge(&grid.grid, Dir::SouthDown);
+
+        let nodes = grid.calculate_edge_nodes(
+            start_edge,
+            end_edge,
+            grid.chunks[[0, 0, 0]].clone(),
+            Dir::North,
+        );
+
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].pos, UVec3::new(16, 0, 0));
+    }
+
+    #[test]
+    fn test_random_grid_path() {
+        let width = 128;
+        let height = 128;
+        let chunk_size = 32;
+
+        // Test a grid with randomized walls, the grid must be solvable
+        let grid_settings = GridSettingsBuilder::new_2d(width, height)
+            .chunk_size(chunk_size)
+            .enable_diagonal_connections()
+            .build();
+
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings);
+
+        for x in 0..width {
+            for y in 0..height {
+                if (x % 4 == 0)
+                    && (y % chunk_size == 0 || y / (chunk_size + chunk_size) == chunk_size)
+                {
+                    grid.grid[[x as usize, y as usize, 0]] = NavCell::new(Nav::Impassable);
+                }
+
+                if (y % 4 == 0)
+                    && (x % chunk_size == 0 || x & (chunk_size + chunk_size) == chunk_size)
+                {
+                    grid.grid[[x as usize, y as usize, 0]] = NavCell::new(Nav::Impassable);
+                }
+            }
+        }
+
+        grid.build();
+
+        let path = grid.pathfind(
+            UVec3::new(7, 7, 0),
+            UVec3::new(121, 121, 0),
+            &HashMap::new(),
+            false,
+        );
+
+        assert!(path.is_some());
+        assert!(!path.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_large_3d_path() {
+        let grid_settings = GridSettingsBuilder::new_3d(128, 128, 4)
+            .chunk_size(16)
+            .chunk_depth(2)
+            .build();
+
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings);
+
+        grid.build();
+        let path = grid.pathfind(
+            UVec3::new(0, 0, 0),
+            UVec3::new(31, 31, 3),
+            &HashMap::new(),
+            false,
+        );
+
+        assert!(path.is_some());
+    }
+
+    #[test]
+    pub fn test_get_astar_path() {
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
+
+        grid.build();
+
+        let path = grid.pathfind_astar(
+            UVec3::new(0, 0, 0),
+            UVec3::new(10, 10, 0),
+            &HashMap::new(),
+            false,
+        );
+
+        assert!(path.is_some());
+        assert_eq!(path.unwrap().len(), 10);
+    }
+
+    #[test]
+    pub fn test_get_jps_path() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+
+        for y in 0..10 {
+            grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
+        }
+
+        grid.build();
+
+        let jps = grid
+            .pathfind_jps(
+                UVec3::new(0, 0, 0),
+                UVec3::new(11, 0, 0),
+                &HashMap::new(),
+                false,
+            )
+            .unwrap();
+
+        let astar = grid
+            .pathfind_astar(
+                UVec3::new(0, 0, 0),
+                UVec3::new(11, 0, 0),
+                &HashMap::new(),
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(jps.len(), astar.len());
+        assert_eq!(jps.cost(), astar.cost());
+        assert_eq!(jps.path().last(), Some(&UVec3::new(11, 0, 0)));
+    }
+
+    #[test]
+    pub fn test_jps_falls_back_on_varying_costs() {
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+
+        grid.set_nav(UVec3::new(5, 5, 0), Nav::Passable(4));
+        grid.build();
+
+        assert!(grid.uniform_cost.is_none());
+
+        let path = grid.pathfind_jps(
+            UVec3::new(0, 0, 0),
+            UVec3::new(10, 10, 0),
+            &HashMap::new(),
+            false,
+        );
+
+        assert!(path.is_some());
+        assert_eq!(path.unwrap().path().last(), Some(&UVec3::new(10, 10, 0)));
+    }
+
+    #[test]
+    fn test_jps_falls_back_on_one_way_moves() {
+        let mut grid: Grid<OrdinalNeighborhood> =
+            Grid::new(&GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build());
+
+        grid.block_direction(UVec3::new(3, 0, 0), Dir::East);
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(6, 0, 0);
+
+        let astar = grid
+            .pathfind_astar(start, goal, &HashMap::new(), false)
+            .unwrap();
+        assert_eq!(astar.cost(), 6);
+
+        let jps = grid
+            .pathfind_jps(start, goal, &HashMap::new(), false)
+            .unwrap();
+        assert_eq!(jps.cost(), astar.cost());
+
+        let jps = grid
+            .find_path(start, goal, PathfindMode::Jps, false)
+            .unwrap();
+        assert_eq!(jps.cost(), astar.cost());
+    }
+
+    #[test]
+    pub fn test_find_path() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+
+        grid.set_nav(UVec3::new(5, 5, 0), Nav::Impassable);
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(11, 11, 0);
+
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::Coarse,
+            PathfindMode::AStar,
+            PathfindMode::Jps,
+            PathfindMode::FlowField,
+            PathfindMode::Theta,
+        ] {
+            let path = grid.find_path(start, goal, mode, false);
+            assert!(path.is_some(), "No path found for {mode:?}");
+            assert_eq!(path.unwrap().path().last(), Some(&goal));
+        }
+
+        let path = grid.find_path(start, UVec3::new(5, 5, 0), PathfindMode::AStar, false);
+        assert!(path.is_none());
+    }
+
+    #[test]
+    fn test_hex_path() {
+        let mut grid: Grid<HexNeighborhood> = Grid::new(&GRID_SETTINGS);
+
+        grid.set_nav(UVec3::new(5, 6, 0), Nav::Impassable);
+        grid.build();
+
+        let start = UVec3::new(0, 11, 0);
+        let goal = UVec3::new(11, 0, 0);
+        let directions = grid.neighborhood.directions();
+
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::Coarse,
+            PathfindMode::AStar,
+            PathfindMode::Jps,
+            PathfindMode::FlowField,
+            PathfindMode::Theta,
+        ] {
+            let path = grid
+                .find_path(start, goal, mode, false)
+                .unwrap_or_else(|| panic!("No path found for {mode:?}"));
+            assert_eq!(path.path().last(), Some(&goal));
+
+            let mut prev = start;
+            for &pos in path.path() {
+                let step = pos.as_ivec3() - prev.as_ivec3();
+                assert!(
+                    directions.contains(&(step.x, step.y, step.z)),
+                    "{mode:?} took a non hex step {step:?}"
+                );
+                prev = pos;
+            }
+        }
+
+        // The straight diagonal is blocked, so the shortest way around takes one extra step
+        let path = grid
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(path.len(), 12);
+    }
+
+    #[test]
+    fn test_find_path_sized() {
+        // A wall down the middle of the grid with a doorway at y = 5
+        let doorway = |width: u32| {
+            let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+            for y in 0..12 {
+                if !(5..5 + width).contains(&y) {
+                    grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
+                }
+            }
+            grid.build();
+            grid
+        };
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(9, 0, 0);
+        let size = UVec2::new(2, 2);
+
+        // A single cell agent fits through the 1 wide doorway, a 2x2 agent doesn't
+        let grid = doorway(1);
+        assert!(grid
+            .find_path(start, goal, PathfindMode::Refined, false)
+            .is_some());
+        for mode in [PathfindMode::Refined, PathfindMode::AStar] {
+            assert!(grid
+                .find_path_sized(start, goal, size, mode, false)
+                .is_none());
+        }
+
+        let grid = doorway(2);
+        let path = grid
+            .find_path_sized(start, goal, size, PathfindMode::Refined, false)
+            .unwrap();
+        assert_eq!(path.path().last(), Some(&goal));
+        for pos in path.path() {
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                assert!(grid.is_passable(*pos + UVec3::new(dx, dy, 0)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_path_multi() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+
+        grid.set_nav(UVec3::new(5, 5, 0), Nav::Impassable);
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let nearest = UVec3::new(3, 0, 0);
+        let goals = [UVec3::new(11, 11, 0), nearest, UVec3::new(0, 8, 0)];
+
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::Coarse,
+            PathfindMode::AStar,
+            PathfindMode::Jps,
+            PathfindMode::FlowField,
+            PathfindMode::Theta,
+        ] {
+            let path = grid.find_path_multi(start, &goals, mode, false);
+            assert!(path.is_some(), "No path found for {mode:?}");
+            assert_eq!(path.unwrap().path().last(), Some(&nearest));
+        }
+
+        // Unreachable goals are skipped
+        let goals = [UVec3::new(5, 5, 0), UVec3::new(11, 11, 0)];
+        let path = grid.find_path_multi(start, &goals, PathfindMode::AStar, false);
+        assert_eq!(path.unwrap().path().last(), Some(&UVec3::new(11, 11, 0)));
+
+        assert!(grid
+            .find_path_multi(start, &[], PathfindMode::AStar, false)
+            .is_none());
+
+        // A walled off goal next to the start is skipped for one in another chunk
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        for pos in [
+            UVec3::new(1, 2, 0),
+            UVec3::new(2, 1, 0),
+            UVec3::new(3, 2, 0),
+            UVec3::new(2, 3, 0),
+        ] {
+            grid.set_nav(pos, Nav::Impassable);
+        }
+        grid.build();
+
+        let goals = [UVec3::new(2, 2, 0), UVec3::new(11, 11, 0)];
+        for mode in [PathfindMode::Refined, PathfindMode::Coarse] {
+            let path = grid.find_path_multi(start, &goals, mode, false).unwrap();
+            assert_eq!(path.goal_index(), Some(1), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn test_find_path_multi_priority() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let north = UVec3::new(0, 4, 0);
+        let east = UVec3::new(4, 0, 0);
+
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::AStar,
+            PathfindMode::Jps,
+            PathfindMode::FlowField,
+            PathfindMode::Theta,
+        ] {
+            // Both goals cost the same, the first one given wins
+            for goals in [[north, east], [east, north]] {
+                let path = grid.find_path_multi(start, &goals, mode, false).unwrap();
+                assert_eq!(path.path().last(), Some(&goals[0]), "{mode:?}");
+                assert_eq!(path.goal_index(), Some(0), "{mode:?}");
+            }
+
+            // A cheaper goal still wins over the preferred ones
+            let goals = [north, east, UVec3::new(2, 0, 0)];
+            let path = grid.find_path_multi(start, &goals, mode, false).unwrap();
+            assert_eq!(path.goal_index(), Some(2), "{mode:?}");
+        }
+
+        let path = grid
+            .find_path(start, north, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(path.goal_index(), Some(0));
+    }
+
+    #[test]
+    fn test_paths_from() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        for y in 0..12 {
+            grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let goals = [
+            UVec3::new(5, 5, 0),
+            UVec3::new(0, 11, 0),
+            start,
+            UVec3::new(8, 8, 0),
+        ];
+        let paths = grid.paths_from(start, &goals);
+
+        // The goal behind the wall is left out
+        assert_eq!(paths.len(), 3);
+        assert!(!paths.contains_key(&UVec3::new(8, 8, 0)));
+        assert_eq!(paths[&start].cost(), 0);
+
+        for goal in [UVec3::new(5, 5, 0), UVec3::new(0, 11, 0)] {
+            let path = &paths[&goal];
+            let expected = grid
+                .find_path(start, goal, PathfindMode::AStar, false)
+                .unwrap();
+            assert_eq!(path.cost(), expected.cost());
+            assert_eq!(path.path().first(), Some(&start));
+            assert_eq!(path.path().last(), Some(&goal));
+        }
+
+        assert!(grid.paths_from(UVec3::new(6, 0, 0), &goals).is_empty());
+    }
+
+    #[test]
+    fn test_set_nav_shapes() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        grid.build();
+
+        grid.set_nav_line(UVec3::new(0, 0, 0), UVec3::new(3, 2, 0), Nav::Impassable);
+        for pos in [(0, 0), (1, 0), (1, 1), (2, 1), (2, 2), (3, 2)] {
+            assert!(!grid.is_passable(UVec3::new(pos.0, pos.1, 0)));
+        }
+
+        // Shapes leaving the grid are clipped
+        grid.set_nav_line(
+            UVec3::new(10, 11, 0),
+            UVec3::new(14, 11, 0),
+            Nav::Impassable,
+        );
+        grid.set_nav_rect(UVec3::new(9, 0, 0), UVec3::new(20, 1, 3), Nav::Impassable);
+        grid.set_nav_filled_circle(UVec3::new(5, 6, 0), 2, Nav::Impassable);
+        grid.set_nav_filled_circle(UVec3::new(0, 11, 0), 1, Nav::Impassable);
+
+        assert!(!grid.is_passable(UVec3::new(11, 11, 0)));
+        assert!(!grid.is_passable(UVec3::new(11, 1, 0)));
+        assert!(!grid.is_passable(UVec3::new(5, 8, 0)));
+        assert!(!grid.is_passable(UVec3::new(6, 7, 0)));
+        assert!(grid.is_passable(UVec3::new(7, 8, 0)));
+        assert!(!grid.is_passable(UVec3::new(1, 11, 0)));
+
+        let impassable = (0..12)
+            .flat_map(|y| (0..12).map(move |x| UVec3::new(x, y, 0)))
+            .filter(|pos| !grid.is_passable(*pos))
+            .count();
+        assert_eq!(impassable, 6 + 2 + 6 + 13 + 3);
+
+        // The changes were rebuilt once each shape was stamped
+        assert!(!grid.needs_build());
+    }
+
+    #[test]
+    fn test_height_cost() {
+        // A terraced hill in the middle of the map, rising to a height of 3 in steps of 1
+        let terraced = |height_cost: HeightCost| {
+            let settings = GridSettingsBuilder::new_2d(16, 12)
+                .chunk_size(4)
+                .height_cost(height_cost)
+                .build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            for y in 0..9 {
+                for x in 5..11 {
+                    let edge = (x - 5).min(10 - x).min(8 - y);
+                    grid.set_cell_height(UVec3::new(x, y, 0), (edge + 1).min(3));
                 }
-                Some(((pos_i - min).as_uvec3(), ent))
-            })
-            .collect();
+            }
+            grid.build();
+            grid
+        };
 
-        // Run pathfinding on the subview
-        let result = pathfind_astar(
-            &self.neighborhood,
-            &view,
-            start_local,
-            goal_local,
-            &blocking_local,
-            partial,
+        let start = UVec3::new(0, 4, 0);
+        let goal = UVec3::new(15, 4, 0);
+
+        // Without a height cost the path goes straight over the hill
+        let flat = terraced(HeightCost(0));
+        assert_eq!(flat.cell_height(UVec3::new(7, 4, 0)), Some(3));
+        let path = flat
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(path.cost(), 15);
+
+        // Climbing the hill and back down costs 6 * 5, walking around it only 10 extra steps
+        let mut grid = terraced(HeightCost(5));
+        assert_eq!(
+            grid.heuristic_distance(start, UVec3::new(7, 4, 0)),
+            7 + 3 * 5
         );
 
-        // Convert path result back to global positions
-        result.map(|mut path| {
-            path.translate_by(min.as_uvec3());
-            path
-        })
+        let path = grid
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(path.cost(), 25);
+        assert!(path.iter().all(|pos| grid.cell_height(*pos) == Some(0)));
+
+        for mode in [PathfindMode::Refined, PathfindMode::Coarse] {
+            let path = grid.find_path(start, goal, mode, false).unwrap();
+            assert!(
+                path.iter().all(|pos| grid.cell_height(*pos) == Some(0)),
+                "{mode:?}"
+            );
+        }
+
+        // Changing a cell keeps its height, and changing a height rebuilds the cached paths
+        grid.set_nav(UVec3::new(7, 4, 0), Nav::Passable(2));
+        assert_eq!(grid.cell_height(UVec3::new(7, 4, 0)), Some(3));
+
+        // A boulder on the way around the hill
+        grid.set_cell_height(UVec3::new(7, 9, 0), 4);
+        assert!(grid.needs_build());
+        grid.build();
+
+        let path = grid
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(path.cost(), 27);
+        assert!(!path.iter().any(|pos| *pos == UVec3::new(7, 9, 0)));
     }
-}
 
-fn compute_cell_neighbors<N: Neighborhood>(
-    neighborhood: &N,
-    grid_view: &ArrayView3<NavCell>,
-    pos: UVec3,
-) -> (UVec3, u32, Vec<UVec3>) {
-    let bits = neighborhood.neighbors(grid_view, pos);
-    let nav = grid_view[[pos.x as usize, pos.y as usize, pos.z as usize]].nav();
+    #[test]
+    fn test_movement_range() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        grid.set_nav(UVec3::new(5, 4, 0), Nav::Impassable);
+        grid.set_nav(UVec3::new(4, 5, 0), Nav::Passable(3));
+        grid.build();
+        grid.set_cost_override(UVec3::new(6, 5, 0), 2);
+
+        let start = UVec3::new(5, 5, 0);
+        let range = grid.movement_range(start, 3);
+
+        assert_eq!(range[&start], 0);
+        assert_eq!(range[&UVec3::new(5, 6, 0)], 1);
+        assert_eq!(range[&UVec3::new(5, 8, 0)], 3);
+        // Weighted cells and cost overrides eat into the budget
+        assert_eq!(range[&UVec3::new(4, 5, 0)], 3);
+        assert_eq!(range[&UVec3::new(6, 5, 0)], 3);
+        assert!(!range.contains_key(&UVec3::new(7, 5, 0)));
+        assert!(!range.contains_key(&UVec3::new(3, 5, 0)));
+        // Impassable cells are never in range
+        assert!(!range.contains_key(&UVec3::new(5, 4, 0)));
+        assert!(range.values().all(|cost| *cost <= 3));
+
+        for (pos, cost) in range.iter().filter(|(pos, _)| **pos != start) {
+            let path = grid
+                .find_path(start, *pos, PathfindMode::AStar, false)
+                .unwrap();
+            assert_eq!(path.cost(), *cost);
+        }
 
-    let special = match nav {
-        Nav::Portal(p) => vec![p.target],
-        _ => Vec::new(),
-    };
+        assert!(grid.movement_range(UVec3::new(5, 4, 0), 3).is_empty());
+    }
 
-    (pos, bits, special)
-}
+    #[test]
+    fn test_find_path_region() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
 
-#[cfg(test)]
-mod tests {
-    use bevy::{math::UVec3, platform::collections::HashMap};
+        grid.set_nav(UVec3::new(5, 5, 0), Nav::Impassable);
+        grid.build();
 
-    use crate::{
-        dir::Dir,
-        grid::{
-            ChunkSettings, CollisionSettings, Grid, GridInternalSettings, GridSettings,
-            GridSettingsBuilder, NavCell, NavSettings, NeighborhoodSettings,
-        },
-        nav::{Nav, Portal},
-        neighbor::OrdinalNeighborhood3d,
-        prelude::{CardinalNeighborhood, OrdinalNeighborhood},
-    };
+        let start = UVec3::new(0, 0, 0);
+        let min = UVec3::new(6, 6, 0);
+        let max = UVec3::new(11, 11, 0);
+
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::Coarse,
+            PathfindMode::AStar,
+            PathfindMode::Jps,
+            PathfindMode::FlowField,
+            PathfindMode::Theta,
+        ] {
+            let path = grid.find_path_region(start, min, max, mode, false);
+            assert!(path.is_some(), "No path found for {mode:?}");
+
+            let end = *path.unwrap().path().last().unwrap();
+            assert!(
+                end.cmpge(min).all() && end.cmple(max).all(),
+                "{mode:?} ended outside the region at {end:?}"
+            );
+        }
 
-    const GRID_SETTINGS: GridSettings = GridSettings(GridInternalSettings {
-        dimensions: UVec3::new(12, 12, 1),
-        chunk_settings: ChunkSettings {
-            size: 4,
-            depth: 1,
-            diagonal_connections: false,
-        },
-        cost_settings: NavSettings {
-            default_movement_cost: 1,
-            default_impassible: false,
-        },
-        collision_settings: CollisionSettings {
-            enabled: false,
-            avoidance_distance: 4,
-        },
-        neighborhood_settings: NeighborhoodSettings {
-            filters: Vec::new(),
-        },
-    });
+        // A* stops at the closest cell of the region
+        let path = grid
+            .find_path_region(start, min, max, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(path.path().last(), Some(&min));
+        assert_eq!(path.cost(), 12);
+
+        // Starting inside the region is an empty path
+        let path = grid
+            .find_path_region(max, min, max, PathfindMode::Refined, false)
+            .unwrap();
+        assert!(path.is_empty());
+
+        // An unreachable region only returns a path towards it when partial is set
+        for x in 10..12 {
+            for y in 10..12 {
+                grid.set_nav(UVec3::new(x, y, 0), Nav::Impassable);
+            }
+        }
+        grid.build();
 
-    const GRID_SETTINGS_3D: GridSettings = GridSettings(GridInternalSettings {
-        dimensions: UVec3::new(12, 12, 12),
-        chunk_settings: ChunkSettings {
-            size: 4,
-            depth: 4,
-            diagonal_connections: false,
-        },
-        cost_settings: NavSettings {
-            default_movement_cost: 1,
-            default_impassible: false,
-        },
-        collision_settings: CollisionSettings {
-            enabled: false,
-            avoidance_distance: 4,
-        },
-        neighborhood_settings: NeighborhoodSettings {
-            filters: Vec::new(),
-        },
-    });
+        let min = UVec3::new(10, 10, 0);
+        assert!(grid
+            .find_path_region(start, min, max, PathfindMode::AStar, false)
+            .is_none());
+
+        let path = grid
+            .find_path_region(start, min, max, PathfindMode::AStar, true)
+            .unwrap();
+        assert!(!path.is_empty());
+    }
 
     #[test]
-    pub fn test_new() {
-        let grid = Grid::<OrdinalNeighborhood3d>::new(&GRID_SETTINGS);
-        assert_eq!(grid.grid.shape(), [12, 12, 1]);
+    fn test_within_goal() {
+        let settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+
+        // A walled off block around the center, only the cells 3 steps out can be stood on
+        for x in 9..14 {
+            for y in 9..14 {
+                grid.set_nav(UVec3::new(x, y, 0), Nav::Impassable);
+            }
+        }
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let centers = [UVec3::new(11, 11, 0)];
+        let find = |radius: u32, mode: PathfindMode| {
+            grid.find_path_with_blocking(
+                start,
+                &SearchGoal::within(grid.neighborhood(), &centers, radius),
+                mode,
+                &HashMap::new(),
+                false,
+                None,
+                ALL_NAV_LAYERS,
+                UVec2::ONE,
+                &SearchContext::default(),
+            )
+        };
+
+        for mode in [
+            PathfindMode::Coarse,
+            PathfindMode::Refined,
+            PathfindMode::AStar,
+        ] {
+            let path = find(3, mode).unwrap();
+            let end = *path.path().last().unwrap();
+            assert_eq!(grid.step_distance(centers[0], end), 3, "{mode:?}");
+            assert_eq!(path.cost(), 19, "{mode:?}");
+
+            assert!(find(2, mode).is_none(), "{mode:?}");
+        }
     }
 
     #[test]
-    pub fn test_build() {
-        let mut grid = Grid::<OrdinalNeighborhood3d>::new(&GRID_SETTINGS);
+    fn test_heuristic_selection() {
+        let build = |settings: GridSettings| {
+            let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+            for y in 0..12 {
+                grid.set_nav(UVec3::new(8, y, 0), Nav::Impassable);
+            }
+            grid.build();
+            grid
+        };
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(15, 0, 0);
+        // Around the top of the wall at (8, 12) and back down
+        let optimal = 24;
+
+        // Per grid
+        let grid = build(
+            GridSettingsBuilder::new_2d(16, 16)
+                .chunk_size(4)
+                .heuristic(HeuristicKind::Chebyshev)
+                .build(),
+        );
+        let path = grid.find_path(start, goal, PathfindMode::AStar, false);
+        assert_eq!(path.unwrap().cost(), optimal);
+
+        // Per request
+        let grid = build(GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build());
+        let path = grid.find_path_with_blocking(
+            start,
+            &SearchGoal::Position(goal),
+            PathfindMode::AStar,
+            &HashMap::new(),
+            false,
+            Some(HeuristicKind::Chebyshev),
+            ALL_NAV_LAYERS,
+            UVec2::ONE,
+            &SearchContext::default(),
+        );
+        assert_eq!(path.unwrap().cost(), optimal);
+    }
+
+    #[test]
+    fn test_cost_overrides() {
+        let settings = GridSettingsBuilder::new_2d(12, 12).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
         grid.build();
 
+        // A danger zone across the grid with a gap at the top
+        for y in 0..11 {
+            grid.set_cost_override(UVec3::new(6, y, 0), 100);
+        }
+
+        // Overrides don't invalidate the grid
         assert!(!grid.needs_build());
-        assert_eq!(grid.grid.shape(), [12, 12, 1]);
-        assert_eq!(grid.chunks.len(), 9); // 3x3 chunks of size 4
+        assert_eq!(grid.cost_override(UVec3::new(6, 0, 0)), Some(100));
+        assert_eq!(grid.cost_override(UVec3::new(6, 11, 0)), None);
 
-        // Test 3d
-        let mut grid_3d = Grid::<OrdinalNeighborhood3d>::new(&GRID_SETTINGS_3D);
-        grid_3d.build();
+        let start = UVec3::new(0, 5, 0);
+        let goal = UVec3::new(11, 5, 0);
 
-        assert!(!grid_3d.needs_build());
-        assert_eq!(grid_3d.grid.shape(), [12, 12, 12]);
-        assert_eq!(grid_3d.chunks.len(), 27); // 3x3x3 chunks of size 4
+        // Up through the gap at (6, 11) and back down
+        for mode in [PathfindMode::AStar, PathfindMode::Jps] {
+            let path = grid.find_path(start, goal, mode, false).unwrap();
+            assert_eq!(path.cost(), 23);
+            assert!(path.path().contains(&UVec3::new(6, 11, 0)));
+        }
+
+        let path = grid.find_path(start, goal, PathfindMode::Theta, false);
+        assert!(path.unwrap().cost() < 100);
+
+        // Closing the gap still leaves the danger zone traversable
+        grid.set_cost_override(UVec3::new(6, 11, 0), 100);
+        for mode in [PathfindMode::AStar, PathfindMode::Refined] {
+            let path = grid.find_path(start, goal, mode, false).unwrap();
+            assert_eq!(path.path().last(), Some(&goal));
+            assert!(path.cost() >= 111);
+        }
+
+        grid.clear_cost_override(UVec3::new(6, 11, 0));
+        assert_eq!(grid.cost_override(UVec3::new(6, 11, 0)), None);
+
+        grid.clear_cost_overrides();
+        let path = grid.find_path(start, goal, PathfindMode::AStar, false);
+        assert_eq!(path.unwrap().cost(), 11);
     }
 
     #[test]
-    pub fn test_faces() {
-        let grid_settings = GridSettingsBuilder::new_2d(4, 4)
-            .chunk_size(4)
-            .enable_diagonal_connections()
-            .build();
+    fn test_block_temporarily() {
+        let settings = GridSettingsBuilder::new_2d(12, 12).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for y in 0..12 {
+            grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
+        }
+        // The only two ways across the wall
+        grid.set_nav(UVec3::new(6, 2, 0), Nav::Passable(1));
+        grid.set_nav(UVec3::new(6, 9, 0), Nav::Passable(1));
+        grid.build();
 
-        let mut grid = Grid::<OrdinalNeighborhood>::new(&grid_settings);
+        let start = UVec3::new(0, 2, 0);
+        let goal = UVec3::new(11, 2, 0);
+        let bridge = UVec3::new(6, 2, 0);
 
-        // Fill grid edges with walls
-        for x in 0..4 {
-            for y in 0..4 {
-                if x == 0 || x == 3 || y == 0 || y == 3 {
-                    grid.grid[[x, y, 0]] = NavCell::new(Nav::Impassable);
-                }
+        grid.block_temporarily(bridge, Duration::from_secs(2));
+        assert!(grid.is_temporarily_blocked(bridge));
+        assert!(!grid.needs_build());
+
+        for mode in [PathfindMode::AStar, PathfindMode::Jps, PathfindMode::Theta] {
+            let path = grid.find_path(start, goal, mode, false).unwrap();
+            assert!(!path.path().contains(&bridge));
+        }
+
+        // Still blocked until the full duration has passed
+        assert!(grid
+            .tick_temporary_blocks(Duration::from_millis(1500))
+            .is_empty());
+        assert_eq!(
+            grid.temporary_block(bridge),
+            Some(Duration::from_millis(500))
+        );
+
+        assert_eq!(
+            grid.tick_temporary_blocks(Duration::from_secs(1)),
+            vec![bridge]
+        );
+        assert!(!grid.is_temporarily_blocked(bridge));
+
+        let path = grid
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(path.cost(), 11);
+    }
+
+    #[test]
+    fn test_entrances() {
+        let settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+
+        assert_eq!(grid.entrances().len(), grid.graph().nodes().len());
+
+        let entrances = grid.entrances_in_chunk(UVec3::new(1, 1, 0));
+        assert!(!entrances.is_empty());
+        assert!(grid.entrances_in_chunk(UVec3::new(4, 0, 0)).is_empty());
+
+        for entrance in entrances {
+            assert_eq!(entrance.chunk(), UVec3::new(1, 1, 0));
+            assert_eq!(grid.chunk_at(entrance.pos()), entrance.chunk());
+            assert!(entrance.dir().is_some());
+            assert!(!entrance.is_portal());
+            assert_eq!(grid.entrance(entrance.pos()), Some(entrance));
+
+            for (to, cost) in grid.entrance_edges(entrance.pos()) {
+                assert!(grid.entrance(to).is_some());
+
+                let path = grid.entrance_path(entrance.pos(), to).unwrap();
+                assert_eq!(path.cost(), cost);
+                assert_eq!(path.path().last(), Some(&to));
             }
         }
 
-        let chunk = grid.chunks[[0, 0, 0]].clone();
+        assert!(grid.entrance(UVec3::new(1, 1, 0)).is_none());
+        assert_eq!(grid.entrance_edges(UVec3::new(1, 1, 0)).count(), 0);
 
-        let mut faces = Vec::new();
+        let entrances = grid
+            .find_entrance_path(UVec3::new(0, 0, 0), UVec3::new(15, 15, 0))
+            .unwrap();
+        assert!(entrances.len() >= 2);
+        for (from, to) in entrances.iter().zip(entrances.iter().skip(1)) {
+            assert!(grid.entrance_path(*from, *to).is_some());
+        }
 
-        faces.push(chunk.face(&grid.grid, Dir::North));
-        faces.push(chunk.face(&grid.grid, Dir::East));
-        faces.push(chunk.face(&grid.grid, Dir::South));
-        faces.push(chunk.face(&grid.grid, Dir::West));
+        assert_eq!(
+            grid.find_entrance_path(UVec3::new(0, 0, 0), UVec3::new(3, 3, 0)),
+            Some(Vec::new())
+        );
+    }
 
-        for face in faces {
-            for cell in face.iter() {
-                assert!(cell.is_impassable());
+    #[test]
+    fn test_chunk_neighbors() {
+        let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        // A wall between the left and right chunks with a single gap at the bottom
+        for y in 0..8 {
+            if y != 1 {
+                grid.set_nav(UVec3::new(4, y, 0), Nav::Impassable);
             }
         }
+        grid.build();
+
+        let neighbors = grid
+            .chunk_neighbors(UVec3::new(0, 0, 0))
+            .collect::<Vec<_>>();
+        assert_eq!(neighbors.len(), 2);
+        // The gap is a chokepoint
+        assert_eq!(neighbors[0], (UVec3::new(1, 0, 0), 1));
+        assert_eq!(neighbors[1].0, UVec3::new(0, 1, 0));
+        assert!(neighbors[1].1 >= 1);
+
+        let neighbors = grid
+            .chunk_neighbors(UVec3::new(0, 1, 0))
+            .collect::<Vec<_>>();
+        assert_eq!(neighbors, vec![(UVec3::new(0, 0, 0), neighbors[0].1)]);
+
+        assert_eq!(grid.chunk_neighbors(UVec3::new(5, 5, 0)).count(), 0);
     }
 
     #[test]
-    pub fn test_calculate_face_nodes() {
-        let grid = Grid::<OrdinalNeighborhood3d>::new(&GRID_SETTINGS);
+    fn test_validate() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        for y in 0..10 {
+            grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
+        }
+        grid.build();
+        assert_eq!(grid.validate(), Vec::new());
 
-        let chunk = grid.chunks.iter().next().unwrap().clone();
-        let neighbor_chunk = grid.chunks.iter().nth(1).unwrap().clone();
+        // Incremental rebuilds stay consistent
+        let gap = UVec3::new(6, 5, 0);
+        grid.set_nav(gap, Nav::Passable(1));
+        grid.set_nav(UVec3::new(2, 2, 0), Nav::Impassable);
+        grid.build();
+        assert_eq!(grid.validate(), Vec::new());
+
+        // Changing a cell behind the grid's back leaves the cached data out of sync
+        grid.grid[[6, 5, 0]] = NavCell::new(Nav::Impassable);
+        let problems = grid.validate();
+        assert!(problems.contains(&GridInconsistency::LabeledImpassable(gap)));
+        assert!(problems.iter().any(|problem| matches!(
+            problem,
+            GridInconsistency::ImpassablePath { pos, .. } if *pos == gap
+        )));
+
+        grid.set_nav(gap, Nav::Impassable);
+        grid.build();
+        assert_eq!(grid.validate(), Vec::new());
+    }
 
-        let edges = grid.calculate_face_nodes(
-            chunk.face(&grid.grid, Dir::North),
-            neighbor_chunk.face(&grid.grid, Dir::South),
-            chunk.clone(),
-            Dir::North,
-        );
+    #[test]
+    fn test_snapshot() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        grid.build();
 
-        assert_eq!(edges.len(), 1);
+        let base = UVec3::new(0, 0, 0);
+        let outpost = UVec3::new(11, 11, 0);
+
+        let mut snapshot = grid.snapshot();
+        assert!(!snapshot.is_diverged());
+        assert!(snapshot
+            .find_path(base, outpost, PathfindMode::Refined, false)
+            .is_some());
+
+        // Wall off the base in the snapshot only
+        snapshot.set_nav(UVec3::new(1, 0, 0), Nav::Impassable);
+        snapshot.set_nav(UVec3::new(0, 1, 0), Nav::Impassable);
+        assert!(snapshot.is_diverged());
+        assert!(snapshot
+            .find_path(base, outpost, PathfindMode::Refined, false)
+            .is_none());
+        assert!(!snapshot.grid().same_component(base, outpost));
+
+        assert!(grid.is_passable(UVec3::new(1, 0, 0)));
+        assert!(grid.same_component(base, outpost));
+        assert!(grid
+            .find_path(base, outpost, PathfindMode::Refined, false)
+            .is_some());
+    }
 
-        // 3D
-        let grid_settings = GridSettingsBuilder::new_3d(12, 12, 12)
-            .chunk_size(4)
-            .chunk_depth(4)
-            .enable_diagonal_connections()
-            .build();
+    #[test]
+    fn test_set_nav_batch() {
+        let settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
 
-        let grid_3d = Grid::<OrdinalNeighborhood3d>::new(&grid_settings);
-        // Center chunk
-        let chunk_3d = grid_3d.chunks[[1, 1, 1]].clone();
-        // Neighbor chunk
-        let neighbor_chunk_3d = grid_3d.chunks[[1, 1, 2]].clone();
+        let version = grid.chunk_version((3, 3, 0));
 
-        let edges_3d = grid_3d.calculate_face_nodes(
-            chunk_3d.face(&grid_3d.grid, Dir::North),
-            neighbor_chunk_3d.face(&grid_3d.grid, Dir::South),
-            chunk_3d.clone(),
-            Dir::North,
+        // Wall off the left half except for a gap at the top
+        grid.set_nav_batch((0..15).map(|y| (UVec3::new(7, y, 0), NavCell::new(Nav::Impassable))));
+        assert!(!grid.dirty);
+        assert!(grid.dirty_chunks.is_empty());
+        assert_eq!(grid.chunk_version((3, 3, 0)), version);
+
+        let path = grid
+            .find_path(
+                UVec3::new(0, 0, 0),
+                UVec3::new(15, 0, 0),
+                PathfindMode::Refined,
+                false,
+            )
+            .unwrap();
+        assert!(path.path().contains(&UVec3::new(7, 15, 0)));
+
+        let open = grid.with_deferred_rebuild(|grid| {
+            for y in 0..15 {
+                grid.set_nav(UVec3::new(7, y, 0), Nav::Passable(1));
+            }
+            grid.is_passable(UVec3::new(7, 0, 0))
+        });
+        assert!(open);
+        assert!(!grid.dirty);
+
+        let path = grid
+            .find_path(
+                UVec3::new(0, 0, 0),
+                UVec3::new(15, 0, 0),
+                PathfindMode::Refined,
+                false,
+            )
+            .unwrap();
+        assert_eq!(path.len(), 15);
+    }
+
+    #[test]
+    fn test_resize() {
+        let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.set_nav(
+            UVec3::new(1, 1, 0),
+            Nav::Portal(Portal::to(UVec3::new(7, 7, 0), 1, true)),
         );
+        grid.build();
 
-        assert_eq!(edges_3d.len(), 1);
-        assert_eq!(edges_3d[0].pos, UVec3::new(2, 2, 0));
-        assert_eq!(edges_3d[0].dir, Some(Dir::North));
+        let version = grid.chunk_version((0, 0, 0));
+
+        // Grow the grid to the east
+        grid.resize(UVec3::new(12, 8, 1), NavCell::new(Nav::Passable(2)));
+        assert_eq!(grid.dimensions(), UVec3::new(12, 8, 1));
+        assert_eq!(grid.chunk_count(), UVec3::new(3, 2, 1));
+        assert!(grid.is_portal(UVec3::new(1, 1, 0)));
+        assert_eq!(grid.nav_cost(UVec3::new(11, 7, 0)), Some(2));
+        grid.build();
+
+        // Chunks away from the new region aren't rebuilt
+        assert_eq!(grid.chunk_version((0, 0, 0)), version);
+        assert!(grid.same_component(UVec3::new(0, 0, 0), UVec3::new(11, 7, 0)));
+
+        let path = grid
+            .find_path(
+                UVec3::new(0, 0, 0),
+                UVec3::new(11, 7, 0),
+                PathfindMode::Refined,
+                false,
+            )
+            .unwrap();
+        assert_eq!(path.path().last(), Some(&UVec3::new(11, 7, 0)));
+
+        // Shrink it past the portal target
+        grid.resize(UVec3::new(6, 6, 1), NavCell::new(Nav::Passable(1)));
+        assert_eq!(grid.chunk_count(), UVec3::new(2, 2, 1));
+        assert!(!grid.is_portal(UVec3::new(1, 1, 0)));
+        assert!(grid.is_passable(UVec3::new(1, 1, 0)));
+        grid.build();
+
+        assert!(grid
+            .graph()
+            .nodes()
+            .iter()
+            .all(|node| grid.in_bounds(node.pos)));
+
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::Coarse,
+            PathfindMode::AStar,
+        ] {
+            let path = grid
+                .find_path(UVec3::new(0, 0, 0), UVec3::new(5, 5, 0), mode, false)
+                .unwrap();
+            assert_eq!(path.path().last(), Some(&UVec3::new(5, 5, 0)), "{mode:?}");
+        }
+        assert!(grid
+            .find_path(
+                UVec3::new(0, 0, 0),
+                UVec3::new(7, 7, 0),
+                PathfindMode::Refined,
+                false,
+            )
+            .is_none());
     }
 
     #[test]
-    pub fn test_build_nodes() {
-        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
-
-        grid.build_nodes();
+    fn test_neighbor_provider() {
+        struct Links;
+
+        impl NeighborProvider for Links {
+            fn neighbors(
+                &self,
+                pos: UVec3,
+                _grid: &ArrayView3<NavCell>,
+                neighbors: &mut Vec<(UVec3, MovementCost)>,
+            ) {
+                // A portal through the wall
+                if pos == UVec3::new(2, 2, 0) {
+                    neighbors.push((UVec3::new(9, 2, 0), 5));
+                }
 
-        let nodes = grid.graph.nodes();
+                // A one-way ledge
+                if pos == UVec3::new(0, 0, 0) {
+                    neighbors.retain(|(neighbor, _)| *neighbor != UVec3::new(1, 0, 0));
+                }
+            }
+        }
 
-        let expected = vec![
-            UVec3::new(2, 3, 0),
-            UVec3::new(3, 2, 0),
-            UVec3::new(2, 7, 0),
-            UVec3::new(3, 6, 0),
-            UVec3::new(2, 4, 0),
-            UVec3::new(3, 10, 0),
-            UVec3::new(2, 8, 0),
-        ];
-        for pos in expected {
-            assert!(
-                nodes.iter().any(|n| n.pos == pos),
-                "Missing node at {pos:?}"
-            );
+        let settings = GridSettingsBuilder::new_2d(12, 12)
+            .chunk_size(4)
+            .neighbor_provider(Links)
+            .build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for y in 0..12 {
+            grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
         }
+        grid.build();
 
-        assert_eq!(grid.graph.node_count(), 24);
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(11, 2, 0);
+
+        assert!(!grid
+            .neighbor_positions(start)
+            .contains(&UVec3::new(1, 0, 0)));
+        assert!(grid
+            .neighbor_positions(UVec3::new(1, 0, 0))
+            .contains(&start));
+        assert!(grid
+            .neighbor_positions(UVec3::new(2, 2, 0))
+            .contains(&UVec3::new(9, 2, 0)));
+        assert!(grid.same_component(start, goal));
+
+        // Every mode searches with A* to follow the links
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::Coarse,
+            PathfindMode::AStar,
+            PathfindMode::FlowField,
+        ] {
+            let path = grid.find_path(start, goal, mode, false).unwrap();
+            assert!(path.path().contains(&UVec3::new(9, 2, 0)), "{mode:?}");
+            assert_ne!(path.path().first(), Some(&UVec3::new(1, 0, 0)));
+            assert_eq!(path.cost(), 11, "{mode:?}");
+        }
 
-        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
+        // The link is one-way
+        assert!(grid
+            .find_path(goal, start, PathfindMode::AStar, false)
+            .is_none());
+    }
 
-        let chunk_size = GRID_SETTINGS.0.chunk_settings.size as usize;
-        let half_chunk_size = chunk_size / 2;
+    #[test]
+    fn test_path_cost() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
 
-        for x in 0..(GRID_SETTINGS.0.dimensions.x as usize) {
-            for y in 0..(GRID_SETTINGS.0.dimensions.y as usize) {
-                if x % chunk_size == 0 && y % half_chunk_size == 0 {
-                    grid.grid[[x, y, 0]] = NavCell::new(Nav::Impassable);
-                } else {
-                    grid.grid[[x, y, 0]] = NavCell::new(Nav::Passable(1));
-                }
+        // Swamp in the middle of the grid
+        for x in 3..9 {
+            for y in 3..9 {
+                grid.set_nav(UVec3::new(x, y, 0), Nav::Passable(3));
             }
         }
+        grid.set_nav(UVec3::new(11, 11, 0), Nav::Passable(2));
+        grid.set_cost_override(UVec3::new(0, 5, 0), 4);
+        grid.build();
 
-        grid.build_nodes();
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(11, 11, 0);
+        let summed_cost = |grid: &Grid<CardinalNeighborhood>, path: &Path| {
+            path.path()
+                .iter()
+                .map(|pos| grid.nav_cost(*pos).unwrap() + grid.cost_override(*pos).unwrap_or(0))
+                .sum::<u32>()
+        };
 
-        assert_eq!(grid.graph.node_count(), 36);
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::AStar,
+            PathfindMode::Jps,
+        ] {
+            let path = grid.find_path(start, goal, mode, false).unwrap();
+            assert!(!path.path().contains(&start), "{mode:?} includes the start");
+            assert_eq!(path.cost(), summed_cost(&grid, &path), "{mode:?}");
+        }
 
-        // Check that there's no nodes with duplicate positions
-        let mut positions = std::collections::HashSet::new();
-        for node in grid.graph.nodes() {
-            assert!(
-                positions.insert(node.pos),
-                "Duplicate node at {:?}",
-                node.pos
-            );
+        // A* finds the cheapest route around the swamp
+        let astar = grid
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(astar.cost(), 23);
+
+        // Coarse paths report the cached edge costs and flow fields their own costs, neither include cost overrides
+        grid.clear_cost_overrides();
+        for mode in [PathfindMode::Coarse, PathfindMode::FlowField] {
+            let path = grid.find_path(start, goal, mode, false).unwrap();
+            assert!(!path.path().contains(&start), "{mode:?} includes the start");
+            assert_eq!(path.path().last(), Some(&goal));
+            assert_eq!(path.cost(), summed_cost(&grid, &path), "{mode:?}");
         }
 
-        let grid_settings = GridSettingsBuilder::new_2d(12, 12)
-            .chunk_size(4)
-            .enable_diagonal_connections()
-            .build();
+        // Partial paths report the cost of the partial route
+        grid.set_nav(UVec3::new(10, 11, 0), Nav::Impassable);
+        grid.set_nav(UVec3::new(11, 10, 0), Nav::Impassable);
+        grid.build();
+        let partial = grid
+            .find_path(start, goal, PathfindMode::AStar, true)
+            .unwrap();
+        assert_ne!(partial.path().last(), Some(&goal));
+        assert_eq!(partial.cost(), summed_cost(&grid, &partial));
+    }
 
-        // Test ordinal
-        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings);
+    #[test]
+    fn test_nav_layers() {
+        const LAND: u32 = 1;
+        const WATER: u32 = 1 << 1;
 
-        grid.build_nodes();
+        let settings = GridSettingsBuilder::new_2d(12, 12).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
 
-        assert_eq!(grid.graph.node_count(), 40);
+        // A river along x = 6 with a bridge at y = 10
+        for y in 0..12 {
+            if y != 10 {
+                grid.set_nav_layers(UVec3::new(6, y, 0), WATER);
+            }
+        }
+        grid.set_nav(UVec3::new(6, 0, 0), Nav::Passable(2));
+        grid.build();
+
+        // Changing the nav keeps the layers
+        assert_eq!(grid.nav_layers(UVec3::new(6, 0, 0)), Some(WATER));
+        assert_eq!(grid.nav_layers(UVec3::new(0, 0, 0)), Some(ALL_NAV_LAYERS));
+        assert_eq!(grid.nav_layers(UVec3::new(12, 0, 0)), None);
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(11, 0, 0);
+        let find = |grid: &Grid<CardinalNeighborhood>, layers: u32| {
+            grid.find_path_with_blocking(
+                start,
+                &SearchGoal::Position(goal),
+                PathfindMode::Refined,
+                &HashMap::new(),
+                false,
+                None,
+                layers,
+                UVec2::ONE,
+                &SearchContext::default(),
+            )
+        };
+
+        // Infantry have to take the bridge
+        let path = find(&grid, LAND).unwrap();
+        assert!(path.path().contains(&UVec3::new(6, 10, 0)));
+        assert!(path.path().iter().all(|pos| pos.x != 6 || pos.y == 10));
+
+        // Amphibious units can cross anywhere
+        assert_eq!(find(&grid, LAND | WATER).unwrap().len(), 11);
+        assert_eq!(find(&grid, ALL_NAV_LAYERS).unwrap().len(), 11);
+
+        // Flooding the bridge cuts infantry off without a rebuild
+        grid.set_nav_layers(UVec3::new(6, 10, 0), WATER);
+        assert!(find(&grid, LAND).is_none());
+        assert!(find(&grid, LAND | WATER).is_some());
     }
 
     #[test]
-    pub fn test_build_nodes_3d() {
-        // Test 3d
-        let grid_settings = GridSettingsBuilder::new_3d(48, 48, 48)
-            .chunk_size(16)
-            .chunk_depth(16)
-            .build();
+    fn test_same_component() {
+        let settings = GridSettingsBuilder::new_2d(12, 12).chunk_size(4).build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
 
-        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings);
+        // Not labeled until the grid is built
+        assert!(!grid.same_component(UVec3::new(0, 0, 0), UVec3::new(1, 0, 0)));
 
-        grid.build_nodes();
+        // Wall off the right side of the grid
+        for y in 0..12 {
+            grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
+        }
+        grid.build();
 
-        // Get the center nodes of the center chunk
-        let center_chunk = grid.chunks[[1, 1, 1]].clone();
-        let center_nodes = grid.graph.nodes_in_chunk(&center_chunk);
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(11, 11, 0);
 
-        // Print all nodes and their directions
-        for node in center_nodes.clone() {
-            println!("Node at {:?} with direction {:?}", node.pos, node.dir);
+        assert!(grid.same_component(start, UVec3::new(5, 11, 0)));
+        assert!(!grid.same_component(start, goal));
+        assert!(!grid.same_component(start, UVec3::new(6, 0, 0)));
+        assert!(!grid.same_component(start, UVec3::new(12, 0, 0)));
+
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::Coarse,
+            PathfindMode::AStar,
+        ] {
+            assert!(grid.find_path(start, goal, mode, false).is_none());
         }
 
-        assert_eq!(center_nodes.len(), 14);
+        // Partial paths still search towards the goal
+        assert!(grid
+            .find_path(start, goal, PathfindMode::AStar, true)
+            .is_some());
+
+        // Opening a gap merges the regions after the incremental rebuild
+        grid.set_nav(UVec3::new(6, 5, 0), Nav::Passable(1));
+        grid.build();
+        assert!(grid.same_component(start, goal));
+        assert!(grid
+            .find_path(start, goal, PathfindMode::Refined, false)
+            .is_some());
+
+        // And closing it splits them again
+        grid.set_nav(UVec3::new(6, 5, 0), Nav::Impassable);
+        grid.build();
+        assert!(!grid.same_component(start, goal));
     }
 
     #[test]
-    pub fn test_connect_internal_nodes() {
-        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
+    fn test_try_find_path() {
+        let settings = GridSettingsBuilder::new_2d(12, 12).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for y in 0..12 {
+            grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
+        }
+        grid.set_nav(UVec3::new(2, 2, 0), Nav::Impassable);
+        grid.build();
 
-        grid.precompute_neighbors();
-        grid.build_nodes();
-        grid.connect_internal_chunk_nodes();
+        let start = UVec3::new(0, 0, 0);
+        let mode = PathfindMode::Refined;
 
-        assert_eq!(grid.graph.node_count(), 24);
-        assert_eq!(grid.graph.edge_count(), 44);
+        assert!(grid
+            .try_find_path(start, UVec3::new(5, 5, 0), mode, false)
+            .is_ok());
+        assert_eq!(
+            grid.try_find_path(start, UVec3::new(12, 0, 0), mode, false),
+            Err(PathError::OutOfBounds(UVec3::new(12, 0, 0)))
+        );
+        assert_eq!(
+            grid.try_find_path(UVec3::new(6, 0, 0), start, mode, false),
+            Err(PathError::StartBlocked)
+        );
+        assert_eq!(
+            grid.try_find_path(start, UVec3::new(2, 2, 0), mode, false),
+            Err(PathError::GoalBlocked)
+        );
+        assert_eq!(
+            grid.try_find_path(start, UVec3::new(11, 11, 0), mode, false),
+            Err(PathError::Disconnected)
+        );
+
+        assert!(matches!(
+            grid.try_set_nav(UVec3::new(0, 12, 0), Nav::Impassable),
+            Err(GridError::OutOfBounds(_))
+        ));
     }
 
     #[test]
-    pub fn test_connect_adjacent_nodes() {
-        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
+    fn test_path_exists() {
+        let settings = GridSettingsBuilder::new_2d(12, 12).chunk_size(4).build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
 
-        grid.precompute_neighbors();
-        grid.build_nodes();
-        grid.connect_adjacent_chunk_nodes();
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(11, 11, 0);
+        assert!(!grid.path_exists(start, goal));
 
-        let edges = grid
-            .graph
-            .node_at(UVec3::new(2, 3, 0))
-            .unwrap()
-            .edges
-            .clone();
+        grid.build();
+        assert!(grid.path_exists(start, goal));
+        assert!(grid.path_exists(start, UVec3::new(2, 3, 0)));
+        assert!(!grid.path_exists(start, UVec3::new(12, 0, 0)));
 
-        assert!(edges.contains_key(&UVec3::new(2, 4, 0)));
+        // A one-way portal is the only way across the wall
+        for y in 0..12 {
+            grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
+        }
+        grid.set_nav(
+            UVec3::new(2, 2, 0),
+            Nav::Portal(Portal::to(UVec3::new(10, 10, 0), 1, true)),
+        );
+        grid.build();
 
-        assert_eq!(edges[&UVec3::new(2, 4, 0)].path().len(), 2);
-        assert_eq!(edges[&UVec3::new(2, 4, 0)].cost(), 1);
+        assert!(grid.path_exists(start, goal));
+        assert!(grid
+            .find_path(start, goal, PathfindMode::Refined, false)
+            .is_some());
 
-        assert_eq!(grid.graph.edge_count(), 24);
+        // The regions are joined, but the portal can't be taken back
+        assert!(grid.same_component(goal, start));
+        assert!(!grid.path_exists(goal, start));
     }
 
     #[test]
-    pub fn test_get_chunk_for_position() {
-        let grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
-        let chunk = grid.chunk_at_position(UVec3::new(0, 0, 0)).unwrap();
+    fn test_closest_reachable_to() {
+        let settings = GridSettingsBuilder::new_2d(12, 12).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for y in 0..12 {
+            grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
+        }
 
-        assert_eq!(chunk.min(), UVec3::new(0, 0, 0));
+        let start = UVec3::new(0, 5, 0);
+        assert_eq!(grid.closest_reachable_to(start, UVec3::new(10, 5, 0)), None);
+
+        grid.build();
+
+        // The wall cuts the goal off, the closest cell is right in front of it
         assert_eq!(
-            chunk.max(),
-            UVec3::new(
-                GRID_SETTINGS.0.chunk_settings.size,
-                GRID_SETTINGS.0.chunk_settings.size,
-                GRID_SETTINGS.0.chunk_settings.depth
-            )
+            grid.closest_reachable_to(start, UVec3::new(10, 5, 0)),
+            Some(UVec3::new(5, 5, 0))
+        );
+        assert_eq!(
+            grid.closest_reachable_to(start, UVec3::new(3, 9, 0)),
+            Some(UVec3::new(3, 9, 0))
+        );
+        assert_eq!(grid.closest_reachable_to(start, start), Some(start));
+        assert_eq!(
+            grid.closest_reachable_to(UVec3::new(6, 0, 0), UVec3::new(10, 5, 0)),
+            None
         );
     }
 
     #[test]
-    pub fn test_get_all_nodes_in_chunk() {
-        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
-        grid.build_nodes();
+    fn test_nearest_and_random_passable() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
 
-        let nodes = grid.graph.nodes_in_chunk(&grid.chunks[[0, 0, 0]]);
+        for x in 3..6 {
+            for y in 3..6 {
+                grid.set_nav(UVec3::new(x, y, 0), Nav::Impassable);
+            }
+        }
+        grid.build();
 
-        assert_eq!(nodes.len(), 2);
+        let open = UVec3::new(1, 1, 0);
+        assert_eq!(grid.nearest_passable(open, 0), Some(open));
+
+        // The center of the wall is two cardinal steps from the nearest open cell
+        let center = UVec3::new(4, 4, 0);
+        assert_eq!(grid.nearest_passable(center, 1), None);
+        let nearest = grid.nearest_passable(center, 2).unwrap();
+        assert!(grid.is_passable(nearest));
+        assert_eq!(nearest.x.abs_diff(4) + nearest.y.abs_diff(4), 2);
+
+        assert_eq!(grid.nearest_passable(UVec3::new(12, 0, 0), 4), None);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let pos = grid.random_passable_cell(&mut rng).unwrap();
+            assert!(grid.is_passable(pos));
+        }
+
+        let mut walled: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        for x in 0..12 {
+            for y in 0..12 {
+                walled.set_nav(UVec3::new(x, y, 0), Nav::Impassable);
+            }
+        }
+        assert_eq!(walled.random_passable_cell(&mut rng), None);
     }
 
     #[test]
-    pub fn test_get_path() {
-        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
-
+    fn test_flood_fill() {
+        let settings = GridSettingsBuilder::new_2d(12, 12).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for y in 0..12 {
+            grid.set_nav(UVec3::new(6, y, 0), Nav::Impassable);
+        }
         grid.build();
 
-        let path = grid.pathfind(
-            UVec3::new(10, 10, 0),
-            UVec3::new(4, 4, 0),
-            &HashMap::new(),
-            false,
-        );
-        let raw_path = grid.pathfind_astar(
-            UVec3::new(10, 10, 0),
-            UVec3::new(4, 4, 0),
-            &HashMap::new(),
-            false,
-        );
+        // A diamond of 1 + 4 + 8 cells in the open
+        let origin = UVec3::new(2, 5, 0);
+        let cells = grid.flood_fill(origin, 2);
+        assert_eq!(cells.len(), 13);
+        assert_eq!(cells[0], origin);
 
-        assert!(path.is_some());
-        // Ensure start cell is the first cell in the path
-        assert_ne!(path.clone().unwrap().path()[0], UVec3::new(10, 10, 0));
-        // Ensure end cell is the last cell in the path
-        assert_eq!(
-            path.clone().unwrap().path().last().unwrap(),
-            &UVec3::new(4, 4, 0)
-        );
+        // The wall stops the fill from spilling over
+        let steps = grid.flood_fill_steps(UVec3::new(4, 5, 0), 4);
+        assert!(steps.iter().all(|(pos, _)| pos.x < 6));
+        assert!(steps.contains(&(UVec3::new(5, 7, 0), 3)));
+        assert!(steps.windows(2).all(|pair| pair[0].1 <= pair[1].1));
 
-        assert_eq!(path.clone().unwrap().len(), raw_path.unwrap().len());
-        assert_eq!(path.unwrap().len(), 6);
+        assert!(grid.flood_fill(UVec3::new(6, 0, 0), 3).is_empty());
     }
 
     #[test]
-    fn test_calculate_face_nodes_returns_center() {
-        //2D
-        let grid_settings = GridSettingsBuilder::new_2d(64, 64).chunk_size(32).build();
+    fn test_find_path_traced() {
+        let settings = GridSettingsBuilder::new_2d(12, 12).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
 
-        let grid: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings);
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(11, 11, 0);
 
-        let start_edge = grid.chunks[[0, 0, 0]].face(&grid.grid, Dir::North);
-        let end_edge = grid.chunks[[0, 1, 0]].face(&grid.grid, Dir::South);
+        let (path, trace) = grid.find_path_traced(start, goal, PathfindMode::AStar, false);
+        let path = path.unwrap();
 
-        let nodes = grid.calculate_face_nodes(
-            start_edge,
-            end_edge,
-            grid.chunks[[0, 0, 0]].clone(),
-            Dir::North,
-        );
+        assert!(!trace.is_empty());
+        assert_eq!(trace.nodes()[0].pos, start);
+        assert_eq!(trace.nodes()[0].g, 0);
+        assert!(trace.max_g() <= path.cost());
 
-        assert_eq!(nodes.len(), 1);
-        assert_eq!(nodes[0].pos, UVec3::new(16, 0, 0));
+        // The HPA* modes aren't recorded
+        let (path, trace) = grid.find_path_traced(start, goal, PathfindMode::Refined, false);
+        assert!(path.is_some());
+        assert!(trace.is_empty());
+    }
 
-        //3D
-        let grid_settings_3d = GridSettingsBuilder::new_3d(64, 64, 64)
-            .chunk_size(32)
-            .chunk_depth(32)
+    #[test]
+    fn test_is_edge_passable() {
+        let settings = GridSettingsBuilder::new_2d(4, 4)
+            .chunk_size(4)
+            .diagonal_movement(DiagonalMovement::NoCornerCutting)
             .build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+        grid.set_nav(UVec3::new(1, 0, 0), Nav::Impassable);
+        grid.block_direction(UVec3::new(2, 2, 0), Dir::North);
+        grid.build();
 
-        let grid_3d: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings_3d);
+        let origin = UVec3::new(0, 0, 0);
+        assert!(grid.is_edge_passable(origin, UVec3::new(0, 1, 0)));
+        assert!(!grid.is_edge_passable(origin, UVec3::new(1, 0, 0)));
 
-        let start_edge = grid_3d.chunks[[0, 0, 0]].face(&grid_3d.grid, Dir::North);
-        let end_edge = grid_3d.chunks[[0, 1, 0]].face(&grid_3d.grid, Dir::South);
-        let nodes_3d = grid_3d.calculate_face_nodes(
-            start_edge,
-            end_edge,
-            grid_3d.chunks[[0, 0, 0]].clone(),
-            Dir::North,
-        );
+        // Cutting around the wall corner isn't allowed
+        assert!(!grid.is_edge_passable(origin, UVec3::new(1, 1, 0)));
 
-        assert_eq!(nodes_3d.len(), 1);
-        assert_eq!(nodes_3d[0].pos, UVec3::new(16, 16, 0));
-        assert_eq!(nodes_3d[0].dir, Some(Dir::North));
+        // One-way
+        assert!(!grid.is_edge_passable(UVec3::new(2, 2, 0), UVec3::new(2, 3, 0)));
+        assert!(grid.is_edge_passable(UVec3::new(2, 3, 0), UVec3::new(2, 2, 0)));
+
+        // Not adjacent or out of bounds
+        assert!(!grid.is_edge_passable(origin, origin));
+        assert!(!grid.is_edge_passable(origin, UVec3::new(0, 2, 0)));
+        assert!(!grid.is_edge_passable(UVec3::new(3, 3, 0), UVec3::new(4, 3, 0)));
     }
 
     #[test]
-    fn test_calculate_edge_nodes_returns_center() {
-        let grid_settings = GridSettingsBuilder::new_2d(64, 64).chunk_size(32).build();
+    fn test_neighbors() {
+        let build = |diagonal_movement: DiagonalMovement| {
+            let settings = GridSettingsBuilder::new_2d(4, 4)
+                .chunk_size(4)
+                .diagonal_movement(diagonal_movement)
+                .build();
+            let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+            grid.set_nav(UVec3::new(1, 0, 0), Nav::Impassable);
+            grid.build();
+            grid
+        };
 
-        let grid: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings);
+        let origin = UVec3::new(0, 0, 0);
 
-        let start_edge = grid.chunks[[0, 0, 0]].edge(&grid.grid, Dir::NorthUp);
-        let end_edge = grid.chunks[[0, 1, 0]].edge(&grid.grid, Dir::SouthDown);
+        let grid = build(DiagonalMovement::Always);
+        let mut neighbors = grid.neighbor_positions(origin).to_vec();
+        neighbors.sort_by_key(|pos| pos.to_array());
+        assert_eq!(neighbors, vec![UVec3::new(0, 1, 0), UVec3::new(1, 1, 0)]);
+        assert!(grid.neighbor_positions(UVec3::new(4, 0, 0)).is_empty());
 
-        let nodes = grid.calculate_edge_nodes(
-            start_edge,
-            end_edge,
-            grid.chunks[[0, 0, 0]].clone(),
-            Dir::North,
+        // Cutting around the wall corner isn't allowed
+        let grid = build(DiagonalMovement::NoCornerCutting);
+        assert_eq!(
+            grid.neighbor_positions(origin).as_slice(),
+            &[UVec3::new(0, 1, 0)]
+        );
+
+        let settings = GridSettingsBuilder::new_3d(3, 3, 3).chunk_size(3).build();
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&settings);
+        grid.build();
+        assert_eq!(grid.neighbor_positions(UVec3::new(1, 1, 1)).len(), 26);
+        assert!(grid
+            .neighbor_positions(UVec3::new(1, 1, 1))
+            .contains(&UVec3::new(1, 1, 2)));
+    }
+
+    #[test]
+    fn test_wrap() {
+        let build = |wrap: BVec3| {
+            let settings = GridSettingsBuilder::new_2d(16, 8)
+                .chunk_size(4)
+                .wrap(wrap)
+                .build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            grid.build();
+            grid
+        };
+
+        let start = UVec3::new(1, 6, 0);
+        let goal = UVec3::new(14, 6, 0);
+
+        let grid = build(BVec3::FALSE);
+        assert_eq!(grid.heuristic_distance(start, goal), 13);
+        assert_eq!(
+            grid.find_path(start, goal, PathfindMode::AStar, false)
+                .unwrap()
+                .cost(),
+            13
         );
 
-        assert_eq!(nodes.len(), 1);
-        assert_eq!(nodes[0].pos, UVec3::new(16, 0, 0));
+        let mut grid = build(BVec3::new(true, false, false));
+        assert!(grid
+            .neighbor_positions(UVec3::new(0, 4, 0))
+            .contains(&UVec3::new(15, 4, 0)));
+        assert!(!grid
+            .neighbor_positions(UVec3::new(4, 0, 0))
+            .contains(&UVec3::new(4, 7, 0)));
+        assert_eq!(grid.heuristic_distance(start, goal), 3);
+
+        // The shorter route crosses the seam in every mode
+        for mode in [
+            PathfindMode::AStar,
+            PathfindMode::Refined,
+            PathfindMode::Coarse,
+            PathfindMode::Jps,
+            PathfindMode::FlowField,
+        ] {
+            let path = grid.find_path(start, goal, mode, false).unwrap();
+            assert_eq!(path.cost(), 3, "{mode:?}");
+            assert!(path.path().contains(&UVec3::new(0, 6, 0)), "{mode:?}");
+            assert!(path.path().contains(&UVec3::new(15, 6, 0)), "{mode:?}");
+        }
+
+        // The hierarchy has entrances along the seam
+        assert!(grid.graph().node_at(UVec3::new(0, 6, 0)).is_some());
+        assert!(grid.graph().node_at(UVec3::new(15, 6, 0)).is_some());
+        assert!(grid.path_exists(start, goal));
+
+        // Walling off the seam sends the path the long way around
+        for y in 0..8 {
+            grid.set_nav(UVec3::new(0, y, 0), Nav::Impassable);
+        }
+        grid.set_nav(UVec3::new(0, 6, 0), Nav::Passable(1));
+        grid.set_nav(UVec3::new(15, 6, 0), Nav::Impassable);
+        grid.build();
+        let path = grid
+            .find_path(start, goal, PathfindMode::Refined, false)
+            .unwrap();
+        assert_eq!(path.cost(), 13);
     }
 
     #[test]
-    fn test_random_grid_path() {
-        let width = 128;
-        let height = 128;
-        let chunk_size = 32;
+    fn test_diagonal_movement_corner_clipping() {
+        let build = |diagonal_movement: DiagonalMovement| {
+            let settings = GridSettingsBuilder::new_2d(12, 12)
+                .chunk_size(4)
+                .enable_diagonal_connections()
+                .diagonal_movement(diagonal_movement)
+                .build();
+            let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+
+            // L-shaped wall enclosing the bottom left chunk except for a diagonal gap at (3, 3) -> (4, 4)
+            for i in 0..4 {
+                grid.set_nav(UVec3::new(i, 4, 0), Nav::Impassable);
+                grid.set_nav(UVec3::new(4, i, 0), Nav::Impassable);
+            }
+            grid.build();
+            grid
+        };
 
-        // Test a grid with randomized walls, the grid must be solvable
-        let grid_settings = GridSettingsBuilder::new_2d(width, height)
-            .chunk_size(chunk_size)
-            .enable_diagonal_connections()
-            .build();
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(11, 11, 0);
 
-        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings);
+        let grid = build(DiagonalMovement::Always);
+        for mode in [PathfindMode::AStar, PathfindMode::Refined] {
+            let path = grid.find_path(start, goal, mode, false);
+            assert!(path.is_some(), "No path found for {mode:?}");
+            assert!(path.unwrap().contains(UVec3::new(4, 4, 0)));
+        }
 
-        for x in 0..width {
-            for y in 0..height {
-                if (x % 4 == 0)
-                    && (y % chunk_size == 0 || y / (chunk_size + chunk_size) == chunk_size)
-                {
-                    grid.grid[[x as usize, y as usize, 0]] = NavCell::new(Nav::Impassable);
-                }
+        let grid = build(DiagonalMovement::NoCornerClipping);
+        for mode in [
+            PathfindMode::AStar,
+            PathfindMode::Refined,
+            PathfindMode::Coarse,
+        ] {
+            assert!(
+                grid.find_path(start, goal, mode, false).is_none(),
+                "{mode:?} squeezed through the corner"
+            );
+        }
 
-                if (y % 4 == 0)
-                    && (x % chunk_size == 0 || x & (chunk_size + chunk_size) == chunk_size)
-                {
-                    grid.grid[[x as usize, y as usize, 0]] = NavCell::new(Nav::Impassable);
-                }
+        let grid = build(DiagonalMovement::Never);
+        let path = grid
+            .find_path(
+                UVec3::new(5, 5, 0),
+                UVec3::new(8, 8, 0),
+                PathfindMode::AStar,
+                false,
+            )
+            .unwrap();
+        assert_eq!(path.len(), 6);
+    }
+
+    #[test]
+    fn test_one_way_ledge() {
+        let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+
+        // A wall along y = 4 with a ledge at x = 1 and a gap at the far end
+        for x in 0..7 {
+            if x != 1 {
+                grid.set_nav(UVec3::new(x, 4, 0), Nav::Impassable);
             }
         }
-
+        let ledge = UVec3::new(1, 4, 0);
+        grid.set_one_way(ledge, UVec3::new(1, 3, 0));
         grid.build();
 
-        let path = grid.pathfind(
-            UVec3::new(7, 7, 0),
-            UVec3::new(121, 121, 0),
-            &HashMap::new(),
-            false,
+        assert_eq!(
+            grid.blocked_directions(UVec3::new(1, 3, 0)),
+            vec![Dir::North]
         );
+        assert!(!grid
+            .neighbor_positions(UVec3::new(1, 3, 0))
+            .contains(&ledge));
+        assert!(grid
+            .neighbor_positions(ledge)
+            .contains(&UVec3::new(1, 3, 0)));
+
+        let top = UVec3::new(1, 6, 0);
+        let bottom = UVec3::new(1, 1, 0);
+
+        for mode in [PathfindMode::AStar, PathfindMode::Refined] {
+            // Dropping down the ledge is the short way
+            let down = grid.find_path(top, bottom, mode, false).unwrap();
+            assert!(down.contains(ledge), "{mode:?} didn't drop down the ledge");
+            assert_eq!(down.cost(), 5);
+
+            // Climbing back up has to go around through the gap
+            let up = grid.find_path(bottom, top, mode, false).unwrap();
+            assert!(up.contains(UVec3::new(7, 4, 0)));
+            assert!(up.cost() > down.cost());
+
+            let cells = std::iter::once(bottom)
+                .chain(up.path().iter().copied())
+                .collect::<Vec<_>>();
+            for step in cells.windows(2) {
+                assert!(
+                    grid.neighbor_positions(step[0]).contains(&step[1]),
+                    "{mode:?} moved from {} to {}",
+                    step[0],
+                    step[1]
+                );
+            }
+        }
 
-        assert!(path.is_some());
-        assert!(!path.unwrap().is_empty());
+        grid.unblock_direction(UVec3::new(1, 3, 0), Dir::North);
+        grid.build();
+        let up = grid
+            .find_path(bottom, top, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(up.cost(), 5);
     }
 
     #[test]
-    fn test_large_3d_path() {
-        let grid_settings = GridSettingsBuilder::new_3d(128, 128, 4)
-            .chunk_size(16)
-            .chunk_depth(2)
-            .build();
+    fn test_cached_path_between() {
+        let build = |diagonal_movement: DiagonalMovement| {
+            let settings = GridSettingsBuilder::new_2d(12, 12)
+                .chunk_size(4)
+                .enable_diagonal_connections()
+                .diagonal_movement(diagonal_movement)
+                .build();
+            let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+
+            // Same diagonal gap at (3, 3) -> (4, 4) as test_diagonal_movement_corner_clipping
+            for i in 0..4 {
+                grid.set_nav(UVec3::new(i, 4, 0), Nav::Impassable);
+                grid.set_nav(UVec3::new(4, i, 0), Nav::Impassable);
+            }
+            grid.build();
+            grid
+        };
 
-        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&grid_settings);
+        let gap = (UVec3::new(3, 3, 0), UVec3::new(4, 4, 0));
 
-        grid.build();
-        let path = grid.pathfind(
-            UVec3::new(0, 0, 0),
-            UVec3::new(31, 31, 3),
-            &HashMap::new(),
-            false,
+        let grid = build(DiagonalMovement::Always);
+        assert_eq!(
+            grid.cached_path_between(gap.0, gap.1),
+            Some([gap.0, gap.1].as_slice())
         );
 
-        assert!(path.is_some());
+        let grid = build(DiagonalMovement::NoCornerClipping);
+        assert!(grid.cached_path_between(gap.0, gap.1).is_none());
+
+        // Every cached path has to be walkable one neighbor step at a time
+        for entrance in grid.entrances() {
+            for (to, _) in grid.entrance_edges(entrance.pos()) {
+                let cells = grid.cached_path_between(entrance.pos(), to).unwrap();
+                assert_eq!(cells.first(), Some(&entrance.pos()));
+                assert_eq!(cells.last(), Some(&to));
+
+                for step in cells.windows(2) {
+                    assert!(
+                        grid.neighbor_positions(step[0]).contains(&step[1]),
+                        "Cached path from {} to {to} clips from {} to {}",
+                        entrance.pos(),
+                        step[0],
+                        step[1]
+                    );
+                    assert_ne!(step, [gap.0, gap.1]);
+                }
+            }
+        }
+
+        assert!(grid
+            .cached_path_between(UVec3::new(0, 0, 0), UVec3::new(1, 1, 0))
+            .is_none());
     }
 
     #[test]
-    pub fn test_get_astar_path() {
-        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&GRID_SETTINGS);
+    fn test_nav_cost() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+
+        grid.set_nav(UVec3::new(1, 1, 0), Nav::Passable(3));
+        grid.set_nav(UVec3::new(2, 2, 0), Nav::Passable(0));
+        grid.set_nav(UVec3::new(3, 3, 0), Nav::Impassable);
+
+        assert_eq!(grid.nav_cost(UVec3::new(0, 0, 0)), Some(1));
+        assert_eq!(grid.nav_cost(UVec3::new(1, 1, 0)), Some(3));
+        assert_eq!(grid.nav_cost(UVec3::new(2, 2, 0)), Some(1));
+        assert_eq!(grid.nav(UVec3::new(2, 2, 0)), Some(Nav::Passable(1)));
+        assert_eq!(grid.nav_cost(UVec3::new(3, 3, 0)), None);
+        assert_eq!(grid.nav_cost(UVec3::new(100, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_weighted_costs_avoid_expensive_cells() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
 
+        // A swamp across the middle of the map with a road through the top row of chunks
+        for x in 0..12 {
+            for y in 0..8 {
+                if (3..9).contains(&x) {
+                    grid.set_nav(UVec3::new(x, y, 0), Nav::Passable(10));
+                }
+            }
+        }
         grid.build();
 
-        let path = grid.pathfind_astar(
-            UVec3::new(0, 0, 0),
-            UVec3::new(10, 10, 0),
-            &HashMap::new(),
-            false,
-        );
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(11, 0, 0);
 
-        assert!(path.is_some());
-        assert_eq!(path.unwrap().len(), 10);
+        let astar = grid
+            .pathfind_astar(start, goal, &HashMap::new(), false)
+            .unwrap();
+        assert!(astar
+            .path()
+            .iter()
+            .all(|pos| grid.nav_cost(*pos) == Some(1)));
+        let expected_cost: u32 = astar
+            .path()
+            .iter()
+            .map(|pos| grid.nav_cost(*pos).unwrap())
+            .sum();
+        assert_eq!(astar.cost(), expected_cost);
+
+        let refined = grid.pathfind(start, goal, &HashMap::new(), false).unwrap();
+        // The road around the swamp costs 27, straight through it would cost 65.
+        // HPA* passes through the chunk entrances on the road, a few steps longer than the best route.
+        assert_eq!(astar.cost(), 27);
+        assert_eq!(refined.cost(), 31);
+        assert!(refined
+            .path()
+            .iter()
+            .all(|pos| grid.nav_cost(*pos) == Some(1)));
+        let refined_cost: u32 = refined
+            .path()
+            .iter()
+            .map(|pos| grid.nav_cost(*pos).unwrap())
+            .sum();
+        assert_eq!(refined.cost(), refined_cost);
+
+        // Cached paths between entrances store the weighted cost
+        for path in grid.graph.all_paths() {
+            let weighted: u32 = path
+                .path()
+                .iter()
+                .skip(1)
+                .map(|pos| grid.nav_cost(*pos).unwrap())
+                .sum();
+            assert_eq!(path.cost(), weighted);
+        }
     }
 
     #[test]
@@ -2393,6 +6822,117 @@ mod tests {
         assert!(path.is_some(), "Path should exist with portal");
     }
 
+    #[test]
+    fn test_add_remove_portal() {
+        let settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for y in 0..16 {
+            grid.set_nav(UVec3::new(8, y, 0), Nav::Impassable);
+        }
+
+        let a = UVec3::new(2, 13, 0);
+        let b = UVec3::new(13, 2, 0);
+        grid.add_portal(a, b, 5).unwrap();
+        assert!(matches!(
+            grid.add_portal(a, UVec3::new(16, 0, 0), 5),
+            Err(GridError::OutOfBounds(_))
+        ));
+        grid.build();
+
+        assert_eq!(grid.portals().len(), 2);
+        assert!(grid
+            .graph
+            .node_at(a)
+            .is_some_and(|node| node.edges.contains_key(&b)));
+
+        let start = UVec3::new(1, 1, 0);
+        let goal = UVec3::new(14, 14, 0);
+        for mode in [PathfindMode::AStar, PathfindMode::Refined] {
+            let path = grid.find_path(start, goal, mode, false).unwrap();
+            assert!(
+                path.path().windows(2).any(|step| step == [a, b]),
+                "{mode:?}"
+            );
+
+            let back = grid.find_path(goal, start, mode, false).unwrap();
+            assert!(
+                back.path().windows(2).any(|step| step == [b, a]),
+                "{mode:?}"
+            );
+        }
+
+        // Removing either end removes the link
+        assert_eq!(grid.remove_portal(b), Some(Portal::to(a, 5, true)));
+        assert_eq!(grid.remove_portal(a), None);
+        assert!(grid.portals().is_empty());
+        grid.build();
+        assert!(grid
+            .find_path(start, goal, PathfindMode::Refined, false)
+            .is_none());
+
+        // One-way portals can't be taken back
+        grid.add_one_way_portal(a, b, 5).unwrap();
+        grid.build();
+        assert_eq!(grid.portals(), vec![(a, Portal::to(b, 5, true))]);
+        assert!(grid
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .is_some());
+        assert!(grid
+            .find_path(goal, start, PathfindMode::AStar, false)
+            .is_none());
+    }
+
+    #[test]
+    fn test_vertical_links() {
+        let settings = GridSettingsBuilder::new_3d(16, 16, 2)
+            .chunk_size(8)
+            .chunk_depth(1)
+            .build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+
+        let start = UVec3::new(2, 2, 0);
+        let goal = UVec3::new(2, 2, 1);
+
+        // Floors aren't connected without stairs
+        assert!(grid
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .is_none());
+
+        let stairs = UVec3::new(12, 12, 0);
+        let landing = UVec3::new(12, 12, 1);
+        grid.set_vertical_links(stairs, VerticalLinks::up(3));
+        grid.set_vertical_links(landing, VerticalLinks::down(3));
+        grid.build();
+
+        assert_eq!(grid.vertical_links(stairs), Some(VerticalLinks::up(3)));
+        assert!(grid.graph.node_at(stairs).is_some());
+        assert!(grid.graph.node_at(landing).is_some());
+
+        for mode in [
+            PathfindMode::AStar,
+            PathfindMode::Refined,
+            PathfindMode::Jps,
+        ] {
+            let path = grid.find_path(start, goal, mode, false).unwrap();
+
+            // The only change of floor is up the stairs
+            let mut previous = start;
+            for &pos in path.path() {
+                if pos.z != previous.z {
+                    assert_eq!((previous, pos), (stairs, landing), "{mode:?}");
+                }
+                previous = pos;
+            }
+            assert_eq!(previous, goal);
+
+            // 20 steps to the stairs, the climb, and 20 steps back
+            if mode != PathfindMode::Refined {
+                assert_eq!(path.cost(), 43, "{mode:?}");
+            }
+        }
+    }
+
     #[test]
     fn test_stairs() {
         let grid_settings = GridSettingsBuilder::new_3d(16, 16, 4)
@@ -2457,4 +6997,413 @@ mod tests {
 
         assert!(path.is_some(), "Path should exist with portal in reverse");
     }
+
+    #[test]
+    #[cfg(feature = "deterministic")]
+    fn test_deterministic_paths() {
+        use rand::Rng;
+
+        // Two grids built independently from the same seed must always return the same paths
+        let build = || {
+            let settings = GridSettingsBuilder::new_2d(32, 32).chunk_size(8).build();
+            let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+            let mut rng = StdRng::seed_from_u64(42);
+            for x in 0..32 {
+                for y in 0..32 {
+                    if rng.random_bool(0.25) {
+                        grid.set_nav(UVec3::new(x, y, 0), Nav::Impassable);
+                    }
+                }
+            }
+            grid.build();
+            grid
+        };
+
+        let first = build();
+        let second = build();
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let modes = [
+            PathfindMode::Refined,
+            PathfindMode::Coarse,
+            PathfindMode::AStar,
+            PathfindMode::Jps,
+            PathfindMode::Theta,
+        ];
+        for _ in 0..20 {
+            let start = first.random_passable_cell(&mut rng).unwrap();
+            let goal = first.random_passable_cell(&mut rng).unwrap();
+            for mode in modes {
+                let a = first.find_path(start, goal, mode, false);
+                let b = second.find_path(start, goal, mode, false);
+                assert_eq!(
+                    a.as_ref().map(|path| path.path().to_vec()),
+                    b.as_ref().map(|path| path.path().to_vec()),
+                    "{mode:?} from {start} to {goal}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_diagonal_cost() {
+        let settings = GridSettingsBuilder::new_2d(16, 16)
+            .chunk_size(4)
+            .diagonal_cost(10, 14)
+            .build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(8, 3, 0);
+
+        // 3 diagonal steps and 5 straight steps
+        let expected = 3 * 14 + 5 * 10;
+        assert_eq!(DiagonalCost::default().distance(start, goal), expected);
+        assert_eq!(grid.neighborhood().heuristic(start, goal), expected);
+
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::AStar,
+            PathfindMode::Jps,
+            PathfindMode::Theta,
+        ] {
+            let path = grid.find_path(start, goal, mode, false).unwrap();
+            assert_eq!(path.cost(), expected, "{mode:?}");
+        }
+
+        // Extra diagonal steps cost more than straight ones, the path makes a single diagonal run
+        // instead of zigzagging between diagonal and straight steps
+        let path = grid
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+        let cells = std::iter::once(start)
+            .chain(path.path().iter().copied().filter(|pos| *pos != start))
+            .collect::<Vec<_>>();
+        let diagonals = cells
+            .windows(2)
+            .map(|step| step[1].as_ivec3() - step[0].as_ivec3())
+            .filter(|dir| dir.x != 0 && dir.y != 0)
+            .collect::<Vec<_>>();
+        assert_eq!(cells.len(), 9);
+        assert_eq!(diagonals.len(), 3);
+        assert!(diagonals.iter().all(|dir| *dir == diagonals[0]));
+
+        // Without the weights the same route costs one per step
+        let settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut unweighted: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+        unweighted.build();
+        let path = unweighted
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(path.cost(), 8);
+    }
+
+    #[test]
+    fn test_heuristic_distance() {
+        use crate::neighbor::{chebyshev_distance, manhattan_distance, octile_distance};
+
+        let a = UVec3::new(1, 1, 0);
+        let b = UVec3::new(9, 4, 0);
+        assert_eq!(manhattan_distance(a, b), 11);
+        assert_eq!(chebyshev_distance(a, b), 8);
+        assert_eq!(octile_distance(a, b), 9);
+
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        grid.build();
+        let path = grid.find_path(a, b, PathfindMode::AStar, false).unwrap();
+        assert_eq!(grid.heuristic_distance(a, b), manhattan_distance(a, b));
+        assert_eq!(grid.heuristic_distance(a, b), path.cost());
+
+        // The distance matches path costs on open terrain with weighted diagonals
+        let settings = GridSettingsBuilder::new_2d(12, 12)
+            .chunk_size(4)
+            .diagonal_cost(10, 14)
+            .build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+        let path = grid.find_path(a, b, PathfindMode::AStar, false).unwrap();
+        assert_eq!(grid.heuristic_distance(a, b), path.cost());
+
+        let settings = GridSettingsBuilder::new_2d(12, 12)
+            .chunk_size(4)
+            .heuristic(HeuristicKind::Chebyshev)
+            .build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+        let path = grid.find_path(a, b, PathfindMode::AStar, false).unwrap();
+        assert_eq!(grid.heuristic_distance(a, b), chebyshev_distance(a, b));
+        assert_eq!(grid.heuristic_distance(a, b), path.cost());
+    }
+
+    #[test]
+    fn test_corridor() {
+        use crate::components::Corridor;
+
+        let settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+
+        // A wall along y = 2 with a gap at x = 2, the corridor runs along y = 0
+        for x in 0..16 {
+            grid.set_nav(UVec3::new(x, 2, 0), Nav::Impassable);
+        }
+        grid.set_nav(UVec3::new(2, 2, 0), Nav::Passable(1));
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(12, 0, 0);
+        let corridor = |width: u32, widen: bool| Corridor {
+            points: vec![start, goal],
+            width,
+            widen,
+        };
+        let find = |corridor: Corridor, goal: UVec3| {
+            grid.find_path_with_blocking(
+                start,
+                &SearchGoal::Position(goal),
+                PathfindMode::Refined,
+                &HashMap::new(),
+                false,
+                None,
+                ALL_NAV_LAYERS,
+                UVec2::ONE,
+                &SearchContext::default().corridor(Some(corridor)),
+            )
+        };
+
+        assert!(corridor(1, false).contains(UVec3::new(6, 1, 0)));
+        assert!(!corridor(1, false).contains(UVec3::new(6, 2, 0)));
+        assert!(!corridor(1, false).contains(UVec3::new(14, 0, 0)));
+
+        let path = find(corridor(1, false), goal).unwrap();
+        assert_eq!(path.len(), 12);
+        assert!(path.path().iter().all(|pos| pos.y <= 1));
+
+        // The goal beyond the wall can only be reached through the gap, outside the corridor
+        let goal = UVec3::new(12, 4, 0);
+        assert!(find(corridor(1, false), goal).is_none());
+
+        // Widening the corridor finds the way through the gap
+        let path = find(corridor(1, true), goal).unwrap();
+        assert!(path.path().contains(&UVec3::new(2, 2, 0)));
+        assert_eq!(path.path().last(), Some(&goal));
+    }
+
+    #[test]
+    fn test_neighbor_bits() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        grid.build();
+
+        let corner = UVec3::new(0, 0, 0);
+        let diagonal = UVec3::new(1, 1, 0);
+        assert!(grid.nav_cell(UVec3::new(12, 0, 0)).is_none());
+        assert_eq!(
+            grid.nav_cell(corner).unwrap().neighbor_bits(),
+            Dir::North.bit() | Dir::East.bit()
+        );
+        assert_eq!(
+            grid.find_path(corner, diagonal, PathfindMode::AStar, false)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // An authored diagonal edge on a cardinal grid
+        grid.allow_direction(corner, Dir::NorthEast);
+        assert!(grid.needs_build());
+        grid.build();
+        assert!(grid.nav_cell(corner).unwrap().has_exit(Dir::NorthEast));
+        for mode in [PathfindMode::AStar, PathfindMode::Refined] {
+            let path = grid.find_path(corner, diagonal, mode, false).unwrap();
+            assert_eq!(path.len(), 1);
+            assert_eq!(path.cost(), 1);
+        }
+
+        // The exact mask replaces the neighborhood and is kept when the nav changes
+        grid.set_neighbor_bits(corner, Dir::East.bit());
+        grid.set_nav(corner, Nav::Passable(2));
+        grid.build();
+        let cell = grid.nav_cell(corner).unwrap();
+        assert_eq!(cell.neighbor_bits(), Dir::East.bit());
+        assert_eq!(cell.added_exit_bits(), Dir::East.bit());
+        assert!(cell.blocked_directions().contains(&Dir::North));
+
+        // Added exits only lead into passable cells
+        grid.set_nav(UVec3::new(1, 0, 0), Nav::Impassable);
+        grid.build();
+        assert_eq!(grid.nav_cell(corner).unwrap().neighbor_bits(), 0);
+
+        grid.set_nav(UVec3::new(1, 0, 0), Nav::Passable(1));
+        grid.reset_neighbor_bits(corner);
+        grid.build();
+        assert_eq!(
+            grid.nav_cell(corner).unwrap().neighbor_bits(),
+            Dir::North.bit() | Dir::East.bit()
+        );
+    }
+
+    #[test]
+    fn test_find_path_filtered() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        grid.build();
+
+        let start = UVec3::new(0, 5, 0);
+        let goal = UVec3::new(11, 5, 0);
+        let avoid_row = |pos: UVec3| pos.y != 5 || pos.x < 2;
+
+        // Every mode searches with the filter applied
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::Jps,
+            PathfindMode::AStar,
+        ] {
+            assert_eq!(grid.find_path(start, goal, mode, false).unwrap().len(), 11);
+            let path = grid
+                .find_path_filtered(start, UVec3::new(11, 4, 0), mode, avoid_row)
+                .unwrap();
+            assert!(path.path().iter().all(|pos| avoid_row(*pos)));
+        }
+
+        // A filtered goal can't be reached
+        assert!(grid
+            .find_path_filtered(start, goal, PathfindMode::AStar, avoid_row)
+            .is_none());
+
+        // The grid isn't changed
+        assert_eq!(
+            grid.find_path(start, goal, PathfindMode::AStar, false)
+                .unwrap()
+                .len(),
+            11
+        );
+    }
+
+    #[test]
+    fn test_world_conversion() {
+        use bevy::math::{Vec2, Vec3};
+
+        let settings = GridSettingsBuilder::new_3d(12, 8, 2).chunk_size(4).build();
+        let grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        let tile_size = Vec2::new(32.0, 16.0);
+        let offset = Vec3::new(-100.0, 50.0, 0.0);
+
+        assert_eq!(grid.bounds(), (UVec3::ZERO, UVec3::new(11, 7, 1)));
+
+        for cell in [
+            UVec3::new(0, 0, 0),
+            UVec3::new(11, 7, 1),
+            UVec3::new(4, 2, 1),
+        ] {
+            let world = grid.cell_to_world(cell, tile_size, offset);
+            assert_eq!(grid.world_to_cell(world, tile_size, offset), Some(cell));
+
+            let world = grid.cell_to_world_isometric(cell, tile_size, offset);
+            assert_eq!(
+                grid.world_to_cell_isometric(world, tile_size, offset),
+                Some(cell)
+            );
+
+            // Anywhere inside the diamond maps to the same cell
+            let inside = world + Vec3::new(tile_size.x * 0.4, 0.0, 0.0);
+            assert_eq!(
+                grid.world_to_cell_isometric(inside, tile_size, offset),
+                Some(cell)
+            );
+        }
+
+        // The first isometric cell sits half a tile below the offset
+        assert_eq!(
+            grid.cell_to_world_isometric(UVec3::ZERO, tile_size, offset),
+            offset - Vec3::new(0.0, 8.0, 0.0)
+        );
+
+        // Outside the grid
+        let past = grid.cell_to_world(UVec3::new(12, 0, 0), tile_size, offset);
+        assert_eq!(grid.world_to_cell(past, tile_size, offset), None);
+        assert_eq!(
+            grid.world_to_cell(offset - Vec3::new(20.0, 0.0, 0.0), tile_size, offset),
+            None
+        );
+        assert_eq!(
+            grid.world_to_cell(offset + Vec3::new(0.0, 0.0, 2.0), tile_size, offset),
+            None
+        );
+    }
+
+    #[test]
+    fn test_repath_from() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        grid.build();
+
+        let start = UVec3::new(0, 4, 0);
+        let goal = UVec3::new(11, 4, 0);
+        let old_path = Path::new((1..12).map(|x| UVec3::new(x, 4, 0)).collect(), 11);
+
+        // Still walkable, nothing to repair
+        let path = grid
+            .repath_from(start, goal, &old_path, &HashMap::new())
+            .unwrap();
+        assert_eq!(path.path(), old_path.path());
+
+        grid.set_nav(UVec3::new(6, 4, 0), Nav::Impassable);
+        grid.build();
+
+        let path = grid
+            .repath_from(start, goal, &old_path, &HashMap::new())
+            .unwrap();
+        assert!(!path.path().contains(&UVec3::new(6, 4, 0)));
+        assert_eq!(path.path().last(), Some(&goal));
+
+        // Only the stretch around the blocked cell is replanned, the tail after it is kept
+        assert_eq!(&path.path()[path.len() - 4..], &old_path.path()[7..]);
+        assert_eq!(path.len(), 13);
+        assert_eq!(path.cost(), 13);
+
+        // Cells occupied by other agents are planned around too
+        let mut blocking = HashMap::new();
+        blocking.insert(UVec3::new(9, 4, 0), Entity::PLACEHOLDER);
+        let path = grid.repath_from(start, goal, &path, &blocking).unwrap();
+        assert!(!path.path().contains(&UVec3::new(6, 4, 0)));
+        assert!(!path.path().contains(&UVec3::new(9, 4, 0)));
+        assert_eq!(path.path().last(), Some(&goal));
+    }
+
+    #[test]
+    fn test_trail_costs() {
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+        grid.build();
+
+        let start = UVec3::new(0, 5, 0);
+        let goal = UVec3::new(11, 5, 0);
+
+        // A worn trail straight across the grid
+        assert!(grid.set_trail_costs((1..11).map(|x| (UVec3::new(x, 5, 0), 3))));
+        assert!(!grid.set_trail_costs((1..11).map(|x| (UVec3::new(x, 5, 0), 3))));
+        assert!(!grid.needs_build());
+        assert_eq!(grid.trail_cost(UVec3::new(4, 5, 0)), Some(3));
+        assert_eq!(grid.cost_override(UVec3::new(4, 5, 0)), None);
+
+        // Stepping off the trail is cheaper
+        let path = grid
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(path.cost(), 13);
+
+        // Trail costs add up with the cost overrides
+        grid.set_cost_override(UVec3::new(4, 5, 0), 2);
+        assert_eq!(grid.extra_cost(UVec3::new(4, 5, 0)), Some(5));
+        grid.clear_cost_overrides();
+        assert_eq!(grid.extra_cost(UVec3::new(4, 5, 0)), Some(3));
+
+        // Replacing the trail clears the cells that aren't in it anymore
+        grid.set_trail_costs([(UVec3::new(4, 5, 0), 1), (UVec3::new(40, 5, 0), 1)]);
+        assert_eq!(grid.trail_cost(UVec3::new(5, 5, 0)), None);
+        assert_eq!(grid.trail_cost(UVec3::new(40, 5, 0)), None);
+
+        grid.set_trail_costs([]);
+        let path = grid
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(path.cost(), 11);
+    }
 }
@@ -0,0 +1,117 @@
+//! Building the HPA* hierarchy of a [`Grid`] on a background task.
+//!
+//! Building a large grid can take long enough to stall loading. The grid is moved into a task on the
+//! [`AsyncComputeTaskPool`] and handed back once it's built, with the progress shared through an atomic.
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use bevy::{
+    prelude::Component,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task, TaskPool},
+};
+
+use super::Grid;
+use crate::neighbor::Neighborhood;
+
+/// A [`Grid`] being built in the background, see [`Grid::build_async`].
+///
+/// Poll it every frame with [`GridBuildTask::poll`] and read [`GridBuildTask::progress`] to drive a loading bar.
+/// With [`crate::plugin::NorthstarPlugin`] insert it on your map entity instead of the [`Grid`]. The plugin inserts
+/// the built [`Grid`] once it's done and reports the progress in [`crate::plugin::HierarchyBuildProgress`].
+/// Pathfinding requests made in the meantime wait in the queue until the grid is inserted.
+///
+/// Dropping the task cancels the build and drops the grid.
+#[derive(Component)]
+pub struct GridBuildTask<N: Neighborhood + 'static> {
+    task: Task<Grid<N>>,
+    // The bits of the completed fraction of the build as an `f32`.
+    progress: Arc<AtomicU32>,
+}
+
+impl<N: Neighborhood + 'static> GridBuildTask<N> {
+    /// Returns the completed fraction of the build, from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        f32::from_bits(self.progress.load(Ordering::Relaxed))
+    }
+
+    /// Returns the built [`Grid`] once the build is done, `None` while it's still running.
+    /// The grid is only returned once, polling again after that panics.
+    pub fn poll(&mut self) -> Option<Grid<N>> {
+        block_on(future::poll_once(&mut self.task))
+    }
+}
+
+impl<N: Neighborhood + 'static> Grid<N> {
+    /// Like [`Grid::build`] but builds the grid on the [`AsyncComputeTaskPool`] so loading a large map doesn't
+    /// block the main thread. The grid is moved into the task and returned by [`GridBuildTask::poll`] once it's built.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let grid_settings = GridSettingsBuilder::new_2d(1024, 1024).chunk_size(32).build();
+    /// let grid: Grid<CardinalNeighborhood> = Grid::new(&grid_settings);
+    ///
+    /// let mut task = grid.build_async();
+    ///
+    /// // Check on the task once per frame
+    /// match task.poll() {
+    ///     Some(grid) => {
+    ///         // The grid is built and ready to pathfind on
+    ///     }
+    ///     None => println!("Building: {:.0}%", task.progress() * 100.0),
+    /// }
+    /// ```
+    pub fn build_async(mut self) -> GridBuildTask<N> {
+        let progress = Arc::new(AtomicU32::new(0.0f32.to_bits()));
+        let shared = progress.clone();
+
+        let task = AsyncComputeTaskPool::get_or_init(TaskPool::default).spawn(async move {
+            self.build_with_progress(|fraction| {
+                shared.store(fraction.to_bits(), Ordering::Relaxed);
+            });
+            self
+        });
+
+        GridBuildTask { task, progress }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::UVec3;
+
+    use crate::{components::PathfindMode, nav::Nav, prelude::*};
+
+    #[test]
+    fn test_build_async() {
+        let settings = GridSettingsBuilder::new_2d(32, 32).chunk_size(8).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for y in 0..30 {
+            grid.set_nav(UVec3::new(16, y, 0), Nav::Impassable);
+        }
+
+        let mut task = grid.build_async();
+        let grid = loop {
+            if let Some(grid) = task.poll() {
+                break grid;
+            }
+            std::thread::yield_now();
+        };
+
+        assert_eq!(task.progress(), 1.0);
+        assert!(!grid.needs_build());
+
+        let path = grid
+            .find_path(
+                UVec3::new(0, 0, 0),
+                UVec3::new(31, 0, 0),
+                PathfindMode::Refined,
+                false,
+            )
+            .unwrap();
+        assert!(path.path().iter().any(|pos| pos.y >= 30));
+    }
+}
@@ -0,0 +1,282 @@
+//! A fluent builder that configures, validates and creates a [`Grid`] in one go.
+use std::marker::PhantomData;
+
+use bevy::math::{BVec3, UVec3};
+
+use super::{DiagonalMovement, Grid, GridError, GridSettingsBuilder, HeightCost};
+use crate::{
+    filter::{NeighborFilter, NeighborProvider},
+    neighbor::{HeuristicKind, Neighborhood},
+    MovementCost,
+};
+
+/// Builder for a [`Grid`] with the neighborhood `N`, configured with chained setters like [`GridSettingsBuilder`].
+///
+/// Unlike [`GridSettingsBuilder`], which panics on invalid values as soon as they're set, every option is checked
+/// together in [`GridBuilder::build`], which returns a [`GridError::InvalidSettings`] describing the first problem:
+/// * The grid is at least 3 cells wide and tall and 1 cell deep, chunks are at least 3x3x1.
+/// * A [`HeuristicKind::Manhattan`] heuristic isn't combined with diagonal moves, where it overestimates.
+/// * A diagonal cost is only set on a neighborhood with diagonal moves, and neither step weight is 0.
+/// * Wrapping isn't set on a hex neighborhood.
+///
+/// ```
+/// use bevy::math::{BVec3, UVec3};
+/// use bevy_northstar::prelude::*;
+///
+/// let grid = GridBuilder::<OrdinalNeighborhood>::new_2d(64, 32)
+///     .chunk_size(8)
+///     .diagonal_cost(10, 14)
+///     .heuristic(HeuristicKind::Octile)
+///     .wrap(BVec3::new(true, false, false))
+///     .build()
+///     .unwrap();
+/// assert_eq!(grid.dimensions(), UVec3::new(64, 32, 1));
+///
+/// // Manhattan distance overestimates the cost of diagonal moves
+/// let invalid = GridBuilder::<OrdinalNeighborhood>::new_2d(64, 32)
+///     .heuristic(HeuristicKind::Manhattan)
+///     .build();
+/// assert!(invalid.is_err());
+/// ```
+pub struct GridBuilder<N: Neighborhood> {
+    settings: GridSettingsBuilder,
+    neighborhood: PhantomData<N>,
+}
+
+impl<N: Neighborhood + Default> GridBuilder<N> {
+    /// Starts building a 2D [`Grid`] with the given width and height.
+    pub fn new_2d(width: u32, height: u32) -> Self {
+        let mut settings = GridSettingsBuilder {
+            dimensions: UVec3::new(width, height, 1),
+            ..Default::default()
+        };
+        settings.chunk_settings.size.z = 1;
+
+        Self::from_settings(settings)
+    }
+
+    /// Starts building a 3D [`Grid`] with the given width, height and depth.
+    pub fn new_3d(width: u32, height: u32, depth: u32) -> Self {
+        Self::from_settings(GridSettingsBuilder {
+            dimensions: UVec3::new(width, height, depth),
+            ..Default::default()
+        })
+    }
+
+    /// Continues from an existing [`GridSettingsBuilder`], for options only available there.
+    pub fn from_settings(settings: GridSettingsBuilder) -> Self {
+        Self {
+            settings,
+            neighborhood: PhantomData,
+        }
+    }
+
+    /// Sets the width and height of each chunk, see [`GridSettingsBuilder::chunk_size`].
+    pub fn chunk_size(mut self, chunk_size: u32) -> Self {
+        self.settings.chunk_settings.size.x = chunk_size;
+        self.settings.chunk_settings.size.y = chunk_size;
+        self
+    }
+
+    /// Sets the depth of each chunk on 3D grids, see [`GridSettingsBuilder::chunk_depth`].
+    pub fn chunk_depth(mut self, chunk_depth: u32) -> Self {
+        self.settings.chunk_settings.size.z = chunk_depth;
+        self
+    }
+
+    /// Adds HPA* connections across chunk corners, see [`GridSettingsBuilder::enable_diagonal_connections`].
+    pub fn enable_diagonal_connections(mut self) -> Self {
+        self.settings = self.settings.enable_diagonal_connections();
+        self
+    }
+
+    /// Sets the movement cost the cells start with, see [`GridSettingsBuilder::default_movement_cost`].
+    pub fn default_movement_cost(mut self, cost: MovementCost) -> Self {
+        self.settings = self.settings.default_movement_cost(cost);
+        self
+    }
+
+    /// Makes the cells start out impassable, see [`GridSettingsBuilder::default_impassable`].
+    pub fn default_impassable(mut self) -> Self {
+        self.settings = self.settings.default_impassable();
+        self
+    }
+
+    /// Enables collision avoidance in the plugin systems, see [`GridSettingsBuilder::enable_collision`].
+    pub fn enable_collision(mut self) -> Self {
+        self.settings = self.settings.enable_collision();
+        self
+    }
+
+    /// Sets the look ahead distance of collision avoidance, see [`GridSettingsBuilder::avoidance_distance`].
+    pub fn avoidance_distance(mut self, distance: u32) -> Self {
+        self.settings = self.settings.avoidance_distance(distance);
+        self
+    }
+
+    /// Adds a [`NeighborFilter`], see [`GridSettingsBuilder::add_neighbor_filter`].
+    pub fn add_neighbor_filter<F>(mut self, filter: F) -> Self
+    where
+        F: NeighborFilter + Send + Sync + 'static,
+    {
+        self.settings = self.settings.add_neighbor_filter(filter);
+        self
+    }
+
+    /// Sets a [`NeighborProvider`], see [`GridSettingsBuilder::neighbor_provider`].
+    pub fn neighbor_provider<P>(mut self, provider: P) -> Self
+    where
+        P: NeighborProvider + Send + Sync + 'static,
+    {
+        self.settings = self.settings.neighbor_provider(provider);
+        self
+    }
+
+    /// Sets the [`HeuristicKind`] of the neighborhood, see [`GridSettingsBuilder::heuristic`].
+    pub fn heuristic(mut self, heuristic: HeuristicKind) -> Self {
+        self.settings = self.settings.heuristic(heuristic);
+        self
+    }
+
+    /// Weighs straight and diagonal steps, see [`GridSettingsBuilder::diagonal_cost`].
+    pub fn diagonal_cost(mut self, straight: MovementCost, diagonal: MovementCost) -> Self {
+        self.settings = self.settings.diagonal_cost(straight, diagonal);
+        self
+    }
+
+    /// Sets how agents are allowed to move diagonally, see [`GridSettingsBuilder::diagonal_movement`].
+    pub fn diagonal_movement(mut self, diagonal_movement: DiagonalMovement) -> Self {
+        self.settings = self.settings.diagonal_movement(diagonal_movement);
+        self
+    }
+
+    /// Sets which axes wrap around, see [`GridSettingsBuilder::wrap`].
+    pub fn wrap(mut self, wrap: BVec3) -> Self {
+        self.settings = self.settings.wrap(wrap);
+        self
+    }
+
+    /// Sets the cost of climbing between cells of different heights, see [`GridSettingsBuilder::height_cost`].
+    pub fn height_cost(mut self, height_cost: HeightCost) -> Self {
+        self.settings = self.settings.height_cost(height_cost);
+        self
+    }
+
+    /// Checks the configuration and creates the [`Grid`]. Call [`Grid::build`] on it once the cells are set.
+    ///
+    /// # Errors
+    /// Returns [`GridError::InvalidSettings`] if the options break one of the rules listed on [`GridBuilder`].
+    pub fn build(self) -> Result<Grid<N>, GridError> {
+        let settings = &self.settings;
+        let invalid = |reason: &str| Err(GridError::InvalidSettings(reason.to_string()));
+
+        let dimensions = settings.dimensions;
+        if dimensions.x < 3 || dimensions.y < 3 || dimensions.z < 1 {
+            return invalid("width and height must be at least 3 and depth at least 1");
+        }
+
+        let chunk_size = settings.chunk_settings.size;
+        if chunk_size.x < 3 || chunk_size.y < 3 || chunk_size.z < 1 {
+            return invalid("chunk width and height must be at least 3 and depth at least 1");
+        }
+
+        let neighborhood = N::from_settings(&settings.neighborhood_settings);
+        let diagonal =
+            neighborhood.is_ordinal() && settings.diagonal_movement != DiagonalMovement::Never;
+
+        if diagonal && settings.neighborhood_settings.heuristic == Some(HeuristicKind::Manhattan) {
+            return invalid("the Manhattan heuristic overestimates diagonal moves");
+        }
+
+        if let Some(cost) = settings.neighborhood_settings.diagonal_cost {
+            if !diagonal || neighborhood.diagonal_cost().is_none() {
+                return invalid("a diagonal cost needs a neighborhood with diagonal moves");
+            }
+
+            if cost.straight == 0 || cost.diagonal == 0 {
+                return invalid("diagonal cost weights must be at least 1");
+            }
+        }
+
+        if settings.wrap.any() && neighborhood.is_hex() {
+            return invalid("wrapping isn't supported on hex grids");
+        }
+
+        Ok(Grid::new(&self.settings.build()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{BVec3, UVec3};
+
+    use super::GridBuilder;
+    use crate::{
+        grid::{DiagonalMovement, GridError},
+        neighbor::{
+            CardinalNeighborhood, HeuristicKind, HexNeighborhood, Neighborhood, OrdinalNeighborhood,
+        },
+    };
+
+    #[test]
+    fn test_grid_builder() {
+        let grid = GridBuilder::<OrdinalNeighborhood>::new_3d(16, 12, 2)
+            .chunk_size(4)
+            .chunk_depth(2)
+            .diagonal_cost(10, 14)
+            .heuristic(HeuristicKind::Octile)
+            .wrap(BVec3::new(true, false, false))
+            .enable_collision()
+            .build()
+            .unwrap();
+
+        assert_eq!(grid.dimensions(), UVec3::new(16, 12, 2));
+        assert_eq!(grid.wrap(), BVec3::new(true, false, false));
+        assert!(grid.neighborhood().diagonal_cost().is_some());
+        assert!(grid.collision());
+
+        // Manhattan distance is fine once diagonal moves are ruled out
+        assert!(GridBuilder::<OrdinalNeighborhood>::new_2d(8, 8)
+            .heuristic(HeuristicKind::Manhattan)
+            .diagonal_movement(DiagonalMovement::Never)
+            .build()
+            .is_ok());
+        assert!(GridBuilder::<CardinalNeighborhood>::new_2d(8, 8)
+            .heuristic(HeuristicKind::Manhattan)
+            .build()
+            .is_ok());
+
+        let invalid = [
+            GridBuilder::<OrdinalNeighborhood>::new_2d(2, 8).build(),
+            GridBuilder::<OrdinalNeighborhood>::new_2d(8, 8)
+                .chunk_size(2)
+                .build(),
+            GridBuilder::<OrdinalNeighborhood>::new_2d(8, 8)
+                .heuristic(HeuristicKind::Manhattan)
+                .build(),
+            GridBuilder::<OrdinalNeighborhood>::new_2d(8, 8)
+                .diagonal_cost(0, 14)
+                .build(),
+            GridBuilder::<OrdinalNeighborhood>::new_2d(8, 8)
+                .diagonal_movement(DiagonalMovement::Never)
+                .diagonal_cost(10, 14)
+                .build(),
+        ];
+        for result in invalid {
+            assert!(matches!(result, Err(GridError::InvalidSettings(_))));
+        }
+
+        assert!(matches!(
+            GridBuilder::<CardinalNeighborhood>::new_2d(8, 8)
+                .diagonal_cost(10, 14)
+                .build(),
+            Err(GridError::InvalidSettings(_))
+        ));
+        assert!(matches!(
+            GridBuilder::<HexNeighborhood>::new_2d(8, 8)
+                .wrap(BVec3::new(true, false, false))
+                .build(),
+            Err(GridError::InvalidSettings(_))
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+//! Memory metrics for the HPA* path cache and a way to drop it.
+//!
+//! Every entrance in the HPA* graph caches the paths to the entrances it connects to.
+//! On large maps these cached paths make up most of the memory used by a [`Grid`].
+use std::mem::size_of;
+
+use bevy::math::UVec3;
+
+use super::Grid;
+use crate::{chunk::Chunk, graph::Graph, neighbor::Neighborhood, node::Node, path::Path};
+
+/// Size of the cached HPA* paths of a [`Grid`] or one of its chunks, returned by [`Grid::cache_stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of paths cached between entrances.
+    pub cached_path_count: usize,
+    /// Total number of cells stored across all cached paths.
+    pub total_cells: usize,
+    /// Number of entrances in the HPA* graph.
+    pub entrance_count: usize,
+    /// Rough estimate of the memory used by the entrances and their cached paths, in bytes.
+    pub bytes_estimate: usize,
+    /// Total number of times the chunks have been rebuilt since the grid was created,
+    /// the initial build counts once for every chunk.
+    pub chunk_rebuilds: u32,
+}
+
+impl CacheStats {
+    fn add_node(&mut self, node: &Node) {
+        self.entrance_count += 1;
+        self.bytes_estimate += size_of::<Node>();
+
+        for path in node.edges.values() {
+            self.cached_path_count += 1;
+            self.total_cells += path.len();
+            self.bytes_estimate +=
+                size_of::<UVec3>() + size_of::<Path>() + path.len() * size_of::<UVec3>();
+        }
+    }
+}
+
+impl<N: Neighborhood + Default> Grid<N> {
+    /// Returns the size of the cached HPA* paths of the whole grid.
+    /// Useful for profiling the memory footprint of large maps.
+    pub fn cache_stats(&self) -> CacheStats {
+        let mut stats = CacheStats::default();
+
+        for node in self.graph.nodes() {
+            stats.add_node(node);
+        }
+        stats.chunk_rebuilds = self.chunks.iter().map(Chunk::version).sum();
+
+        stats
+    }
+
+    /// Returns the size of the cached HPA* paths of the chunk at `chunk`, see [`Grid::chunk_at`] for the chunk coordinates.
+    /// Returns `None` if the chunk is out of bounds.
+    pub fn chunk_cache_stats(&self, chunk: UVec3) -> Option<CacheStats> {
+        let chunk = self
+            .chunks
+            .get((chunk.x as usize, chunk.y as usize, chunk.z as usize))?;
+
+        let mut stats = CacheStats::default();
+        for node in self.graph.nodes_in_chunk(chunk) {
+            stats.add_node(node);
+        }
+        stats.chunk_rebuilds = chunk.version();
+
+        Some(stats)
+    }
+
+    /// Drops every entrance and cached path of the HPA* graph, and any route cached with [`Grid::set_route_cache_capacity`],
+    /// to recover memory on constrained platforms.
+    /// The grid is left dirty, the cache is recomputed on the next call to [`Grid::build`]
+    /// which has to happen before pathfinding on the grid again, same as after [`Grid::set_nav`].
+    pub fn clear_caches(&mut self) {
+        self.graph = Graph::new();
+        self.clear_route_cache();
+
+        for (index, chunk) in self.chunks.indexed_iter_mut() {
+            chunk.set_all_edges_dirty(true);
+            self.dirty_chunks.insert(index);
+        }
+
+        self.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::UVec3;
+
+    use crate::{
+        components::PathfindMode,
+        grid::{Grid, GridSettingsBuilder},
+        nav::Nav,
+        neighbor::CardinalNeighborhood,
+    };
+
+    #[test]
+    fn test_cache_stats() {
+        let settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.set_nav(UVec3::new(5, 5, 0), Nav::Impassable);
+        grid.build();
+
+        let stats = grid.cache_stats();
+        assert_eq!(stats.entrance_count, grid.entrances().len());
+        assert!(stats.cached_path_count > 0);
+        assert!(stats.total_cells >= stats.cached_path_count);
+        assert!(stats.bytes_estimate > 0);
+        assert_eq!(stats.chunk_rebuilds, 16);
+
+        let chunk = grid.chunk_cache_stats(UVec3::ZERO).unwrap();
+        assert!(chunk.entrance_count > 0 && chunk.entrance_count < stats.entrance_count);
+        assert_eq!(chunk.chunk_rebuilds, 1);
+        assert!(grid.chunk_cache_stats(UVec3::new(4, 0, 0)).is_none());
+
+        let path = grid
+            .find_path(
+                UVec3::ZERO,
+                UVec3::new(15, 15, 0),
+                PathfindMode::Refined,
+                false,
+            )
+            .unwrap();
+
+        grid.clear_caches();
+        assert_eq!(grid.cache_stats().cached_path_count, 0);
+        assert_eq!(grid.cache_stats().entrance_count, 0);
+
+        // Rebuilding recomputes the same cache
+        grid.build();
+        let rebuilt = grid.cache_stats();
+        assert_eq!(rebuilt.cached_path_count, stats.cached_path_count);
+        assert_eq!(rebuilt.total_cells, stats.total_cells);
+        assert_eq!(rebuilt.chunk_rebuilds, 32);
+
+        let repath = grid
+            .find_path(
+                UVec3::ZERO,
+                UVec3::new(15, 15, 0),
+                PathfindMode::Refined,
+                false,
+            )
+            .unwrap();
+        assert_eq!(repath.cost(), path.cost());
+    }
+}
@@ -0,0 +1,276 @@
+//! Connected component labeling of the passable cells, used to reject unreachable goals without searching.
+//!
+//! Each chunk labels its own components when it's rebuilt and records the links leaving it.
+//! The chunk components are then joined through those links, which only touches the chunk level data,
+//! so incremental rebuilds only pay for the dirty chunks.
+use bevy::math::UVec3;
+use ndarray::{s, Array3};
+
+use super::Grid;
+use crate::{components::PathfindMode, neighbor::Neighborhood, pathfind::hierarchy_connects};
+
+// Label of impassable cells.
+const NO_COMPONENT: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Default)]
+struct ChunkComponents {
+    // Number of components inside the chunk.
+    count: u32,
+    // Links from a component in the chunk to a cell outside of it.
+    links: Vec<(u32, UVec3)>,
+}
+
+/// Connected components of the passable cells in a [`Grid`].
+/// Movement rules are treated as two-way, so cells joined by a one-way link share a component.
+#[derive(Debug, Clone)]
+pub(crate) struct Connectivity {
+    // Component of every cell within its chunk.
+    labels: Array3<u32>,
+    chunks: Array3<ChunkComponents>,
+    // Index of the first component of every chunk in `roots`.
+    offsets: Array3<u32>,
+    // Grid wide component of every chunk component.
+    roots: Vec<u32>,
+}
+
+impl Connectivity {
+    pub(crate) fn new(dimensions: UVec3, chunk_count: (usize, usize, usize)) -> Self {
+        Self {
+            labels: Array3::from_elem(
+                (
+                    dimensions.x as usize,
+                    dimensions.y as usize,
+                    dimensions.z as usize,
+                ),
+                NO_COMPONENT,
+            ),
+            chunks: Array3::default(chunk_count),
+            offsets: Array3::zeros(chunk_count),
+            roots: Vec::new(),
+        }
+    }
+
+    /// Resizes the labels, keeping them where the old and new bounds overlap.
+    /// The chunks that changed need to be relabeled afterwards.
+    pub(crate) fn resize(&mut self, dimensions: UVec3, chunk_count: (usize, usize, usize)) {
+        let mut resized = Self::new(dimensions, chunk_count);
+
+        let (x, y, z) = self.labels.dim();
+        let overlap = s![
+            ..x.min(dimensions.x as usize),
+            ..y.min(dimensions.y as usize),
+            ..z.min(dimensions.z as usize)
+        ];
+        resized
+            .labels
+            .slice_mut(overlap)
+            .assign(&self.labels.slice(overlap));
+
+        for (index, chunk) in resized.chunks.indexed_iter_mut() {
+            if let Some(old) = self.chunks.get(index) {
+                *chunk = old.clone();
+            }
+        }
+
+        *self = resized;
+    }
+}
+
+impl<N: Neighborhood> Grid<N> {
+    /// Returns true if `a` and `b` are in the same connected region of passable cells.
+    ///
+    /// The regions are labeled when the grid is built and relabeled for the dirty chunks on every rebuild,
+    /// so this is a cheap way to find out a goal is unreachable before running a search.
+    /// Dynamic [`crate::components::Blocking`] entities are not taken into account and one-way movement,
+    /// like one-way portals, is treated as two-way, so a `true` result doesn't guarantee a path exists.
+    ///
+    /// Returns false if either position is out of bounds or impassable, or the grid hasn't been built.
+    pub fn same_component(&self, a: UVec3, b: UVec3) -> bool {
+        match (self.component(a), self.component(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Returns true if a path from `start` to `goal` exists, without building the path.
+    ///
+    /// Unreachable goals are rejected with [`Grid::same_component`], the remaining goals are checked by
+    /// walking the connected entrances of the HPA* graph instead of stitching and refining a full path.
+    /// Useful for validating a building placement or a click target when you don't need the route itself.
+    ///
+    /// If this returns true, [`Grid::find_path`] finds a path between the same positions in any [`PathfindMode`].
+    /// Like [`Grid::find_path`], dynamic [`crate::components::Blocking`] entities are not taken into account.
+    ///
+    /// Returns false if either position is out of bounds or impassable, or the grid hasn't been built.
+    pub fn path_exists(&self, start: UVec3, goal: UVec3) -> bool {
+        if self.needs_build() || !self.same_component(start, goal) {
+            return false;
+        }
+
+        // Neighbor providers bypass the hierarchy, see `Grid::find_path_with_blocking`
+        if self.has_neighbor_provider() {
+            return self
+                .find_path(start, goal, PathfindMode::AStar, false)
+                .is_some();
+        }
+
+        hierarchy_connects(self, &self.neighborhood, start, goal)
+    }
+
+    /// Returns the cell reachable from `start` that's closest to `goal`, or `goal` itself if it can be reached.
+    /// This is where a [`crate::components::Pathfind::partial`] A* path to `goal` ends, without building the path.
+    /// Useful for previewing where a unit will end up, like drawing a ghost at the destination, before committing to the move.
+    ///
+    /// Closeness is measured with the grid's heuristic. Returns `start` if no reachable cell is closer to the goal.
+    /// Like [`Grid::find_path`], dynamic [`crate::components::Blocking`] entities are not taken into account.
+    ///
+    /// Returns `None` if `start` is out of bounds or impassable, or the grid hasn't been built.
+    pub fn closest_reachable_to(&self, start: UVec3, goal: UVec3) -> Option<UVec3> {
+        if self.needs_build() || !self.is_passable(start) {
+            return None;
+        }
+
+        if self.path_exists(start, goal) {
+            return Some(goal);
+        }
+
+        let closest = self
+            .find_path(start, goal, PathfindMode::AStar, true)
+            .and_then(|path| path.path().last().copied());
+
+        Some(closest.unwrap_or(start))
+    }
+
+    pub(super) fn component(&self, pos: UVec3) -> Option<u32> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+
+        let label = self.connectivity.labels[[pos.x as usize, pos.y as usize, pos.z as usize]];
+        if label == NO_COMPONENT {
+            return None;
+        }
+
+        let offset = self.connectivity.offsets[self.chunk_index_of(pos)];
+        self.connectivity
+            .roots
+            .get((offset + label) as usize)
+            .copied()
+    }
+
+    /// Relabels the dirty chunks and joins the chunk components across the whole grid.
+    /// The neighbors need to be precomputed first.
+    pub(crate) fn update_connectivity(&mut self) {
+        let dirty = self.dirty_chunks.iter().copied().collect::<Vec<_>>();
+        for index in dirty {
+            self.label_chunk(index);
+        }
+
+        let connectivity = &mut self.connectivity;
+
+        let mut total = 0;
+        for (index, chunk) in connectivity.chunks.indexed_iter() {
+            connectivity.offsets[index] = total;
+            total += chunk.count;
+        }
+
+        let mut parents = (0..total).collect::<Vec<_>>();
+        for (index, chunk) in connectivity.chunks.indexed_iter() {
+            let offset = connectivity.offsets[index];
+
+            for &(label, target) in &chunk.links {
+                let target_label =
+                    connectivity.labels[[target.x as usize, target.y as usize, target.z as usize]];
+                if target_label == NO_COMPONENT {
+                    continue;
+                }
+
+                let target_chunk = (
+                    (target.x / self.chunk_settings.size.x) as usize,
+                    (target.y / self.chunk_settings.size.y) as usize,
+                    (target.z / self.chunk_settings.size.z) as usize,
+                );
+                let target_offset = connectivity.offsets[target_chunk];
+
+                union(&mut parents, offset + label, target_offset + target_label);
+            }
+        }
+
+        connectivity.roots = (0..total).map(|i| find(&mut parents, i)).collect();
+    }
+
+    // Labels the components inside a single chunk and records the links leaving it.
+    fn label_chunk(&mut self, index: (usize, usize, usize)) {
+        let chunk = &self.chunks[index];
+        let min = chunk.min();
+        let size = chunk.max() - min;
+        let local = |pos: UVec3| {
+            let pos = pos - min;
+            (pos.x * size.y + pos.y) * size.z + pos.z
+        };
+
+        let mut parents = (0..size.x * size.y * size.z).collect::<Vec<_>>();
+        let mut links = Vec::new();
+
+        for pos in chunk.bounds() {
+            if !self.is_passable(pos) {
+                continue;
+            }
+
+            for neighbor in self.navcell(pos).neighbor_iter(pos) {
+                if !self.is_passable(neighbor) {
+                    continue;
+                }
+
+                if neighbor.cmpge(min).all() && neighbor.cmplt(chunk.max()).all() {
+                    union(&mut parents, local(pos), local(neighbor));
+                } else {
+                    links.push((local(pos), neighbor));
+                }
+            }
+        }
+
+        // Compact the roots into labels starting at 0
+        let mut labels = vec![NO_COMPONENT; parents.len()];
+        let mut count = 0;
+        for pos in chunk.bounds() {
+            let label = if self.is_passable(pos) {
+                let root = find(&mut parents, local(pos)) as usize;
+                if labels[root] == NO_COMPONENT {
+                    labels[root] = count;
+                    count += 1;
+                }
+                labels[root]
+            } else {
+                NO_COMPONENT
+            };
+
+            self.connectivity.labels[[pos.x as usize, pos.y as usize, pos.z as usize]] = label;
+        }
+
+        for (cell, _) in links.iter_mut() {
+            *cell = labels[find(&mut parents, *cell) as usize];
+        }
+        links.sort_unstable_by_key(|(label, target)| (*label, target.to_array()));
+        links.dedup();
+
+        self.connectivity.chunks[index] = ChunkComponents { count, links };
+    }
+}
+
+fn find(parents: &mut [u32], mut i: u32) -> u32 {
+    while parents[i as usize] != i {
+        // Path halving
+        parents[i as usize] = parents[parents[i as usize] as usize];
+        i = parents[i as usize];
+    }
+    i
+}
+
+fn union(parents: &mut [u32], a: u32, b: u32) {
+    let a = find(parents, a);
+    let b = find(parents, b);
+    if a != b {
+        parents[a.max(b) as usize] = a.min(b);
+    }
+}
@@ -0,0 +1,230 @@
+//! Read-only access to the HPA* abstract graph for custom hierarchical planners.
+//!
+//! Entrances are the cells on the chunk borders, and portals, that the HPA* graph is built from.
+//! Every entrance is identified by its position and stores the cost of the cached paths
+//! to the other entrances it connects to.
+use bevy::{math::UVec3, platform::collections::HashMap};
+
+use super::Grid;
+use crate::{
+    dir::Dir, neighbor::Neighborhood, node::Node, path::Path, pathfind::pathfind, MovementCost,
+};
+
+/// An entrance in the HPA* abstract graph of a [`Grid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entrance {
+    pos: UVec3,
+    chunk: UVec3,
+    dir: Option<Dir>,
+    portal: bool,
+}
+
+impl Entrance {
+    fn from_node(node: &Node) -> Self {
+        let (x, y, z) = node.chunk_index;
+
+        Self {
+            pos: node.pos,
+            chunk: UVec3::new(x as u32, y as u32, z as u32),
+            dir: node.dir,
+            portal: node.portal,
+        }
+    }
+
+    /// The position of the entrance, which also identifies it in the graph.
+    pub fn pos(&self) -> UVec3 {
+        self.pos
+    }
+
+    /// The coordinates of the chunk the entrance belongs to, see [`Grid::chunk_at`].
+    pub fn chunk(&self) -> UVec3 {
+        self.chunk
+    }
+
+    /// The chunk border the entrance is on, `None` for portals.
+    pub fn dir(&self) -> Option<Dir> {
+        self.dir
+    }
+
+    /// Returns true if the entrance is one end of a portal between chunks.
+    pub fn is_portal(&self) -> bool {
+        self.portal
+    }
+}
+
+impl<N: Neighborhood + Default> Grid<N> {
+    /// Returns every [`Entrance`] in the HPA* graph.
+    pub fn entrances(&self) -> Vec<Entrance> {
+        self.graph
+            .nodes()
+            .into_iter()
+            .map(Entrance::from_node)
+            .collect()
+    }
+
+    /// Returns the entrances of the chunk at `chunk`, see [`Grid::chunk_at`] for the chunk coordinates.
+    /// Returns an empty list if the chunk is out of bounds.
+    pub fn entrances_in_chunk(&self, chunk: UVec3) -> Vec<Entrance> {
+        let Some(chunk) = self
+            .chunks
+            .get((chunk.x as usize, chunk.y as usize, chunk.z as usize))
+        else {
+            return Vec::new();
+        };
+
+        self.graph
+            .nodes_in_chunk(chunk)
+            .into_iter()
+            .map(Entrance::from_node)
+            .collect()
+    }
+
+    /// Returns the [`Entrance`] at `pos`, if there is one.
+    pub fn entrance(&self, pos: UVec3) -> Option<Entrance> {
+        self.graph.node_at(pos).map(Entrance::from_node)
+    }
+
+    /// Iterates over the entrances reachable from the entrance at `pos` and the cost of the cached path to each.
+    /// Empty if there is no entrance at `pos`.
+    pub fn entrance_edges(&self, pos: UVec3) -> impl Iterator<Item = (UVec3, MovementCost)> + '_ {
+        self.graph
+            .node_at(pos)
+            .into_iter()
+            .flat_map(|node| node.edges.iter().map(|(to, path)| (*to, path.cost())))
+    }
+
+    /// Iterates over the chunks connected to the chunk at `chunk` and the number of entrances leading into each,
+    /// see [`Grid::chunk_at`] for the chunk coordinates. Portals count as entrances to the chunk they lead to.
+    ///
+    /// Chunks connected by a single entrance are chokepoints of the map.
+    /// The chunks are sorted by z, y, then x. Empty if the chunk is out of bounds or has no entrances.
+    pub fn chunk_neighbors(&self, chunk: UVec3) -> impl Iterator<Item = (UVec3, usize)> {
+        let mut counts: HashMap<UVec3, usize> = HashMap::new();
+
+        for entrance in self.entrances_in_chunk(chunk) {
+            for (to, _) in self.entrance_edges(entrance.pos) {
+                if let Some(to) = self.entrance(to).filter(|to| to.chunk != chunk) {
+                    *counts.entry(to.chunk).or_default() += 1;
+                }
+            }
+        }
+
+        let mut neighbors: Vec<_> = counts.into_iter().collect();
+        neighbors.sort_unstable_by_key(|(chunk, _)| (chunk.z, chunk.y, chunk.x));
+        neighbors.into_iter()
+    }
+
+    /// Returns a snapshot of the HPA* graph as a directed [`petgraph::Graph`] for running your own graph algorithms,
+    /// such as finding chokepoints, over the same connectivity the hierarchy plans with.
+    /// Every [`Entrance`] is a node and every cached path between two entrances is an edge weighted by its cost.
+    ///
+    /// Nodes are added sorted by z, y, then x, so the same grid always produces the same node indices.
+    /// The snapshot isn't updated with the grid, get a new one after calling [`Grid::build`]. Requires the `petgraph` feature.
+    #[cfg(feature = "petgraph")]
+    pub fn abstract_graph(&self) -> petgraph::Graph<Entrance, MovementCost> {
+        let mut entrances = self.entrances();
+        entrances.sort_unstable_by_key(|entrance| (entrance.pos.z, entrance.pos.y, entrance.pos.x));
+
+        let mut graph = petgraph::Graph::with_capacity(entrances.len(), self.graph.edge_count());
+        let indices: HashMap<UVec3, petgraph::graph::NodeIndex> = entrances
+            .iter()
+            .map(|entrance| (entrance.pos, graph.add_node(*entrance)))
+            .collect();
+
+        for entrance in &entrances {
+            let mut edges: Vec<_> = self.entrance_edges(entrance.pos).collect();
+            edges.sort_unstable_by_key(|(pos, _)| (pos.z, pos.y, pos.x));
+
+            for (to, cost) in edges {
+                if let Some(to) = indices.get(&to) {
+                    graph.add_edge(indices[&entrance.pos], *to, cost);
+                }
+            }
+        }
+
+        graph
+    }
+
+    /// Returns the cached [`Path`] between the entrances at `from` and `to`.
+    /// The path includes both entrances.
+    pub fn entrance_path(&self, from: UVec3, to: UVec3) -> Option<&Path> {
+        self.graph.node_at(from)?.edges.get(&to)
+    }
+
+    /// Returns the cells of the cached path between the entrances at `a` and `b`, see [`Entrance::pos`].
+    /// The cells include both entrances. Returns `None` if the entrances aren't connected.
+    ///
+    /// Useful for asserting on the exact cells the hierarchy will refine through in tests.
+    pub fn cached_path_between(&self, a: UVec3, b: UVec3) -> Option<&[UVec3]> {
+        self.entrance_path(a, b).map(Path::path)
+    }
+
+    /// Runs a coarse HPA* search from `start` to `goal` and returns the entrances it passes through, in order.
+    /// Blocking entities are not taken into account.
+    ///
+    /// Returns an empty list if `start` and `goal` are in the same chunk, since no entrances are needed,
+    /// and `None` if no path could be found or the grid needs to be built.
+    pub fn find_entrance_path(&self, start: UVec3, goal: UVec3) -> Option<Vec<UVec3>> {
+        if self.needs_build() {
+            return None;
+        }
+
+        let path = pathfind(
+            self,
+            &self.neighborhood,
+            start,
+            goal,
+            &HashMap::new(),
+            false,
+            false,
+        )?;
+
+        Some(path.graph_path.into_iter().collect())
+    }
+}
+
+#[cfg(all(test, feature = "petgraph"))]
+mod tests {
+    use bevy::math::UVec3;
+
+    use crate::{
+        grid::{Grid, GridSettingsBuilder},
+        nav::Nav,
+        neighbor::CardinalNeighborhood,
+    };
+
+    #[test]
+    fn test_abstract_graph() {
+        let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+
+        let graph = grid.abstract_graph();
+        assert_eq!(graph.node_count(), grid.entrances().len());
+
+        for edge in graph.raw_edges() {
+            let from = graph[edge.source()].pos();
+            let to = graph[edge.target()].pos();
+            assert_eq!(
+                grid.entrance_edges(from).find(|(pos, _)| *pos == to),
+                Some((to, edge.weight))
+            );
+        }
+
+        // The same grid produces the same graph
+        let again = grid.abstract_graph();
+        assert!(graph
+            .node_weights()
+            .zip(again.node_weights())
+            .all(|(a, b)| a == b));
+
+        // Walling off the right half disconnects the graph
+        for y in 0..8 {
+            grid.set_nav(UVec3::new(4, y, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let graph = grid.abstract_graph();
+        assert!(petgraph::algo::connected_components(&graph) > 1);
+    }
+}
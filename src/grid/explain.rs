@@ -0,0 +1,461 @@
+//! Explaining why a search took the path it did, for diagnosing unexpected paths.
+use std::{cmp::Ordering, fmt};
+
+use bevy::{
+    math::{BVec3, IVec3, UVec3},
+    reflect::Reflect,
+};
+use smallvec::SmallVec;
+
+use super::{wrap_position, Grid};
+use crate::{
+    components::PathfindMode,
+    neighbor::{offset_index, Neighborhood},
+    trace::SearchTrace,
+    MovementCost,
+};
+
+/// Why a neighbor of a step in a [`PathExplanation`] wasn't moved to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum RejectReason {
+    /// The cell is impassable or blocked with [`Grid::block_temporarily`].
+    Blocked,
+    /// The diagonal move cuts a corner the grid's [`crate::grid::DiagonalMovement`] policy disallows.
+    CornerCut,
+    /// The move is removed by a [`crate::filter::NeighborFilter`] or a blocked direction.
+    Filtered,
+    /// The cell can be entered, but its f-score is higher than the step taken.
+    HigherCost {
+        /// The g-score the cell would have had coming from the step.
+        g: MovementCost,
+        /// The heuristic estimate from the cell to the goal.
+        h: u32,
+    },
+    /// The cell can be entered and has the same f-score as the step taken, the search preferred the step.
+    Tie {
+        /// The g-score the cell would have had coming from the step.
+        g: MovementCost,
+        /// The heuristic estimate from the cell to the goal.
+        h: u32,
+    },
+    /// The cell can be entered and has a lower f-score than the step taken,
+    /// but no path on from it to the goal is cheaper than the path found.
+    Detour {
+        /// The g-score the cell would have had coming from the step.
+        g: MovementCost,
+        /// The heuristic estimate from the cell to the goal.
+        h: u32,
+    },
+}
+
+/// A neighbor of a step in a [`PathExplanation`] that the path didn't move to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct RejectedNeighbor {
+    /// The position of the neighbor.
+    pub pos: UVec3,
+    /// Why the path didn't move to it.
+    pub reason: RejectReason,
+}
+
+/// A cell of the path in a [`PathExplanation`] along with its scores.
+#[derive(Debug, Clone, PartialEq, Eq, Reflect)]
+pub struct ExplainedStep {
+    /// The position of the cell.
+    pub pos: UVec3,
+    /// The cost of the path from the start to the cell, the g-score.
+    pub g: MovementCost,
+    /// The heuristic estimate of the remaining cost to the goal, the h-score.
+    pub h: u32,
+    /// The estimated cost of the whole path through the cell, `g + h`.
+    pub f: u32,
+    /// The neighbors of the cell the path doesn't continue to, other than the cell it came from.
+    /// Empty for the goal.
+    pub rejected: Vec<RejectedNeighbor>,
+}
+
+/// Why a path took the steps it did, returned by [`Grid::explain_path`].
+///
+/// Lists the scores of every cell of the path starting with the start, and for each cell the neighbors
+/// that weren't taken and why. Formatting it with `{}` prints one line per step, handy for bug reports.
+#[derive(Debug, Clone, Default, Reflect)]
+pub struct PathExplanation {
+    steps: Vec<ExplainedStep>,
+    trace: SearchTrace,
+}
+
+impl PathExplanation {
+    /// Returns the cells of the path from the start to the goal with their scores.
+    /// Empty if no path was found.
+    pub fn steps(&self) -> &[ExplainedStep] {
+        &self.steps
+    }
+
+    /// Returns true if a path was found.
+    pub fn is_found(&self) -> bool {
+        !self.steps.is_empty()
+    }
+
+    /// Returns the cost of the path, the g-score of the goal.
+    pub fn cost(&self) -> MovementCost {
+        self.steps.last().map_or(0, |step| step.g)
+    }
+
+    /// Returns every cell the search expanded, see [`SearchTrace`].
+    pub fn trace(&self) -> &SearchTrace {
+        &self.trace
+    }
+}
+
+impl fmt::Display for PathExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.steps.is_empty() {
+            return writeln!(f, "no path found, {} cells expanded", self.trace.len());
+        }
+
+        for step in &self.steps {
+            write!(
+                f,
+                "{:?} g: {} h: {} f: {}",
+                step.pos.to_array(),
+                step.g,
+                step.h,
+                step.f
+            )?;
+
+            for rejected in &step.rejected {
+                write!(f, ", {:?} ", rejected.pos.to_array())?;
+                match rejected.reason {
+                    RejectReason::Blocked => write!(f, "blocked")?,
+                    RejectReason::CornerCut => write!(f, "corner cut")?,
+                    RejectReason::Filtered => write!(f, "filtered")?,
+                    RejectReason::HigherCost { g, h } => {
+                        write!(f, "higher cost g: {g} h: {h} f: {}", g.saturating_add(h))?
+                    }
+                    RejectReason::Tie { g, h } => {
+                        write!(f, "tie g: {g} h: {h} f: {}", g.saturating_add(h))?
+                    }
+                    RejectReason::Detour { g, h } => {
+                        write!(f, "detour g: {g} h: {h} f: {}", g.saturating_add(h))?
+                    }
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<N: Neighborhood + Default> Grid<N> {
+    /// Searches for a path from `start` to `goal` with [`PathfindMode::AStar`] and explains the steps it took.
+    ///
+    /// Every cell of the path is listed with its g, h and f scores, along with the neighbors the path didn't move to
+    /// and why: they're blocked, the move cuts a corner, a filter or blocked direction removes it,
+    /// or they score worse than, the same as, or better than the step taken but lead nowhere cheaper.
+    /// The cells the search expanded are recorded as well, see [`SearchTrace`].
+    ///
+    /// This is a lot slower than [`Grid::find_path`] and only meant for diagnosing unexpected paths,
+    /// printing the [`PathExplanation`] gives a readable summary to attach to a bug report.
+    pub fn explain_path(&self, start: UVec3, goal: UVec3) -> PathExplanation {
+        let (path, trace) = self.find_path_traced(start, goal, PathfindMode::AStar, false);
+        let Some(path) = path else {
+            return PathExplanation {
+                steps: Vec::new(),
+                trace,
+            };
+        };
+
+        let cells: Vec<UVec3> = std::iter::once(start)
+            .chain(path.path().iter().copied())
+            .collect();
+
+        // The cost of stepping from `from` onto its neighbor `to`, the same way the search adds it up
+        let step_cost = |from: UVec3, to: UVec3| {
+            let cost = self.navcell(from).move_cost(from, to, self.navcell(to));
+            self.neighborhood
+                .step_cost(from, to, cost)
+                .saturating_add(self.extra_cost(to).unwrap_or(0))
+        };
+
+        // The g and h scores of every cell of the path
+        let mut scores = Vec::with_capacity(cells.len());
+        let mut g: MovementCost = 0;
+        for (index, &pos) in cells.iter().enumerate() {
+            if index > 0 {
+                g = g.saturating_add(step_cost(cells[index - 1], pos));
+            }
+            scores.push((g, self.heuristic_distance(pos, goal)));
+        }
+
+        let corner_filter = self.diagonal_movement.filter();
+        let grid = self.grid.view();
+
+        let steps = cells
+            .iter()
+            .enumerate()
+            .map(|(index, &pos)| {
+                let (g, h) = scores[index];
+
+                let mut rejected = Vec::new();
+                if let Some(&next) = cells.get(index + 1) {
+                    let previous = index.checked_sub(1).map(|index| cells[index]);
+                    let (next_g, next_h) = scores[index + 1];
+                    let next_f = next_g.saturating_add(next_h);
+                    let allowed: SmallVec<[UVec3; 8]> =
+                        self.navcell(pos).neighbor_iter(pos).collect();
+
+                    for neighbor in self.move_candidates(pos) {
+                        if neighbor == next || Some(neighbor) == previous {
+                            continue;
+                        }
+
+                        let reason = if self.navcell(neighbor).is_impassable()
+                            || self.temporary_blocks.contains_key(&neighbor)
+                        {
+                            RejectReason::Blocked
+                        } else if allowed.contains(&neighbor) {
+                            let g = g.saturating_add(step_cost(pos, neighbor));
+                            let h = self.heuristic_distance(neighbor, goal);
+                            match g.saturating_add(h).cmp(&next_f) {
+                                Ordering::Greater => RejectReason::HigherCost { g, h },
+                                Ordering::Equal => RejectReason::Tie { g, h },
+                                Ordering::Less => RejectReason::Detour { g, h },
+                            }
+                        } else {
+                            // The move is a corner cut if the diagonal policy alone removes it
+                            let offset = neighbor.as_ivec3() - pos.as_ivec3();
+                            let cut = offset.abs().max_element() == 1
+                                && corner_filter.as_ref().is_some_and(|filter| {
+                                    let bit = 1 << offset_index(offset);
+                                    filter.filter(pos, bit, &grid) & bit == 0
+                                });
+
+                            if cut {
+                                RejectReason::CornerCut
+                            } else {
+                                RejectReason::Filtered
+                            }
+                        };
+
+                        rejected.push(RejectedNeighbor {
+                            pos: neighbor,
+                            reason,
+                        });
+                    }
+                }
+
+                ExplainedStep {
+                    pos,
+                    g,
+                    h,
+                    f: g.saturating_add(h),
+                    rejected,
+                }
+            })
+            .collect();
+
+        PathExplanation { steps, trace }
+    }
+
+    // Every cell a move out of `pos` could lead to whether the move is allowed or not: the neighborhood's directions,
+    // straight steps across wrapped edges, and the portals, vertical links and provider links of the cell.
+    fn move_candidates(&self, pos: UVec3) -> SmallVec<[UVec3; 8]> {
+        let mut candidates = SmallVec::<[UVec3; 8]>::new();
+
+        for &(x, y, z) in self.neighborhood.directions() {
+            let offset = IVec3::new(x, y, z);
+            let wrap = if offset.abs().element_sum() == 1 {
+                self.wrap
+            } else {
+                BVec3::FALSE
+            };
+
+            if let Some(neighbor) = wrap_position(pos.as_ivec3() + offset, self.dimensions, wrap) {
+                if neighbor != pos && !candidates.contains(&neighbor) {
+                    candidates.push(neighbor);
+                }
+            }
+        }
+
+        for neighbor in self.navcell(pos).neighbor_iter(pos) {
+            if !candidates.contains(&neighbor) {
+                candidates.push(neighbor);
+            }
+        }
+
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::math::{BVec3, UVec3};
+
+    use super::{RejectReason, RejectedNeighbor};
+    use crate::{
+        components::PathfindMode,
+        grid::{DiagonalMovement, Grid, GridSettingsBuilder},
+        nav::Nav,
+        prelude::{CardinalNeighborhood, HexNeighborhood, OrdinalNeighborhood},
+    };
+
+    #[test]
+    fn test_explain_path() {
+        let settings = GridSettingsBuilder::new_2d(6, 6).chunk_size(3).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for y in 0..4 {
+            grid.set_nav(UVec3::new(2, y, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(4, 0, 0);
+        let explanation = grid.explain_path(start, goal);
+        let path = grid
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+
+        assert!(explanation.is_found());
+        assert!(!explanation.trace().is_empty());
+        assert_eq!(explanation.cost(), path.cost());
+
+        let steps = explanation.steps();
+        assert_eq!(steps.len(), path.len() + 1);
+        assert_eq!((steps[0].pos, steps[0].g), (start, 0));
+        assert_eq!(steps.last().unwrap().pos, goal);
+        assert!(steps.last().unwrap().rejected.is_empty());
+        for step in steps {
+            assert_eq!(step.h, grid.heuristic_distance(step.pos, goal));
+            assert_eq!(step.f, step.g + step.h);
+        }
+
+        // Walking up beside the wall, the wall is rejected as blocked
+        assert!(steps
+            .iter()
+            .any(|step| step.rejected.contains(&RejectedNeighbor {
+                pos: UVec3::new(2, step.pos.y, 0),
+                reason: RejectReason::Blocked,
+            })));
+
+        // Neighbors are only rejected for their cost when they score worse than the step taken
+        for (step, next) in steps.iter().zip(&steps[1..]) {
+            for rejected in &step.rejected {
+                if let RejectReason::HigherCost { g, h } = rejected.reason {
+                    assert!(g + h > next.f);
+                }
+            }
+        }
+
+        // Walled off goals have nothing to explain
+        let explanation = grid.explain_path(start, UVec3::new(2, 0, 0));
+        assert!(!explanation.is_found());
+        assert!(explanation.to_string().starts_with("no path found"));
+    }
+
+    #[test]
+    fn test_explain_path_corner_cut() {
+        let settings = GridSettingsBuilder::new_2d(4, 4)
+            .chunk_size(4)
+            .diagonal_movement(DiagonalMovement::NoCornerCutting)
+            .build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+        grid.set_nav(UVec3::new(1, 1, 0), Nav::Impassable);
+        grid.build();
+
+        let explanation = grid.explain_path(UVec3::new(0, 0, 0), UVec3::new(2, 2, 0));
+        let steps = explanation.steps();
+
+        // The diagonal through the wall is blocked, the diagonals around its corners are cut
+        assert!(steps[0].rejected.contains(&RejectedNeighbor {
+            pos: UVec3::new(1, 1, 0),
+            reason: RejectReason::Blocked,
+        }));
+        assert!(steps[1]
+            .rejected
+            .iter()
+            .any(|rejected| rejected.reason == RejectReason::CornerCut));
+        assert!(explanation.to_string().contains("corner cut"));
+    }
+
+    #[test]
+    fn test_explain_path_ties_and_temporary_blocks() {
+        let settings = GridSettingsBuilder::new_2d(5, 3).chunk_size(5).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+
+        // Heading to the opposite corner, stepping east or north first scores the same
+        let explanation = grid.explain_path(UVec3::new(0, 0, 0), UVec3::new(2, 2, 0));
+        assert!(matches!(
+            explanation.steps()[0].rejected.as_slice(),
+            [RejectedNeighbor {
+                reason: RejectReason::Tie { g: 1, h: 3 },
+                ..
+            }]
+        ));
+        assert!(explanation.to_string().contains("tie"));
+
+        // The temporarily blocked cell straight ahead is blocked, not a cheaper neighbor the search skipped
+        let blocked = UVec3::new(2, 1, 0);
+        grid.block_temporarily(blocked, Duration::from_secs(1));
+        let explanation = grid.explain_path(UVec3::new(0, 1, 0), UVec3::new(4, 1, 0));
+        let rejected = explanation
+            .steps()
+            .iter()
+            .flat_map(|step| &step.rejected)
+            .filter(|rejected| rejected.pos == blocked)
+            .collect::<Vec<_>>();
+        assert!(!rejected.is_empty());
+        assert!(rejected
+            .iter()
+            .all(|rejected| rejected.reason == RejectReason::Blocked));
+    }
+
+    #[test]
+    fn test_explain_path_wrapped_and_hex() {
+        let settings = GridSettingsBuilder::new_2d(6, 3)
+            .chunk_size(3)
+            .wrap(BVec3::new(true, false, false))
+            .build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+
+        // The path wraps west around the edge, the step east costs more
+        let explanation = grid.explain_path(UVec3::new(0, 1, 0), UVec3::new(4, 1, 0));
+        let steps = explanation.steps();
+        assert_eq!(steps[1].pos, UVec3::new(5, 1, 0));
+        assert!(steps[0].rejected.contains(&RejectedNeighbor {
+            pos: UVec3::new(1, 1, 0),
+            reason: RejectReason::HigherCost { g: 1, h: 3 },
+        }));
+
+        // A wall across the seam is blocked as well
+        grid.set_nav(UVec3::new(5, 1, 0), Nav::Impassable);
+        grid.build();
+        let explanation = grid.explain_path(UVec3::new(0, 1, 0), UVec3::new(2, 1, 0));
+        assert!(explanation.steps()[0].rejected.contains(&RejectedNeighbor {
+            pos: UVec3::new(5, 1, 0),
+            reason: RejectReason::Blocked,
+        }));
+
+        // Every hex neighbor that isn't taken is listed once, none of them cut corners
+        let settings = GridSettingsBuilder::new_2d(5, 5).chunk_size(5).build();
+        let mut grid: Grid<HexNeighborhood> = Grid::new(&settings);
+        grid.set_nav(UVec3::new(2, 2, 0), Nav::Impassable);
+        grid.build();
+
+        let explanation = grid.explain_path(UVec3::new(1, 2, 0), UVec3::new(3, 2, 0));
+        let steps = explanation.steps();
+        assert_eq!(steps[0].rejected.len(), 5);
+        assert!(steps[0].rejected.contains(&RejectedNeighbor {
+            pos: UVec3::new(2, 2, 0),
+            reason: RejectReason::Blocked,
+        }));
+        assert!(steps
+            .iter()
+            .flat_map(|step| &step.rejected)
+            .all(|rejected| rejected.reason != RejectReason::CornerCut));
+    }
+}
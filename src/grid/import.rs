@@ -0,0 +1,234 @@
+//! Constructing a [`Grid`] straight from map data instead of calling [`Grid::set_nav`] for every cell.
+//!
+//! The constructors only fill in the cells, call [`Grid::build`] afterwards like with [`Grid::new`].
+use bevy::math::UVec3;
+
+use super::{Grid, GridError, GridSettings};
+use crate::{nav::Nav, neighbor::Neighborhood, MovementCost};
+
+impl<N: Neighborhood + Default> Grid<N> {
+    /// Creates a 2D grid from rows of passable cells, `rows[y][x]` being the cell at `(x, y, 0)`.
+    /// `true` cells are passable with the default movement cost of the settings, `false` cells are impassable.
+    ///
+    /// # Errors
+    /// Returns [`GridError::DimensionMismatch`] if the grid in the settings isn't 2D,
+    /// or if the number of rows or the length of any row doesn't match its height and width.
+    ///
+    /// # Example
+    /// ```
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let settings = GridSettingsBuilder::new_2d(3, 3).chunk_size(3).build();
+    /// let mut grid: Grid<CardinalNeighborhood> = Grid::from_bools(
+    ///     &settings,
+    ///     &[&[true, false, true], &[true, true, true], &[true, true, true]],
+    /// )
+    /// .unwrap();
+    /// grid.build();
+    /// ```
+    pub fn from_bools(settings: &GridSettings, rows: &[&[bool]]) -> Result<Self, GridError> {
+        let dimensions = settings.0.dimensions;
+        let cost = settings.0.cost_settings.default_movement_cost;
+
+        if dimensions.z != 1 {
+            return Err(GridError::DimensionMismatch(format!(
+                "rows describe a 2D grid but the grid is {dimensions}"
+            )));
+        }
+
+        if rows.len() != dimensions.y as usize {
+            return Err(GridError::DimensionMismatch(format!(
+                "{} rows for a grid {} cells high",
+                rows.len(),
+                dimensions.y
+            )));
+        }
+
+        if let Some((y, row)) = rows
+            .iter()
+            .enumerate()
+            .find(|(_, row)| row.len() != dimensions.x as usize)
+        {
+            return Err(GridError::DimensionMismatch(format!(
+                "row {y} has {} cells for a grid {} cells wide",
+                row.len(),
+                dimensions.x
+            )));
+        }
+
+        let mut grid = Self::new(settings);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, passable) in row.iter().enumerate() {
+                let nav = if *passable {
+                    Nav::Passable(cost)
+                } else {
+                    Nav::Impassable
+                };
+                grid.set_nav(UVec3::new(x as u32, y as u32, 0), nav);
+            }
+        }
+
+        Ok(grid)
+    }
+
+    /// Creates a grid from the movement cost of every cell, ordered by x, then y, then z.
+    /// The cost of the cell at `(x, y, z)` is at `x + y * width + z * width * height`, the row-major order
+    /// most tilemap formats store their layers in. A cost of 0 marks the cell as impassable.
+    ///
+    /// # Errors
+    /// Returns [`GridError::DimensionMismatch`] if the number of costs doesn't match the grid dimensions in the settings.
+    pub fn from_costs(settings: &GridSettings, costs: &[MovementCost]) -> Result<Self, GridError> {
+        let dimensions = settings.0.dimensions;
+        let expected = dimensions.x as usize * dimensions.y as usize * dimensions.z as usize;
+
+        if costs.len() != expected {
+            return Err(GridError::DimensionMismatch(format!(
+                "{} costs for a {dimensions} grid of {expected} cells",
+                costs.len()
+            )));
+        }
+
+        let mut grid = Self::new(settings);
+        for (i, cost) in costs.iter().enumerate() {
+            let i = i as u32;
+            let pos = UVec3::new(
+                i % dimensions.x,
+                i / dimensions.x % dimensions.y,
+                i / (dimensions.x * dimensions.y),
+            );
+            let nav = if *cost == 0 {
+                Nav::Impassable
+            } else {
+                Nav::Passable(*cost)
+            };
+            grid.set_nav(pos, nav);
+        }
+
+        Ok(grid)
+    }
+
+    /// Creates a 2D grid from a grayscale image the size of the grid, such as a PNG exported from a map editor.
+    /// Dark pixels, with a brightness below half, are impassable and the rest are passable with the default movement cost of the settings.
+    ///
+    /// Images are stored top row first, so the top row of the image is the top row of the grid, `y = height - 1`.
+    /// Colored images are converted to grayscale.
+    ///
+    /// # Errors
+    /// Returns [`GridError::Image`] if the image can't be read, and [`GridError::DimensionMismatch`]
+    /// if the grid in the settings isn't 2D or the image size doesn't match its width and height.
+    #[cfg(feature = "image")]
+    pub fn from_image(
+        settings: &GridSettings,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Self, GridError> {
+        let image = image::open(path)
+            .map_err(|err| GridError::Image(err.to_string()))?
+            .into_luma8();
+
+        let dimensions = settings.0.dimensions;
+        if dimensions.z != 1 || image.width() != dimensions.x || image.height() != dimensions.y {
+            return Err(GridError::DimensionMismatch(format!(
+                "{}x{} image for a {dimensions} grid",
+                image.width(),
+                image.height()
+            )));
+        }
+
+        let cost = settings.0.cost_settings.default_movement_cost;
+
+        let mut grid = Self::new(settings);
+        for (x, row, pixel) in image.enumerate_pixels() {
+            let nav = if pixel.0[0] < 128 {
+                Nav::Impassable
+            } else {
+                Nav::Passable(cost)
+            };
+            grid.set_nav(UVec3::new(x, dimensions.y - 1 - row, 0), nav);
+        }
+
+        Ok(grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::UVec3;
+
+    use super::*;
+    use crate::{grid::GridSettingsBuilder, neighbor::CardinalNeighborhood};
+
+    #[test]
+    fn test_from_bools() {
+        let settings = GridSettingsBuilder::new_2d(3, 3).chunk_size(3).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::from_bools(
+            &settings,
+            &[
+                &[true, false, true],
+                &[true, true, true],
+                &[true, true, true],
+            ],
+        )
+        .unwrap();
+        grid.build();
+
+        assert_eq!(grid.nav(UVec3::new(1, 0, 0)), Some(Nav::Impassable));
+        assert_eq!(grid.nav(UVec3::new(1, 1, 0)), Some(Nav::Passable(1)));
+        assert!(grid.is_passable(UVec3::new(2, 0, 0)));
+
+        assert!(matches!(
+            Grid::<CardinalNeighborhood>::from_bools(&settings, &[&[true, true, true]]),
+            Err(GridError::DimensionMismatch(_))
+        ));
+        assert!(matches!(
+            Grid::<CardinalNeighborhood>::from_bools(
+                &settings,
+                &[&[true; 3], &[true; 3], &[true; 2]]
+            ),
+            Err(GridError::DimensionMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_costs() {
+        let settings = GridSettingsBuilder::new_3d(3, 3, 2).chunk_size(3).build();
+        let mut costs: Vec<u32> = (1..=18).collect();
+        costs[4] = 0;
+        let grid: Grid<CardinalNeighborhood> = Grid::from_costs(&settings, &costs).unwrap();
+
+        assert_eq!(grid.nav(UVec3::new(1, 0, 0)), Some(Nav::Passable(2)));
+        assert_eq!(grid.nav(UVec3::new(0, 1, 0)), Some(Nav::Passable(4)));
+        assert_eq!(grid.nav(UVec3::new(1, 1, 0)), Some(Nav::Impassable));
+        assert_eq!(grid.nav(UVec3::new(1, 1, 1)), Some(Nav::Passable(14)));
+
+        assert!(matches!(
+            Grid::<CardinalNeighborhood>::from_costs(&settings, &costs[..17]),
+            Err(GridError::DimensionMismatch(_))
+        ));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_from_image() {
+        let mut image = image::GrayImage::from_pixel(4, 3, image::Luma([255]));
+        // The top left pixel is the top left cell of the grid
+        image.put_pixel(0, 0, image::Luma([0]));
+
+        let path = std::env::temp_dir().join("bevy_northstar_test_from_image.png");
+        image.save(&path).unwrap();
+
+        let settings = GridSettingsBuilder::new_2d(4, 3).chunk_size(3).build();
+        let grid: Grid<CardinalNeighborhood> = Grid::from_image(&settings, &path).unwrap();
+        assert_eq!(grid.nav(UVec3::new(0, 2, 0)), Some(Nav::Impassable));
+        assert_eq!(grid.nav(UVec3::new(0, 0, 0)), Some(Nav::Passable(1)));
+
+        let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        assert!(matches!(
+            Grid::<CardinalNeighborhood>::from_image(&settings, &path),
+            Err(GridError::DimensionMismatch(_))
+        ));
+        assert!(matches!(
+            Grid::<CardinalNeighborhood>::from_image(&settings, "missing.png"),
+            Err(GridError::Image(_))
+        ));
+    }
+}
@@ -0,0 +1,107 @@
+//! Converting between cells and world positions on square and isometric tilemaps.
+//!
+//! The math matches the debug gizmos of `NorthstarDebugPlugin`, so gameplay code and debug drawing agree.
+use bevy::math::{IVec2, UVec3, Vec2, Vec3};
+
+use super::Grid;
+use crate::neighbor::Neighborhood;
+
+impl<N: Neighborhood + Default> Grid<N> {
+    /// Returns the inclusive `(min, max)` corners of the cells in the grid.
+    pub fn bounds(&self) -> (UVec3, UVec3) {
+        (UVec3::ZERO, self.dimensions.saturating_sub(UVec3::ONE))
+    }
+
+    /// Converts a world position on a square tilemap to the cell under it, `None` if it's outside the grid.
+    ///
+    /// Cells are `tile_size` apart with the center of cell `(0, 0)` at `offset`, like a tilemap anchored at its first tile.
+    /// The cell's depth is the world z relative to `offset`, rounded to the nearest layer.
+    ///
+    /// # Example
+    /// ```
+    /// use bevy::math::{UVec3, Vec2, Vec3};
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let grid: Grid<CardinalNeighborhood> = Grid::new(&GridSettingsBuilder::new_2d(16, 16).build());
+    /// let tile_size = Vec2::splat(8.0);
+    ///
+    /// let world = grid.cell_to_world(UVec3::new(3, 5, 0), tile_size, Vec3::ZERO);
+    /// assert_eq!(world, Vec3::new(24.0, 40.0, 0.0));
+    ///
+    /// let inside = world + Vec3::new(3.0, -3.0, 0.0);
+    /// assert_eq!(grid.world_to_cell(inside, tile_size, Vec3::ZERO), Some(UVec3::new(3, 5, 0)));
+    /// assert_eq!(grid.world_to_cell(Vec3::new(-8.0, 0.0, 0.0), tile_size, Vec3::ZERO), None);
+    /// ```
+    pub fn world_to_cell(&self, world: Vec3, tile_size: Vec2, offset: Vec3) -> Option<UVec3> {
+        let local = world - offset;
+        self.cell_in_bounds(square_to_cell(local.truncate(), tile_size), local.z)
+    }
+
+    /// Converts a cell to the world position of its center on a square tilemap, see [`Grid::world_to_cell`].
+    pub fn cell_to_world(&self, cell: UVec3, tile_size: Vec2, offset: Vec3) -> Vec3 {
+        square_to_world(cell, tile_size).extend(cell.z as f32) + offset
+    }
+
+    /// Converts a world position on an isometric tilemap of diamond tiles to the cell under it,
+    /// `None` if it's outside the grid. Matches `DebugTilemapType::Isometric`.
+    ///
+    /// `tile_size` is the full width and height of a diamond. Increasing x moves a cell right and down the screen,
+    /// increasing y moves it right and up, with cell `(0, 0)` half a tile below `offset`.
+    /// The cell's depth is the world z relative to `offset`, rounded to the nearest layer.
+    pub fn world_to_cell_isometric(
+        &self,
+        world: Vec3,
+        tile_size: Vec2,
+        offset: Vec3,
+    ) -> Option<UVec3> {
+        let local = world - offset;
+        self.cell_in_bounds(isometric_to_cell(local.truncate(), tile_size), local.z)
+    }
+
+    /// Converts a cell to the world position of its center on an isometric tilemap,
+    /// see [`Grid::world_to_cell_isometric`].
+    pub fn cell_to_world_isometric(&self, cell: UVec3, tile_size: Vec2, offset: Vec3) -> Vec3 {
+        isometric_to_world(cell, tile_size).extend(cell.z as f32) + offset
+    }
+
+    // The cell at `cell` and depth `z`, `None` if it's outside the grid.
+    fn cell_in_bounds(&self, cell: IVec2, z: f32) -> Option<UVec3> {
+        let z = z.round();
+        if cell.cmplt(IVec2::ZERO).any() || z < 0.0 {
+            return None;
+        }
+
+        let cell = cell.as_uvec2().extend(z as u32);
+        self.in_bounds(cell).then_some(cell)
+    }
+}
+
+// The center of `cell` on a square tilemap, relative to the center of cell `(0, 0)`.
+pub(crate) fn square_to_world(cell: UVec3, tile_size: Vec2) -> Vec2 {
+    cell.truncate().as_vec2() * tile_size
+}
+
+// The square tile under `local`, relative to the center of cell `(0, 0)`. May be negative.
+pub(crate) fn square_to_cell(local: Vec2, tile_size: Vec2) -> IVec2 {
+    (local / tile_size + 0.5).floor().as_ivec2()
+}
+
+// The center of `cell` on an isometric tilemap, relative to the tilemap's origin.
+pub(crate) fn isometric_to_world(cell: UVec3, tile_size: Vec2) -> Vec2 {
+    let half = tile_size * 0.5;
+    let (x, y) = (cell.x as f32, cell.y as f32);
+
+    Vec2::new((y + x) * half.x, (y - x) * half.y - half.y)
+}
+
+// The diamond tile under `local`, relative to the tilemap's origin. May be negative.
+pub(crate) fn isometric_to_cell(local: Vec2, tile_size: Vec2) -> IVec2 {
+    let half = tile_size * 0.5;
+    let across = local.x / half.x + 1.0;
+    let up = local.y / half.y + 1.0;
+
+    IVec2::new(
+        ((across - up) / 2.0).floor() as i32,
+        ((across + up) / 2.0).floor() as i32,
+    )
+}
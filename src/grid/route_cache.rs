@@ -0,0 +1,284 @@
+//! Optional cache of the coarse HPA* routes towards a goal chunk, shared by repeated searches to the same destination.
+//!
+//! For every goal chunk in the cache a tree over the HPA* graph stores, for each entrance, the next entrance on the
+//! cheapest route into the goal chunk. Searches towards a cached goal chunk follow the tree from their start entrance
+//! instead of searching the graph, only the cells inside the start and goal chunks are searched again.
+use std::{cmp::Reverse, collections::BinaryHeap, sync::Mutex};
+
+use bevy::{math::UVec3, platform::collections::HashMap};
+use ndarray::Array3;
+
+use super::Grid;
+use crate::{chunk::Chunk, neighbor::Neighborhood, path::Path, MovementCost};
+
+/// Route trees by goal chunk, evicting the least recently used one once `capacity` is reached.
+#[derive(Default)]
+pub(crate) struct RouteCache {
+    capacity: usize,
+    // Searches only borrow the grid, the lock lets them fill the cache, including from parallel searches.
+    state: Mutex<RouteCacheState>,
+}
+
+impl Clone for RouteCache {
+    fn clone(&self) -> Self {
+        Self {
+            capacity: self.capacity,
+            state: Mutex::new(self.state.lock().unwrap().clone()),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct RouteCacheState {
+    trees: HashMap<(usize, usize, usize), RouteTree>,
+    // Ticks on every lookup, trees remember the tick they were last used on.
+    clock: u64,
+}
+
+#[derive(Clone)]
+struct RouteTree {
+    goal_chunk: (usize, usize, usize),
+    // For every entrance that reaches the goal chunk, the next entrance on the way and the cost left to get there.
+    // The entrances of the goal chunk point to themselves.
+    next: HashMap<UVec3, (UVec3, MovementCost)>,
+    // The chunk versions the tree was built from.
+    versions: Array3<u32>,
+    last_used: u64,
+}
+
+enum Route {
+    Found(Path),
+    Unreachable,
+    // A chunk along the way was rebuilt since the tree was built.
+    Stale,
+}
+
+impl RouteTree {
+    fn build<N: Neighborhood>(grid: &Grid<N>, goal_chunk: &Chunk, clock: u64) -> Self {
+        // Dijkstra outwards from the goal chunk entrances, walking the edges backwards
+        let mut incoming: HashMap<UVec3, Vec<(UVec3, MovementCost)>> = HashMap::new();
+        for node in grid.graph.nodes() {
+            for (to, path) in &node.edges {
+                incoming
+                    .entry(*to)
+                    .or_default()
+                    .push((node.pos, path.cost()));
+            }
+        }
+
+        let mut next = HashMap::new();
+        let mut to_visit: BinaryHeap<Reverse<(MovementCost, [u32; 3])>> = BinaryHeap::new();
+        for node in grid.graph.nodes_in_chunk(goal_chunk) {
+            next.insert(node.pos, (node.pos, 0));
+            to_visit.push(Reverse((0, node.pos.to_array())));
+        }
+
+        while let Some(Reverse((cost, pos))) = to_visit.pop() {
+            let pos = UVec3::from_array(pos);
+            if next.get(&pos).is_some_and(|&(_, best)| cost > best) {
+                continue;
+            }
+
+            for &(from, edge_cost) in incoming.get(&pos).into_iter().flatten() {
+                let new_cost = cost.saturating_add(edge_cost);
+                if next.get(&from).is_none_or(|&(_, best)| new_cost < best) {
+                    next.insert(from, (pos, new_cost));
+                    to_visit.push(Reverse((new_cost, from.to_array())));
+                }
+            }
+        }
+
+        Self {
+            goal_chunk: goal_chunk.index(),
+            next,
+            versions: grid.chunks.map(Chunk::version),
+            last_used: clock,
+        }
+    }
+
+    fn route<N: Neighborhood>(&self, grid: &Grid<N>, start: UVec3) -> Route {
+        let current = |pos: UVec3| {
+            grid.graph.node_at(pos).is_some_and(|node| {
+                grid.chunks[node.chunk_index].version() == self.versions[node.chunk_index]
+            })
+        };
+
+        // A rebuilt goal or start chunk can add entrances the tree doesn't know about
+        if grid.chunks[self.goal_chunk].version() != self.versions[self.goal_chunk]
+            || !current(start)
+        {
+            return Route::Stale;
+        }
+
+        let Some(&(_, cost)) = self.next.get(&start) else {
+            return Route::Unreachable;
+        };
+
+        let mut nodes = vec![start];
+        let mut pos = start;
+        while let Some(&(next, _)) = self.next.get(&pos) {
+            if next == pos {
+                break;
+            }
+            if !current(next) {
+                return Route::Stale;
+            }
+
+            nodes.push(next);
+            pos = next;
+        }
+
+        Route::Found(Path::new(nodes, cost))
+    }
+}
+
+impl<N: Neighborhood + Default> Grid<N> {
+    /// Caches the coarse HPA* route towards the goal chunk of searches, for up to `capacity` goal chunks.
+    /// A capacity of 0, the default, disables the cache and drops the cached routes.
+    ///
+    /// Hierarchical searches, [`PathfindMode::Refined`] and [`PathfindMode::Coarse`], towards a cached goal chunk
+    /// follow the stored route over the entrances instead of searching the HPA* graph, and only search the cells
+    /// of the start and goal chunks. Worth enabling when many agents head to the same few destinations,
+    /// such as a base or a rally point. Once the capacity is reached the least recently used goal chunk is dropped.
+    ///
+    /// A route is recomputed when any chunk it passes through is rebuilt, routes through unchanged chunks are kept,
+    /// even if a rebuild elsewhere opened up a shorter way.
+    ///
+    /// [`PathfindMode::Refined`]: crate::prelude::PathfindMode::Refined
+    /// [`PathfindMode::Coarse`]: crate::prelude::PathfindMode::Coarse
+    pub fn set_route_cache_capacity(&mut self, capacity: usize) {
+        self.route_cache = RouteCache {
+            capacity,
+            ..Default::default()
+        };
+    }
+
+    /// Returns the number of goal chunks the route cache can hold, see [`Grid::set_route_cache_capacity`].
+    pub fn route_cache_capacity(&self) -> usize {
+        self.route_cache.capacity
+    }
+
+    /// Returns the number of goal chunks currently in the route cache.
+    pub fn cached_route_count(&self) -> usize {
+        self.route_cache.state.lock().unwrap().trees.len()
+    }
+
+    /// Drops every cached route, keeping the capacity set with [`Grid::set_route_cache_capacity`].
+    pub fn clear_route_cache(&mut self) {
+        self.set_route_cache_capacity(self.route_cache.capacity);
+    }
+
+    /// Returns the cached route over the entrances from `start` into `goal_chunk`,
+    /// building the route tree of the goal chunk if it isn't cached or is out of date.
+    /// Returns `None` if the cache is disabled or `start` has no route to the goal chunk.
+    pub(crate) fn cached_route(&self, start: UVec3, goal_chunk: &Chunk) -> Option<Path> {
+        let capacity = self.route_cache.capacity;
+        if capacity == 0 {
+            return None;
+        }
+
+        let mut state = self.route_cache.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+
+        let key = goal_chunk.index();
+        if let Some(tree) = state.trees.get_mut(&key) {
+            tree.last_used = clock;
+            match tree.route(self, start) {
+                Route::Found(path) => return Some(path),
+                Route::Unreachable => return None,
+                Route::Stale => {}
+            }
+        }
+
+        let tree = RouteTree::build(self, goal_chunk, clock);
+        let route = match tree.route(self, start) {
+            Route::Found(path) => Some(path),
+            Route::Unreachable | Route::Stale => None,
+        };
+
+        if !state.trees.contains_key(&key) && state.trees.len() >= capacity {
+            let oldest = state
+                .trees
+                .iter()
+                .min_by_key(|(_, tree)| tree.last_used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                state.trees.remove(&oldest);
+            }
+        }
+        state.trees.insert(key, tree);
+
+        route
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::UVec3;
+
+    use crate::{
+        components::PathfindMode,
+        grid::{Grid, GridSettingsBuilder},
+        nav::Nav,
+        neighbor::CardinalNeighborhood,
+    };
+
+    #[test]
+    fn test_route_cache() {
+        let settings = GridSettingsBuilder::new_2d(24, 24).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for y in 0..20 {
+            grid.set_nav(UVec3::new(10, y, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let goal = UVec3::new(22, 2, 0);
+        let starts = [
+            UVec3::new(1, 1, 0),
+            UVec3::new(2, 14, 0),
+            UVec3::new(5, 21, 0),
+        ];
+        let uncached = starts.map(|start| {
+            grid.find_path(start, goal, PathfindMode::Refined, false)
+                .unwrap()
+        });
+
+        grid.set_route_cache_capacity(2);
+        for (start, expected) in starts.iter().zip(&uncached) {
+            let path = grid
+                .find_path(*start, goal, PathfindMode::Refined, false)
+                .unwrap();
+            assert_eq!(path.path().last(), Some(&goal));
+            assert_eq!(path.cost(), expected.cost());
+        }
+        // Every search shared the tree of the goal chunk
+        assert_eq!(grid.cached_route_count(), 1);
+
+        // Narrowing the gap rebuilds the chunks on the route, the next search takes the new way around
+        grid.set_nav(UVec3::new(10, 20, 0), Nav::Impassable);
+        grid.set_nav(UVec3::new(10, 21, 0), Nav::Impassable);
+        grid.build();
+
+        let mut uncached_grid = grid.clone();
+        uncached_grid.set_route_cache_capacity(0);
+        let expected = uncached_grid
+            .find_path(starts[0], goal, PathfindMode::Refined, false)
+            .unwrap();
+        let path = grid
+            .find_path(starts[0], goal, PathfindMode::Refined, false)
+            .unwrap();
+        assert!(path.cost() > uncached[0].cost());
+        assert_eq!(path.cost(), expected.cost());
+
+        // The least recently used goal chunk is dropped once the capacity is reached
+        for goal in [UVec3::new(1, 22, 0), UVec3::new(22, 22, 0)] {
+            grid.find_path(starts[0], goal, PathfindMode::Coarse, false);
+        }
+        assert_eq!(grid.cached_route_count(), 2);
+
+        grid.clear_route_cache();
+        assert_eq!(grid.cached_route_count(), 0);
+        assert_eq!(grid.route_cache_capacity(), 2);
+    }
+}
@@ -0,0 +1,657 @@
+//! Serialization of a built [`Grid`] so the HPA* hierarchy can be precomputed offline or stored in save games.
+use bevy::{
+    math::{BVec3, UVec3},
+    platform::collections::HashMap,
+};
+use ndarray::Array3;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ChunkSettings, CollisionSettings, DiagonalMovement, Grid, GridError, GridInternalSettings,
+    GridSettings, HeightCost, NavSettings, NeighborhoodSettings,
+};
+use crate::{
+    dir::Dir,
+    nav::{Nav, NavCell, Portal, VerticalLinks},
+    neighbor::{DiagonalCost, HeuristicKind, Neighborhood},
+    node::Node,
+    path::Path,
+};
+
+// Every serialized grid starts with the magic bytes followed by the little endian format version.
+const MAGIC: &[u8; 4] = b"NSGR";
+// Bump this whenever the snapshot layout changes so old data fails with `GridError::UnsupportedVersion`.
+const FORMAT_VERSION: u32 = 12;
+const HEADER_LEN: usize = MAGIC.len() + 4;
+
+#[derive(Serialize, Deserialize)]
+struct GridSnapshot {
+    dimensions: [u32; 3],
+    chunk_size: [u32; 3],
+    diagonal_connections: bool,
+    collision_enabled: bool,
+    avoidance_distance: u32,
+    // The axes whose edges wrap around to the opposite edge.
+    wrap: [bool; 3],
+    // The cost of each unit of height difference between cells.
+    height_cost: u32,
+    // The `DiagonalMovement` policy, its filter is added back when loading.
+    diagonal_movement: u8,
+    // The `HeuristicKind` set for the neighborhood, `None` uses the neighborhood's own heuristic.
+    heuristic: Option<u8>,
+    // The straight and diagonal step weights of the `DiagonalCost`.
+    diagonal_cost: Option<(u32, u32)>,
+    // Cells in logical (x, y, z) order.
+    cells: Vec<CellSnapshot>,
+    // The entrance nodes and their cached paths, `None` if the grid wasn't built.
+    nodes: Option<Vec<NodeSnapshot>>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum NavSnapshot {
+    Passable(u32),
+    Impassable,
+    Portal {
+        cost: u32,
+        target: [u32; 3],
+        one_way: bool,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct CellSnapshot {
+    nav: NavSnapshot,
+    neighbor_bits: u32,
+    special_neighbors: Vec<[u32; 3]>,
+    layers: u32,
+    // Links added by a `NeighborProvider` and their cost.
+    links: Vec<([u32; 3], u32)>,
+    // The cost of the up and down vertical links.
+    vertical: (Option<u32>, Option<u32>),
+    // Neighbor bits of the one-way moves out of the cell that are blocked.
+    blocked_exits: u32,
+    // Neighbor bits of the moves out of the cell allowed on top of the neighborhood.
+    added_exits: u32,
+    height: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NodeSnapshot {
+    pos: [u32; 3],
+    chunk_index: [u64; 3],
+    dir: Option<u8>,
+    portal: bool,
+    edges: Vec<EdgeSnapshot>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EdgeSnapshot {
+    target: [u32; 3],
+    path: Vec<[u32; 3]>,
+    graph_path: Vec<[u32; 3]>,
+    cost: u32,
+}
+
+impl<N: Neighborhood> Grid<N> {
+    /// Serializes the grid cells and, if the grid is built, the chunk entrances and cached paths to bytes.
+    ///
+    /// Load the bytes with [`Grid::deserialize`] or [`Grid::deserialize_with_settings`]
+    /// to skip rebuilding the hierarchy at startup.
+    /// [`crate::filter::NeighborFilter`]s can't be serialized, but their effect on the precomputed neighbors is kept.
+    /// The same goes for the links of a [`crate::filter::NeighborProvider`], load the grid with
+    /// [`Grid::deserialize_with_settings`] and the provider set so searches keep using A*.
+    pub fn serialize(&self) -> Vec<u8> {
+        let cells = self
+            .grid
+            .iter()
+            .map(|cell| CellSnapshot {
+                nav: match cell.nav {
+                    Nav::Passable(cost) => NavSnapshot::Passable(cost),
+                    Nav::Impassable => NavSnapshot::Impassable,
+                    Nav::Portal(portal) => NavSnapshot::Portal {
+                        cost: portal.cost,
+                        target: portal.target.to_array(),
+                        one_way: portal.one_way,
+                    },
+                },
+                neighbor_bits: cell.neighbor_bits,
+                special_neighbors: cell
+                    .special_neighbors
+                    .iter()
+                    .map(|p| p.to_array())
+                    .collect(),
+                layers: cell.layers,
+                links: cell
+                    .links
+                    .iter()
+                    .map(|(target, cost)| (target.to_array(), *cost))
+                    .collect(),
+                vertical: (cell.vertical.up, cell.vertical.down),
+                blocked_exits: cell.blocked_exits,
+                added_exits: cell.added_exits,
+                height: cell.height,
+            })
+            .collect();
+
+        let nodes = (self.built && !self.dirty).then(|| {
+            let mut nodes = self
+                .graph
+                .nodes()
+                .into_iter()
+                .map(|node| {
+                    let mut edges = node
+                        .edges
+                        .iter()
+                        .map(|(target, path)| EdgeSnapshot {
+                            target: target.to_array(),
+                            path: path.path.iter().map(|p| p.to_array()).collect(),
+                            graph_path: path.graph_path.iter().map(|p| p.to_array()).collect(),
+                            cost: path.cost(),
+                        })
+                        .collect::<Vec<_>>();
+                    // Sort so the same grid always serializes to the same bytes.
+                    edges.sort_unstable_by_key(|edge| edge.target);
+
+                    let (cx, cy, cz) = node.chunk_index;
+                    NodeSnapshot {
+                        pos: node.pos.to_array(),
+                        chunk_index: [cx as u64, cy as u64, cz as u64],
+                        dir: node.dir.map(|dir| dir as u8),
+                        portal: node.portal,
+                        edges,
+                    }
+                })
+                .collect::<Vec<_>>();
+            nodes.sort_unstable_by_key(|node| node.pos);
+            nodes
+        });
+
+        let snapshot = GridSnapshot {
+            dimensions: self.dimensions.to_array(),
+            chunk_size: self.chunk_settings.size.to_array(),
+            diagonal_connections: self.chunk_settings.diagonal_connections,
+            collision_enabled: self.collision_settings.enabled,
+            avoidance_distance: self.collision_settings.avoidance_distance,
+            wrap: self.wrap.into(),
+            height_cost: self.height_cost.0,
+            diagonal_movement: self.diagonal_movement as u8,
+            heuristic: self.heuristic.map(|heuristic| heuristic as u8),
+            diagonal_cost: self
+                .diagonal_cost
+                .map(|cost| (cost.straight, cost.diagonal)),
+            cells,
+            nodes,
+        };
+
+        let mut bytes = Vec::from(*MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, &snapshot)
+            .expect("Grid snapshot should always serialize to memory");
+
+        bytes
+    }
+}
+
+impl<N: Neighborhood + Default> Grid<N> {
+    /// Loads a grid from bytes created with [`Grid::serialize`].
+    ///
+    /// If the grid was built when it was serialized the hierarchy is restored and the grid is ready to pathfind,
+    /// otherwise [`Grid::build`] needs to be called.
+    /// The heuristic, diagonal step costs and [`DiagonalMovement`] policy are restored,
+    /// other neighbor filters aren't, use [`Grid::deserialize_with_settings`] to provide them.
+    ///
+    /// # Errors
+    /// Returns a [`GridError`] if the bytes aren't a serialized grid, were written by an incompatible version,
+    /// or are corrupted.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, GridError> {
+        let snapshot = decode(bytes)?;
+        let diagonal_movement = diagonal_movement(snapshot.diagonal_movement)?;
+
+        let settings = GridSettings(GridInternalSettings {
+            dimensions: UVec3::from_array(snapshot.dimensions),
+            chunk_settings: ChunkSettings {
+                size: UVec3::from_array(snapshot.chunk_size),
+                diagonal_connections: snapshot.diagonal_connections,
+            },
+            cost_settings: NavSettings::default(),
+            collision_settings: CollisionSettings {
+                enabled: snapshot.collision_enabled,
+                avoidance_distance: snapshot.avoidance_distance,
+            },
+            neighborhood_settings: NeighborhoodSettings {
+                filters: diagonal_movement.filter().into_iter().collect(),
+                heuristic: snapshot.heuristic.map(heuristic).transpose()?,
+                diagonal_cost: snapshot
+                    .diagonal_cost
+                    .map(|(straight, diagonal)| DiagonalCost { straight, diagonal }),
+                provider: None,
+            },
+            wrap: BVec3::from_array(snapshot.wrap),
+            height_cost: HeightCost(snapshot.height_cost),
+            diagonal_movement,
+        });
+
+        Self::from_snapshot(snapshot, &settings)
+    }
+
+    /// Loads a grid from bytes created with [`Grid::serialize`] using the given [`GridSettings`].
+    ///
+    /// The cached hierarchy is only restored if the chunk settings match, otherwise the cells are loaded
+    /// and [`Grid::build`] needs to be called to rebuild the hierarchy with the new chunk settings.
+    /// The neighbor filters in the settings should match the ones used when the grid was built.
+    ///
+    /// # Errors
+    /// Returns [`GridError::SettingsMismatch`] if the grid dimensions don't match the settings,
+    /// or any of the errors returned by [`Grid::deserialize`].
+    pub fn deserialize_with_settings(
+        settings: &GridSettings,
+        bytes: &[u8],
+    ) -> Result<Self, GridError> {
+        let mut snapshot = decode(bytes)?;
+        let expected = &settings.0;
+
+        if UVec3::from_array(snapshot.dimensions) != expected.dimensions {
+            return Err(GridError::SettingsMismatch(format!(
+                "dimensions are {:?}, expected {:?}",
+                UVec3::from_array(snapshot.dimensions),
+                expected.dimensions
+            )));
+        }
+
+        if UVec3::from_array(snapshot.chunk_size) != expected.chunk_settings.size
+            || snapshot.diagonal_connections != expected.chunk_settings.diagonal_connections
+            || BVec3::from_array(snapshot.wrap) != expected.wrap
+            || HeightCost(snapshot.height_cost) != expected.height_cost
+            || diagonal_movement(snapshot.diagonal_movement)? != expected.diagonal_movement
+            || snapshot
+                .diagonal_cost
+                .map(|(straight, diagonal)| DiagonalCost { straight, diagonal })
+                != expected.neighborhood_settings.diagonal_cost
+        {
+            // The cached entrances are only valid for the chunk layout, edge wrapping, climbing costs
+            // and diagonal moves they were built with.
+            snapshot.nodes = None;
+        }
+
+        Self::from_snapshot(snapshot, settings)
+    }
+
+    fn from_snapshot(snapshot: GridSnapshot, settings: &GridSettings) -> Result<Self, GridError> {
+        let dimensions = settings.0.dimensions;
+
+        // Validate before allocating anything sized by the snapshot.
+        if snapshot.chunk_size.contains(&0) {
+            return Err(GridError::Corrupted("chunk size is 0".to_string()));
+        }
+
+        let expected_cells = dimensions.x as usize * dimensions.y as usize * dimensions.z as usize;
+        if snapshot.cells.len() != expected_cells {
+            return Err(GridError::Corrupted(format!(
+                "expected {expected_cells} cells, found {}",
+                snapshot.cells.len()
+            )));
+        }
+
+        let in_bounds = |pos: [u32; 3]| -> Result<UVec3, GridError> {
+            let pos = UVec3::from_array(pos);
+            if pos.cmplt(dimensions).all() {
+                Ok(pos)
+            } else {
+                Err(GridError::Corrupted(format!(
+                    "position {pos:?} is out of bounds"
+                )))
+            }
+        };
+
+        let mut grid = Grid::new(settings);
+
+        let cells = snapshot
+            .cells
+            .into_iter()
+            .map(|cell| {
+                let nav = match cell.nav {
+                    NavSnapshot::Passable(cost) => Nav::Passable(cost),
+                    NavSnapshot::Impassable => Nav::Impassable,
+                    NavSnapshot::Portal {
+                        cost,
+                        target,
+                        one_way,
+                    } => Nav::Portal(Portal::to(in_bounds(target)?, cost, one_way)),
+                };
+
+                let mut navcell = NavCell::new(nav);
+                navcell.neighbor_bits = cell.neighbor_bits;
+                navcell.special_neighbors = cell
+                    .special_neighbors
+                    .into_iter()
+                    .map(in_bounds)
+                    .collect::<Result<_, _>>()?;
+                navcell.layers = cell.layers;
+                navcell.links = cell
+                    .links
+                    .into_iter()
+                    .map(|(target, cost)| Ok((in_bounds(target)?, cost)))
+                    .collect::<Result<_, GridError>>()?;
+                navcell.vertical = VerticalLinks {
+                    up: cell.vertical.0,
+                    down: cell.vertical.1,
+                };
+                navcell.blocked_exits = cell.blocked_exits;
+                navcell.added_exits = cell.added_exits;
+                navcell.height = cell.height;
+                navcell.climb = cell.height.saturating_mul(settings.0.height_cost.0);
+
+                Ok(navcell)
+            })
+            .collect::<Result<Vec<_>, GridError>>()?;
+
+        grid.grid = Array3::from_shape_vec(
+            (
+                dimensions.x as usize,
+                dimensions.y as usize,
+                dimensions.z as usize,
+            ),
+            cells,
+        )
+        .map_err(|e| GridError::Corrupted(e.to_string()))?;
+        grid.one_way = grid
+            .grid
+            .iter()
+            .any(|cell| cell.blocked_exits != 0 || cell.added_exits != 0);
+
+        let Some(node_snapshots) = snapshot.nodes else {
+            // Without the hierarchy the grid behaves like a freshly created one and needs to be built.
+            return Ok(grid);
+        };
+
+        let positions = node_snapshots
+            .iter()
+            .map(|node| UVec3::from_array(node.pos))
+            .collect::<bevy::platform::collections::HashSet<_>>();
+
+        let mut nodes = Vec::with_capacity(node_snapshots.len());
+        for node in node_snapshots {
+            let chunk_index = (
+                node.chunk_index[0] as usize,
+                node.chunk_index[1] as usize,
+                node.chunk_index[2] as usize,
+            );
+            if grid.chunks.get(chunk_index).is_none() {
+                return Err(GridError::Corrupted(format!(
+                    "chunk {chunk_index:?} is out of bounds"
+                )));
+            }
+
+            let dir =
+                match node.dir {
+                    Some(index) => Some(Dir::from_bit_index(index as usize).ok_or_else(|| {
+                        GridError::Corrupted(format!("invalid direction {index}"))
+                    })?),
+                    None => None,
+                };
+
+            let mut edges = HashMap::with_capacity(node.edges.len());
+            for edge in node.edges {
+                let target = in_bounds(edge.target)?;
+                if !positions.contains(&target) {
+                    return Err(GridError::Corrupted(format!(
+                        "edge to missing node {target:?}"
+                    )));
+                }
+
+                let steps = edge
+                    .path
+                    .into_iter()
+                    .map(in_bounds)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut path = Path::new(steps, edge.cost);
+                path.graph_path = edge
+                    .graph_path
+                    .into_iter()
+                    .map(in_bounds)
+                    .collect::<Result<_, _>>()?;
+
+                edges.insert(target, path);
+            }
+
+            nodes.push(Node {
+                pos: in_bounds(node.pos)?,
+                chunk_index,
+                edges,
+                dir,
+                portal: node.portal,
+            });
+        }
+
+        grid.graph.add_nodes(&nodes);
+
+        for chunk in grid.chunks.iter_mut() {
+            chunk.clean();
+        }
+
+        grid.uniform_cost = grid.compute_uniform_cost();
+        grid.update_connectivity();
+        grid.dirty_chunks.clear();
+        grid.dirty = false;
+        grid.built = true;
+
+        Ok(grid)
+    }
+}
+
+fn diagonal_movement(value: u8) -> Result<DiagonalMovement, GridError> {
+    match value {
+        0 => Ok(DiagonalMovement::Always),
+        1 => Ok(DiagonalMovement::Never),
+        2 => Ok(DiagonalMovement::NoCornerClipping),
+        3 => Ok(DiagonalMovement::NoCornerCutting),
+        _ => Err(GridError::Corrupted(format!(
+            "invalid diagonal movement {value}"
+        ))),
+    }
+}
+
+fn heuristic(value: u8) -> Result<HeuristicKind, GridError> {
+    match value {
+        0 => Ok(HeuristicKind::Manhattan),
+        1 => Ok(HeuristicKind::Chebyshev),
+        2 => Ok(HeuristicKind::Octile),
+        3 => Ok(HeuristicKind::Euclidean),
+        _ => Err(GridError::Corrupted(format!("invalid heuristic {value}"))),
+    }
+}
+
+fn decode(bytes: &[u8]) -> Result<GridSnapshot, GridError> {
+    if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(GridError::InvalidHeader);
+    }
+
+    let mut version = [0; 4];
+    version.copy_from_slice(&bytes[MAGIC.len()..HEADER_LEN]);
+    let version = u32::from_le_bytes(version);
+
+    if version != FORMAT_VERSION {
+        return Err(GridError::UnsupportedVersion {
+            found: version,
+            expected: FORMAT_VERSION,
+        });
+    }
+
+    bincode::deserialize(&bytes[HEADER_LEN..]).map_err(|e| GridError::Corrupted(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::UVec3;
+
+    use super::*;
+    use crate::{
+        components::PathfindMode,
+        grid::GridSettingsBuilder,
+        nav::Nav,
+        neighbor::{CardinalNeighborhood, OrdinalNeighborhood},
+    };
+
+    fn built_grid() -> Grid<OrdinalNeighborhood> {
+        let settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+
+        for y in 0..12 {
+            grid.set_nav(UVec3::new(8, y, 0), Nav::Impassable);
+        }
+        grid.set_nav(UVec3::new(2, 14, 0), Nav::Passable(5));
+        grid.set_nav_layers(UVec3::new(3, 14, 0), 2);
+        grid.set_cell_height(UVec3::new(4, 14, 0), 3);
+        grid.block_direction(UVec3::new(2, 13, 0), Dir::North);
+        grid.build();
+
+        grid
+    }
+
+    #[test]
+    fn test_round_trip_restores_hierarchy() {
+        let grid = built_grid();
+        let bytes = grid.serialize();
+
+        let loaded = Grid::<OrdinalNeighborhood>::deserialize(&bytes).unwrap();
+
+        assert!(!loaded.needs_build());
+        assert_eq!(loaded.graph().nodes().len(), grid.graph().nodes().len());
+        assert_eq!(loaded.nav(UVec3::new(2, 14, 0)), Some(Nav::Passable(5)));
+        assert_eq!(loaded.nav_layers(UVec3::new(3, 14, 0)), Some(2));
+        assert_eq!(loaded.cell_height(UVec3::new(4, 14, 0)), Some(3));
+        assert_eq!(
+            loaded.blocked_directions(UVec3::new(2, 13, 0)),
+            vec![Dir::North]
+        );
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(15, 0, 0);
+        let expected = grid
+            .pathfind(start, goal, &Default::default(), false)
+            .unwrap();
+        let path = loaded
+            .pathfind(start, goal, &Default::default(), false)
+            .unwrap();
+
+        assert_eq!(path.cost(), expected.cost());
+        assert_eq!(path.path(), expected.path());
+
+        // Serialization is deterministic
+        assert_eq!(loaded.serialize(), bytes);
+    }
+
+    #[test]
+    fn test_round_trip_restores_neighborhood_settings() {
+        let settings = GridSettingsBuilder::new_2d(8, 8)
+            .chunk_size(4)
+            .diagonal_cost(10, 14)
+            .diagonal_movement(DiagonalMovement::NoCornerCutting)
+            .heuristic(HeuristicKind::Octile)
+            .build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+        grid.set_nav(UVec3::new(1, 1, 0), Nav::Impassable);
+        grid.build();
+
+        let loaded = Grid::<OrdinalNeighborhood>::deserialize(&grid.serialize()).unwrap();
+        assert_eq!(
+            loaded.diagonal_movement(),
+            DiagonalMovement::NoCornerCutting
+        );
+        assert_eq!(loaded.heuristic(), Some(HeuristicKind::Octile));
+        assert_eq!(loaded.diagonal_cost(), Some(DiagonalCost::default()));
+
+        let (start, goal) = (UVec3::new(0, 0, 0), UVec3::new(7, 5, 0));
+        assert_eq!(
+            loaded.heuristic_distance(start, goal),
+            grid.heuristic_distance(start, goal)
+        );
+
+        // The corner of the wall can't be cut after loading either
+        assert!(!loaded
+            .neighbor_positions(UVec3::new(0, 1, 0))
+            .contains(&UVec3::new(1, 2, 0)));
+
+        let expected = grid
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+        let path = loaded
+            .find_path(start, goal, PathfindMode::AStar, false)
+            .unwrap();
+        assert_eq!(path.cost(), expected.cost());
+
+        // Loading with settings that weigh diagonals differently drops the cached hierarchy
+        let unweighted = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let loaded =
+            Grid::<OrdinalNeighborhood>::deserialize_with_settings(&unweighted, &grid.serialize())
+                .unwrap();
+        assert!(loaded.needs_build());
+    }
+
+    #[test]
+    fn test_unbuilt_grid_needs_build() {
+        let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+
+        let mut loaded = Grid::<CardinalNeighborhood>::deserialize(&grid.serialize()).unwrap();
+        assert!(loaded.needs_build());
+
+        loaded.build();
+        assert!(loaded
+            .pathfind(
+                UVec3::new(0, 0, 0),
+                UVec3::new(7, 7, 0),
+                &Default::default(),
+                false
+            )
+            .is_some());
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let bytes = built_grid().serialize();
+
+        assert!(matches!(
+            Grid::<OrdinalNeighborhood>::deserialize(b"not a grid"),
+            Err(GridError::InvalidHeader)
+        ));
+
+        let mut old = bytes.clone();
+        old[MAGIC.len()..HEADER_LEN].copy_from_slice(&0u32.to_le_bytes());
+        assert!(matches!(
+            Grid::<OrdinalNeighborhood>::deserialize(&old),
+            Err(GridError::UnsupportedVersion {
+                found: 0,
+                expected: FORMAT_VERSION
+            })
+        ));
+
+        assert!(matches!(
+            Grid::<OrdinalNeighborhood>::deserialize(&bytes[..bytes.len() / 2]),
+            Err(GridError::Corrupted(_))
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_with_settings() {
+        let bytes = built_grid().serialize();
+
+        let matching = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let loaded =
+            Grid::<OrdinalNeighborhood>::deserialize_with_settings(&matching, &bytes).unwrap();
+        assert!(!loaded.needs_build());
+
+        // A different chunk size keeps the cells but requires a rebuild
+        let rechunked = GridSettingsBuilder::new_2d(16, 16).chunk_size(8).build();
+        let loaded =
+            Grid::<OrdinalNeighborhood>::deserialize_with_settings(&rechunked, &bytes).unwrap();
+        assert!(loaded.needs_build());
+        assert_eq!(loaded.nav(UVec3::new(8, 0, 0)), Some(Nav::Impassable));
+
+        let resized = GridSettingsBuilder::new_2d(32, 32).chunk_size(4).build();
+        assert!(matches!(
+            Grid::<OrdinalNeighborhood>::deserialize_with_settings(&resized, &bytes),
+            Err(GridError::SettingsMismatch(_))
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+//! Copy-on-write snapshots of a [`Grid`] for "what-if" planning.
+//!
+//! A snapshot reads straight from the grid it was taken from until it's changed.
+//! The first change copies the grid, and rebuilding only recomputes the chunks that were changed.
+use std::borrow::Cow;
+
+use bevy::math::UVec3;
+
+use super::Grid;
+use crate::{components::PathfindMode, nav::Nav, neighbor::Neighborhood, path::Path};
+
+/// A throwaway view of a [`Grid`] that can be changed and pathfound on without touching the grid, see [`Grid::snapshot`].
+///
+/// Taking a snapshot is free, it borrows the grid and shares its nav data and HPA* hierarchy. The first call to
+/// [`GridSnapshot::set_nav`] clones the grid, so a changed snapshot costs as much memory as the grid itself.
+/// Rebuilding a changed snapshot only recomputes the changed chunks and their neighbors, the rest of the hierarchy
+/// is copied as is. Drop the snapshot to discard the changes.
+pub struct GridSnapshot<'a, N: Neighborhood + Default> {
+    grid: Cow<'a, Grid<N>>,
+}
+
+impl<N: Neighborhood + Default> Clone for GridSnapshot<'_, N> {
+    fn clone(&self) -> Self {
+        Self {
+            grid: self.grid.clone(),
+        }
+    }
+}
+
+impl<'a, N: Neighborhood + Default> GridSnapshot<'a, N> {
+    /// Sets the [`Nav`] at `pos` in the snapshot only. The first change copies the grid.
+    /// The snapshot is rebuilt the next time it's pathfound on, or with [`GridSnapshot::build`].
+    ///
+    /// # Panics
+    /// Panics if `pos` is out of bounds, like [`Grid::set_nav`].
+    pub fn set_nav(&mut self, pos: UVec3, nav: Nav) {
+        self.grid.to_mut().set_nav(pos, nav);
+    }
+
+    /// Rebuilds the chunks changed since the last build. Does nothing if the snapshot hasn't changed.
+    pub fn build(&mut self) {
+        if self.grid.needs_build() {
+            self.grid.to_mut().build();
+        }
+    }
+
+    /// Builds the snapshot if needed and finds a path from `start` to `goal`, see [`Grid::find_path`].
+    pub fn find_path(
+        &mut self,
+        start: UVec3,
+        goal: UVec3,
+        mode: PathfindMode,
+        partial: bool,
+    ) -> Option<Path> {
+        self.build();
+        self.grid.find_path(start, goal, mode, partial)
+    }
+
+    /// Returns the grid as seen by the snapshot, for any other read-only query such as [`Grid::same_component`].
+    /// Call [`GridSnapshot::build`] first after making changes.
+    pub fn grid(&self) -> &Grid<N> {
+        &self.grid
+    }
+
+    /// Returns true if the snapshot has been changed and holds its own copy of the grid.
+    pub fn is_diverged(&self) -> bool {
+        matches!(self.grid, Cow::Owned(_))
+    }
+
+    /// Consumes the snapshot and returns its grid, for committing the changes.
+    pub fn into_grid(self) -> Grid<N> {
+        self.grid.into_owned()
+    }
+}
+
+impl<N: Neighborhood + Default> Grid<N> {
+    /// Takes a [`GridSnapshot`] of the grid for exploring hypothetical changes, such as checking whether a base
+    /// would still be connected if a wall were built, without changing the grid.
+    ///
+    /// The snapshot is free to take and shares the grid's data until it's changed, see [`GridSnapshot`] for the memory cost.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build());
+    /// grid.build();
+    ///
+    /// let mut snapshot = grid.snapshot();
+    /// for y in 0..8 {
+    ///     snapshot.set_nav(UVec3::new(4, y, 0), Nav::Impassable);
+    /// }
+    /// assert!(snapshot.find_path(UVec3::ZERO, UVec3::new(7, 0, 0), PathfindMode::AStar, false).is_none());
+    ///
+    /// // The grid itself is untouched
+    /// drop(snapshot);
+    /// assert!(grid.find_path(UVec3::ZERO, UVec3::new(7, 0, 0), PathfindMode::AStar, false).is_some());
+    /// ```
+    pub fn snapshot(&self) -> GridSnapshot<'_, N> {
+        GridSnapshot {
+            grid: Cow::Borrowed(self),
+        }
+    }
+}
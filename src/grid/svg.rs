@@ -0,0 +1,213 @@
+//! Rendering a grid and a path to SVG, for bug reports and visual test artifacts without running a Bevy app.
+use std::io::{self, Write};
+
+use bevy::math::{UVec3, Vec2};
+
+use super::{
+    projection::{isometric_to_world, square_to_world},
+    Grid,
+};
+use crate::{nav::Nav, neighbor::Neighborhood, path::Path};
+
+// Width and height of a square tile in the SVG, isometric diamonds are twice as wide.
+const TILE_SIZE: f32 = 16.0;
+
+/// The projection [`Grid::export_path_svg`] draws the cells with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SvgProjection {
+    /// Square tiles, matching [`Grid::cell_to_world`].
+    #[default]
+    Square,
+    /// Diamond tiles, matching [`Grid::cell_to_world_isometric`].
+    Isometric,
+}
+
+impl SvgProjection {
+    fn tile_size(self) -> Vec2 {
+        match self {
+            SvgProjection::Square => Vec2::splat(TILE_SIZE),
+            SvgProjection::Isometric => Vec2::new(TILE_SIZE * 2.0, TILE_SIZE),
+        }
+    }
+
+    // The center of `cell` in SVG coordinates, which grow downwards.
+    fn center(self, cell: UVec3) -> Vec2 {
+        let world = match self {
+            SvgProjection::Square => square_to_world(cell, self.tile_size()),
+            SvgProjection::Isometric => isometric_to_world(cell, self.tile_size()),
+        };
+
+        Vec2::new(world.x, -world.y)
+    }
+
+    // The corners of the tile centered at `center`.
+    fn corners(self, center: Vec2) -> [Vec2; 4] {
+        let half = self.tile_size() * 0.5;
+        match self {
+            SvgProjection::Square => [
+                center + Vec2::new(-half.x, -half.y),
+                center + Vec2::new(half.x, -half.y),
+                center + Vec2::new(half.x, half.y),
+                center + Vec2::new(-half.x, half.y),
+            ],
+            SvgProjection::Isometric => [
+                center + Vec2::new(0.0, -half.y),
+                center + Vec2::new(half.x, 0.0),
+                center + Vec2::new(0.0, half.y),
+                center + Vec2::new(-half.x, 0.0),
+            ],
+        }
+    }
+}
+
+impl<N: Neighborhood + Default> Grid<N> {
+    /// Renders the cells of the grid and `path` as an SVG image to `out`.
+    ///
+    /// Passable cells are drawn light, impassable cells dark and portals purple. The path is drawn as a line through
+    /// the centers of its cells, from a green start to a red goal. 3D grids draw the layer the path starts on.
+    ///
+    /// # Example
+    /// ```
+    /// use bevy::{math::UVec3, platform::collections::HashMap};
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let mut grid: Grid<CardinalNeighborhood> = Grid::new(&GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build());
+    /// grid.set_nav(UVec3::new(4, 2, 0), Nav::Impassable);
+    /// grid.build();
+    ///
+    /// let path = grid.pathfind(UVec3::new(0, 2, 0), UVec3::new(7, 2, 0), &HashMap::new(), false).unwrap();
+    ///
+    /// let mut svg = Vec::new();
+    /// grid.export_path_svg(&path, SvgProjection::Square, &mut svg).unwrap();
+    /// assert!(String::from_utf8(svg).unwrap().starts_with("<svg"));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns any error from writing to `out`.
+    pub fn export_path_svg(
+        &self,
+        path: &Path,
+        projection: SvgProjection,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        let layer = path.iter().next().map_or(0, |pos| pos.z);
+        let cells = (0..self.height())
+            .flat_map(|y| (0..self.width()).map(move |x| UVec3::new(x, y, layer)))
+            .collect::<Vec<_>>();
+
+        // Fit the view box around every tile
+        let (min, max) = cells
+            .iter()
+            .flat_map(|cell| projection.corners(projection.center(*cell)))
+            .fold(
+                (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+                |(min, max), corner| (min.min(corner), max.max(corner)),
+            );
+        let size = max - min;
+
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}" height="{}">"#,
+            min.x, min.y, size.x, size.y, size.x, size.y
+        )?;
+
+        for cell in cells {
+            let fill = match self.nav(cell) {
+                Some(Nav::Impassable) | None => "#333333",
+                Some(Nav::Portal(_)) => "#8a6fdf",
+                Some(Nav::Passable(_)) => "#f4f4f4",
+            };
+
+            writeln!(
+                out,
+                r##"  <polygon points="{}" fill="{fill}" stroke="#bbbbbb" stroke-width="0.5"/>"##,
+                points(&projection.corners(projection.center(cell)))
+            )?;
+        }
+
+        let centers = path
+            .iter()
+            .map(|pos| projection.center(*pos))
+            .collect::<Vec<_>>();
+
+        if let (Some(start), Some(goal)) = (centers.first(), centers.last()) {
+            writeln!(
+                out,
+                r##"  <polyline points="{}" fill="none" stroke="#1f77d0" stroke-width="3" stroke-linejoin="round"/>"##,
+                points(&centers)
+            )?;
+
+            let radius = TILE_SIZE * 0.3;
+            writeln!(
+                out,
+                r##"  <circle cx="{}" cy="{}" r="{radius}" fill="#2ca02c"/>"##,
+                start.x, start.y
+            )?;
+            writeln!(
+                out,
+                r##"  <circle cx="{}" cy="{}" r="{radius}" fill="#d62728"/>"##,
+                goal.x, goal.y
+            )?;
+        }
+
+        writeln!(out, "</svg>")
+    }
+}
+
+// Formats positions as the `points` attribute of a polygon or polyline.
+fn points(positions: &[Vec2]) -> String {
+    positions
+        .iter()
+        .map(|pos| format!("{},{}", pos.x, pos.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{math::UVec3, platform::collections::HashMap};
+
+    use super::SvgProjection;
+    use crate::{
+        grid::{Grid, GridSettingsBuilder},
+        nav::Nav,
+        path::Path,
+        prelude::CardinalNeighborhood,
+    };
+
+    #[test]
+    fn test_export_path_svg() {
+        let settings = GridSettingsBuilder::new_2d(8, 6).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.set_nav(UVec3::new(4, 2, 0), Nav::Impassable);
+        grid.build();
+
+        let path = grid
+            .pathfind(
+                UVec3::new(0, 2, 0),
+                UVec3::new(7, 2, 0),
+                &HashMap::new(),
+                false,
+            )
+            .unwrap();
+
+        for projection in [SvgProjection::Square, SvgProjection::Isometric] {
+            let mut svg = Vec::new();
+            grid.export_path_svg(&path, projection, &mut svg).unwrap();
+            let svg = String::from_utf8(svg).unwrap();
+
+            assert!(svg.starts_with("<svg"));
+            assert!(svg.trim_end().ends_with("</svg>"));
+            assert_eq!(svg.matches("<polygon").count(), 8 * 6);
+            assert_eq!(svg.matches("#333333").count(), 1);
+            assert_eq!(svg.matches("<polyline").count(), 1);
+            assert_eq!(svg.matches("<circle").count(), 2);
+        }
+
+        // An empty path only draws the grid
+        let mut svg = Vec::new();
+        grid.export_path_svg(&Path::new(Vec::new(), 0), SvgProjection::Square, &mut svg)
+            .unwrap();
+        assert!(!String::from_utf8(svg).unwrap().contains("<polyline"));
+    }
+}
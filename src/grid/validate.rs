@@ -0,0 +1,235 @@
+//! Self-checks of the cached grid data, for tracking down a hierarchy or connectivity labeling
+//! that went out of sync with the cells after incremental rebuilds.
+use bevy::{
+    math::UVec3,
+    platform::collections::{HashMap, HashSet},
+};
+
+use super::Grid;
+use crate::neighbor::Neighborhood;
+
+/// A problem found by [`Grid::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum GridInconsistency {
+    /// An entrance of the HPA* graph is on an impassable cell.
+    #[error("entrance at {0} is impassable")]
+    ImpassableEntrance(UVec3),
+    /// An entrance has an edge to a position that isn't an entrance.
+    #[error("edge from {from} leads to {to}, which isn't an entrance")]
+    DanglingEdge {
+        /// The entrance the edge leaves from.
+        from: UVec3,
+        /// The position the edge leads to.
+        to: UVec3,
+    },
+    /// The cached path of an edge doesn't start at `from` or doesn't end at `to`.
+    #[error("cached path from {from} to {to} doesn't connect them")]
+    MisalignedPath {
+        /// The entrance the edge leaves from.
+        from: UVec3,
+        /// The entrance the edge leads to.
+        to: UVec3,
+    },
+    /// The cached path between two entrances of the same chunk leaves the chunk at `pos`.
+    #[error("cached path from {from} to {to} leaves the chunk at {pos}")]
+    PathLeavesChunk {
+        /// The entrance the edge leaves from.
+        from: UVec3,
+        /// The entrance the edge leads to.
+        to: UVec3,
+        /// The first cell of the path outside of the chunk.
+        pos: UVec3,
+    },
+    /// The cached path of an edge passes through the impassable cell at `pos`.
+    #[error("cached path from {from} to {to} crosses the impassable cell {pos}")]
+    ImpassablePath {
+        /// The entrance the edge leaves from.
+        from: UVec3,
+        /// The entrance the edge leads to.
+        to: UVec3,
+        /// The first impassable cell of the path.
+        pos: UVec3,
+    },
+    /// The cached path from `from` to `to` can be walked backwards, but `to` has no edge back to `from`.
+    #[error("edge from {from} to {to} has no edge back")]
+    AsymmetricEdge {
+        /// The entrance the edge leaves from.
+        from: UVec3,
+        /// The entrance the edge leads to.
+        to: UVec3,
+    },
+    /// A passable cell has no connected component label.
+    #[error("passable cell {0} has no component")]
+    UnlabeledCell(UVec3),
+    /// An impassable cell has a connected component label.
+    #[error("impassable cell {0} has a component")]
+    LabeledImpassable(UVec3),
+    /// The connected cells `a` and `b` are labeled as different components.
+    #[error("connected cells {a} and {b} are in different components")]
+    SplitComponent {
+        /// A cell of the first component.
+        a: UVec3,
+        /// A cell of the second component.
+        b: UVec3,
+    },
+    /// The cells `a` and `b` share a component label but aren't connected.
+    #[error("disconnected cells {a} and {b} share a component")]
+    MergedComponent {
+        /// A cell of the component.
+        a: UVec3,
+        /// A cell that isn't connected to `a`.
+        b: UVec3,
+    },
+}
+
+impl<N: Neighborhood + Default> Grid<N> {
+    /// Checks the HPA* graph and the connected component labels against the cells of the grid
+    /// and returns every problem found, empty if the cached data is consistent.
+    ///
+    /// Meant for debugging, such as asserting in a test that incremental rebuilds after heavy editing
+    /// leave the grid in the same state a full build would. It walks every cached path and every cell,
+    /// so avoid calling it every frame. Build the grid first, pending changes are expected to be out of sync.
+    ///
+    /// The checks are:
+    /// * Every entrance is passable and every edge leads to another entrance.
+    /// * Every cached path connects its entrances through passable cells,
+    ///   and paths between entrances of the same chunk stay within the chunk.
+    /// * Every edge whose path can be walked backwards has an edge back, one-way movement is allowed to be one-way.
+    /// * Every passable cell, and no impassable cell, has a component label,
+    ///   and cells share a label exactly when they're connected.
+    pub fn validate(&self) -> Vec<GridInconsistency> {
+        let mut problems = Vec::new();
+
+        self.validate_graph(&mut problems);
+        self.validate_components(&mut problems);
+
+        problems
+    }
+
+    fn validate_graph(&self, problems: &mut Vec<GridInconsistency>) {
+        let mut nodes = self.graph.nodes();
+        nodes.sort_unstable_by_key(|node| (node.pos.z, node.pos.y, node.pos.x));
+
+        for node in nodes {
+            let from = node.pos;
+            if !self.is_passable(from) {
+                problems.push(GridInconsistency::ImpassableEntrance(from));
+            }
+
+            let chunk = &self.chunks[node.chunk_index];
+
+            let mut edges = node.edges.iter().collect::<Vec<_>>();
+            edges.sort_unstable_by_key(|(to, _)| (to.z, to.y, to.x));
+
+            for (&to, path) in edges {
+                let Some(target) = self.graph.node_at(to) else {
+                    problems.push(GridInconsistency::DanglingEdge { from, to });
+                    continue;
+                };
+
+                let cells = path.path();
+                if cells.first() != Some(&from) || cells.last() != Some(&to) {
+                    problems.push(GridInconsistency::MisalignedPath { from, to });
+                }
+
+                if target.chunk_index == node.chunk_index {
+                    let outside = cells
+                        .iter()
+                        .find(|pos| pos.cmplt(chunk.min()).any() || pos.cmpge(chunk.max()).any());
+                    if let Some(&pos) = outside {
+                        problems.push(GridInconsistency::PathLeavesChunk { from, to, pos });
+                    }
+                }
+
+                if let Some(&pos) = cells.iter().find(|pos| !self.is_passable(**pos)) {
+                    problems.push(GridInconsistency::ImpassablePath { from, to, pos });
+                    continue;
+                }
+
+                let reversible = cells.windows(2).all(|step| {
+                    self.navcell(step[1])
+                        .neighbor_iter(step[1])
+                        .any(|pos| pos == step[0])
+                });
+                if reversible && !target.edges.contains_key(&from) {
+                    problems.push(GridInconsistency::AsymmetricEdge { from, to });
+                }
+            }
+        }
+    }
+
+    fn validate_components(&self, problems: &mut Vec<GridInconsistency>) {
+        let (width, height) = (self.width(), self.height());
+        let index = |pos: UVec3| ((pos.z * height + pos.y) * width + pos.x) as usize;
+
+        // Label the cells from scratch, treating every move as two-way like the stored labels do
+        let mut parents = (0..self.grid.len()).collect::<Vec<_>>();
+        for ((x, y, z), cell) in self.grid.indexed_iter() {
+            let pos = UVec3::new(x as u32, y as u32, z as u32);
+            if !cell.is_passable() {
+                continue;
+            }
+
+            for neighbor in cell.neighbor_iter(pos) {
+                if self.is_passable(neighbor) {
+                    union(&mut parents, index(pos), index(neighbor));
+                }
+            }
+        }
+
+        // Each connected region has to map to exactly one stored component and the other way around
+        let mut by_region: HashMap<usize, (u32, UVec3)> = HashMap::new();
+        let mut by_component: HashMap<u32, (usize, UVec3)> = HashMap::new();
+        let mut reported = HashSet::new();
+
+        for z in 0..self.depth() {
+            for y in 0..height {
+                for x in 0..width {
+                    let pos = UVec3::new(x, y, z);
+
+                    let component = self.component(pos);
+                    let component = match (self.is_passable(pos), component) {
+                        (true, Some(component)) => component,
+                        (true, None) => {
+                            problems.push(GridInconsistency::UnlabeledCell(pos));
+                            continue;
+                        }
+                        (false, Some(_)) => {
+                            problems.push(GridInconsistency::LabeledImpassable(pos));
+                            continue;
+                        }
+                        (false, None) => continue,
+                    };
+                    let region = find(&mut parents, index(pos));
+
+                    let (seen_component, a) = *by_region.entry(region).or_insert((component, pos));
+                    if seen_component != component && reported.insert((region, component)) {
+                        problems.push(GridInconsistency::SplitComponent { a, b: pos });
+                    }
+
+                    let (seen_region, a) = *by_component.entry(component).or_insert((region, pos));
+                    if seen_region != region && reported.insert((region, component)) {
+                        problems.push(GridInconsistency::MergedComponent { a, b: pos });
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn find(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        // Path halving
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+fn union(parents: &mut [usize], a: usize, b: usize) {
+    let a = find(parents, a);
+    let b = find(parents, b);
+    if a != b {
+        parents[a.max(b)] = a.min(b);
+    }
+}
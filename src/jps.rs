@@ -0,0 +1,556 @@
+//! Jump Point Search used by the crate for uniform cost grids.
+use bevy::{
+    log,
+    math::{IVec3, UVec3},
+    platform::collections::HashMap,
+    prelude::Entity,
+};
+use indexmap::map::Entry::{Occupied, Vacant};
+use ndarray::ArrayView3;
+use smallvec::SmallVec;
+use std::collections::BinaryHeap;
+
+use crate::{
+    astar::SearchContext,
+    goal::SearchGoal,
+    nav::NavCell,
+    neighbor::{offset_index, Neighborhood},
+    path::Path,
+    FxIndexMap, SmallestCostHolder,
+};
+
+const CARDINAL_2D_DIRS: [IVec3; 4] = [
+    IVec3::new(1, 0, 0),
+    IVec3::new(-1, 0, 0),
+    IVec3::new(0, 1, 0),
+    IVec3::new(0, -1, 0),
+];
+
+const ORDINAL_2D_DIRS: [IVec3; 8] = [
+    IVec3::new(1, 0, 0),
+    IVec3::new(-1, 0, 0),
+    IVec3::new(0, 1, 0),
+    IVec3::new(0, -1, 0),
+    IVec3::new(1, 1, 0),
+    IVec3::new(1, -1, 0),
+    IVec3::new(-1, 1, 0),
+    IVec3::new(-1, -1, 0),
+];
+
+/// Jump Point Search over a [`crate::grid::Grid`] of [`crate::nav::NavCell`]s.
+///
+/// Jumps are only performed within a single z-layer. Cells that have vertical neighbors or
+/// portals are always treated as jump points and their vertical/special neighbors are expanded
+/// like regular A* successors.
+///
+/// The caller is responsible for ensuring every passable cell shares the same movement cost and
+/// that the [`Neighborhood`] has no filters. Use [`crate::astar::astar_grid`] otherwise.
+///
+/// # Arguments
+/// * `neighborhood` - Reference to the [`Neighborhood`] to use.
+/// * `grid` - A reference to a 3D array representing the grid, as an [`ndarray::ArrayView3`] of [`NavCell`].
+/// * `start` - The start position as [`bevy::math::UVec3`].
+/// * `goal` - The [`SearchGoal`] to reach.
+/// * `size_hint` - A hint for the size of the binary heap.
+/// * `partial` - If `true`, the algorithm will return the closest node if the goal is not reachable.
+/// * `blocking` - Pass [`crate::plugin::BlockingMap`] or a new `HashMap<UVec3, Entity>` to indicate which positions are blocked by entities.
+/// * `context` - Caps the node expansions of the search and records them when tracing.
+///
+/// # Returns
+/// * [`Option<Path>`] - An optional path object. If a path is found, it returns `Some(Path)`, otherwise it returns `None`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn jps_grid<N: Neighborhood>(
+    neighborhood: &N,
+    grid: &ArrayView3<NavCell>,
+    start: UVec3,
+    goal: &SearchGoal,
+    size_hint: usize,
+    partial: bool,
+    blocking: &HashMap<UVec3, Entity>,
+    context: &SearchContext,
+) -> Option<Path> {
+    let jumper = Jumper {
+        grid: grid.view(),
+        goal: *goal,
+        blocking,
+        ordinal: neighborhood.is_ordinal(),
+    };
+
+    let directions: &[IVec3] = if jumper.ordinal {
+        &ORDINAL_2D_DIRS
+    } else {
+        &CARDINAL_2D_DIRS
+    };
+
+    let mut to_visit = BinaryHeap::with_capacity(size_hint / 2);
+    to_visit.push(SmallestCostHolder {
+        estimated_cost: 0,
+        cost: 0,
+        index: 0,
+        tie_break: 0,
+    });
+
+    // Position -> (parent index, cost, reached by jumping)
+    let mut visited: FxIndexMap<UVec3, (usize, u32, bool)> = FxIndexMap::default();
+    visited.insert(start, (usize::MAX, 0, false));
+
+    let mut closest_node = start;
+    let mut closest_distance = goal.heuristic(neighborhood, start);
+
+    while let Some(SmallestCostHolder { cost, index, .. }) = to_visit.pop() {
+        let successors = {
+            let (current_pos, &(parent, current_cost, jumped)) = visited.get_index(index).unwrap();
+            let current_pos = *current_pos;
+            let current_distance = goal.heuristic(neighborhood, current_pos);
+
+            if current_distance < closest_distance {
+                closest_node = current_pos;
+                closest_distance = current_distance;
+            }
+
+            if goal.is_goal(current_pos) {
+                return Some(reconstruct_path(&visited, index, current_cost));
+            }
+
+            if cost > current_cost {
+                continue;
+            }
+
+            if !context.expand(current_pos, current_cost, current_distance) {
+                break;
+            }
+
+            // Nodes reached by a jump only continue in the natural and forced directions,
+            // the start and nodes reached by vertical moves or portals look in every direction
+            let travel = if jumped && parent != usize::MAX {
+                let parent_pos = visited.get_index(parent).unwrap().0;
+                Some((current_pos.as_ivec3() - parent_pos.as_ivec3()).signum())
+            } else {
+                None
+            };
+            let pruned = match travel {
+                Some(dir) => jumper.pruned_directions(current_pos, dir),
+                None => directions.iter().copied().collect(),
+            };
+
+            let mut successors: Vec<(UVec3, u32, bool)> = Vec::new();
+
+            for dir in pruned {
+                if let Some((jump_point, steps)) = jumper.jump(current_pos, dir) {
+                    // Every cell along a jump costs the same, so the whole jump is weighed as one step
+                    let step_cost = neighborhood.step_cost(
+                        current_pos,
+                        (current_pos.as_ivec3() + dir).as_uvec3(),
+                        steps * jumper.cell(jump_point).cost,
+                    );
+                    successors.push((jump_point, step_cost, true));
+                }
+            }
+
+            // Vertical movement and portals are expanded like regular A* neighbors
+            for neighbor in jumper.cell(current_pos).neighbor_iter(current_pos) {
+                let planar_step = neighbor.z == current_pos.z
+                    && (neighbor.as_ivec3() - current_pos.as_ivec3())
+                        .abs()
+                        .max_element()
+                        <= 1;
+
+                if planar_step || blocking.contains_key(&neighbor) {
+                    continue;
+                }
+
+                let step_cost = neighborhood.step_cost(
+                    current_pos,
+                    neighbor,
+                    jumper.cell(current_pos).move_cost(
+                        current_pos,
+                        neighbor,
+                        jumper.cell(neighbor),
+                    ),
+                );
+                successors.push((neighbor, step_cost, false));
+            }
+
+            successors
+        };
+
+        for (successor, move_cost, jumped) in successors {
+            let new_cost = cost + move_cost;
+            let h;
+            let n;
+            match visited.entry(successor) {
+                Vacant(e) => {
+                    h = goal.heuristic(neighborhood, successor);
+                    n = e.index();
+                    e.insert((index, new_cost, jumped));
+                }
+                Occupied(mut e) => {
+                    if e.get().1 > new_cost {
+                        h = goal.heuristic(neighborhood, successor);
+                        n = e.index();
+                        e.insert((index, new_cost, jumped));
+                    } else {
+                        continue;
+                    }
+                }
+            }
+
+            to_visit.push(SmallestCostHolder {
+                estimated_cost: context.weighted(h),
+                cost: new_cost,
+                index: n,
+                tie_break: 0,
+            });
+        }
+    }
+
+    if partial {
+        if closest_node == start {
+            return None;
+        }
+
+        let Some(index) = visited.get_index_of(&closest_node) else {
+            log::error!("Closest node is missing from the visited set.");
+            return None;
+        };
+
+        Some(reconstruct_path(&visited, index, visited[&closest_node].1))
+    } else {
+        None
+    }
+}
+
+// Walks the parent chain back to the start and fills in the cells skipped by each jump.
+fn reconstruct_path(
+    visited: &FxIndexMap<UVec3, (usize, u32, bool)>,
+    index: usize,
+    cost: u32,
+) -> Path {
+    let mut jump_points = vec![];
+    let mut current = index;
+
+    while current != usize::MAX {
+        let (pos, &(parent, _, jumped)) = visited.get_index(current).unwrap();
+        jump_points.push((*pos, jumped));
+        current = parent;
+    }
+
+    jump_points.reverse();
+
+    let mut steps: Vec<UVec3> = Vec::with_capacity(jump_points.len());
+    for (pos, jumped) in jump_points {
+        let previous = steps.last().copied();
+        if let (true, Some(previous)) = (jumped, previous) {
+            let dir = (pos.as_ivec3() - previous.as_ivec3()).signum();
+            let mut cell = previous.as_ivec3() + dir;
+            while cell != pos.as_ivec3() {
+                steps.push(cell.as_uvec3());
+                cell += dir;
+            }
+        }
+        steps.push(pos);
+    }
+
+    Path::new(steps, cost)
+}
+
+struct Jumper<'a> {
+    grid: ArrayView3<'a, NavCell>,
+    goal: SearchGoal<'a>,
+    blocking: &'a HashMap<UVec3, Entity>,
+    ordinal: bool,
+}
+
+impl Jumper<'_> {
+    #[inline(always)]
+    fn cell(&self, pos: UVec3) -> &NavCell {
+        &self.grid[[pos.x as usize, pos.y as usize, pos.z as usize]]
+    }
+
+    // Checks the precomputed neighbors to see if we can step from `pos` in `dir`.
+    #[inline(always)]
+    fn can_step(&self, pos: UVec3, dir: IVec3) -> bool {
+        if self.cell(pos).neighbor_bits & (1 << offset_index(dir)) == 0 {
+            return false;
+        }
+
+        let next = (pos.as_ivec3() + dir).as_uvec3();
+        !self.blocking.contains_key(&next)
+    }
+
+    // Is the position `offset` away from `pos` a walkable cell on the same z-layer.
+    #[inline(always)]
+    fn walkable(&self, pos: UVec3, offset: IVec3) -> bool {
+        let target = pos.as_ivec3() + offset;
+        let shape = self.grid.shape();
+
+        if target.cmplt(IVec3::ZERO).any()
+            || target.x as usize >= shape[0]
+            || target.y as usize >= shape[1]
+        {
+            return false;
+        }
+
+        let target = target.as_uvec3();
+        self.cell(target).is_passable() && !self.blocking.contains_key(&target)
+    }
+
+    // Cells with vertical neighbors or portals can't be skipped over.
+    #[inline(always)]
+    fn is_layer_exit(&self, pos: UVec3) -> bool {
+        // The 8 offsets on the same z-layer sit in the middle of the neighbor mask
+        const PLANAR_MASK: u32 = 0xFF << 9;
+        let cell = self.cell(pos);
+        let vertical = cell.neighbor_bits & !PLANAR_MASK;
+
+        vertical != 0 || !cell.special_neighbors.is_empty()
+    }
+
+    fn has_forced_neighbor(&self, pos: UVec3, dir: IVec3) -> bool {
+        let IVec3 { x: dx, y: dy, .. } = dir;
+
+        if dx != 0 && dy != 0 {
+            return (!self.walkable(pos, IVec3::new(-dx, 0, 0))
+                && self.walkable(pos, IVec3::new(-dx, dy, 0)))
+                || (!self.walkable(pos, IVec3::new(0, -dy, 0))
+                    && self.walkable(pos, IVec3::new(dx, -dy, 0)));
+        }
+
+        // Perpendicular to the direction of travel
+        let side = IVec3::new(dy, dx, 0);
+
+        if self.ordinal {
+            (!self.walkable(pos, side) && self.walkable(pos, dir + side))
+                || (!self.walkable(pos, -side) && self.walkable(pos, dir - side))
+        } else {
+            (self.walkable(pos, side) && !self.walkable(pos, side - dir))
+                || (self.walkable(pos, -side) && !self.walkable(pos, -side - dir))
+        }
+    }
+
+    /// Returns the directions worth searching from the jump point `pos` reached while travelling in `dir`.
+    /// Every other neighbor is reached at least as cheaply through the jump point's parent.
+    fn pruned_directions(&self, pos: UVec3, dir: IVec3) -> SmallVec<[IVec3; 8]> {
+        let IVec3 { x: dx, y: dy, .. } = dir;
+        let mut dirs = SmallVec::new();
+
+        if dx != 0 && dy != 0 {
+            dirs.extend([IVec3::new(dx, 0, 0), IVec3::new(0, dy, 0), dir]);
+
+            // A wall beside the diagonal opens up the cell diagonally past it
+            if !self.walkable(pos, IVec3::new(-dx, 0, 0))
+                && self.walkable(pos, IVec3::new(-dx, dy, 0))
+            {
+                dirs.push(IVec3::new(-dx, dy, 0));
+            }
+            if !self.walkable(pos, IVec3::new(0, -dy, 0))
+                && self.walkable(pos, IVec3::new(dx, -dy, 0))
+            {
+                dirs.push(IVec3::new(dx, -dy, 0));
+            }
+
+            return dirs;
+        }
+
+        dirs.push(dir);
+
+        // Perpendicular to the direction of travel
+        let side = IVec3::new(dy, dx, 0);
+
+        for side in [side, -side] {
+            if self.ordinal {
+                if !self.walkable(pos, side) && self.walkable(pos, dir + side) {
+                    dirs.push(dir + side);
+                }
+            } else if dy != 0 || (self.walkable(pos, side) && !self.walkable(pos, side - dir)) {
+                // Vertical scans on cardinal grids always branch out horizontally
+                dirs.push(side);
+            }
+        }
+
+        dirs
+    }
+
+    /// Travels from `pos` in `dir` until a jump point is found.
+    /// Returns the jump point and the number of steps taken to reach it.
+    fn jump(&self, pos: UVec3, dir: IVec3) -> Option<(UVec3, u32)> {
+        let mut current = pos;
+        let mut steps = 0;
+
+        loop {
+            if !self.can_step(current, dir) {
+                return None;
+            }
+
+            current = (current.as_ivec3() + dir).as_uvec3();
+            steps += 1;
+
+            if self.goal.is_goal(current)
+                || self.is_layer_exit(current)
+                || self.has_forced_neighbor(current, dir)
+            {
+                return Some((current, steps));
+            }
+
+            if dir.x != 0 && dir.y != 0 {
+                // Diagonal moves stop if either straight component finds a jump point
+                if self.jump(current, IVec3::new(dir.x, 0, 0)).is_some()
+                    || self.jump(current, IVec3::new(0, dir.y, 0)).is_some()
+                {
+                    return Some((current, steps));
+                }
+            } else if !self.ordinal && dir.y != 0 {
+                // For cardinal grids vertical scans spawn horizontal scans
+                if self.jump(current, IVec3::new(1, 0, 0)).is_some()
+                    || self.jump(current, IVec3::new(-1, 0, 0)).is_some()
+                {
+                    return Some((current, steps));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::astar::astar_grid;
+    use crate::grid::{Grid, GridSettingsBuilder};
+    use crate::nav::Nav;
+    use crate::neighbor::{CardinalNeighborhood, OrdinalNeighborhood};
+
+    #[test]
+    fn test_jps_open_grid() {
+        let grid_settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid = Grid::<OrdinalNeighborhood>::new(&grid_settings);
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(15, 10, 0);
+
+        let path = jps_grid(
+            grid.neighborhood(),
+            &grid.view(),
+            start,
+            &SearchGoal::Position(goal),
+            64,
+            false,
+            &HashMap::new(),
+            &SearchContext::default(),
+        )
+        .unwrap();
+
+        assert_eq!(path.path().first(), Some(&start));
+        assert_eq!(path.path().last(), Some(&goal));
+        // Every step should be to an adjacent cell
+        for window in path.path().windows(2) {
+            let delta = (window[1].as_ivec3() - window[0].as_ivec3()).abs();
+            assert!(delta.max_element() == 1);
+        }
+        assert_eq!(path.len(), 16);
+        assert_eq!(path.cost(), 15);
+    }
+
+    #[test]
+    fn test_jps_matches_astar_cost() {
+        let grid_settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid = Grid::<CardinalNeighborhood>::new(&grid_settings);
+
+        // Wall with a single gap
+        for y in 0..15 {
+            grid.set_nav(UVec3::new(8, y, 0), Nav::Impassable);
+        }
+        for x in 2..8 {
+            grid.set_nav(UVec3::new(x, 12, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(15, 0, 0);
+
+        let jps = jps_grid(
+            grid.neighborhood(),
+            &grid.view(),
+            start,
+            &SearchGoal::Position(goal),
+            64,
+            false,
+            &HashMap::new(),
+            &SearchContext::default(),
+        )
+        .unwrap();
+
+        let astar = astar_grid(
+            grid.neighborhood(),
+            &grid.view(),
+            start,
+            goal,
+            64,
+            false,
+            &HashMap::new(),
+            &HashMap::new(),
+        )
+        .unwrap();
+
+        assert_eq!(jps.cost(), astar.cost());
+        assert_eq!(jps.len(), astar.len());
+        assert!(jps.path().iter().all(|pos| grid.is_passable(*pos)));
+    }
+
+    #[test]
+    fn test_jps_prunes_neighbors() {
+        let grid_settings = GridSettingsBuilder::new_2d(32, 32).chunk_size(8).build();
+        let mut grid = Grid::<OrdinalNeighborhood>::new(&grid_settings);
+        for y in 4..28 {
+            grid.set_nav(UVec3::new(16, y, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let start = UVec3::new(2, 16, 0);
+        let goal = UVec3::new(30, 16, 0);
+        let context = SearchContext::default().traced();
+
+        let jps = jps_grid(
+            grid.neighborhood(),
+            &grid.view(),
+            start,
+            &SearchGoal::Position(goal),
+            64,
+            false,
+            &HashMap::new(),
+            &context,
+        )
+        .unwrap();
+
+        // Diagonally around the bottom end of the wall
+        assert_eq!(jps.cost(), 28);
+
+        // Only the jump points around the wall ends are expanded
+        let expanded = context.into_trace().unwrap().len();
+        assert!(expanded <= 12, "expanded {expanded} nodes");
+    }
+
+    #[test]
+    fn test_jps_no_path() {
+        let grid_settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let mut grid = Grid::<OrdinalNeighborhood>::new(&grid_settings);
+
+        for y in 0..8 {
+            grid.set_nav(UVec3::new(4, y, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let path = jps_grid(
+            grid.neighborhood(),
+            &grid.view(),
+            UVec3::new(0, 0, 0),
+            &SearchGoal::Position(UVec3::new(7, 7, 0)),
+            64,
+            false,
+            &HashMap::new(),
+            &SearchContext::default(),
+        );
+
+        assert!(path.is_none());
+    }
+}
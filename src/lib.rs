@@ -11,14 +11,19 @@ use std::hash::BuildHasherDefault;
 mod astar;
 mod chunk;
 pub mod components;
+mod cooperative;
 #[cfg(feature = "gui-debug")]
 pub mod debug;
 mod dijkstra;
 pub mod dir;
+pub mod events;
 pub mod filter;
 mod flood_fill;
+pub mod flow_field;
+mod goal;
 mod graph;
 pub mod grid;
+mod jps;
 mod macros;
 pub mod nav;
 pub mod neighbor;
@@ -27,6 +32,8 @@ pub mod path;
 pub mod pathfind;
 pub mod plugin;
 pub mod raycast;
+mod theta;
+pub mod trace;
 
 /// Crate Prelude
 pub mod prelude {
@@ -34,16 +41,31 @@ pub mod prelude {
     #[cfg(feature = "gui-debug")]
     pub use crate::debug::{DebugTilemapType, NorthstarDebugPlugin};
     pub use crate::dir::Dir;
+    pub use crate::events::{self, PathCompleted};
     pub use crate::filter;
-    pub use crate::grid::{Grid, GridSettingsBuilder};
-    pub use crate::nav::{Nav, Portal};
+    pub use crate::flow_field::{DistanceField, FlowField};
+    #[cfg(feature = "svg")]
+    pub use crate::grid::SvgProjection;
+    pub use crate::grid::{
+        DiagonalMovement, Grid, GridBuildTask, GridBuilder, GridSettingsBuilder, HeightCost,
+    };
+    #[cfg(feature = "explain")]
+    pub use crate::grid::{ExplainedStep, PathExplanation, RejectReason, RejectedNeighbor};
+    pub use crate::nav::{Nav, NavLayers, Portal, VerticalLinks, ALL_NAV_LAYERS};
     pub use crate::neighbor::*;
-    pub use crate::path::Path;
+    pub use crate::path::{Path, SimplifyMode};
+    #[cfg(feature = "rng")]
+    pub use crate::plugin::NorthstarRng;
     pub use crate::plugin::{
-        BlockingMap, NorthstarPlugin, NorthstarPluginSettings, PathingSet, Stats,
+        AutoAdvance, AvoidanceLookahead, AvoidancePolicy, BlockingMap, CoalescePathRequests,
+        CooperativeConfig, FlowFieldCache, GoalIndex, HierarchyBuildProgress,
+        KeepStalePathOnFailure, NorthstarPlugin, NorthstarPluginSettings, ParallelPathfinding,
+        PathfindingBudget, PathingSet, PathingStage, RerouteConfig, SmoothNextPos, Stats,
+        TrailConfig, TrailField,
     };
+    pub use crate::trace::{SearchTrace, TracedNode};
     pub use crate::MovementCost;
-    pub use crate::{CardinalGrid, CardinalGrid3d, OrdinalGrid, OrdinalGrid3d};
+    pub use crate::{CardinalGrid, CardinalGrid3d, HexGrid, OrdinalGrid, OrdinalGrid3d};
 }
 
 /// Alias for movement cost type.
@@ -57,6 +79,8 @@ pub type CardinalGrid3d = grid::Grid<neighbor::CardinalNeighborhood3d>;
 pub type OrdinalGrid = grid::Grid<neighbor::OrdinalNeighborhood>;
 /// Alias for a 3d OrdinalNeighborhood grid. Allows all 26 directions.
 pub type OrdinalGrid3d = grid::Grid<neighbor::OrdinalNeighborhood3d>;
+/// Alias for a 2d HexNeighborhood grid. Allows the 6 directions of a hex tile.
+pub type HexGrid = grid::Grid<neighbor::HexNeighborhood>;
 
 /// No pathing failure markers
 pub type WithoutPathingFailures = (
@@ -73,11 +97,17 @@ pub(crate) struct SmallestCostHolder<Id> {
     estimated_cost: Id,
     cost: Id,
     index: usize,
+    // Orders entries with the same total cost, lowest first.
+    tie_break: u32,
 }
 
 impl<Id: PartialEq> PartialEq for SmallestCostHolder<Id> {
     fn eq(&self, other: &Self) -> bool {
-        self.estimated_cost.eq(&other.estimated_cost) && self.cost.eq(&other.cost)
+        // Compares the same fields as `Ord`
+        self.estimated_cost.eq(&other.estimated_cost)
+            && self.cost.eq(&other.cost)
+            && self.tie_break == other.tie_break
+            && (!cfg!(feature = "deterministic") || self.index == other.index)
     }
 }
 
@@ -115,7 +145,16 @@ impl<Id: Ord + std::ops::Add<Output = Id> + Copy> Ord for SmallestCostHolder<Id>
         let other_total = other.cost + other.estimated_cost;
 
         // Reverse ordering for min-heap behavior
-        other_total.cmp(&self_total)
+        let ordering = other_total
+            .cmp(&self_total)
+            .then_with(|| other.tie_break.cmp(&self.tie_break));
+
+        // The most recently discovered node is expanded first on a full tie
+        if cfg!(feature = "deterministic") {
+            ordering.then_with(|| self.index.cmp(&other.index))
+        } else {
+            ordering
+        }
     }
 }
 
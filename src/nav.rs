@@ -1,7 +1,16 @@
 //! `Nav` and `NavCell` structs for navigation and movement cost data.
 use bevy::math::UVec3;
 
-use crate::{prelude::ORDINAL_3D_OFFSETS, MovementCost};
+use crate::{dir::Dir, prelude::ORDINAL_3D_OFFSETS, MovementCost};
+
+/// Bitmask of movement layers, such as land, water, or air.
+/// A [`NavCell`] can be entered by an agent if its layers intersect the agent's layers,
+/// see [`crate::grid::Grid::set_nav_layers`] and [`crate::components::Pathfind::layers`].
+/// The meaning of each bit is up to you.
+pub type NavLayers = u32;
+
+/// Every movement layer. The default for cells and agents so layers have no effect until they're set.
+pub const ALL_NAV_LAYERS: NavLayers = NavLayers::MAX;
 
 /// Navigation state for a cell (position) in the `Grid`.
 #[derive(Clone, Copy, Debug)]
@@ -39,11 +48,35 @@ pub struct NavCell {
     pub(crate) neighbor_bits: u32,
     // Special neighbors for this cell, such as portals or ladders.
     pub(crate) special_neighbors: Vec<UVec3>,
+    // Movement layers that can enter this cell.
+    pub(crate) layers: NavLayers,
+    // Extra links added by a `NeighborProvider` and their traversal cost.
+    pub(crate) links: Vec<(UVec3, MovementCost)>,
+    // Explicit connections to the cells directly above and below, such as stairs.
+    pub(crate) vertical: VerticalLinks,
+    // Moves out of this cell that are never allowed, as neighbor bits. Used for one-way moves like ledges.
+    pub(crate) blocked_exits: u32,
+    // Moves out of this cell that are allowed whenever the target is passable, as neighbor bits. Used for authored edges like ramps.
+    pub(crate) added_exits: u32,
+    // Elevation of the cell on a 2.5D grid, see `Grid::set_cell_height`.
+    pub(crate) height: u32,
+    // The height scaled by the grid's `HeightCost`, stored so the searches don't need the grid settings.
+    pub(crate) climb: MovementCost,
 }
 
 impl NavCell {
     /// Creates a new `NavCell` with the given `Nav` state.
+    /// A movement cost of 0 is clamped to 1 to avoid zero cost cycles during pathfinding.
     pub fn new(nav: Nav) -> Self {
+        let nav = match nav {
+            Nav::Passable(cost) => Nav::Passable(cost.max(1)),
+            Nav::Portal(portal) => Nav::Portal(Portal {
+                cost: portal.cost.max(1),
+                ..portal
+            }),
+            Nav::Impassable => Nav::Impassable,
+        };
+
         Self {
             nav,
             cost: match nav {
@@ -53,14 +86,37 @@ impl NavCell {
             },
             neighbor_bits: 0,
             special_neighbors: Vec::new(),
+            layers: ALL_NAV_LAYERS,
+            links: Vec::new(),
+            vertical: VerticalLinks::default(),
+            blocked_exits: 0,
+            added_exits: 0,
+            height: 0,
+            climb: 0,
         }
     }
 
+    /// Returns the movement cost of entering this cell.
+    /// Impassable cells return 0.
+    pub fn cost(&self) -> MovementCost {
+        self.cost
+    }
+
     /// Is this cell passable?
     pub fn is_passable(&self) -> bool {
         matches!(self.nav, Nav::Passable { .. } | Nav::Portal { .. })
     }
 
+    /// Returns the [`NavLayers`] that can enter this cell.
+    pub fn layers(&self) -> NavLayers {
+        self.layers
+    }
+
+    /// Is this cell passable for an agent moving on the given [`NavLayers`]?
+    pub fn is_passable_for(&self, layers: NavLayers) -> bool {
+        self.is_passable() && self.layers & layers != 0
+    }
+
     /// Is this cell impassable?
     pub fn is_impassable(&self) -> bool {
         matches!(self.nav, Nav::Impassable)
@@ -91,8 +147,99 @@ impl NavCell {
             });
 
         let special = self.special_neighbors.clone().into_iter();
+        let links = self.links.iter().map(|(target, _)| *target);
+
+        standard.chain(special).chain(links)
+    }
+
+    /// Returns the traversal cost of a link to `target` added by a [`crate::filter::NeighborProvider`],
+    /// `None` if there's no such link and the cost of entering `target` applies.
+    pub fn link_cost(&self, target: UVec3) -> Option<MovementCost> {
+        self.links
+            .iter()
+            .find(|(link, _)| *link == target)
+            .map(|(_, cost)| *cost)
+    }
+
+    /// Returns the directions agents can't move out of this cell in, see [`crate::grid::Grid::block_direction`].
+    pub fn blocked_directions(&self) -> Vec<Dir> {
+        ORDINAL_3D_OFFSETS
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| (self.blocked_exits >> i) & 1 != 0)
+            .filter_map(|(_, offset)| Dir::from_offset(*offset))
+            .collect()
+    }
+
+    /// Returns the elevation of this cell set with [`crate::grid::Grid::set_cell_height`], 0 by default.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the [`VerticalLinks`] connecting this cell to the cells directly above and below.
+    pub fn vertical_links(&self) -> VerticalLinks {
+        self.vertical
+    }
+
+    /// Returns the moves out of this cell that were cached by the last [`crate::grid::Grid::build`],
+    /// one bit per direction, see [`Dir::bit`].
+    pub fn neighbor_bits(&self) -> u32 {
+        self.neighbor_bits
+    }
+
+    /// Returns true if the cached neighbors allow moving out of this cell in `dir`.
+    pub fn has_exit(&self, dir: Dir) -> bool {
+        self.neighbor_bits & dir.bit() != 0
+    }
+
+    /// Returns the moves out of this cell that are always blocked, see [`crate::grid::Grid::block_direction`].
+    pub fn blocked_exit_bits(&self) -> u32 {
+        self.blocked_exits
+    }
+
+    /// Returns the moves out of this cell that are allowed on top of the neighborhood,
+    /// see [`crate::grid::Grid::allow_direction`].
+    pub fn added_exit_bits(&self) -> u32 {
+        self.added_exits
+    }
+
+    // The extra cost of climbing or descending from this cell to `target_cell` on a grid with a `HeightCost`.
+    #[inline(always)]
+    pub(crate) fn climb_cost(&self, target_cell: &NavCell) -> MovementCost {
+        self.climb.abs_diff(target_cell.climb)
+    }
+
+    // The cost of moving from this cell at `pos` to its neighbor `target`.
+    // Provider links and vertical links have their own cost, any other move costs the cost of entering `target`
+    // plus the cost of the height difference.
+    #[inline(always)]
+    pub(crate) fn move_cost(
+        &self,
+        pos: UVec3,
+        target: UVec3,
+        target_cell: &NavCell,
+    ) -> MovementCost {
+        if let Some(cost) = self.link_cost(target) {
+            return cost;
+        }
 
-        standard.chain(special)
+        if pos.x == target.x && pos.y == target.y {
+            let vertical = if target.z == pos.z + 1 {
+                self.vertical.up
+            } else if target.z + 1 == pos.z {
+                self.vertical.down
+            } else {
+                None
+            };
+
+            if let Some(cost) = vertical {
+                return cost;
+            }
+        }
+
+        target_cell
+            .cost
+            .saturating_add(self.climb_cost(target_cell))
     }
 }
 
@@ -103,10 +250,62 @@ impl Default for NavCell {
             cost: 1,
             neighbor_bits: 0,
             special_neighbors: Vec::new(),
+            layers: ALL_NAV_LAYERS,
+            links: Vec::new(),
+            vertical: VerticalLinks::default(),
+            blocked_exits: 0,
+            added_exits: 0,
+            height: 0,
+            climb: 0,
         }
     }
 }
 
+/// Connections from a cell to the cells directly above (z + 1) and below (z - 1) it, such as stairs, ramps, or ladders.
+/// Set them with [`crate::grid::Grid::set_vertical_links`].
+///
+/// Planar neighborhoods such as [`crate::prelude::CardinalNeighborhood`] never move between z-layers on their own,
+/// so on a multi-floor grid the floors are only joined where a cell has vertical links.
+/// With a 3D neighborhood the links set the cost of moving straight up or down from the cell.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct VerticalLinks {
+    /// The movement cost of moving up to the cell above, `None` if it isn't connected.
+    pub up: Option<MovementCost>,
+    /// The movement cost of moving down to the cell below, `None` if it isn't connected.
+    pub down: Option<MovementCost>,
+}
+
+impl VerticalLinks {
+    /// Connects the cell to the cell above at the given movement cost.
+    pub fn up(cost: MovementCost) -> Self {
+        Self {
+            up: Some(cost.max(1)),
+            down: None,
+        }
+    }
+
+    /// Connects the cell to the cell below at the given movement cost.
+    pub fn down(cost: MovementCost) -> Self {
+        Self {
+            up: None,
+            down: Some(cost.max(1)),
+        }
+    }
+
+    /// Connects the cell to both the cell above and below at the given movement cost.
+    pub fn both(cost: MovementCost) -> Self {
+        Self {
+            up: Some(cost.max(1)),
+            down: Some(cost.max(1)),
+        }
+    }
+
+    /// Returns true if the cell isn't connected to the cells above or below it.
+    pub fn is_empty(&self) -> bool {
+        self.up.is_none() && self.down.is_none()
+    }
+}
+
 /// Represents a portal that can be used to transition to another cell in the grid.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Portal {
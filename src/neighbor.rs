@@ -1,9 +1,12 @@
 //! This module defines the `Neighborhood` trait and its implementations for different types of neighborhoods.
-use bevy::math::{IVec3, UVec3};
+use bevy::{
+    math::{BVec3, IVec3, UVec3},
+    reflect::Reflect,
+};
 use ndarray::ArrayView3;
 use std::sync::Arc;
 
-use crate::{filter::NeighborFilter, grid::NeighborhoodSettings, nav::NavCell};
+use crate::{filter::NeighborFilter, grid::NeighborhoodSettings, nav::NavCell, MovementCost};
 
 /// The `Neighborhood` trait defines the interface for different neighborhood types.
 /// You can implement this trait to define custom neighborhoods and hueristics.
@@ -95,6 +98,42 @@ pub trait Neighborhood: Clone + Default + Sync + Send {
     fn is_ordinal(&self) -> bool {
         false
     }
+
+    /// Returns the weighted costs of straight and diagonal steps.
+    /// `None` if every step costs the same, which is the default.
+    fn diagonal_cost(&self) -> Option<DiagonalCost> {
+        None
+    }
+
+    /// Returns the cost of stepping from `from` onto the neighboring cell `to`, which costs `cost` to enter.
+    /// Weighs the cost by the [`DiagonalCost`] of the neighborhood if it has one.
+    #[inline(always)]
+    fn step_cost(&self, from: UVec3, to: UVec3, cost: MovementCost) -> MovementCost {
+        match self.diagonal_cost() {
+            Some(diagonal_cost) => diagonal_cost.step(from, to, cost),
+            None => cost,
+        }
+    }
+
+    /// Returns true if the neighborhood moves between hexagonal tiles.
+    /// Search modes that assume square tiles fall back to A* for hex neighborhoods.
+    fn is_hex(&self) -> bool {
+        false
+    }
+
+    /// Returns the number of steps between `a` and `b` on open terrain, ignoring movement costs:
+    /// the [`hex_distance`] for hex neighborhoods, the [`chebyshev_distance`] for ordinal neighborhoods
+    /// and the [`manhattan_distance`] otherwise.
+    fn step_distance(&self, a: UVec3, b: UVec3) -> u32 {
+        if self.is_hex() {
+            hex_distance(a, b)
+        } else if self.is_ordinal() {
+            chebyshev_distance(a, b)
+        } else {
+            manhattan_distance(a, b)
+        }
+    }
+
     /// Returns any settings for the neighborhood.
     fn settings(&self) -> Option<NeighborhoodSettings> {
         None
@@ -106,10 +145,363 @@ pub trait Neighborhood: Clone + Default + Sync + Send {
     }
 }
 
+/// The heuristic used to estimate the remaining cost from a position to the goal.
+///
+/// By default each [`Neighborhood`] uses its own heuristic. [`CardinalNeighborhood`] and [`CardinalNeighborhood3d`]
+/// use Manhattan distance, [`OrdinalNeighborhood3d`] uses Chebyshev distance, and [`OrdinalNeighborhood`] uses a weighted
+/// Chebyshev distance that trades optimal paths for faster searches.
+///
+/// Diagonal steps cost the same as straight steps, so a heuristic is only admissible (never overestimates,
+/// guaranteeing the cheapest path with [`crate::components::PathfindMode::AStar`]) for some neighborhoods:
+///
+/// | Heuristic | Cardinal neighborhoods | Ordinal neighborhoods |
+/// |-----------|------------------------|-----------------------|
+/// | [`HeuristicKind::Manhattan`] | Admissible | Overestimates |
+/// | [`HeuristicKind::Chebyshev`] | Admissible | Admissible |
+/// | [`HeuristicKind::Octile`] | Admissible | Overestimates |
+/// | [`HeuristicKind::Euclidean`] | Admissible | Overestimates |
+///
+/// Tighter heuristics expand fewer cells, so Manhattan is the best choice for cardinal neighborhoods.
+/// An overestimating heuristic is still useful when search speed matters more than the shortest path.
+///
+/// When the grid weighs diagonal steps with a [`DiagonalCost`], the ordinal neighborhoods default to the exact
+/// octile distance of [`DiagonalCost::distance`] and a set heuristic is multiplied by the straight step weight.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum HeuristicKind {
+    /// Sum of the distance along each axis.
+    #[default]
+    Manhattan,
+    /// Largest distance along any axis.
+    Chebyshev,
+    /// Chebyshev distance with diagonal steps costing √2 and 3D diagonal steps costing √3.
+    Octile,
+    /// Straight line distance.
+    Euclidean,
+}
+
+impl HeuristicKind {
+    /// Returns the estimated distance from `pos` to `target`, rounded down.
+    pub fn distance(&self, pos: UVec3, target: UVec3) -> u32 {
+        match self {
+            HeuristicKind::Manhattan => manhattan_distance(pos, target),
+            HeuristicKind::Chebyshev => chebyshev_distance(pos, target),
+            HeuristicKind::Octile => octile_distance(pos, target),
+            HeuristicKind::Euclidean => {
+                let delta = UVec3::new(
+                    pos.x.abs_diff(target.x),
+                    pos.y.abs_diff(target.y),
+                    pos.z.abs_diff(target.z),
+                );
+                delta.as_vec3().length() as u32
+            }
+        }
+    }
+}
+
+/// Returns the sum of the distances along each axis between `a` and `b`,
+/// the number of steps between them in a cardinal neighborhood.
+pub fn manhattan_distance(a: UVec3, b: UVec3) -> u32 {
+    a.x.abs_diff(b.x) + a.y.abs_diff(b.y) + a.z.abs_diff(b.z)
+}
+
+/// Returns the largest distance along any axis between `a` and `b`,
+/// the number of steps between them in an ordinal neighborhood.
+pub fn chebyshev_distance(a: UVec3, b: UVec3) -> u32 {
+    a.x.abs_diff(b.x)
+        .max(a.y.abs_diff(b.y))
+        .max(a.z.abs_diff(b.z))
+}
+
+/// Returns the length of the shortest route between `a` and `b` with diagonal steps of length √2
+/// and 3D diagonal steps of length √3, rounded down.
+/// Use [`DiagonalCost::distance`] for the integer costs of a grid with weighted diagonals.
+pub fn octile_distance(a: UVec3, b: UVec3) -> u32 {
+    let mut axes = [a.x.abs_diff(b.x), a.y.abs_diff(b.y), a.z.abs_diff(b.z)];
+    axes.sort_unstable();
+    let [low, mid, high] = axes.map(|d| d as f32);
+
+    (high
+        + (std::f32::consts::SQRT_2 - 1.0) * mid
+        + (3.0_f32.sqrt() - std::f32::consts::SQRT_2) * low) as u32
+}
+
+/// Integer weights for straight and diagonal steps, set with [`crate::grid::GridSettingsBuilder::diagonal_cost`].
+///
+/// By default a diagonal step costs the same as a straight step, so every path with the same number of steps
+/// costs the same and paths can zigzag between diagonal and straight steps. Weighing diagonal steps by roughly √2
+/// makes the cheapest path the geometrically shortest one, a single diagonal run followed by a straight run.
+///
+/// Every cell cost is multiplied by the weight of the step entering it, so path costs are scaled by `straight`.
+/// 3D diagonal steps that change all three axes cost `2 * diagonal - straight`, approximating √3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub struct DiagonalCost {
+    /// Weight of a step along a single axis.
+    pub straight: MovementCost,
+    /// Weight of a step along two axes.
+    pub diagonal: MovementCost,
+}
+
+impl Default for DiagonalCost {
+    /// Straight steps weigh 10 and diagonal steps weigh 14, an integer approximation of √2.
+    fn default() -> Self {
+        Self {
+            straight: 10,
+            diagonal: 14,
+        }
+    }
+}
+
+impl DiagonalCost {
+    /// Returns the cost of stepping from `from` to `to` onto a cell costing `cost` to enter.
+    /// Steps between cells that aren't adjacent, such as links added by a [`crate::grid::NeighborProvider`], weigh as straight steps.
+    pub fn step(&self, from: UVec3, to: UVec3, cost: MovementCost) -> MovementCost {
+        let delta = [
+            from.x.abs_diff(to.x),
+            from.y.abs_diff(to.y),
+            from.z.abs_diff(to.z),
+        ];
+
+        let weight = if delta.iter().any(|&d| d > 1) {
+            self.straight
+        } else {
+            match delta.iter().filter(|&&d| d == 1).count() {
+                0 | 1 => self.straight,
+                2 => self.diagonal,
+                _ => (2 * self.diagonal).saturating_sub(self.straight),
+            }
+        };
+
+        cost.saturating_mul(weight)
+    }
+
+    /// Returns the cost of the shortest path from `pos` to `target` across cells costing 1,
+    /// taking as many diagonal steps as possible.
+    pub fn distance(&self, pos: UVec3, target: UVec3) -> u32 {
+        let mut axes = [
+            pos.x.abs_diff(target.x),
+            pos.y.abs_diff(target.y),
+            pos.z.abs_diff(target.z),
+        ];
+        axes.sort_unstable();
+        let [low, mid, high] = axes;
+
+        let extra = self.diagonal.saturating_sub(self.straight);
+        high * self.straight + mid * extra + low * extra
+    }
+}
+
+// Wraps a neighborhood to replace its heuristic, used for per request heuristics.
+#[derive(Clone, Default)]
+pub(crate) struct HeuristicOverride<N: Neighborhood> {
+    pub(crate) neighborhood: N,
+    pub(crate) kind: HeuristicKind,
+}
+
+impl<N: Neighborhood> Neighborhood for HeuristicOverride<N> {
+    #[inline(always)]
+    fn directions(&self) -> &'static [(i32, i32, i32)] {
+        self.neighborhood.directions()
+    }
+
+    fn neighbors(&self, grid: &ArrayView3<NavCell>, pos: UVec3) -> u32 {
+        self.neighborhood.neighbors(grid, pos)
+    }
+
+    fn filters(&self) -> &[Arc<dyn NeighborFilter + Send + Sync + 'static>] {
+        self.neighborhood.filters()
+    }
+
+    #[inline(always)]
+    fn heuristic(&self, pos: UVec3, target: UVec3) -> u32 {
+        let scale = self
+            .neighborhood
+            .diagonal_cost()
+            .map_or(1, |cost| cost.straight);
+        self.kind.distance(pos, target).saturating_mul(scale)
+    }
+
+    #[inline(always)]
+    fn is_ordinal(&self) -> bool {
+        self.neighborhood.is_ordinal()
+    }
+
+    #[inline(always)]
+    fn diagonal_cost(&self) -> Option<DiagonalCost> {
+        self.neighborhood.diagonal_cost()
+    }
+
+    #[inline(always)]
+    fn is_hex(&self) -> bool {
+        self.neighborhood.is_hex()
+    }
+
+    fn settings(&self) -> Option<NeighborhoodSettings> {
+        self.neighborhood.settings()
+    }
+
+    fn from_settings(settings: &NeighborhoodSettings) -> Self {
+        Self {
+            neighborhood: N::from_settings(settings),
+            kind: settings.heuristic.unwrap_or_default(),
+        }
+    }
+}
+
+// Wraps a neighborhood so its heuristic measures the shorter way around the wrapped axes of a grid.
+// Taking the minimum of the wrapped and unwrapped distance keeps an admissible heuristic admissible.
+#[derive(Clone, Default)]
+pub(crate) struct WrappedHeuristic<N: Neighborhood> {
+    pub(crate) neighborhood: N,
+    pub(crate) dimensions: UVec3,
+    pub(crate) wrap: BVec3,
+}
+
+impl<N: Neighborhood> Neighborhood for WrappedHeuristic<N> {
+    #[inline(always)]
+    fn directions(&self) -> &'static [(i32, i32, i32)] {
+        self.neighborhood.directions()
+    }
+
+    fn neighbors(&self, grid: &ArrayView3<NavCell>, pos: UVec3) -> u32 {
+        self.neighborhood.neighbors(grid, pos)
+    }
+
+    fn filters(&self) -> &[Arc<dyn NeighborFilter + Send + Sync + 'static>] {
+        self.neighborhood.filters()
+    }
+
+    fn heuristic(&self, pos: UVec3, target: UVec3) -> u32 {
+        let mut best = self.neighborhood.heuristic(pos, target);
+
+        // Every combination of wrapped axes, crossing the seam moves the lower of the two positions a grid length on
+        for axes in 1..8 {
+            let crossed = BVec3::new(axes & 1 != 0, axes & 2 != 0, axes & 4 != 0);
+            if (crossed & !self.wrap).any() {
+                continue;
+            }
+
+            let (mut a, mut b) = (pos, target);
+            for axis in (0..3).filter(|axis| crossed.test(*axis)) {
+                if a[axis] < b[axis] {
+                    a[axis] += self.dimensions[axis];
+                } else {
+                    b[axis] += self.dimensions[axis];
+                }
+            }
+
+            best = best.min(self.neighborhood.heuristic(a, b));
+        }
+
+        best
+    }
+
+    #[inline(always)]
+    fn is_ordinal(&self) -> bool {
+        self.neighborhood.is_ordinal()
+    }
+
+    #[inline(always)]
+    fn diagonal_cost(&self) -> Option<DiagonalCost> {
+        self.neighborhood.diagonal_cost()
+    }
+
+    #[inline(always)]
+    fn step_cost(&self, from: UVec3, to: UVec3, cost: MovementCost) -> MovementCost {
+        self.neighborhood.step_cost(from, to, cost)
+    }
+
+    #[inline(always)]
+    fn is_hex(&self) -> bool {
+        self.neighborhood.is_hex()
+    }
+
+    fn settings(&self) -> Option<NeighborhoodSettings> {
+        self.neighborhood.settings()
+    }
+
+    fn from_settings(settings: &NeighborhoodSettings) -> Self {
+        Self {
+            neighborhood: N::from_settings(settings),
+            dimensions: UVec3::ZERO,
+            wrap: BVec3::FALSE,
+        }
+    }
+}
+
+// Wraps a neighborhood so its heuristic adds the cost of the height difference on grids with a `HeightCost`.
+// Every path has to climb at least the difference between its ends, so an admissible heuristic stays admissible.
+#[derive(Clone, Default)]
+pub(crate) struct HeightHeuristic<'a, N: Neighborhood> {
+    pub(crate) neighborhood: N,
+    pub(crate) cells: Option<ArrayView3<'a, NavCell>>,
+}
+
+impl<N: Neighborhood> HeightHeuristic<'_, N> {
+    fn climb(&self, pos: UVec3) -> MovementCost {
+        self.cells
+            .as_ref()
+            .and_then(|cells| cells.get([pos.x as usize, pos.y as usize, pos.z as usize]))
+            .map_or(0, |cell| cell.climb)
+    }
+}
+
+impl<N: Neighborhood> Neighborhood for HeightHeuristic<'_, N> {
+    #[inline(always)]
+    fn directions(&self) -> &'static [(i32, i32, i32)] {
+        self.neighborhood.directions()
+    }
+
+    fn neighbors(&self, grid: &ArrayView3<NavCell>, pos: UVec3) -> u32 {
+        self.neighborhood.neighbors(grid, pos)
+    }
+
+    fn filters(&self) -> &[Arc<dyn NeighborFilter + Send + Sync + 'static>] {
+        self.neighborhood.filters()
+    }
+
+    fn heuristic(&self, pos: UVec3, target: UVec3) -> u32 {
+        self.neighborhood
+            .heuristic(pos, target)
+            .saturating_add(self.climb(pos).abs_diff(self.climb(target)))
+    }
+
+    #[inline(always)]
+    fn is_ordinal(&self) -> bool {
+        self.neighborhood.is_ordinal()
+    }
+
+    #[inline(always)]
+    fn diagonal_cost(&self) -> Option<DiagonalCost> {
+        self.neighborhood.diagonal_cost()
+    }
+
+    #[inline(always)]
+    fn step_cost(&self, from: UVec3, to: UVec3, cost: MovementCost) -> MovementCost {
+        self.neighborhood.step_cost(from, to, cost)
+    }
+
+    #[inline(always)]
+    fn is_hex(&self) -> bool {
+        self.neighborhood.is_hex()
+    }
+
+    fn settings(&self) -> Option<NeighborhoodSettings> {
+        self.neighborhood.settings()
+    }
+
+    fn from_settings(settings: &NeighborhoodSettings) -> Self {
+        Self {
+            neighborhood: N::from_settings(settings),
+            cells: None,
+        }
+    }
+}
+
 /// Use `CardinalNeighborhood` for standard 2D pathfinding with no diagonal movement.
 #[derive(Clone, Default)]
 pub struct CardinalNeighborhood {
     pub(crate) filters: Vec<Arc<dyn NeighborFilter + Send + Sync + 'static>>,
+    pub(crate) heuristic: Option<HeuristicKind>,
 }
 
 impl Neighborhood for CardinalNeighborhood {
@@ -127,12 +519,17 @@ impl Neighborhood for CardinalNeighborhood {
 
     #[inline(always)]
     fn heuristic(&self, pos: UVec3, target: UVec3) -> u32 {
+        if let Some(kind) = self.heuristic {
+            return kind.distance(pos, target);
+        }
+
         ((pos.x as i32 - target.x as i32).abs() + (pos.y as i32 - target.y as i32).abs()) as u32
     }
 
     fn from_settings(settings: &NeighborhoodSettings) -> Self {
         Self {
             filters: settings.filters.clone(),
+            heuristic: settings.heuristic,
         }
     }
 
@@ -140,6 +537,8 @@ impl Neighborhood for CardinalNeighborhood {
     fn settings(&self) -> Option<NeighborhoodSettings> {
         Some(NeighborhoodSettings {
             filters: self.filters.clone(),
+            heuristic: self.heuristic,
+            ..Default::default()
         })
     }
 
@@ -153,6 +552,7 @@ impl Neighborhood for CardinalNeighborhood {
 #[derive(Clone, Default)]
 pub struct CardinalNeighborhood3d {
     pub(crate) filters: Vec<Arc<dyn NeighborFilter + Send + Sync + 'static>>,
+    pub(crate) heuristic: Option<HeuristicKind>,
 }
 
 impl Neighborhood for CardinalNeighborhood3d {
@@ -173,6 +573,10 @@ impl Neighborhood for CardinalNeighborhood3d {
 
     #[inline(always)]
     fn heuristic(&self, pos: UVec3, target: UVec3) -> u32 {
+        if let Some(kind) = self.heuristic {
+            return kind.distance(pos, target);
+        }
+
         let dx = pos.x.max(target.x) - pos.x.min(target.x);
         let dy = pos.y.max(target.y) - pos.y.min(target.y);
         let dz = pos.z.max(target.z) - pos.z.min(target.z);
@@ -182,6 +586,7 @@ impl Neighborhood for CardinalNeighborhood3d {
     fn from_settings(settings: &NeighborhoodSettings) -> Self {
         Self {
             filters: settings.filters.clone(),
+            heuristic: settings.heuristic,
         }
     }
 
@@ -189,6 +594,8 @@ impl Neighborhood for CardinalNeighborhood3d {
     fn settings(&self) -> Option<NeighborhoodSettings> {
         Some(NeighborhoodSettings {
             filters: self.filters.clone(),
+            heuristic: self.heuristic,
+            ..Default::default()
         })
     }
 
@@ -202,6 +609,8 @@ impl Neighborhood for CardinalNeighborhood3d {
 #[derive(Clone, Default)]
 pub struct OrdinalNeighborhood {
     pub(crate) filters: Vec<Arc<dyn NeighborFilter + Send + Sync + 'static>>,
+    pub(crate) heuristic: Option<HeuristicKind>,
+    pub(crate) diagonal_cost: Option<DiagonalCost>,
 }
 
 impl Neighborhood for OrdinalNeighborhood {
@@ -231,6 +640,15 @@ impl Neighborhood for OrdinalNeighborhood {
 
     #[inline(always)]
     fn heuristic(&self, pos: UVec3, target: UVec3) -> u32 {
+        if let Some(kind) = self.heuristic {
+            let scale = self.diagonal_cost.map_or(1, |cost| cost.straight);
+            return kind.distance(pos, target).saturating_mul(scale);
+        }
+
+        if let Some(diagonal_cost) = self.diagonal_cost {
+            return diagonal_cost.distance(pos, target);
+        }
+
         let dx = (target.x as i32 - pos.x as i32).unsigned_abs();
         let dy = (target.y as i32 - pos.y as i32).unsigned_abs();
         let dz = (target.z as i32 - pos.z as i32).unsigned_abs();
@@ -247,9 +665,16 @@ impl Neighborhood for OrdinalNeighborhood {
         true
     }
 
+    #[inline(always)]
+    fn diagonal_cost(&self) -> Option<DiagonalCost> {
+        self.diagonal_cost
+    }
+
     fn from_settings(settings: &NeighborhoodSettings) -> Self {
         Self {
             filters: settings.filters.clone(),
+            heuristic: settings.heuristic,
+            diagonal_cost: settings.diagonal_cost,
         }
     }
 
@@ -257,6 +682,9 @@ impl Neighborhood for OrdinalNeighborhood {
     fn settings(&self) -> Option<NeighborhoodSettings> {
         Some(NeighborhoodSettings {
             filters: self.filters.clone(),
+            heuristic: self.heuristic,
+            diagonal_cost: self.diagonal_cost,
+            ..Default::default()
         })
     }
 
@@ -271,6 +699,8 @@ impl Neighborhood for OrdinalNeighborhood {
 #[derive(Clone, Default)]
 pub struct OrdinalNeighborhood3d {
     pub(crate) filters: Vec<Arc<dyn NeighborFilter + Send + Sync + 'static>>,
+    pub(crate) heuristic: Option<HeuristicKind>,
+    pub(crate) diagonal_cost: Option<DiagonalCost>,
 }
 
 impl Neighborhood for OrdinalNeighborhood3d {
@@ -311,6 +741,15 @@ impl Neighborhood for OrdinalNeighborhood3d {
 
     #[inline(always)]
     fn heuristic(&self, pos: UVec3, target: UVec3) -> u32 {
+        if let Some(kind) = self.heuristic {
+            let scale = self.diagonal_cost.map_or(1, |cost| cost.straight);
+            return kind.distance(pos, target).saturating_mul(scale);
+        }
+
+        if let Some(diagonal_cost) = self.diagonal_cost {
+            return diagonal_cost.distance(pos, target);
+        }
+
         /* let dx = (target.x as i32 - pos.x as i32).abs() as u32;
         let dy = (target.y as i32 - pos.y as i32).abs() as u32;
         let dz = (target.z as i32 - pos.z as i32).abs() as u32;
@@ -333,9 +772,16 @@ impl Neighborhood for OrdinalNeighborhood3d {
         true
     }
 
+    #[inline(always)]
+    fn diagonal_cost(&self) -> Option<DiagonalCost> {
+        self.diagonal_cost
+    }
+
     fn from_settings(settings: &NeighborhoodSettings) -> Self {
         Self {
             filters: settings.filters.clone(),
+            heuristic: settings.heuristic,
+            diagonal_cost: settings.diagonal_cost,
         }
     }
 
@@ -343,6 +789,9 @@ impl Neighborhood for OrdinalNeighborhood3d {
     fn settings(&self) -> Option<NeighborhoodSettings> {
         Some(NeighborhoodSettings {
             filters: self.filters.clone(),
+            heuristic: self.heuristic,
+            diagonal_cost: self.diagonal_cost,
+            ..Default::default()
         })
     }
 
@@ -351,6 +800,92 @@ impl Neighborhood for OrdinalNeighborhood3d {
     }
 }
 
+/// Use `HexNeighborhood` for 2D pathfinding on hexagonal tiles.
+///
+/// Cells are addressed with axial coordinates, the `x` axis running along the rows and the `y` axis
+/// running diagonally, so every row is offset half a tile from the one before it. This matches pointy-top
+/// hex maps such as `HexCoordSystem::Row` in `bevy_ecs_tilemap`. Each cell has 6 neighbors.
+///
+/// [`crate::components::PathfindMode::Jps`] and [`crate::components::PathfindMode::Theta`] assume square
+/// tiles and fall back to A* with this neighborhood.
+#[derive(Clone, Default)]
+pub struct HexNeighborhood {
+    pub(crate) filters: Vec<Arc<dyn NeighborFilter + Send + Sync + 'static>>,
+    pub(crate) heuristic: Option<HeuristicKind>,
+}
+
+impl Neighborhood for HexNeighborhood {
+    #[inline(always)]
+    fn directions(&self) -> &'static [(i32, i32, i32)] {
+        // Axial neighbors, the third coordinate is always 0.
+        static DIRECTIONS: [(i32, i32, i32); 6] = [
+            (1, 0, 0),  // East
+            (-1, 0, 0), // West
+            (0, 1, 0),  // North-East
+            (0, -1, 0), // South-West
+            (1, -1, 0), // South-East
+            (-1, 1, 0), // North-West
+        ];
+        &DIRECTIONS
+    }
+
+    #[inline(always)]
+    fn heuristic(&self, pos: UVec3, target: UVec3) -> u32 {
+        if let Some(kind) = self.heuristic {
+            return kind.distance(pos, target);
+        }
+
+        hex_distance(pos, target)
+    }
+
+    #[inline(always)]
+    fn is_hex(&self) -> bool {
+        true
+    }
+
+    fn from_settings(settings: &NeighborhoodSettings) -> Self {
+        Self {
+            filters: settings.filters.clone(),
+            heuristic: settings.heuristic,
+        }
+    }
+
+    #[inline(always)]
+    fn settings(&self) -> Option<NeighborhoodSettings> {
+        Some(NeighborhoodSettings {
+            filters: self.filters.clone(),
+            heuristic: self.heuristic,
+            ..Default::default()
+        })
+    }
+
+    fn filters(&self) -> &[Arc<dyn NeighborFilter + Send + Sync + 'static>] {
+        &self.filters
+    }
+}
+
+/// Returns the number of hex steps between two axial positions, see [`HexNeighborhood`].
+/// Any difference in `z` is added on top.
+#[inline(always)]
+pub fn hex_distance(pos: UVec3, target: UVec3) -> u32 {
+    let dq = pos.x as i64 - target.x as i64;
+    let dr = pos.y as i64 - target.y as i64;
+
+    ((dq.abs() + dr.abs() + (dq + dr).abs()) / 2) as u32 + pos.z.abs_diff(target.z)
+}
+
+/// Returns the bit index in a neighbor mask for a unit `offset`.
+/// Matches the ordering of [`ORDINAL_3D_OFFSETS`].
+#[inline(always)]
+pub(crate) const fn offset_index(offset: IVec3) -> usize {
+    let raw = ((offset.z + 1) * 9 + (offset.y + 1) * 3 + (offset.x + 1)) as usize;
+    if raw > 13 {
+        raw - 1
+    } else {
+        raw
+    }
+}
+
 pub(crate) const ORDINAL_3D_OFFSETS: [IVec3; 26] = {
     let mut offsets = [IVec3::ZERO; 26];
     let mut index = 0;
@@ -373,14 +908,25 @@ pub(crate) const ORDINAL_3D_OFFSETS: [IVec3; 26] = {
     offsets
 };
 
+// Every bit of a neighbor mask, one for each of the 26 directions.
+pub(crate) const ALL_EXITS: u32 = (1 << ORDINAL_3D_OFFSETS.len()) - 1;
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_offset_index() {
+        for (i, offset) in ORDINAL_3D_OFFSETS.iter().enumerate() {
+            assert_eq!(offset_index(*offset), i);
+        }
+    }
+
     #[test]
     fn test_cardinal_neighbors() {
         let neighborhood = CardinalNeighborhood {
             filters: Vec::new(),
+            heuristic: None,
         };
         let cells: [NavCell; 9] = std::array::from_fn(|_| NavCell::default());
         let grid = ArrayView3::from_shape((3, 3, 1), &cells).unwrap();
@@ -390,10 +936,38 @@ mod tests {
         assert_eq!(neighbors.count_ones(), 4);
     }
 
+    #[test]
+    fn test_hex_neighbors() {
+        let neighborhood = HexNeighborhood::default();
+        let cells: [NavCell; 9] = std::array::from_fn(|_| NavCell::default());
+        let grid = ArrayView3::from_shape((3, 3, 1), &cells).unwrap();
+
+        let neighbors = neighborhood.neighbors(&grid, UVec3::new(1, 1, 0));
+
+        assert_eq!(neighbors.count_ones(), 6);
+        // The two diagonals outside the hex are not neighbors
+        assert_eq!(neighbors & (1 << offset_index(IVec3::new(1, 1, 0))), 0);
+        assert_eq!(neighbors & (1 << offset_index(IVec3::new(-1, -1, 0))), 0);
+
+        assert_eq!(
+            neighborhood.heuristic(UVec3::new(0, 0, 0), UVec3::new(2, 0, 0)),
+            2
+        );
+        assert_eq!(
+            neighborhood.heuristic(UVec3::new(2, 0, 0), UVec3::new(0, 2, 0)),
+            2
+        );
+        assert_eq!(
+            neighborhood.heuristic(UVec3::new(0, 0, 0), UVec3::new(2, 2, 0)),
+            4
+        );
+    }
+
     #[test]
     fn test_cardinal_neighbors_3d() {
         let neighborhood = CardinalNeighborhood3d {
             filters: Vec::new(),
+            heuristic: None,
         };
         let cells: [NavCell; 27] = std::array::from_fn(|_| NavCell::default());
         let grid = ArrayView3::from_shape((3, 3, 3), &cells).unwrap();
@@ -407,6 +981,8 @@ mod tests {
     fn test_ordinal_neighbors_3d() {
         let neighborhood = OrdinalNeighborhood3d {
             filters: Vec::new(),
+            heuristic: None,
+            diagonal_cost: None,
         };
         let cells: [NavCell; 27] = std::array::from_fn(|_| NavCell::default());
         let grid = ArrayView3::from_shape((3, 3, 3), &cells).unwrap();
@@ -449,6 +1025,8 @@ mod tests {
     fn test_ordinal_neighbors_at_0() {
         let neighborhood = OrdinalNeighborhood3d {
             filters: Vec::new(),
+            heuristic: None,
+            diagonal_cost: None,
         };
         let cells: [NavCell; 27] = std::array::from_fn(|_| NavCell::default());
         let grid = ArrayView3::from_shape((3, 3, 3), &cells).unwrap();
@@ -503,6 +1081,8 @@ mod tests {
     fn test_ordinal_neighbors_no_depth() {
         let neighborhood = OrdinalNeighborhood3d {
             filters: Vec::new(),
+            heuristic: None,
+            diagonal_cost: None,
         };
         let cells: [NavCell; 9] = std::array::from_fn(|_| NavCell::default());
 
@@ -559,6 +1139,8 @@ mod tests {
     fn test_ordinal_heuristic() {
         let neighborhood = OrdinalNeighborhood3d {
             filters: Vec::new(),
+            heuristic: None,
+            diagonal_cost: None,
         };
 
         assert_eq!(
@@ -582,4 +1164,21 @@ mod tests {
             7
         );
     }
+
+    #[test]
+    fn test_heuristic_kinds() {
+        let pos = UVec3::new(0, 0, 0);
+        let target = UVec3::new(3, 4, 0);
+
+        assert_eq!(HeuristicKind::Manhattan.distance(pos, target), 7);
+        assert_eq!(HeuristicKind::Chebyshev.distance(pos, target), 4);
+        assert_eq!(HeuristicKind::Octile.distance(pos, target), 5);
+        assert_eq!(HeuristicKind::Euclidean.distance(pos, target), 5);
+
+        let neighborhood = CardinalNeighborhood::from_settings(&NeighborhoodSettings {
+            heuristic: Some(HeuristicKind::Chebyshev),
+            ..Default::default()
+        });
+        assert_eq!(neighborhood.heuristic(pos, target), 4);
+    }
 }
@@ -31,8 +31,13 @@ impl Node {
     }
 
     /// Returns all positions that are connected to this `Node`.
+    /// With the `deterministic` feature they're sorted by z, y, then x instead of following the hash map order.
     pub(crate) fn edges(&self) -> Vec<UVec3> {
-        self.edges.keys().cloned().collect()
+        let mut edges: Vec<UVec3> = self.edges.keys().cloned().collect();
+        if cfg!(feature = "deterministic") {
+            edges.sort_unstable_by_key(|pos| (pos.z, pos.y, pos.x));
+        }
+        edges
     }
 
     pub(crate) fn remove_edges_to_positions(&mut self, positions: &[UVec3]) {
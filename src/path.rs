@@ -1,9 +1,29 @@
 //! This module defines the important `Path` component.
-use bevy::math::UVec3;
-use bevy::prelude::Component;
+use bevy::math::{IVec3, UVec3};
+use bevy::platform::collections::HashSet;
+use bevy::prelude::{Component, ReflectComponent};
 use bevy::reflect::Reflect;
 use std::collections::VecDeque;
 
+use crate::{
+    dir::Dir,
+    grid::{DiagonalMovement, Grid},
+    neighbor::Neighborhood,
+    raycast::{bresenham_path, bresenham_path_filtered, bresenham_walk},
+    MovementCost,
+};
+
+/// How [`Path::simplified`] decides whether a stretch of the path can be replaced with a straight line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum SimplifyMode {
+    /// Only collapse a stretch when the straight line costs the same as the cells it replaces,
+    /// so the simplified path keeps the cost of the original.
+    #[default]
+    CostAccurate,
+    /// Collapse whenever the straight line is clear of impassable cells, even through more expensive terrain.
+    Geometric,
+}
+
 /// The path struct and component containing the path result of a pathfinding operation.
 ///
 /// This is returned by pathfinding functions.
@@ -11,11 +31,13 @@ use std::collections::VecDeque;
 /// systems have pathfound to the goal position.
 ///
 #[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component)]
 pub struct Path {
     pub(crate) path: VecDeque<UVec3>,
     pub(crate) graph_path: VecDeque<UVec3>,
     cost: u32,
     is_reversed: bool,
+    goal_index: Option<usize>,
 }
 
 impl Path {
@@ -32,6 +54,7 @@ impl Path {
             graph_path: VecDeque::new(),
             cost,
             is_reversed: false,
+            goal_index: None,
         }
     }
 
@@ -48,6 +71,7 @@ impl Path {
             graph_path: VecDeque::new(),
             cost,
             is_reversed: false,
+            goal_index: None,
         }
     }
 
@@ -72,11 +96,29 @@ impl Path {
         self.path.as_slices().0
     }
 
-    /// Returns the movement cost of the path
+    /// Returns the total movement cost of the path, the sum of the costs of entering each position after the start.
+    ///
+    /// [`crate::components::PathfindMode::Coarse`] paths report the sum of the cached HPA* edge costs.
+    /// The other modes report the weighted cost of the returned route,
+    /// including [`crate::grid::Grid::set_cost_override`] costs except for [`crate::components::PathfindMode::FlowField`].
+    /// Partial paths report the cost of the partial route. Useful for comparing the cost of reaching different targets.
     pub fn cost(&self) -> u32 {
         self.cost
     }
 
+    /// Returns the index of the goal the path leads to, in the order the goals were given to
+    /// [`crate::components::Pathfind::new_multi`] or [`crate::grid::Grid::find_path_multi`]. Single goals are index 0.
+    ///
+    /// When several goals cost the same to reach, the one given first wins.
+    /// `None` for partial paths, region goals, and paths created by hand.
+    pub fn goal_index(&self) -> Option<usize> {
+        self.goal_index
+    }
+
+    pub(crate) fn set_goal_index(&mut self, goal_index: Option<usize>) {
+        self.goal_index = goal_index;
+    }
+
     /// Returns the length of the path
     pub fn len(&self) -> usize {
         self.path.len()
@@ -87,6 +129,91 @@ impl Path {
         self.path.is_empty()
     }
 
+    /// Returns the HPA* entrances the path was planned through, in order.
+    /// Empty for paths that weren't found through the hierarchy.
+    pub fn graph_path(&self) -> std::collections::vec_deque::Iter<'_, UVec3> {
+        self.graph_path.iter()
+    }
+
+    /// Returns the HPA* graph edge, the pair of entrances, that each remaining position of the path was refined from.
+    /// The result lines up with [`Path::path`], use [`crate::grid::Grid::chunk_at`] to find the chunk of each position.
+    ///
+    /// An entrance belongs to the edge leading into it. Positions before the first entrance and after the last one
+    /// were searched within the start and goal chunks and return `None`, as do paths that didn't use the hierarchy.
+    /// When refinement cuts past an entrance, the shortcut belongs to the edge leading to the next entrance the path reaches.
+    pub fn graph_edges(&self) -> Vec<Option<(UVec3, UVec3)>> {
+        let nodes = self.graph_path.iter().copied().collect::<Vec<_>>();
+        let mut edges = vec![None; self.path.len()];
+
+        // Walk backwards so the edges are still found once the start of the path has been consumed
+        let mut next = nodes.len();
+        for (i, pos) in self.path.iter().enumerate().rev() {
+            if let Some(node) = nodes[..next].iter().rposition(|node| node == pos) {
+                next = node;
+                edges[i] = node
+                    .checked_sub(1)
+                    .map(|previous| (nodes[previous], nodes[node]));
+            } else if next > 0 && next < nodes.len() {
+                edges[i] = Some((nodes[next - 1], nodes[next]));
+            }
+        }
+
+        edges
+    }
+
+    /// Returns an iterator over the remaining positions in the path without consuming them.
+    pub fn iter(&self) -> std::collections::vec_deque::Iter<'_, UVec3> {
+        self.path.iter()
+    }
+
+    /// Returns up to the next `n` positions in the path without removing them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let path = Path::new(vec![UVec3::new(1, 0, 0), UVec3::new(2, 0, 0), UVec3::new(3, 0, 0)], 3);
+    /// assert_eq!(path.peek(2), &[UVec3::new(1, 0, 0), UVec3::new(2, 0, 0)]);
+    /// assert_eq!(path.peek(10).len(), 3);
+    /// ```
+    pub fn peek(&self, n: usize) -> &[UVec3] {
+        let remaining = self.remaining();
+        &remaining[..n.min(remaining.len())]
+    }
+
+    /// Returns all the positions left in the path as a slice.
+    pub fn remaining(&self) -> &[UVec3] {
+        self.path()
+    }
+
+    /// Returns the direction of the step from the `i`th position left in the path to the next one,
+    /// or `None` if there's no position after it. See [`Dir::between`] for how longer steps are snapped.
+    ///
+    /// To get the direction of the move to the [`crate::components::NextPos`] use [`crate::components::NextDir`] instead.
+    pub fn direction_at(&self, i: usize) -> Option<Dir> {
+        Dir::between(*self.path.get(i)?, *self.path.get(i + 1)?)
+    }
+
+    /// Returns true if the path contains the given position.
+    pub fn contains(&self, pos: UVec3) -> bool {
+        self.path.contains(&pos)
+    }
+
+    /// Cuts the path off after the first occurrence of `pos`, keeping `pos` as the new final position.
+    /// Returns false and leaves the path untouched if `pos` isn't in the path.
+    ///
+    /// The path cost isn't recalculated since the path doesn't have access to the grid cell costs.
+    pub fn truncate_to(&mut self, pos: UVec3) -> bool {
+        if let Some(index) = self.path.iter().position(|p| *p == pos) {
+            self.path.truncate(index + 1);
+            true
+        } else {
+            false
+        }
+    }
+
     /// Reverse the path in place.
     pub fn reverse(&mut self) {
         self.path.make_contiguous().reverse();
@@ -99,6 +226,223 @@ impl Path {
         self.path.pop_front()
     }
 
+    /// Removes the positions up to and including `pos` from the front of the path, for consuming the path
+    /// as the agent reaches each cell. Skipping ahead several cells at once is fine.
+    /// Returns false and leaves the path untouched if `pos` isn't in the path.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use bevy_northstar::prelude::*;
+    ///
+    /// let mut path = Path::new(vec![UVec3::new(1, 0, 0), UVec3::new(2, 0, 0), UVec3::new(3, 0, 0)], 3);
+    /// assert!(path.advance(UVec3::new(2, 0, 0)));
+    /// assert_eq!(path.remaining(), &[UVec3::new(3, 0, 0)]);
+    /// assert!(!path.advance(UVec3::new(9, 0, 0)));
+    /// ```
+    pub fn advance(&mut self, pos: UVec3) -> bool {
+        if let Some(index) = self.path.iter().position(|p| *p == pos) {
+            self.path.drain(..=index);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns a copy of the path reduced to the waypoints where it turns, string-pulling the cells in between.
+    ///
+    /// A stretch of cells is replaced with a straight line between the kept waypoints when the line is clear of
+    /// impassable cells, traced the same way as [`Grid::line_of_sight`] and respecting the grid's neighbor filters.
+    /// The first and last positions are always kept and portal jumps are never collapsed.
+    /// Consecutive waypoints are no longer adjacent cells, move along the straight line between them.
+    ///
+    /// With [`SimplifyMode::CostAccurate`] the path cost is unchanged. With [`SimplifyMode::Geometric`]
+    /// the cost is recalculated for the straight lines taken. Dynamic [`crate::components::Blocking`] entities aren't considered.
+    pub fn simplified<N: Neighborhood + Default>(
+        &self,
+        grid: &Grid<N>,
+        mode: SimplifyMode,
+    ) -> Path {
+        let cells = self.path.iter().copied().collect::<Vec<_>>();
+        if cells.len() < 3 {
+            return self.clone();
+        }
+
+        let view = grid.view();
+        let ordinal = grid.neighborhood().is_ordinal();
+        let filtered = !grid.neighborhood().filters().is_empty() || grid.has_one_way_moves();
+
+        let step_cost = |from: UVec3, to: UVec3| -> MovementCost {
+            let cost = grid.nav_cost(to).unwrap_or_default();
+            let climb = grid.navcell(from).climb_cost(grid.navcell(to));
+            grid.neighborhood()
+                .step_cost(from, to, cost.saturating_add(climb))
+                .saturating_add(grid.extra_cost(to).unwrap_or_default())
+        };
+        let adjacent = |a: UVec3, b: UVec3| a.as_ivec3().distance_squared(b.as_ivec3()) <= 3;
+
+        // Cost of following the path from the first cell to each cell
+        let mut accumulated: Vec<MovementCost> = vec![0; cells.len()];
+        for index in 1..cells.len() {
+            accumulated[index] =
+                accumulated[index - 1].saturating_add(step_cost(cells[index - 1], cells[index]));
+        }
+
+        let last = cells.len() - 1;
+        let mut waypoints = vec![cells[0]];
+        let mut cost = self.cost.saturating_sub(accumulated[last]);
+        let mut i = 0;
+
+        while i < last {
+            // Lines can't follow a portal, stop at the first jump
+            let mut end = i + 1;
+            if adjacent(cells[i], cells[end]) {
+                while end < last && adjacent(cells[end], cells[end + 1]) {
+                    end += 1;
+                }
+            }
+
+            let mut next = i + 1;
+            let mut next_cost = accumulated[next] - accumulated[i];
+
+            for j in (i + 2..=end).rev() {
+                let line = if filtered {
+                    bresenham_path_filtered(&view, cells[i], cells[j], ordinal)
+                } else {
+                    bresenham_path(&view, cells[i], cells[j], ordinal)
+                };
+                let Some(line) = line else {
+                    continue;
+                };
+
+                let line_cost = line
+                    .windows(2)
+                    .map(|step| step_cost(step[0], step[1]))
+                    .fold(0, MovementCost::saturating_add);
+
+                if mode == SimplifyMode::Geometric || line_cost == accumulated[j] - accumulated[i] {
+                    next = j;
+                    next_cost = line_cost;
+                    break;
+                }
+            }
+
+            waypoints.push(cells[next]);
+            cost = cost.saturating_add(next_cost);
+            i = next;
+        }
+
+        let mut path = Path::new(waypoints, cost);
+        path.graph_path = self.graph_path.clone();
+        path.is_reversed = self.is_reversed;
+        path.goal_index = self.goal_index;
+        path
+    }
+
+    /// Returns every cell the path occupies in order, each cell once, for gameplay code reacting to the cells
+    /// an agent passes through like collision or trail systems. The position the agent starts from isn't part of
+    /// the path, so neither it nor the cells around the first step are included.
+    ///
+    /// Waypoints that aren't neighbors, such as the ones of [`crate::prelude::PathfindMode::Theta`] or
+    /// [`Path::simplified`] paths, are filled in with the cells along the straight line between them.
+    /// Steps across a wrapped edge and portal jumps aren't filled in.
+    ///
+    /// A diagonal step passes the corner shared with its shoulder cells, the cells next to both ends of the step.
+    /// Whether the shoulder cells count as occupied follows the [`DiagonalMovement`] policy of the grid:
+    /// * [`DiagonalMovement::Always`] and [`DiagonalMovement::Never`] leave them out, the step cuts straight
+    ///   through the corner and can pass between two walls.
+    /// * [`DiagonalMovement::NoCornerClipping`] includes the passable ones, the step slips past a wall on one side
+    ///   by sweeping over the open side.
+    /// * [`DiagonalMovement::NoCornerCutting`] includes all of them, the step needs the whole corner to be clear.
+    ///
+    /// Only the policy set with [`crate::grid::GridSettingsBuilder::diagonal_movement`] is known to the grid,
+    /// adding a filter like [`crate::filter::NoCornerCutting`] directly counts as [`DiagonalMovement::Always`].
+    pub fn occupied_cells<N: Neighborhood + Default>(&self, grid: &Grid<N>) -> Vec<UVec3> {
+        let view = grid.view();
+        let ordinal = grid.neighborhood().is_ordinal();
+        let policy = grid.diagonal_movement();
+
+        let mut cells = Vec::with_capacity(self.path.len());
+        let mut seen = HashSet::new();
+        let mut occupy = |pos: UVec3| {
+            if seen.insert(pos) {
+                cells.push(pos);
+            }
+        };
+
+        let mut steps = Vec::with_capacity(self.path.len());
+        for &pos in &self.path {
+            let Some(&last) = steps.last() else {
+                steps.push(pos);
+                continue;
+            };
+
+            // Wrapped steps and portal jumps are neighbors too
+            let adjacent = (pos.as_ivec3() - last.as_ivec3()).abs().max_element() <= 1
+                || grid.navcell(last).neighbor_iter(last).any(|n| n == pos);
+            if adjacent {
+                steps.push(pos);
+            } else {
+                // Both ends are in the grid, so the line never leaves it and the walk reaches `pos`
+                let _ = bresenham_walk(&view, last, pos, ordinal, |cell| {
+                    if cell != last {
+                        steps.push(cell);
+                    }
+                    true
+                });
+            }
+        }
+
+        let shoulders = matches!(
+            policy,
+            DiagonalMovement::NoCornerClipping | DiagonalMovement::NoCornerCutting
+        );
+
+        if let Some(&first) = steps.first() {
+            occupy(first);
+        }
+
+        for step in steps.windows(2) {
+            let (from, to) = (step[0], step[1]);
+            let delta = to.as_ivec3() - from.as_ivec3();
+
+            // Only neighbors on more than one axis have shoulders, wrapped steps and portal jumps don't
+            let axes = [IVec3::X, IVec3::Y, IVec3::Z]
+                .map(|axis| axis * delta)
+                .into_iter()
+                .filter(|axis| *axis != IVec3::ZERO)
+                .collect::<Vec<_>>();
+
+            if shoulders && axes.len() > 1 && delta.abs().max_element() == 1 {
+                // The cells reached by moving along some, but not all, of the axes of the step
+                for mask in 1..(1 << axes.len()) - 1 {
+                    let offset = (0..axes.len())
+                        .filter(|axis| mask & (1 << axis) != 0)
+                        .map(|axis| axes[axis])
+                        .sum::<IVec3>();
+                    let shoulder = (from.as_ivec3() + offset).as_uvec3();
+
+                    if policy == DiagonalMovement::NoCornerCutting || grid.is_passable(shoulder) {
+                        occupy(shoulder);
+                    }
+                }
+            }
+
+            occupy(to);
+        }
+
+        cells
+    }
+
+    /// Inserts `pos` at the front of the path, adding the `cost` of entering it to the path cost.
+    pub(crate) fn push_front(&mut self, pos: UVec3, cost: u32) {
+        self.path.push_front(pos);
+        // Pushing to the front can wrap the ring buffer, `Path::path` needs the positions in one slice
+        self.path.make_contiguous();
+        self.cost = self.cost.saturating_add(cost);
+    }
+
     /// Returns the next position in the path without removing it.
     pub fn next(&self) -> Option<UVec3> {
         // Get the next position in the path
@@ -130,3 +474,249 @@ impl IntoIterator for Path {
         self.path.into_iter()
     }
 }
+
+impl<'a> IntoIterator for &'a Path {
+    type Item = &'a UVec3;
+    type IntoIter = std::collections::vec_deque::Iter<'a, UVec3>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.path.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::PathfindMode, grid::GridSettingsBuilder, nav::Nav, CardinalGrid, OrdinalGrid,
+    };
+
+    fn test_path() -> Path {
+        Path::new(
+            vec![
+                UVec3::new(1, 0, 0),
+                UVec3::new(2, 0, 0),
+                UVec3::new(3, 0, 0),
+                UVec3::new(4, 0, 0),
+            ],
+            4,
+        )
+    }
+
+    #[test]
+    fn test_peek_and_remaining() {
+        let mut path = test_path();
+
+        assert_eq!(path.peek(0), &[]);
+        assert_eq!(path.peek(2), &[UVec3::new(1, 0, 0), UVec3::new(2, 0, 0)]);
+        assert_eq!(path.peek(10).len(), 4);
+
+        path.pop();
+        assert_eq!(path.remaining().len(), 3);
+        assert_eq!(path.peek(1), &[UVec3::new(2, 0, 0)]);
+        assert_eq!(path.iter().count(), 3);
+        assert_eq!((&path).into_iter().next(), Some(&UVec3::new(2, 0, 0)));
+    }
+
+    #[test]
+    fn test_push_front() {
+        // The positions fill the buffer from the start, so pushing to the front wraps around
+        let mut path = test_path();
+        path.push_front(UVec3::new(0, 0, 0), 1);
+
+        let expected = [
+            UVec3::new(0, 0, 0),
+            UVec3::new(1, 0, 0),
+            UVec3::new(2, 0, 0),
+            UVec3::new(3, 0, 0),
+            UVec3::new(4, 0, 0),
+        ];
+        assert_eq!(path.path(), &expected);
+        assert_eq!(path.remaining(), &expected);
+        assert_eq!(path.peek(3), &expected[..3]);
+        assert_eq!(path.cost(), 5);
+    }
+
+    #[test]
+    fn test_simplified() {
+        let mut grid = CardinalGrid::new(&GridSettingsBuilder::new_2d(5, 3).chunk_size(5).build());
+        grid.set_nav(UVec3::new(2, 0, 0), Nav::Passable(10));
+        // Only the bottom two rows are open
+        for x in 0..5 {
+            grid.set_nav(UVec3::new(x, 2, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        // Detours around the expensive cell
+        let path = Path::new(
+            vec![
+                UVec3::new(1, 0, 0),
+                UVec3::new(1, 1, 0),
+                UVec3::new(2, 1, 0),
+                UVec3::new(3, 1, 0),
+                UVec3::new(3, 0, 0),
+                UVec3::new(4, 0, 0),
+            ],
+            6,
+        );
+
+        let accurate = path.simplified(&grid, SimplifyMode::CostAccurate);
+        assert_eq!(accurate.cost(), 6);
+        assert!(accurate.len() < path.len());
+        assert_eq!(accurate.path().first(), Some(&UVec3::new(1, 0, 0)));
+        assert_eq!(accurate.path().last(), Some(&UVec3::new(4, 0, 0)));
+        assert!(!accurate.contains(UVec3::new(2, 0, 0)));
+        for pair in accurate.path().windows(2) {
+            assert!(grid.line_of_sight(pair[0], pair[1]));
+        }
+
+        let geometric = path.simplified(&grid, SimplifyMode::Geometric);
+        assert_eq!(
+            geometric.path(),
+            &[UVec3::new(1, 0, 0), UVec3::new(4, 0, 0)]
+        );
+        // Moving onto the first cell, then the straight line through the expensive cell
+        assert_eq!(geometric.cost(), 13);
+    }
+
+    #[test]
+    fn test_occupied_cells() {
+        let build = |diagonal_movement: DiagonalMovement| {
+            let settings = GridSettingsBuilder::new_2d(5, 5)
+                .chunk_size(5)
+                .diagonal_movement(diagonal_movement)
+                .build();
+            let mut grid = OrdinalGrid::new(&settings);
+            grid.set_nav(UVec3::new(1, 0, 0), Nav::Impassable);
+            grid.build();
+            grid
+        };
+
+        // Two diagonal steps past the wall at (1, 0), then a straight line between waypoints
+        let path = Path::new(
+            vec![
+                UVec3::new(0, 0, 0),
+                UVec3::new(1, 1, 0),
+                UVec3::new(2, 2, 0),
+                UVec3::new(2, 4, 0),
+            ],
+            5,
+        );
+
+        assert_eq!(
+            path.occupied_cells(&build(DiagonalMovement::Always)),
+            vec![
+                UVec3::new(0, 0, 0),
+                UVec3::new(1, 1, 0),
+                UVec3::new(2, 2, 0),
+                UVec3::new(2, 3, 0),
+                UVec3::new(2, 4, 0),
+            ]
+        );
+
+        // Only the open shoulder of the first step is swept
+        assert_eq!(
+            path.occupied_cells(&build(DiagonalMovement::NoCornerClipping)),
+            vec![
+                UVec3::new(0, 0, 0),
+                UVec3::new(0, 1, 0),
+                UVec3::new(1, 1, 0),
+                UVec3::new(2, 1, 0),
+                UVec3::new(1, 2, 0),
+                UVec3::new(2, 2, 0),
+                UVec3::new(2, 3, 0),
+                UVec3::new(2, 4, 0),
+            ]
+        );
+
+        let cutting = path.occupied_cells(&build(DiagonalMovement::NoCornerCutting));
+        assert_eq!(cutting.len(), 9);
+        assert!(cutting.contains(&UVec3::new(1, 0, 0)));
+    }
+
+    #[test]
+    fn test_truncate_to() {
+        let mut path = test_path();
+
+        assert!(path.contains(UVec3::new(3, 0, 0)));
+        assert!(!path.truncate_to(UVec3::new(9, 0, 0)));
+        assert_eq!(path.len(), 4);
+
+        assert!(path.truncate_to(UVec3::new(2, 0, 0)));
+        assert_eq!(path.len(), 2);
+        assert!(!path.contains(UVec3::new(3, 0, 0)));
+        assert_eq!(path.remaining().last(), Some(&UVec3::new(2, 0, 0)));
+    }
+
+    #[test]
+    fn test_direction_at() {
+        let path = Path::new(
+            vec![
+                UVec3::new(0, 0, 0),
+                UVec3::new(1, 1, 0),
+                UVec3::new(1, 2, 0),
+                UVec3::new(4, 2, 1),
+            ],
+            4,
+        );
+
+        assert_eq!(path.direction_at(0), Some(Dir::NorthEast));
+        assert_eq!(path.direction_at(1), Some(Dir::North));
+        // Longer steps snap to the closest direction
+        assert_eq!(path.direction_at(2), Some(Dir::EastUp));
+        assert_eq!(path.direction_at(3), None);
+    }
+
+    #[test]
+    fn test_graph_edges() {
+        let mut grid =
+            CardinalGrid::new(&GridSettingsBuilder::new_2d(12, 12).chunk_size(4).build());
+        grid.build();
+
+        let path = grid
+            .find_path(
+                UVec3::new(0, 0, 0),
+                UVec3::new(11, 11, 0),
+                PathfindMode::Coarse,
+                false,
+            )
+            .unwrap();
+        let nodes = path.graph_path().copied().collect::<Vec<_>>();
+        assert!(nodes.len() >= 2);
+
+        let edges = path.graph_edges();
+        assert_eq!(edges.len(), path.len());
+
+        // Every edge is a step of the graph path and the edges are followed in order
+        let steps = nodes.windows(2).map(|w| (w[0], w[1])).collect::<Vec<_>>();
+        let mut last = 0;
+        for edge in edges.iter().flatten() {
+            let index = steps.iter().position(|step| step == edge).unwrap();
+            assert!(index >= last);
+            last = index;
+        }
+
+        // The start and goal chunk stretches don't belong to an edge
+        assert_eq!(edges.last(), Some(&None));
+        let first = path.path().iter().position(|pos| *pos == nodes[0]).unwrap();
+        assert!(edges[..=first].iter().all(Option::is_none));
+        assert_eq!(edges[first + 1], Some(steps[0]));
+
+        // Consuming the path keeps the remaining edges
+        let mut advanced = path.clone();
+        for _ in 0..=first + 1 {
+            advanced.pop();
+        }
+        assert_eq!(advanced.graph_edges(), edges[first + 2..]);
+
+        let astar = grid
+            .find_path(
+                UVec3::new(0, 0, 0),
+                UVec3::new(11, 11, 0),
+                PathfindMode::AStar,
+                false,
+            )
+            .unwrap();
+        assert!(astar.graph_edges().iter().all(Option::is_none));
+    }
+}
@@ -1,23 +1,29 @@
 //! This module defines pathfinding functions which can be called directly.
 
+use std::collections::VecDeque;
+
 use bevy::{
     ecs::entity::Entity,
     log,
-    math::{IVec3, UVec3},
+    math::{IVec3, UVec2, UVec3},
     platform::collections::{HashMap, HashSet},
 };
 use ndarray::ArrayView3;
 
 use crate::{
-    astar::{astar_graph, astar_grid},
+    astar::{astar_graph, astar_grid, astar_grid_to, SearchContext},
     chunk::Chunk,
     dijkstra::dijkstra_grid,
+    goal::SearchGoal,
     grid::Grid,
-    nav::NavCell,
+    jps::jps_grid,
+    nav::{NavCell, NavLayers, ALL_NAV_LAYERS},
     node::Node,
     path::Path,
     prelude::Neighborhood,
     raycast::{bresenham_path, bresenham_path_filtered},
+    theta::theta_grid,
+    MovementCost,
 };
 
 /// AStar pathfinding
@@ -33,6 +39,7 @@ use crate::{
 /// * `goal` - The goal position.
 /// * `blocking` - A hashmap of blocked positions for dynamic obstacles.
 /// * `partial` - If true, the pathfinding will return a partial path if the goal is blocked.
+/// * `cost_overrides` - Extra movement cost added on top of the [`NavCell`] cost when entering a position.
 #[inline(always)]
 // This has to be moved internally since the base A* and Djikstra algorithms use precomputed neighbors now.
 pub(crate) fn pathfind_astar<N: Neighborhood>(
@@ -42,49 +49,465 @@ pub(crate) fn pathfind_astar<N: Neighborhood>(
     goal: UVec3,
     blocking: &HashMap<UVec3, Entity>,
     partial: bool,
+    cost_overrides: &HashMap<UVec3, MovementCost>,
+) -> Option<Path> {
+    pathfind_astar_goals(
+        neighborhood,
+        grid,
+        start,
+        &SearchGoal::Position(goal),
+        blocking,
+        partial,
+        cost_overrides,
+        ALL_NAV_LAYERS,
+        UVec2::ONE,
+        &SearchContext::default(),
+    )
+}
+
+/// AStar pathfinding to whichever position satisfying `goal` is cheapest to reach.
+///
+/// # Arguments
+/// * `neighborhood` - The [`Neighborhood`] to use for the pathfinding.
+/// * `grid` - The [`ArrayView3`] of [`NavCell`]s to use for the pathfinding.
+/// * `start` - The starting position.
+/// * `goal` - The [`SearchGoal`] to reach.
+/// * `blocking` - A hashmap of blocked positions for dynamic obstacles.
+/// * `partial` - If true, the pathfinding will return a partial path towards the nearest goal if none are reachable.
+/// * `cost_overrides` - Extra movement cost added on top of the [`NavCell`] cost when entering a position.
+/// * `layers` - The [`NavLayers`] the agent can move on, cells on other layers are treated as impassable.
+/// * `footprint` - The size of the agent in cells, see [`crate::components::AgentSize`].
+/// * `context` - Caps the node expansions of the search and records them when tracing.
+#[inline(always)]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn pathfind_astar_goals<N: Neighborhood>(
+    neighborhood: &N,
+    grid: &ArrayView3<NavCell>,
+    start: UVec3,
+    goal: &SearchGoal,
+    blocking: &HashMap<UVec3, Entity>,
+    partial: bool,
+    cost_overrides: &HashMap<UVec3, MovementCost>,
+    layers: NavLayers,
+    footprint: UVec2,
+    context: &SearchContext,
+) -> Option<Path> {
+    search_viable_goal(grid, start, goal, blocking, partial, |goal| {
+        astar_grid_to(
+            neighborhood,
+            grid,
+            start,
+            goal,
+            1024,
+            partial,
+            blocking,
+            cost_overrides,
+            layers,
+            footprint,
+            context,
+        )
+    })
+}
+
+/// Jump Point Search pathfinding to whichever position satisfying `goal` is cheapest to reach.
+///
+/// Falls back to [`pathfind_astar_goals`] when `uniform_cost` is false, `cost_overrides` isn't empty, or the [`Neighborhood`]
+/// has filters since the jump rules assume every step costs the same and the default neighbor rules.
+///
+/// # Arguments
+/// * `neighborhood` - The [`Neighborhood`] to use for the pathfinding.
+/// * `grid` - The [`ArrayView3`] of [`NavCell`]s to use for the pathfinding.
+/// * `start` - The starting position.
+/// * `goal` - The [`SearchGoal`] to reach.
+/// * `blocking` - A hashmap of blocked positions for dynamic obstacles.
+/// * `partial` - If true, the pathfinding will return a partial path if the goal is blocked.
+/// * `uniform_cost` - If every passable cell in `grid` shares the same movement cost.
+/// * `cost_overrides` - Extra movement cost added on top of the [`NavCell`] cost when entering a position.
+/// * `one_way` - If any cell has blocked or added moves, jumps can't follow those so A* is used instead.
+/// * `context` - Caps the node expansions of the search and records them when tracing.
+#[inline(always)]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn pathfind_jps<N: Neighborhood>(
+    neighborhood: &N,
+    grid: &ArrayView3<NavCell>,
+    start: UVec3,
+    goal: &SearchGoal,
+    blocking: &HashMap<UVec3, Entity>,
+    partial: bool,
+    uniform_cost: bool,
+    cost_overrides: &HashMap<UVec3, MovementCost>,
+    one_way: bool,
+    context: &SearchContext,
 ) -> Option<Path> {
-    // Ensure the goal is within bounds of the grid
+    if !uniform_cost || !cost_overrides.is_empty() || !neighborhood.filters().is_empty() || one_way
+    {
+        return pathfind_astar_goals(
+            neighborhood,
+            grid,
+            start,
+            goal,
+            blocking,
+            partial,
+            cost_overrides,
+            ALL_NAV_LAYERS,
+            UVec2::ONE,
+            context,
+        );
+    }
+
+    search_viable_goal(grid, start, goal, blocking, partial, |goal| {
+        jps_grid(
+            neighborhood,
+            grid,
+            start,
+            goal,
+            1024,
+            partial,
+            blocking,
+            context,
+        )
+    })
+}
+
+/// Theta* any-angle pathfinding to whichever position satisfying `goal` is found first.
+/// The returned path only contains the waypoints where the path changes direction.
+///
+/// # Arguments
+/// * `neighborhood` - The [`Neighborhood`] to use for the pathfinding.
+/// * `grid` - The [`ArrayView3`] of [`NavCell`]s to use for the pathfinding.
+/// * `start` - The starting position.
+/// * `goal` - The [`SearchGoal`] to reach.
+/// * `blocking` - A hashmap of blocked positions for dynamic obstacles.
+/// * `partial` - If true, the pathfinding will return a partial path if the goal is blocked.
+/// * `cost_overrides` - Extra movement cost added on top of the [`NavCell`] cost when entering a position.
+/// * `context` - Caps the node expansions of the search and records them when tracing.
+#[inline(always)]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn pathfind_theta<N: Neighborhood>(
+    neighborhood: &N,
+    grid: &ArrayView3<NavCell>,
+    start: UVec3,
+    goal: &SearchGoal,
+    blocking: &HashMap<UVec3, Entity>,
+    partial: bool,
+    cost_overrides: &HashMap<UVec3, MovementCost>,
+    context: &SearchContext,
+) -> Option<Path> {
+    search_viable_goal(grid, start, goal, blocking, partial, |goal| {
+        theta_grid(
+            neighborhood,
+            grid,
+            start,
+            goal,
+            1024,
+            partial,
+            blocking,
+            cost_overrides,
+            context,
+        )
+    })
+}
+
+// Narrows `goal` down to the positions worth searching for and runs `search` with it.
+// The start position is removed from the returned path.
+fn search_viable_goal(
+    grid: &ArrayView3<NavCell>,
+    start: UVec3,
+    goal: &SearchGoal,
+    blocking: &HashMap<UVec3, Entity>,
+    partial: bool,
+    search: impl FnOnce(&SearchGoal) -> Option<Path>,
+) -> Option<Path> {
+    let mut path = match goal {
+        SearchGoal::Region { min, max } => {
+            let (min, max) = viable_region(grid, start, *min, *max)?;
+            search(&SearchGoal::Region { min, max })?
+        }
+        // The centers don't need to be passable, the cells around them are the goals
+        SearchGoal::Within { .. } => {
+            if !viable_start(grid, start) {
+                return None;
+            }
+            search(goal)?
+        }
+        _ => {
+            let goals = viable_goals(grid, start, goal.positions(), blocking, partial)?;
+            search(&SearchGoal::from_slice(&goals))?
+        }
+    };
+
+    path.path.pop_front();
+    Some(path)
+}
+
+// Returns true if `start` is in bounds and passable.
+fn viable_start(grid: &ArrayView3<NavCell>, start: UVec3) -> bool {
     let shape = grid.shape();
-    if start.x as usize >= shape[0] || start.y as usize >= shape[1] || start.z as usize >= shape[2]
+
+    if (start.x as usize) >= shape[0]
+        || (start.y as usize) >= shape[1]
+        || (start.z as usize) >= shape[2]
     {
         log::warn!("Start is out of bounds: {:?}", start);
+        return false;
+    }
+
+    !grid[[start.x as usize, start.y as usize, start.z as usize]].is_impassable()
+}
+
+// Validates the start position and returns the goals worth searching for.
+// Out of bounds goals are dropped. Impassable or blocked goals are dropped unless `partial` is set.
+// Returns `None` if the start is invalid or no goals remain.
+fn viable_goals(
+    grid: &ArrayView3<NavCell>,
+    start: UVec3,
+    goals: &[UVec3],
+    blocking: &HashMap<UVec3, Entity>,
+    partial: bool,
+) -> Option<Vec<UVec3>> {
+    let shape = grid.shape();
+    let in_bounds = |pos: UVec3| {
+        (pos.x as usize) < shape[0] && (pos.y as usize) < shape[1] && (pos.z as usize) < shape[2]
+    };
+
+    if !viable_start(grid, start) {
         return None;
     }
 
-    if goal.x as usize >= shape[0] || goal.y as usize >= shape[1] || goal.z as usize >= shape[2] {
-        log::warn!("Goal is out of bounds: {:?}", goal);
+    let goals: Vec<UVec3> = goals
+        .iter()
+        .copied()
+        .filter(|goal| {
+            if !in_bounds(*goal) {
+                log::warn!("Goal is out of bounds: {:?}", goal);
+                return false;
+            }
+
+            // If the goal is impassable or blocked and partial isn't set, skip it
+            partial
+                || (!grid[[goal.x as usize, goal.y as usize, goal.z as usize]].is_impassable()
+                    && !blocking.contains_key(goal))
+        })
+        .collect();
+
+    if goals.is_empty() {
+        None
+    } else {
+        Some(goals)
+    }
+}
+
+// Validates the start position and clamps the region between `min` and `max` to the grid bounds.
+// Returns `None` if the start is invalid or the region lies entirely outside of the grid.
+fn viable_region(
+    grid: &ArrayView3<NavCell>,
+    start: UVec3,
+    min: UVec3,
+    max: UVec3,
+) -> Option<(UVec3, UVec3)> {
+    if !viable_start(grid, start) {
         return None;
     }
 
-    // If the goal is impassibe and partial isn't set, return none
-    if grid[[start.x as usize, start.y as usize, start.z as usize]].is_impassable()
-        || grid[[goal.x as usize, goal.y as usize, goal.z as usize]].is_impassable() && !partial
-    {
+    let shape = grid.shape();
+    let bounds = UVec3::new(shape[0] as u32, shape[1] as u32, shape[2] as u32);
+
+    if min.cmpge(bounds).any() || min.cmpgt(max).any() {
+        log::warn!("Region is out of bounds: {:?} to {:?}", min, max);
         return None;
     }
 
-    // if goal is in the blocking map, return None
-    if blocking.contains_key(&goal) && !partial {
-        //log::error!("Goal is in the blocking map");
+    Some((min, max.min(bounds - 1)))
+}
+
+/// HPA* pathfinding to the nearest of the `goals`.
+/// Goals walled off from `start` are dropped with [`Grid::same_component`], then the hierarchy is only searched
+/// towards the goal with the lowest heuristic. The next nearest goal is only searched if that search fails,
+/// which can happen when one-way moves or `blocking` entities cut the goal off.
+/// If `partial` is set and no goal is reachable, a partial path towards the nearest goal is returned.
+pub(crate) fn pathfind_goals<N: Neighborhood, H: Neighborhood>(
+    grid: &Grid<N>,
+    neighborhood: &H,
+    start: UVec3,
+    goals: &[UVec3],
+    blocking: &HashMap<UVec3, Entity>,
+    partial: bool,
+    refined: bool,
+) -> Option<Path> {
+    let mut candidates = goals.to_vec();
+    let mut seen = HashSet::new();
+    candidates.retain(|goal| seen.insert(*goal));
+
+    let nearest = candidates
+        .iter()
+        .copied()
+        .min_by_key(|goal| neighborhood.heuristic(start, *goal))?;
+
+    // The sort is stable so the first goal given wins a tie
+    candidates.retain(|goal| grid.same_component(start, *goal));
+    candidates.sort_by_key(|goal| neighborhood.heuristic(start, *goal));
+
+    for goal in candidates {
+        if let Some(path) = pathfind(grid, neighborhood, start, goal, blocking, false, refined) {
+            return Some(path);
+        }
+    }
+
+    if partial {
+        return pathfind(grid, neighborhood, start, nearest, blocking, true, refined);
+    }
+
+    None
+}
+
+/// HPA* pathfinding to the closest reachable cell inside the region between `min` and `max` (inclusive).
+/// Every chunk overlapping the region is treated as a potential goal chunk, using its passable cell closest to `start` as the goal.
+/// If `partial` is set and the region can't be reached, a partial path towards the region is returned.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn pathfind_region<N: Neighborhood, H: Neighborhood>(
+    grid: &Grid<N>,
+    neighborhood: &H,
+    start: UVec3,
+    min: UVec3,
+    max: UVec3,
+    blocking: &HashMap<UVec3, Entity>,
+    partial: bool,
+    refined: bool,
+) -> Option<Path> {
+    let (min, max) = viable_region(&grid.view(), start, min, max)?;
+
+    pathfind_chunk_candidates(
+        grid,
+        neighborhood,
+        start,
+        box_cells(min, max),
+        start.clamp(min, max),
+        blocking,
+        partial,
+        refined,
+    )
+}
+
+/// HPA* pathfinding to the closest reachable cell of a [`SearchGoal::Within`] goal.
+/// Like [`pathfind_region`], every chunk with a cell in range is treated as a potential goal chunk.
+/// If `partial` is set and no cell in range can be reached, a partial path towards the nearest center is returned.
+pub(crate) fn pathfind_within<N: Neighborhood, H: Neighborhood>(
+    grid: &Grid<N>,
+    neighborhood: &H,
+    start: UVec3,
+    goal: &SearchGoal,
+    blocking: &HashMap<UVec3, Entity>,
+    partial: bool,
+    refined: bool,
+) -> Option<Path> {
+    let SearchGoal::Within {
+        centers, radius, ..
+    } = *goal
+    else {
+        return None;
+    };
+
+    if !viable_start(&grid.view(), start) {
         return None;
     }
 
-    let path = astar_grid(neighborhood, grid, start, goal, 1024, partial, blocking);
+    let nearest = centers
+        .iter()
+        .copied()
+        .filter(|center| grid.in_bounds(*center))
+        .min_by_key(|center| neighborhood.heuristic(start, *center))?;
 
-    if let Some(mut path) = path {
-        path.path.pop_front();
-        Some(path)
-    } else {
-        None
+    // Every cell in the box around each center, narrowed down to the cells in range
+    let bounds = grid.dimensions() - UVec3::ONE;
+    let cells = centers
+        .iter()
+        .filter(|center| grid.in_bounds(**center))
+        .flat_map(|center| {
+            box_cells(
+                center.saturating_sub(UVec3::splat(radius)),
+                center.saturating_add(UVec3::splat(radius)).min(bounds),
+            )
+        })
+        .filter(|pos| goal.is_goal(*pos));
+
+    pathfind_chunk_candidates(
+        grid,
+        neighborhood,
+        start,
+        cells,
+        nearest,
+        blocking,
+        partial,
+        refined,
+    )
+}
+
+// Every position in the box between `min` and `max` (inclusive).
+fn box_cells(min: UVec3, max: UVec3) -> impl Iterator<Item = UVec3> {
+    (min.z..=max.z).flat_map(move |z| {
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| UVec3::new(x, y, z)))
+    })
+}
+
+// HPA* pathfinding to whichever of the goal `cells` is cheapest to reach.
+// Only the passable cell closest to `start` in each chunk is searched for, the chunks share their entrances.
+// If `partial` is set and no cell can be reached, a partial path towards `fallback` is returned.
+#[allow(clippy::too_many_arguments)]
+fn pathfind_chunk_candidates<N: Neighborhood, H: Neighborhood>(
+    grid: &Grid<N>,
+    neighborhood: &H,
+    start: UVec3,
+    cells: impl Iterator<Item = UVec3>,
+    fallback: UVec3,
+    blocking: &HashMap<UVec3, Entity>,
+    partial: bool,
+    refined: bool,
+) -> Option<Path> {
+    // The best candidate goal for each chunk
+    let mut chunk_goals: HashMap<(usize, usize, usize), (u32, UVec3)> = HashMap::new();
+    for pos in cells {
+        if !grid.is_passable(pos) || blocking.contains_key(&pos) {
+            continue;
+        }
+
+        let distance = neighborhood.heuristic(start, pos);
+        chunk_goals
+            .entry(grid.chunk_index_of(pos))
+            .and_modify(|best| {
+                if distance < best.0 {
+                    *best = (distance, pos);
+                }
+            })
+            .or_insert((distance, pos));
+    }
+
+    if chunk_goals.is_empty() {
+        if !partial {
+            return None;
+        }
+
+        return pathfind(grid, neighborhood, start, fallback, blocking, true, refined);
     }
+
+    let goals: Vec<UVec3> = chunk_goals.values().map(|(_, pos)| *pos).collect();
+    pathfind_goals(
+        grid,
+        neighborhood,
+        start,
+        &goals,
+        blocking,
+        partial,
+        refined,
+    )
 }
 
 /// HPA* pathfinding.
 // Keeping this internal for now since Grid has it's own helper function to call this
 // and [`Grid`] is required for it.
 #[inline(always)]
-pub(crate) fn pathfind<N: Neighborhood>(
+pub(crate) fn pathfind<N: Neighborhood, H: Neighborhood>(
     grid: &Grid<N>,
+    neighborhood: &H,
     start: UVec3,
     goal: UVec3,
     blocking: &HashMap<UVec3, Entity>,
@@ -116,13 +539,14 @@ pub(crate) fn pathfind<N: Neighborhood>(
     // If the start and goal are in the same chunk, use AStar directly
     if start_chunk == goal_chunk {
         let path = astar_grid(
-            &grid.neighborhood,
+            neighborhood,
             &grid.view(),
             start,
             goal,
             100,
             partial,
             blocking,
+            grid.extra_costs(),
         );
 
         if let Some(mut path) = path {
@@ -143,14 +567,26 @@ pub(crate) fn pathfind<N: Neighborhood>(
     let mut cost = 0;
 
     for start_node in &start_nodes {
+        // Reuse the cached route into the goal chunk if it ends at an entrance the goal is reachable from
+        let cached_route = grid
+            .cached_route(start_node.pos, goal_chunk)
+            .filter(|route| {
+                let end = route.path.back();
+                goal_nodes.iter().any(|node| end == Some(&node.pos))
+            });
+
         for goal_node in goal_nodes.clone() {
-            let node_path = astar_graph(
-                &grid.neighborhood,
-                grid.graph(),
-                start_node.pos,
-                goal_node.pos,
-                100,
-            );
+            let node_path = match &cached_route {
+                Some(route) if route.path.back() != Some(&goal_node.pos) => continue,
+                Some(route) => Some(route.clone()),
+                None => astar_graph(
+                    neighborhood,
+                    grid.graph(),
+                    start_node.pos,
+                    goal_node.pos,
+                    100,
+                ),
+            };
 
             if let Some(mut node_path) = node_path {
                 let start_keys: HashSet<_> = start_paths.keys().copied().collect();
@@ -182,16 +618,30 @@ pub(crate) fn pathfind<N: Neighborhood>(
                     cost += cached_path.cost();
                 }
 
-                // Add end path to path
+                // Add end path to path, the exit node was already added by the last cached edge
                 let end_path = goal_paths.get(&(goal_pos - goal_chunk.min())).unwrap();
                 path.extend(
                     end_path
                         .path()
                         .iter()
                         .rev()
+                        .skip(1)
                         .map(|pos| *pos + goal_chunk.min()),
                 );
+                // The end path was searched from the goal, walking it backwards enters the goal instead of the exit node
+                let end_cells = end_path.path();
                 cost += end_path.cost();
+                if end_cells.len() > 1 {
+                    let last = end_cells.len() - 1;
+                    let into_goal =
+                        neighborhood.step_cost(end_cells[1], end_cells[0], grid.navcell(goal).cost);
+                    let into_exit = neighborhood.step_cost(
+                        end_cells[last - 1],
+                        end_cells[last],
+                        grid.navcell(*goal_pos).cost,
+                    );
+                    cost = (cost + into_goal).saturating_sub(into_exit);
+                }
 
                 if path.is_empty() {
                     return None;
@@ -209,16 +659,19 @@ pub(crate) fn pathfind<N: Neighborhood>(
                 }
 
                 if !refined {
-                    // If we're not refining, return the path as is
+                    // If we're not refining, return the path as is without the starting position
                     let mut path = Path::new(path, cost);
+                    path.path.pop_front();
                     path.graph_path = node_path.path;
                     return Some(path);
                 }
 
                 let mut refined_path = optimize_path(
-                    &grid.neighborhood,
+                    neighborhood,
                     &grid.view(),
                     &Path::from_slice(&path, cost),
+                    grid.extra_costs(),
+                    grid.has_one_way_moves(),
                 );
 
                 // remove the starting position from the refined path
@@ -235,6 +688,88 @@ pub(crate) fn pathfind<N: Neighborhood>(
     None
 }
 
+/// Returns true if [`pathfind`] would find a path from `start` to `goal` without building it.
+///
+/// Follows the same steps as the hierarchical search, but only checks the start chunk reaches an entrance
+/// that is connected to an entrance the goal chunk reaches through the abstract graph.
+/// The connection is found with a breadth first search over the entrances instead of stitching the cached paths together.
+pub(crate) fn hierarchy_connects<N: Neighborhood, H: Neighborhood>(
+    grid: &Grid<N>,
+    neighborhood: &H,
+    start: UVec3,
+    goal: UVec3,
+) -> bool {
+    if !grid.in_bounds(start) || !grid.in_bounds(goal) {
+        return false;
+    }
+
+    if grid.navcell(start).is_impassable() || grid.navcell(goal).is_impassable() {
+        return false;
+    }
+
+    let (Some(start_chunk), Some(goal_chunk)) =
+        (grid.chunk_at_position(start), grid.chunk_at_position(goal))
+    else {
+        return false;
+    };
+
+    // The hierarchy isn't used within a single chunk
+    if start_chunk == goal_chunk {
+        return astar_grid(
+            neighborhood,
+            &grid.view(),
+            start,
+            goal,
+            100,
+            false,
+            &HashMap::new(),
+            grid.extra_costs(),
+        )
+        .is_some();
+    }
+
+    let blocking = HashMap::new();
+    let Some((start_nodes, _)) =
+        filter_and_rank_chunk_nodes(grid, start_chunk, start, goal, &blocking)
+    else {
+        return false;
+    };
+    let Some((goal_nodes, _)) =
+        filter_and_rank_chunk_nodes(grid, goal_chunk, goal, start, &blocking)
+    else {
+        return false;
+    };
+
+    let goal_nodes = goal_nodes
+        .iter()
+        .map(|node| node.pos)
+        .collect::<HashSet<_>>();
+
+    let mut visited = start_nodes
+        .iter()
+        .map(|node| node.pos)
+        .collect::<HashSet<_>>();
+    let mut queue = visited.iter().copied().collect::<VecDeque<_>>();
+
+    while let Some(current) = queue.pop_front() {
+        if goal_nodes.contains(&current) {
+            return true;
+        }
+
+        let Some(node) = grid.graph().node_at(current) else {
+            continue;
+        };
+
+        for next in node.edges.keys() {
+            if visited.insert(*next) {
+                queue.push_back(*next);
+            }
+        }
+    }
+
+    false
+}
+
 // Some times the Graph A* will return a path that has valid but redundant nodes at the start and end
 // of the path. Leading to awkward paths where the agent appears to veers off before heading to the goal.
 // This trims the path to ensure that only one entrance and exit node is used for the start and goal chunks.
@@ -333,19 +868,45 @@ pub(crate) fn trim_path(
 /// * `neighborhood` - The [`Neighborhood`] to use for the pathfinding.
 /// * `grid` - The [`ArrayView3`] of the grid.
 /// * `path` - The [`Path`] to optimize.
-/// * `ordinal` - If true, use ordinal movement. If false, use cardinal movement.
+/// * `cost_overrides` - Extra movement cost added on top of the [`NavCell`] cost when entering a position.
 ///
 #[inline(always)]
 pub(crate) fn optimize_path<N: Neighborhood>(
     neighborhood: &N,
     grid: &ArrayView3<NavCell>,
     path: &Path,
+    cost_overrides: &HashMap<UVec3, MovementCost>,
+    one_way: bool,
 ) -> Path {
     if path.is_empty() {
         return path.clone();
     }
 
-    let filtered = !neighborhood.filters().is_empty();
+    // Shortcuts have to follow the precomputed neighbors when some moves are filtered out or one-way
+    let filtered = !neighborhood.filters().is_empty() || one_way;
+
+    let step_cost = |from: UVec3, to: UVec3| {
+        let extra_cost = cost_overrides.get(&to).copied().unwrap_or(0);
+        let from_cell = &grid[[from.x as usize, from.y as usize, from.z as usize]];
+        let to_cell = &grid[[to.x as usize, to.y as usize, to.z as usize]];
+        neighborhood
+            .step_cost(
+                from,
+                to,
+                to_cell.cost.saturating_add(from_cell.climb_cost(to_cell)),
+            )
+            .saturating_add(extra_cost)
+    };
+
+    // Accumulated movement cost along the original path so shortcuts can be compared against it
+    let mut accumulated = Vec::with_capacity(path.len());
+    let mut total = 0;
+    for (index, pos) in path.path.iter().enumerate() {
+        if index > 0 {
+            total = step_cost(path.path[index - 1], *pos).saturating_add(total);
+        }
+        accumulated.push(total);
+    }
 
     let mut refined_path = Vec::with_capacity(path.len());
     let mut i = 0;
@@ -375,6 +936,15 @@ pub(crate) fn optimize_path<N: Neighborhood>(
             };
 
             if let Some(shortcut) = maybe_shortcut {
+                // Don't take a shortcut through terrain that costs more than the original route
+                let shortcut_cost = shortcut
+                    .windows(2)
+                    .map(|step| step_cost(step[0], step[1]))
+                    .fold(0u32, u32::saturating_add);
+                if shortcut_cost > accumulated[farthest] - accumulated[i] {
+                    continue;
+                }
+
                 refined_path.extend(shortcut.into_iter().skip(1));
                 i = farthest;
                 shortcut_taken = true;
@@ -397,11 +967,11 @@ pub(crate) fn optimize_path<N: Neighborhood>(
         }
     }
 
-    // Recompute cost of new path
+    // Recompute cost of new path, the starting position isn't entered so it doesn't add to the cost
     let cost = refined_path
-        .iter()
-        .map(|pos| grid[[pos.x as usize, pos.y as usize, pos.z as usize]].cost)
-        .sum();
+        .windows(2)
+        .map(|step| step_cost(step[0], step[1]))
+        .fold(0, u32::saturating_add);
 
     let mut path = Path::new(refined_path.clone(), cost);
     path.graph_path = refined_path.into();
@@ -499,14 +1069,16 @@ fn filter_and_rank_chunk_nodes<'a, N: Neighborhood>(
 ) -> Option<(Vec<&'a Node>, HashMap<UVec3, Path>)> {
     let nodes = grid.graph().nodes_in_chunk(chunk);
 
-    // Adjust the blocking map to the local chunk coordinates
+    // Adjust the blocking map to the local chunk coordinates, positions outside of the chunk would be clamped into it
     let adjusted_blocking = blocking
         .iter()
+        .filter(|(pos, _)| pos.cmpge(chunk.min()).all() && pos.cmplt(chunk.max()).all())
         .map(|(pos, entity)| (chunk.to_local(pos), *entity))
         .collect::<HashMap<_, _>>();
 
     // Get paths from source to all nodes in this chunk
     let paths = dijkstra_grid(
+        grid.neighborhood(),
         &grid.chunk_view(chunk),
         source - chunk.min(),
         &nodes
@@ -527,11 +1099,20 @@ fn filter_and_rank_chunk_nodes<'a, N: Neighborhood>(
         return None;
     }
 
+    // Entrances on a wrapped edge are measured the shorter way around
+    let distance = |a: UVec3, b: UVec3| {
+        if grid.wrap().any() {
+            grid.heuristic_distance(a, b) as i32
+        } else {
+            manhattan_distance(a, b)
+        }
+    };
+
     let mut ranked_nodes = filtered_nodes
         .iter()
         .map(|node| {
-            let d_start = manhattan_distance(node.pos, source);
-            let d_goal = manhattan_distance(node.pos, target);
+            let d_start = distance(node.pos, source);
+            let d_goal = distance(node.pos, target);
             (*node, d_start + d_goal)
         })
         .collect::<Vec<_>>();
@@ -586,6 +1167,7 @@ pub(crate) fn reroute_path<N: Neighborhood>(
             goal,
             blocking,
             false,
+            grid.extra_costs(),
         );
     }
 
@@ -597,6 +1179,7 @@ pub(crate) fn reroute_path<N: Neighborhood>(
             *pos,
             blocking,
             false,
+            grid.extra_costs(),
         );
         if new_path.is_some() && !new_path.as_ref().unwrap().is_empty() {
             new_path
@@ -615,7 +1198,15 @@ pub(crate) fn reroute_path<N: Neighborhood>(
 
         let last_pos = *new_path.path().last().unwrap();
 
-        let hpa = pathfind(grid, last_pos, goal, blocking, false, refined);
+        let hpa = pathfind(
+            grid,
+            &grid.neighborhood,
+            last_pos,
+            goal,
+            blocking,
+            false,
+            refined,
+        );
 
         if let Some(hpa) = hpa {
             for pos in hpa.path() {
@@ -630,3 +1221,84 @@ pub(crate) fn reroute_path<N: Neighborhood>(
 
     None
 }
+
+// How many cells past the invalidated stretch of a path a repair may rejoin the old path at.
+const REPAIR_REJOIN_WINDOW: usize = 16;
+
+// Repairs `path` for an agent at `start` by replanning only the stretch that's no longer walkable
+// and reusing the valid rest of the old path. The path must be the remainder of a path to `goal`,
+// `start` excluded. Returns `None` if the path doesn't end at `goal` or the stretch can't be bridged.
+pub(crate) fn repair_path<N: Neighborhood>(
+    grid: &Grid<N>,
+    path: &Path,
+    start: UVec3,
+    goal: UVec3,
+    blocking: &HashMap<UVec3, Entity>,
+) -> Option<Path> {
+    let steps = path.path();
+    if steps.last() != Some(&goal) || !grid.in_bounds(start) {
+        return None;
+    }
+
+    let walkable = |from: UVec3, to: UVec3| {
+        if !grid.is_passable(to) || blocking.contains_key(&to) {
+            return false;
+        }
+
+        // Steps between adjacent cells must still be allowed by the neighbor rules,
+        // longer steps from refined paths or portals are only checked for passability.
+        let adjacent = (from.as_ivec3() - to.as_ivec3()).abs().max_element() <= 1;
+        !adjacent || grid.navcell(from).neighbor_iter(from).any(|pos| pos == to)
+    };
+
+    let mut invalid = None;
+    let mut from = start;
+    for (i, &step) in steps.iter().enumerate() {
+        if !walkable(from, step) {
+            invalid = Some(i);
+        }
+        from = step;
+    }
+
+    let Some(invalid) = invalid else {
+        return Some(path.clone());
+    };
+
+    // The old path is unusable up to and including `invalid`, rejoin it soon after.
+    let rejoin = &steps[invalid + 1..steps.len().min(invalid + 1 + REPAIR_REJOIN_WINDOW)];
+    if rejoin.is_empty() {
+        return None;
+    }
+
+    let bridge = pathfind_astar_goals(
+        &grid.neighborhood,
+        &grid.view(),
+        start,
+        &SearchGoal::Any(rejoin),
+        blocking,
+        false,
+        grid.extra_costs(),
+        ALL_NAV_LAYERS,
+        UVec2::ONE,
+        &SearchContext::default(),
+    )?;
+
+    let joined = *bridge.path().last()?;
+    let tail_start = invalid + 1 + rejoin.iter().position(|pos| *pos == joined)?;
+
+    let mut cost = bridge.cost();
+    let mut repaired = bridge.path().to_vec();
+    for step in steps[tail_start..].windows(2) {
+        let (from, to) = (step[0], step[1]);
+        let move_cost = grid.navcell(from).move_cost(from, to, grid.navcell(to));
+        cost = cost
+            .saturating_add(grid.neighborhood.step_cost(from, to, move_cost))
+            .saturating_add(grid.extra_cost(to).unwrap_or_default());
+        repaired.push(to);
+    }
+
+    let mut new_path = Path::new(repaired, cost);
+    new_path.graph_path = path.graph_path.clone();
+    new_path.set_goal_index(path.goal_index());
+    Some(new_path)
+}
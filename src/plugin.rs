@@ -1,14 +1,35 @@
 //! Northstar Plugin. This plugin handles the pathfinding and collision avoidance systems.
-use std::collections::VecDeque;
 #[cfg(feature = "stats")]
 use std::time::Instant;
+use std::{borrow::Cow, cmp::Reverse, collections::VecDeque, sync::Arc, time::Duration};
 
-use bevy::{log, platform::collections::HashMap, prelude::*};
+use bevy::{
+    log,
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    tasks::{
+        block_on, futures_lite::future, AsyncComputeTaskPool, ComputeTaskPool, Task, TaskPool,
+    },
+};
+#[cfg(feature = "rng")]
+use rand::{rand_core::impls, RngCore};
 
-use crate::{prelude::*, WithoutPathingFailures};
+use crate::{
+    astar::SearchContext,
+    cooperative::{plan_window, ReservationTable},
+    goal::SearchGoal,
+    pathfind::repair_path,
+    prelude::*,
+    trace::SearchTrace,
+    WithoutPathingFailures,
+};
+
+#[cfg(feature = "gui-debug")]
+use crate::components::debug_components::DebugSearch;
 
 /// General settings for the Northstar plugin.
-#[derive(Resource, Debug, Copy, Clone)]
+#[derive(Resource, Debug, Copy, Clone, Reflect)]
+#[reflect(Resource)]
 pub struct NorthstarPluginSettings {
     /// The maximum number of agents that can be processed per frame.
     /// This is useful to stagger the pathfinding and collision avoidance systems
@@ -27,6 +48,263 @@ impl Default for NorthstarPluginSettings {
     }
 }
 
+/// The `PathfindingBudget` `Resource` limits how much pathfinding work the [`NorthstarPlugin`] does per frame.
+///
+/// New [`Pathfind`] requests are queued first in, first out and serviced until the budget is spent,
+/// the remaining requests are deferred to the following frames.
+/// At least one request is serviced every frame so the queue always makes progress.
+#[derive(Resource, Debug, Default, Copy, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct PathfindingBudget {
+    /// The maximum number of paths computed per frame.
+    /// Falls back to [`NorthstarPluginSettings::max_pathfinding_agents_per_frame`] when `None`.
+    pub max_paths: Option<usize>,
+    /// The maximum time spent computing paths per frame.
+    pub max_time: Option<Duration>,
+    /// Insert the [`PathPending`] marker on agents while their request waits in the queue.
+    pub mark_pending: bool,
+}
+
+/// The `ParallelPathfinding` `Resource` toggles computing paths for multiple agents at the same time
+/// on Bevy's [`ComputeTaskPool`]. Enabled by default.
+///
+/// Requests are still serviced in queue order and the results are applied in the same order either way,
+/// disable it if you'd rather keep all pathfinding on the system's thread.
+/// When enabled, the [`PathfindingBudget::max_time`] is checked between batches of requests rather than between every request.
+#[derive(Resource, Debug, Copy, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct ParallelPathfinding(pub bool);
+
+impl Default for ParallelPathfinding {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// The `AvoidancePolicy` `Resource` tunes how agents react when the next position in their path is occupied
+/// by another [`Blocking`] entity while collision avoidance is enabled.
+#[derive(Resource, Debug, Default, Copy, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct AvoidancePolicy {
+    /// The number of wait steps an agent takes before trying to path around the blocked position.
+    /// A wait step inserts a [`NextPos`] equal to the agent's current position, so your movement system
+    /// should handle the agent standing still for a step.
+    ///
+    /// Waiting avoids recomputing paths when two agents only briefly contest a cell. Defaults to 0, which reroutes immediately.
+    pub wait_ticks: u32,
+}
+
+/// The `RerouteConfig` `Resource` limits how often agents search a new path after local avoidance fails,
+/// so an agent pinned in place doesn't keep rerouting every frame.
+#[derive(Resource, Debug, Default, Copy, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct RerouteConfig {
+    /// The number of reroutes an agent may attempt before [`RerouteFailed`] is inserted and it stops retrying.
+    /// The count resets when the agent steps to a new cell or gets a new [`Pathfind`] request.
+    /// Defaults to `None`, which keeps rerouting for as long as a path is found.
+    pub max_attempts: Option<u32>,
+    /// The number of frames an agent waits in place after a reroute before it may reroute again. Defaults to 0.
+    pub cooldown_ticks: u32,
+}
+
+/// The `KeepStalePathOnFailure` `Resource` keeps agents moving along their old [`Path`] when a reroute fails,
+/// instead of inserting [`RerouteFailed`] and leaving them frozen in place.
+///
+/// After a failed reroute the agent steps to the next cell of its old path if that cell is free,
+/// and searches again the next time local avoidance fails, waiting in place while the next cell is taken.
+/// [`RerouteConfig`] still applies, so the cooldown spaces out the attempts and running out of attempts
+/// inserts [`RerouteFailed`] as usual.
+///
+/// The old path may lead into a cell that has since been blocked. Another agent standing on the path
+/// is handled by the next avoidance pass, which waits, steps around it locally or reroutes again.
+/// A cell that became impassable is repaired around, or fails with [`PathError::PathInvalidated`] when it can't be.
+///
+/// Defaults to false.
+#[derive(Resource, Debug, Default, Copy, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct KeepStalePathOnFailure(pub bool);
+
+/// The `AvoidanceLookahead` `Resource` sets how many steps ahead along their paths agents look for conflicts
+/// with other [`Blocking`] agents while collision avoidance is enabled.
+///
+/// When another agent is going to reach a cell on an agent's path at about the same step, the agent with the lower
+/// [`PathPriority`], or the lower entity index, reroutes around that cell early instead of stopping once they're next to each other.
+/// Agents heading the same way don't conflict, and only conflicts within the grid's avoidance distance are rerouted around.
+/// If there's no way around, the agent keeps its path and the usual avoidance applies.
+///
+/// Defaults to 0, which only checks the next position.
+#[derive(Resource, Debug, Default, Copy, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct AvoidanceLookahead(pub u32);
+
+/// The `SmoothNextPos` `Resource` stops agents from stepping straight back to the cell they just left,
+/// which looks like stuttering when a reroute or avoidance path starts by backtracking one cell.
+///
+/// When the next step leads back to the cell the agent came from and one of the next few cells along the path
+/// can be stepped to directly, the agent skips ahead to it instead. The backtrack is kept when there's no such cell.
+/// Only applies to single cell agents moved with [`NextPos`] outside of cooperative planning.
+///
+/// Defaults to false.
+#[derive(Resource, Debug, Default, Copy, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct SmoothNextPos(pub bool);
+
+/// The `AutoAdvance` `Resource` removes [`NextPos`] once the agent's [`AgentPos`] has reached it,
+/// so moving the agent and updating its [`AgentPos`] is enough to be given the next step of its path.
+///
+/// The check runs at the start of [`PathingStage::Advance`], so an agent whose [`AgentPos`] is updated
+/// before [`PathingSet`] gets its next [`NextPos`] in the same frame. Agents waiting in place are given
+/// a [`NextPos`] at their own position, which is removed and re-evaluated every frame while they wait.
+/// Leave it off to remove [`NextPos`] yourself, for example to hold an agent on a cell for a few frames.
+///
+/// Defaults to false.
+#[derive(Resource, Debug, Default, Copy, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct AutoAdvance(pub bool);
+
+/// The `CoalescePathRequests` `Resource` computes identical path requests made in the same frame only once,
+/// such as a squad that all stand on the same cell and share their leader's target.
+///
+/// Requests are identical when the agents share the start position, the whole [`Pathfind`] request,
+/// their [`AgentSize`] and their [`IgnoreBlocking`] list. Every requester gets a copy of the same [`Path`].
+/// Requests differing in anything else, such as their layers, are computed separately.
+/// Only the first request counts towards the [`PathfindingBudget`].
+/// [`AsyncPathfind`] requests and agents being debugged with `DebugSearch` are never coalesced.
+///
+/// Defaults to false.
+#[derive(Resource, Debug, Default, Copy, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct CoalescePathRequests(pub bool);
+
+/// The `CooperativeConfig` `Resource` enables cooperative pathfinding for agents with the [`Blocking`] component.
+/// It isn't inserted by default, insert it to opt in.
+///
+/// Instead of reactively rerouting around each other, cooperative agents plan their next moves around the
+/// future positions of the other agents using a space-time reservation table (Windowed Hierarchical Cooperative A*).
+/// Every step each agent reserves the cells it will occupy over the next `window` moves and agents planned after it
+/// avoid those cells, waiting in place when they need to let another agent through.
+/// This resolves situations the regular collision avoidance can deadlock on, like two agents crossing through a 1-wide corridor.
+///
+/// Agents are planned in a fixed priority order and only follow their [`Path`], they won't take a different route if
+/// their way is reserved for the whole window. Agents without [`Blocking`] keep using the regular collision avoidance.
+#[derive(Resource, Debug, Copy, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct CooperativeConfig {
+    /// The number of moves each agent plans and reserves ahead.
+    /// Larger windows resolve longer conflicts, like long corridors, at the cost of a bigger search per agent.
+    pub window: u32,
+}
+
+impl Default for CooperativeConfig {
+    fn default() -> Self {
+        Self { window: 16 }
+    }
+}
+
+/// The `TrailConfig` `Resource` makes agents leave a decaying trail of extra movement cost behind them.
+/// It isn't inserted by default, insert it to opt in.
+///
+/// Every frame each agent following a [`Path`], unless [`PathfindingPaused`], adds `deposit` to the [`TrailField`] at the cells it covers and
+/// every value in the field is multiplied by `decay`. The rounded values are added on top of the cell costs
+/// by the searches, see [`Grid::set_trail_costs`], so agents spread out over cheaper routes next to busy ones
+/// and crowds form lanes. A cell walked on every frame settles at a cost of about `deposit / (1.0 - decay)`.
+/// Removing the resource clears the trails.
+#[derive(Resource, Debug, Copy, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct TrailConfig {
+    /// The cost added to the cells under each moving agent every frame.
+    pub deposit: f32,
+    /// The fraction of the trail kept every frame, between `0.0` and `1.0`. Lower values fade faster.
+    pub decay: f32,
+}
+
+impl Default for TrailConfig {
+    fn default() -> Self {
+        Self {
+            deposit: 1.0,
+            decay: 0.9,
+        }
+    }
+}
+
+/// The `TrailField` `Resource` holds the trail cost left behind by agents while [`TrailConfig`] is inserted.
+/// Cells are dropped once their trail fades below half a cost point.
+#[derive(Resource, Debug, Default, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct TrailField(pub HashMap<UVec3, f32>);
+
+impl TrailField {
+    /// Returns the trail cost at `pos`, `0.0` if no agent has passed recently.
+    pub fn get(&self, pos: UVec3) -> f32 {
+        self.0.get(&pos).copied().unwrap_or_default()
+    }
+}
+
+/// The `NorthstarRng` `Resource` is the seeded random number generator behind any randomness in the plugin systems,
+/// so a run can be reproduced in tests and replays by inserting it with the same seed.
+///
+/// None of the built-in systems draw from it yet. Searches break ties by cell order, and collision avoidance and
+/// [`CooperativeConfig`] planning process agents in a fixed order, so the plugin already gives the same results for
+/// the same inputs. Systems that add randomness will take it from this resource, and it's a convenient source for
+/// your own, for example [`Grid::random_passable_cell`] accepts it directly.
+///
+/// For deterministic multiplayer, insert it with a seed shared by every peer and enable the `deterministic` feature.
+/// The generator is a SplitMix64 implementing [`RngCore`], it gives the same sequence on every platform
+/// but isn't suitable for anything security related.
+///
+/// Requires the `rng` feature.
+#[cfg(feature = "rng")]
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct NorthstarRng {
+    seed: u64,
+    state: u64,
+}
+
+#[cfg(feature = "rng")]
+impl NorthstarRng {
+    /// Creates a generator that starts from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+
+    /// Returns the seed the generator was created with or last reseeded with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restarts the generator from `seed`, such as when a replay or a new match begins.
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+}
+
+#[cfg(feature = "rng")]
+impl Default for NorthstarRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+#[cfg(feature = "rng")]
+impl RngCore for NorthstarRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dst);
+    }
+}
+
 /// NorthstarPlugin is the main plugin for the Northstar pathfinding and collision avoidance systems.
 ///
 #[derive(Default)]
@@ -35,7 +313,7 @@ pub struct NorthstarPlugin<N: Neighborhood> {
 }
 
 /// Tracks the average time the pathfinding algorithm takes.
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Reflect)]
 pub struct PathfindingStats {
     /// The average time taken for pathfinding in seconds.
     pub average_time: f64,
@@ -48,7 +326,7 @@ pub struct PathfindingStats {
 }
 
 /// Tracks the average time the collision avoidance algorithms take.
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Reflect)]
 pub struct CollisionStats {
     /// The average time taken for collision avoidance in seconds.
     pub average_time: f64,
@@ -61,7 +339,8 @@ pub struct CollisionStats {
 }
 
 /// The `Stats` `Resource` holds the pathfinding and collision avoidance statistics.
-#[derive(Resource, Default, Debug)]
+#[derive(Resource, Default, Debug, Reflect)]
+#[reflect(Resource)]
 pub struct Stats {
     /// Pathfinding frame time statistics.
     pub pathfinding: PathfindingStats,
@@ -113,28 +392,110 @@ impl Stats {
 
 impl<N: 'static + Neighborhood> Plugin for NorthstarPlugin<N> {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.configure_sets(
             Update,
             (
-                tag_pathfinding_requests,
-                update_blocking_map,
-                pathfind::<N>,
-                next_position::<N>,
-                reroute_path::<N>,
+                PathingStage::Plan,
+                PathingStage::Advance,
+                PathingStage::Reroute,
             )
                 .chain()
                 .in_set(PathingSet),
         )
+        .add_systems(
+            Update,
+            (
+                (
+                    poll_grid_builds::<N>,
+                    track_target_entities,
+                    update_goal_index,
+                    tag_pathfinding_requests,
+                    tick_temporary_blocks::<N>,
+                    update_blocking_map::<N>,
+                    update_trails::<N>.run_if(resource_exists::<TrailConfig>),
+                    clear_trails::<N>.run_if(resource_removed::<TrailConfig>),
+                    evict_flow_fields::<N>,
+                    pathfind::<N>,
+                    poll_pathfinding_tasks,
+                )
+                    .chain()
+                    .in_set(PathingStage::Plan),
+                (
+                    auto_advance,
+                    cooperative_next_position::<N>.run_if(resource_exists::<CooperativeConfig>),
+                    accumulate_agent_speed.run_if(not(resource_exists::<CooperativeConfig>)),
+                    next_position::<N>,
+                    apply_agent_speed::<N>.run_if(not(resource_exists::<CooperativeConfig>)),
+                    update_next_dir,
+                )
+                    .chain()
+                    .in_set(PathingStage::Advance),
+                reroute_path::<N>.in_set(PathingStage::Reroute),
+            ),
+        )
         .insert_resource(NorthstarPluginSettings::default())
+        .insert_resource(PathfindingBudget::default())
+        .insert_resource(ParallelPathfinding::default())
+        .insert_resource(PathfindingQueue::default())
         .insert_resource(BlockingMap::default())
+        .insert_resource(GoalIndex::default())
+        .insert_resource(TrailField::default())
+        .insert_resource(AvoidancePolicy::default())
+        .insert_resource(AvoidanceLookahead::default())
+        .insert_resource(SmoothNextPos::default())
+        .insert_resource(AutoAdvance::default())
+        .insert_resource(CoalescePathRequests::default())
+        .insert_resource(RerouteConfig::default())
+        .insert_resource(KeepStalePathOnFailure::default())
         .insert_resource(Stats::default())
         .insert_resource(DirectionMap::default())
+        .insert_resource(FlowFieldCache::default())
+        .insert_resource(HierarchyBuildProgress::default())
         .register_type::<Path>()
         .register_type::<Pathfind>()
+        .register_type::<AsyncPathfind>()
+        .register_type::<PathPriority>()
         .register_type::<PathfindMode>()
+        .register_type::<PathError>()
         .register_type::<NextPos>()
+        .register_type::<NextDir>()
+        .register_type::<ManualAdvance>()
+        .register_type::<PathfindingPaused>()
+        .register_type::<AgentPos>()
+        .register_type::<AgentSpeed>()
+        .register_type::<AgentSize>()
         .register_type::<AgentOfGrid>()
-        .register_type::<GridAgents>();
+        .register_type::<GridAgents>()
+        .register_type::<Blocking>()
+        .register_type::<IgnoreBlocking>()
+        .register_type::<PathPending>()
+        .register_type::<PathfindingInProgress>()
+        .register_type::<AvoidanceFailed>()
+        .register_type::<PathfindingFailed>()
+        .register_type::<RerouteFailed>()
+        .register_type::<SearchTrace>()
+        .register_type::<NorthstarPluginSettings>()
+        .register_type::<PathfindingBudget>()
+        .register_type::<ParallelPathfinding>()
+        .register_type::<AvoidancePolicy>()
+        .register_type::<AvoidanceLookahead>()
+        .register_type::<SmoothNextPos>()
+        .register_type::<AutoAdvance>()
+        .register_type::<CoalescePathRequests>()
+        .register_type::<RerouteConfig>()
+        .register_type::<KeepStalePathOnFailure>()
+        .register_type::<CooperativeConfig>()
+        .register_type::<TrailConfig>()
+        .register_type::<TrailField>()
+        .register_type::<Stats>()
+        .register_type::<BlockingMap>()
+        .register_type::<DirectionMap>()
+        .register_type::<GoalIndex>()
+        .register_type::<HierarchyBuildProgress>();
+
+        #[cfg(feature = "rng")]
+        app.init_resource::<NorthstarRng>()
+            .register_type::<NorthstarRng>();
     }
 }
 
@@ -143,405 +504,3747 @@ impl<N: 'static + Neighborhood> Plugin for NorthstarPlugin<N> {
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PathingSet;
 
+/// The stages of the [`PathingSet`], run in the order they're declared.
+/// Use them to slot your own systems in between the pathfinding systems, for example to start a walk
+/// animation as soon as [`NextPos`] is inserted but before blocked agents are rerouted.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathingStage {
+    /// Polls grid builds, updates the [`BlockingMap`] and computes the [`Path`] for [`Pathfind`] requests.
+    Plan,
+    /// Pops the next step of each [`Path`] into [`NextPos`] and [`NextDir`], avoiding [`Blocking`] agents on the way.
+    Advance,
+    /// Searches a new [`Path`] for agents that local avoidance marked with [`AvoidanceFailed`].
+    Reroute,
+}
+
 /// The `BlockingMap` `Resource` contains a map of positions of entities holding the `Blocking` component.
+/// Agents with an [`AgentSize`] occupy every cell they cover.
 /// The map is rebuilt every frame at the beginning of the `PathingSet`.
-#[derive(Resource, Default)]
+/// Cells blocked with [`Grid::block_temporarily`] are included with [`Entity::PLACEHOLDER`] as the entity.
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
 pub struct BlockingMap(pub HashMap<UVec3, Entity>);
 
 /// The `DirectionMap` `Resource` contains a map of every pathfinding entity's last moved direction.
 /// This is mainly used for collision avoidance but could be used for other purposes.
-#[derive(Resource, Default)]
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
 pub struct DirectionMap(pub HashMap<Entity, Vec3>);
 
+/// The `GoalIndex` `Resource` indexes the agents with a [`Pathfind`] by the cells they're heading to,
+/// for questions like how many units are heading to an objective without scanning every agent.
+///
+/// The index is updated at the beginning of the `PathingSet` whenever a [`Pathfind`] is added, changed or removed,
+/// including the goals of agents chasing a target. An agent is heading to every goal of a [`Pathfind::new_multi`] request
+/// and every cell of a [`Pathfind::new_region`] request. Results are sorted by entity.
+#[derive(Resource, Default, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct GoalIndex {
+    cells: HashMap<UVec3, Vec<Entity>>,
+    regions: HashMap<Entity, (UVec3, UVec3)>,
+    agents: HashMap<Entity, IndexedGoal>,
+}
+
+#[derive(Debug, Reflect)]
+struct IndexedGoal {
+    goals: Vec<UVec3>,
+    grid: Option<Entity>,
+}
+
+impl GoalIndex {
+    /// Returns the agents heading to `cell`.
+    pub fn agents_to(&self, cell: UVec3) -> Vec<Entity> {
+        let mut agents = self.cells.get(&cell).cloned().unwrap_or_default();
+        agents.extend(self.regions.iter().filter_map(|(entity, (min, max))| {
+            (cell.cmpge(*min).all() && cell.cmple(*max).all()).then_some(*entity)
+        }));
+
+        agents.sort_unstable();
+        agents
+    }
+
+    /// Returns the agents heading to any cell in the inclusive region from `min` to `max`.
+    pub fn agents_to_region(&self, min: UVec3, max: UVec3) -> Vec<Entity> {
+        let (min, max) = (min.min(max), min.max(max));
+        let inside = |cell: &UVec3| cell.cmpge(min).all() && cell.cmple(max).all();
+
+        let mut agents: Vec<Entity> = self
+            .cells
+            .iter()
+            .filter(|(cell, _)| inside(cell))
+            .flat_map(|(_, agents)| agents.iter().copied())
+            .chain(
+                self.regions
+                    .iter()
+                    .filter(|(_, (goal_min, goal_max))| {
+                        goal_min.cmple(max).all() && goal_max.cmpge(min).all()
+                    })
+                    .map(|(entity, _)| *entity),
+            )
+            .collect();
+
+        agents.sort_unstable();
+        agents.dedup();
+        agents
+    }
+
+    /// Returns the agents of the grid entity `grid` heading to `cell`, see [`AgentOfGrid`].
+    pub fn grid_agents_to(&self, grid: Entity, cell: UVec3) -> Vec<Entity> {
+        let mut agents = self.agents_to(cell);
+        agents.retain(|entity| self.agents[entity].grid == Some(grid));
+        agents
+    }
+
+    /// Returns the number of agents heading to `cell`.
+    pub fn count_to(&self, cell: UVec3) -> usize {
+        self.agents_to(cell).len()
+    }
+
+    fn insert(&mut self, entity: Entity, pathfind: &Pathfind, grid: Option<Entity>) {
+        self.remove(entity);
+
+        let goals = pathfind.goals().to_vec();
+        for goal in &goals {
+            let agents = self.cells.entry(*goal).or_default();
+            if !agents.contains(&entity) {
+                agents.push(entity);
+            }
+        }
+
+        if let Some(region) = pathfind.region {
+            self.regions.insert(entity, region);
+        }
+        self.agents.insert(entity, IndexedGoal { goals, grid });
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        self.regions.remove(&entity);
+        let Some(goal) = self.agents.remove(&entity) else {
+            return;
+        };
+
+        for cell in goal.goals {
+            if let Some(agents) = self.cells.get_mut(&cell) {
+                agents.retain(|agent| *agent != entity);
+                if agents.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+}
+
+/// The `FlowFieldCache` `Resource` holds the [`FlowField`]s shared by agents using [`PathfindMode::FlowField`].
+/// Fields are keyed by the entity of the [`Grid`] they were computed on and their goal,
+/// and recomputed when the grid changes in the chunks they cover.
+/// Fields are dropped once their grid is despawned or no agent is pathfinding to their goal
+/// with [`PathfindMode::FlowField`] any more.
+#[derive(Resource, Default)]
+pub struct FlowFieldCache(pub HashMap<(Entity, UVec3), FlowField>);
+
+/// The `HierarchyBuildProgress` `Resource` reports the progress of the grids being built with [`Grid::build_async`],
+/// from 0.0 to 1.0. Insert the [`GridBuildTask`] on your map entity and read this to drive a loading bar.
+/// With several grids building at once this is the progress of the slowest one. Stays at 1.0 while nothing is building.
+#[derive(Resource, Debug, Clone, Copy, Reflect)]
+#[reflect(Resource)]
+pub struct HierarchyBuildProgress(pub f32);
+
+impl Default for HierarchyBuildProgress {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
 #[derive(Component)]
 #[component(storage = "SparseSet")]
 pub(crate) struct NeedsPathfinding;
 
-// Flags all the entities with a changed `Pathfind` component to request pathfinding.
-fn tag_pathfinding_requests(mut commands: Commands, query: Query<Entity, Changed<Pathfind>>) {
-    for entity in query.iter() {
-        commands.entity(entity).insert(NeedsPathfinding);
-    }
+// The number of wait steps an agent has taken in a row because its next position was blocked.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub(crate) struct AvoidanceWait(u32);
+
+// The reroutes an agent has attempted since it last stepped to a new cell, and the frame it may reroute again after.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub(crate) struct RerouteAttempts {
+    attempts: u32,
+    ready_at: u32,
 }
 
-// The main pathfinding system. Queries for entities with the a changed `Pathfind` component.
-// It will pathfind to the goal position and insert a `Path` component with the path found.
-fn pathfind<N: Neighborhood + 'static>(
-    grid: Single<&Grid<N>>,
+// The progress towards its next cell an agent with an `AgentSpeed` has built up, in cells.
+#[derive(Component, Default)]
+pub(crate) struct SpeedProgress(f32);
+
+// Holds back an agent with an `AgentSpeed` that hasn't built up the progress for a whole cell yet.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub(crate) struct SpeedStalled;
+
+// A running `AsyncPathfind` search and when it was started.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub(crate) struct PathfindTask {
+    task: Task<PathResult>,
+    started: bevy::platform::time::Instant,
+}
+
+// Pathfinding requests in the order they were made.
+#[derive(Resource, Default)]
+pub(crate) struct PathfindingQueue(VecDeque<Entity>);
+
+// Inserts the grids built in the background once they're done and reports the progress of the rest.
+fn poll_grid_builds<N: Neighborhood + 'static>(
     mut commands: Commands,
-    query: Query<(Entity, &AgentPos, &Pathfind), With<NeedsPathfinding>>,
-    blocking: Res<BlockingMap>,
-    settings: Res<NorthstarPluginSettings>,
-    //mut queue: Local<VecDeque<Entity>>,
-    #[cfg(feature = "stats")] mut stats: ResMut<Stats>,
+    mut builds: Query<(Entity, &mut GridBuildTask<N>)>,
+    mut progress: ResMut<HierarchyBuildProgress>,
 ) {
-    let grid = grid.into_inner();
+    if builds.is_empty() {
+        return;
+    }
 
-    // Limit the number of agents processed per frame to prevent stutters
-    let mut count = 0;
+    let mut slowest: f32 = 1.0;
 
-    for (entity, start, pathfind) in &query {
-        if count >= settings.max_pathfinding_agents_per_frame {
-            return;
+    for (entity, mut build) in &mut builds {
+        match build.poll() {
+            Some(grid) => {
+                commands
+                    .entity(entity)
+                    .remove::<GridBuildTask<N>>()
+                    .insert(grid);
+            }
+            None => slowest = slowest.min(build.progress()),
         }
+    }
+
+    progress.0 = slowest;
+}
 
-        if start.0 == pathfind.goal {
-            commands.entity(entity).remove::<Pathfind>();
-            commands.entity(entity).remove::<NeedsPathfinding>();
+// Points the goal of `Pathfind::target_entity` requests at the position of their target.
+// The goal only follows the target once it strays further than the `repath_threshold`, changing the goal requests a new path.
+// Requests whose target is gone are removed and fail with `PathError::TargetLost`.
+fn track_target_entities(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Pathfind)>,
+    targets: Query<&AgentPos>,
+) {
+    for (entity, mut pathfind) in &mut query {
+        let Some(target) = pathfind.target else {
             continue;
-        }
+        };
 
-        #[cfg(feature = "stats")]
-        let start_time = Instant::now();
+        let Ok(target_pos) = targets.get(target) else {
+            commands
+                .entity(entity)
+                .remove::<(Pathfind, Path, NextPos)>()
+                .insert(PathfindingFailed);
 
-        let blocking = if grid.collision() {
-            &blocking.0
-        } else {
-            &HashMap::new()
+            commands.trigger_targets(
+                events::PathfindingFailed {
+                    entity,
+                    reason: PathError::TargetLost,
+                },
+                entity,
+            );
+            continue;
         };
 
-        let path = match pathfind.mode {
-            PathfindMode::Refined => {
-                grid.pathfind(start.0, pathfind.goal, blocking, pathfind.partial)
-            }
-            PathfindMode::Coarse => {
-                grid.pathfind_coarse(start.0, pathfind.goal, blocking, pathfind.partial)
-            }
-            PathfindMode::AStar => {
-                grid.pathfind_astar(start.0, pathfind.goal, blocking, pathfind.partial)
-            }
-        };
+        let strayed = (target_pos.0.as_ivec3() - pathfind.goal.as_ivec3())
+            .abs()
+            .max_element() as u32;
 
-        #[cfg(feature = "stats")]
-        let elapsed_time = start_time.elapsed().as_secs_f64();
+        if pathfind.is_added() || strayed > pathfind.repath_threshold {
+            pathfind.goal = target_pos.0;
+        }
+    }
+}
 
-        if let Some(path) = path {
-            #[cfg(feature = "stats")]
-            stats.add_pathfinding(elapsed_time, path.cost() as f64);
+// Flags all the entities with a changed `Pathfind` component to request pathfinding and queues them.
+// Keeps the `GoalIndex` up to date with the `Pathfind` goals that were added, changed or removed.
+#[allow(clippy::type_complexity)]
+fn update_goal_index(
+    mut index: ResMut<GoalIndex>,
+    changed: Query<
+        (Entity, &Pathfind, Option<&AgentOfGrid>),
+        Or<(Changed<Pathfind>, Changed<AgentOfGrid>)>,
+    >,
+    mut removed: RemovedComponents<Pathfind>,
+) {
+    for entity in removed.read() {
+        index.remove(entity);
+    }
 
-            commands
-                .entity(entity)
-                .insert(path)
-                .remove::<PathfindingFailed>()
-                .remove::<NeedsPathfinding>();
-            // We remove PathfindingFailed even if it's not there.
-        } else {
-            #[cfg(feature = "stats")]
-            stats.add_pathfinding(elapsed_time, 0.0);
+    for (entity, pathfind, grid) in &changed {
+        index.insert(entity, pathfind, grid.map(|grid| grid.0));
+    }
+}
 
-            commands
-                .entity(entity)
-                .insert(PathfindingFailed)
-                .remove::<NeedsPathfinding>()
-                .remove::<NextPos>(); // Just to be safe
+fn tag_pathfinding_requests(
+    mut commands: Commands,
+    query: Query<(Entity, Has<NeedsPathfinding>), Changed<Pathfind>>,
+    mut queue: ResMut<PathfindingQueue>,
+    budget: Res<PathfindingBudget>,
+) {
+    for (entity, queued) in query.iter() {
+        // Cancel any search still running for the old request and forget its reroutes
+        commands
+            .entity(entity)
+            .remove::<(PathfindTask, PathfindingInProgress, RerouteAttempts)>();
+
+        if queued {
+            // Already waiting, the new request replaces the old one in place
+            continue;
+        }
+
+        commands.entity(entity).insert(NeedsPathfinding);
+        if budget.mark_pending {
+            commands.entity(entity).insert(PathPending);
         }
 
-        count += 1;
+        queue.0.push_back(entity);
     }
 }
 
-// The `next_position` system is responsible for popping the front of the path into a `NextPos` component.
-// If collision is enabled it will check for nearyby blocked paths and reroute the path if necessary.
+// Drops the cached flow fields of despawned grids and of goals no flow field request is heading to any more.
+fn evict_flow_fields<N: Neighborhood + 'static>(
+    mut flow_fields: ResMut<FlowFieldCache>,
+    mut removed_grids: RemovedComponents<Grid<N>>,
+    requests: Query<&Pathfind>,
+) {
+    let removed = removed_grids.read().collect::<HashSet<_>>();
+    if flow_fields.0.is_empty() {
+        return;
+    }
+
+    let goals = requests
+        .iter()
+        .filter(|pathfind| pathfind.mode == PathfindMode::FlowField)
+        .map(|pathfind| pathfind.goal)
+        .collect::<HashSet<_>>();
+
+    let evicted = |(grid, goal): &(Entity, UVec3)| removed.contains(grid) || !goals.contains(goal);
+    if flow_fields.0.keys().any(evicted) {
+        flow_fields.0.retain(|key, _| !evicted(key));
+    }
+}
+
+// The main pathfinding system. Services the queued pathfinding requests within the `PathfindingBudget`.
+// Requests are computed in batches, in parallel on the `ComputeTaskPool` if `ParallelPathfinding` is enabled.
+// It will pathfind to the goal position and insert a `Path` component with the path found.
+// `AsyncPathfind` requests are spawned on the `AsyncComputeTaskPool` instead, sharing a snapshot of the grid.
+// With `CoalescePathRequests` identical requests in the same frame are searched once and share the path.
 #[allow(clippy::too_many_arguments)]
 #[allow(clippy::type_complexity)]
-fn next_position<N: Neighborhood + 'static>(
-    mut query: Query<
-        (Entity, &mut Path, &AgentPos, &Pathfind),
-        (WithoutPathingFailures, Without<NextPos>),
-    >,
-    grid: Single<&Grid<N>>,
-    mut blocking: ResMut<BlockingMap>,
-    mut direction: ResMut<DirectionMap>,
+fn pathfind<N: Neighborhood + 'static>(
+    grid: Single<(Entity, Ref<Grid<N>>)>,
+    mut snapshot: Local<Option<Arc<Grid<N>>>>,
     mut commands: Commands,
+    query: Query<
+        (
+            Entity,
+            &AgentPos,
+            &Pathfind,
+            Has<AsyncPathfind>,
+            Option<&AgentSize>,
+            Option<&IgnoreBlocking>,
+        ),
+        With<NeedsPathfinding>,
+    >,
+    blocking: Res<BlockingMap>,
+    mut flow_fields: ResMut<FlowFieldCache>,
     settings: Res<NorthstarPluginSettings>,
-    mut queue: Local<VecDeque<Entity>>,
+    budget: Res<PathfindingBudget>,
+    parallel: Res<ParallelPathfinding>,
+    coalesce: Res<CoalescePathRequests>,
+    mut queue: ResMut<PathfindingQueue>,
+    #[cfg(feature = "gui-debug")] debug_searches: Query<(), With<DebugSearch>>,
     #[cfg(feature = "stats")] mut stats: ResMut<Stats>,
 ) {
+    let (grid_entity, grid) = grid.into_inner();
+    if grid.is_changed() {
+        *snapshot = None;
+    }
     let grid = grid.into_inner();
 
-    // Initialize the queue with all candidates once
-    if queue.is_empty() {
-        for (entity, ..) in query.iter() {
-            queue.push_back(entity);
-        }
-    }
+    let empty = HashMap::new();
+    let blocking = if grid.collision() {
+        &blocking.0
+    } else {
+        &empty
+    };
 
-    let mut processed = 0;
+    // Limit the work done per frame to prevent stutters
+    let frame_start = bevy::platform::time::Instant::now();
+    let max_paths = budget
+        .max_paths
+        .unwrap_or(settings.max_pathfinding_agents_per_frame);
+    let mut count = 0;
 
-    for _ in 0..queue.len() {
-        if processed >= settings.max_collision_avoidance_agents_per_frame {
-            break;
+    let batch_size = if parallel.0 {
+        ComputeTaskPool::get_or_init(TaskPool::default)
+            .thread_num()
+            .max(1)
+    } else {
+        1
+    };
+    let mut batch = Vec::with_capacity(batch_size);
+    // The requests searched this frame by start and goal, identical requests share their results
+    let mut coalesced: HashMap<(UVec3, UVec3), Vec<CoalescedRequest>> = HashMap::new();
+
+    loop {
+        if let Some(max_time) = budget.max_time {
+            if count > 0 && frame_start.elapsed() >= max_time {
+                break;
+            }
         }
 
-        let entity = queue.pop_front().unwrap();
+        batch.clear();
 
-        // If the entity still exists and is valid
-        if let Ok((entity, mut path, position, pathfind)) = query.get_mut(entity) {
-            if position.0 == pathfind.goal {
-                commands.entity(entity).remove::<Path>();
-                commands.entity(entity).remove::<Pathfind>();
+        while batch.len() < batch_size && count + batch.len() < max_paths {
+            let Some(entity) = queue.0.pop_front() else {
+                break;
+            };
+
+            // The entity was despawned or the request was removed while queued
+            let Ok((entity, start, pathfind, is_async, size, ignore)) = query.get(entity) else {
+                if let Ok(mut entity_commands) = commands.get_entity(entity) {
+                    entity_commands.remove::<(NeedsPathfinding, PathPending)>();
+                }
                 continue;
-            }
+            };
 
-            let next = if grid.collision() {
-                #[cfg(feature = "stats")]
-                let start = Instant::now();
+            let blocking = without_ignored(blocking, ignore);
 
-                let success = avoidance(
-                    grid,
+            if reached_goal(grid, pathfind, start.0, &blocking) {
+                commands.entity(entity).remove::<Pathfind>();
+                commands
+                    .entity(entity)
+                    .remove::<(NeedsPathfinding, PathPending)>();
+                commands.trigger_targets(
+                    PathCompleted {
+                        entity,
+                        goal: start.0,
+                    },
                     entity,
-                    &mut path,
-                    pathfind,
-                    position.0,
-                    &blocking.0,
-                    &direction.0,
-                    grid.avoidance_distance() as usize,
                 );
+                continue;
+            }
 
-                if !success {
-                    commands.entity(entity).insert(AvoidanceFailed);
-                    continue;
-                }
+            // Searches are only recorded for agents flagged for debugging
+            #[cfg(feature = "gui-debug")]
+            let trace = debug_searches.contains(entity);
+            #[cfg(not(feature = "gui-debug"))]
+            let trace = false;
 
-                #[cfg(feature = "stats")]
-                let elapsed = start.elapsed().as_secs_f64();
-                #[cfg(feature = "stats")]
-                stats.add_collision(elapsed, path.cost() as f64);
-
-                processed += 1;
-                path.pop()
-            } else {
-                path.pop()
-            };
-
-            if let Some(next) = next {
-                direction
-                    .0
-                    .insert(entity, next.as_vec3() - position.0.as_vec3());
+            if is_async {
+                let snapshot = snapshot
+                    .get_or_insert_with(|| Arc::new(grid.clone()))
+                    .clone();
+                let (start, pathfind, blocking) =
+                    (start.0, pathfind.clone(), blocking.into_owned());
+                let size = size.copied().unwrap_or_default();
 
-                if blocking.0.contains_key(&next) && grid.collision() {
-                    // Someone beat us to it - requeue without inserting NextPos
-                    queue.push_back(entity);
-                    continue;
-                }
+                let task = AsyncComputeTaskPool::get_or_init(TaskPool::default).spawn(async move {
+                    timed_path(&snapshot, start, &pathfind, size, None, &blocking, trace)
+                });
 
-                blocking.0.remove(&position.0);
-                blocking.0.insert(next, entity);
-                commands.entity(entity).insert(NextPos(next));
+                commands
+                    .entity(entity)
+                    .insert((
+                        PathfindTask {
+                            task,
+                            started: bevy::platform::time::Instant::now(),
+                        },
+                        PathfindingInProgress,
+                    ))
+                    .remove::<(NeedsPathfinding, PathPending)>();
 
-                // Re-queue for next frame
-                queue.push_back(entity);
+                count += 1;
+                continue;
             }
-        }
-    }
-}
-
-// The `avoidance` function does a lookahead on the path, if any blocking entities are found
-// that are moving in the opposite relateive direciton it will attempt a short astar reroute.
-#[allow(clippy::too_many_arguments)]
-fn avoidance<N: Neighborhood + 'static>(
-    grid: &Grid<N>,
-    entity: Entity,
-    path: &mut Path,
-    pathfind: &Pathfind,
-    position: UVec3,
-    blocking: &HashMap<UVec3, Entity>,
-    direction: &HashMap<Entity, Vec3>,
-    avoidance_distance: usize,
-) -> bool {
-    if path.is_empty() {
-        log::error!("Path is empty for entity: {:?}", entity);
-        return false;
-    }
-
-    // Check if the next few positions are blocked
-    let count = if path.path().len() > avoidance_distance {
-        avoidance_distance
-    } else {
-        path.path().len()
-    };
-
-    // Precompute the dot product for the current position
-    let next_position = path.path.front().unwrap();
 
-    if path.path.len() == 1 && blocking.contains_key(next_position) {
-        return false;
-    }
+            let size = size.copied().unwrap_or_default();
 
-    let difference = next_position.as_vec3() - position.as_vec3();
+            if coalesce.0 && !trace {
+                let requests = coalesced.entry((start.0, pathfind.goal)).or_default();
+                let same = requests
+                    .iter_mut()
+                    .find(|request| request.matches(pathfind, size, ignore));
 
-    let unblocked_pos: Vec<UVec3> = path
-        .path
-        .iter()
-        .take(count)
-        .filter(|pos| {
-            if let Some(blocking_entity) = blocking.get(*pos) {
-                // If the blocking entity is the same as the current entity, skip it
-                if *blocking_entity == entity {
-                    return true;
+                match same {
+                    Some(CoalescedRequest {
+                        path: Some(path), ..
+                    }) => {
+                        commands
+                            .entity(entity)
+                            .remove::<(NeedsPathfinding, PathPending)>();
+                        apply_path(&mut commands, entity, PathResult::shared(path.clone()));
+                        continue;
+                    }
+                    // The same request is in this batch, wait for its result
+                    Some(request) => {
+                        request.followers.push(entity);
+                        continue;
+                    }
+                    None => requests.push(CoalescedRequest {
+                        leader: entity,
+                        pathfind,
+                        size,
+                        ignore,
+                        followers: Vec::new(),
+                        path: None,
+                    }),
                 }
+            }
 
-                // Too risky to move to the next position regardless of the direction
-                if *pos == next_position {
-                    return false;
-                }
+            if !size.is_multi_cell() && uses_flow_field(grid, pathfind) {
+                // Reuse the cached field for this goal unless the grid has changed underneath it.
+                // The cache is updated here so the batch can share it immutably.
+                let key = (grid_entity, pathfind.goal);
+                let stale = !matches!(
+                    flow_fields.0.get(&key),
+                    Some(field) if field.is_valid(grid)
+                );
 
-                if let Some(blocking_dir) = direction.get(blocking_entity) {
-                    let dot = difference.dot(*blocking_dir);
-                    if dot <= 0.0 {
-                        return false;
-                    }
-                } else {
-                    // They have no direction
-                    return false;
+                if stale {
+                    flow_fields
+                        .0
+                        .insert(key, grid.compute_flow_field(pathfind.goal));
                 }
             }
-            true
-        })
-        .cloned()
-        .collect();
 
-    // If we have a blocked position in the path, repath
-    if unblocked_pos.len() < count {
-        let avoidance_goal = {
-            let _ = info_span!("avoidance_goal", name = "avoidance_goal").entered();
+            batch.push((entity, start.0, pathfind, size, trace, blocking));
+        }
 
-            // Get the first unlocked position AFTER skipping the count, we don't care about the direction
-            path.path
+        if batch.is_empty() {
+            break;
+        }
+
+        let flow_fields = &flow_fields.0;
+        let results: Vec<PathResult> = if batch.len() > 1 {
+            // Each request only reads the grid, so they can be computed at the same time.
+            // Any writes are applied through `Commands` after the batch completes.
+            ComputeTaskPool::get_or_init(TaskPool::default).scope(|scope| {
+                for (_, start, pathfind, size, trace, blocking) in &batch {
+                    scope.spawn(async move {
+                        let field = flow_fields.get(&(grid_entity, pathfind.goal));
+                        timed_path(grid, *start, pathfind, *size, field, blocking, *trace)
+                    });
+                }
+            })
+        } else {
+            batch
                 .iter()
-                .skip(count)
-                .find(|pos| !blocking.contains_key(&**pos))
+                .map(|(_, start, pathfind, size, trace, blocking)| {
+                    let field = flow_fields.get(&(grid_entity, pathfind.goal));
+                    timed_path(grid, *start, pathfind, *size, field, blocking, *trace)
+                })
+                .collect()
         };
 
-        // If we have an avoidance goal, astar path to that
-        if let Some(avoidance_goal) = avoidance_goal {
-            // Calculate a good radius from the current position to the avoidance goal
-            let radius = position.as_vec3().distance(avoidance_goal.as_vec3()) as u32
-                + grid.avoidance_distance();
+        count += batch.len();
 
-            //let new_path = grid.pathfind_astar(position, *avoidance_goal, blocking, false);
-            let new_path =
-                grid.pathfind_astar_radius(position, *avoidance_goal, radius, blocking, false);
+        for (&(entity, start, pathfind, ..), result) in batch.iter().zip(results) {
+            #[cfg(feature = "stats")]
+            stats.add_pathfinding(
+                result.elapsed.as_secs_f64(),
+                result.path.as_ref().map_or(0.0, |path| path.cost() as f64),
+            );
 
-            // Replace the first few positions of path until the avoidance goal
-            if let Some(new_path) = new_path {
-                // Get every position AFTER the avoidance goal in the old path
-                let old_path = path
-                    .path
-                    .iter()
-                    .skip_while(|pos| *pos != avoidance_goal)
-                    .cloned()
-                    .collect::<Vec<UVec3>>();
+            if let Some(request) = coalesced
+                .get_mut(&(start, pathfind.goal))
+                .and_then(|requests| requests.iter_mut().find(|request| request.leader == entity))
+            {
+                for follower in request.followers.drain(..) {
+                    commands
+                        .entity(follower)
+                        .remove::<(NeedsPathfinding, PathPending)>();
+                    apply_path(
+                        &mut commands,
+                        follower,
+                        PathResult::shared(result.path.clone()),
+                    );
+                }
+                request.path = Some(result.path.clone());
+            }
 
-                // Combine the new path with the old path
-                let mut combined_path = new_path.path().to_vec();
-                combined_path.extend(old_path);
+            commands
+                .entity(entity)
+                .remove::<(NeedsPathfinding, PathPending)>();
+            apply_path(&mut commands, entity, result);
+        }
+    }
 
-                if combined_path.is_empty() {
-                    log::error!("Combined path is empty for entity: {:?}", entity);
-                    return false;
-                }
+    // Queued requests identical to one searched this frame don't need the budget, hand them the result right away
+    if coalesce.0 && !coalesced.is_empty() {
+        queue.0.retain(|entity| {
+            let Ok((entity, start, pathfind, is_async, size, ignore)) = query.get(*entity) else {
+                return true;
+            };
 
-                let graph_path = path.graph_path.clone();
+            #[cfg(feature = "gui-debug")]
+            if debug_searches.contains(entity) {
+                return true;
+            }
 
-                // Replace the path with the combined path
-                *path = Path::from_slice(&combined_path, new_path.cost());
-                path.graph_path = graph_path;
-            } else {
-                return false;
+            let size = size.copied().unwrap_or_default();
+            let path = coalesced
+                .get(&(start.0, pathfind.goal))
+                .and_then(|requests| {
+                    requests
+                        .iter()
+                        .find(|request| request.matches(pathfind, size, ignore))
+                })
+                .and_then(|request| request.path.clone());
+
+            match path {
+                Some(path) if !is_async => {
+                    commands
+                        .entity(entity)
+                        .remove::<(NeedsPathfinding, PathPending)>();
+                    apply_path(&mut commands, entity, PathResult::shared(path));
+                    false
+                }
+                _ => true,
             }
-        } else {
-            // No easy avoidance astar path to the next entrance
-            return false;
-        }
+        });
     }
+}
 
-    // DELETE THIS
-    // We must have gotten to this point because of partial paths
-    if path.path.is_empty() {
-        // if goal is in blocking
-        if blocking.contains_key(&pathfind.goal) {
-            return false;
-        }
+// Inserts the path found for a request, or marks the request as failed.
+// The recorded search is inserted for agents being debugged.
+fn apply_path(commands: &mut Commands, entity: Entity, result: PathResult) {
+    if let Some(trace) = result.trace {
+        commands.entity(entity).insert(trace);
+    }
 
-        let new_path = grid.pathfind(position, pathfind.goal, blocking, false);
+    match result.path {
+        Ok(path) => {
+            // We remove PathfindingFailed even if it's not there.
+            commands
+                .entity(entity)
+                .insert(path)
+                .remove::<PathfindingFailed>();
+        }
+        Err(reason) => {
+            commands
+                .entity(entity)
+                .insert(PathfindingFailed)
+                .remove::<NextPos>(); // Just to be safe
 
-        if let Some(new_path) = new_path {
-            *path = new_path;
-            return true;
-        } else {
-            return false;
+            commands.trigger_targets(events::PathfindingFailed { entity, reason }, entity);
         }
     }
-
-    true
 }
 
-// The `reroute_path` system is responsible for rerouting the path if the `AvoidanceFailed` component is present.
-// It will attempt to find a new full path to the goal position and insert it into the entity.
-// If the reroute fails, it will insert a `RerouteFailed` component to the entity.
-// Once an entity has a reroute failure, no pathfinding will be attempted until the user handles reinserts the `Pathfind` component.
-fn reroute_path<N: Neighborhood + 'static>(
-    mut query: Query<(Entity, &AgentPos, &Pathfind, &Path), With<AvoidanceFailed>>,
-    grid: Single<&Grid<N>>,
-    blocking: Res<BlockingMap>,
+// Polls the running `AsyncPathfind` searches and applies the results of the finished ones.
+// Dropping a task cancels it, which happens when the agent's `Pathfind` is removed or the search times out.
+fn poll_pathfinding_tasks(
     mut commands: Commands,
-    settings: Res<NorthstarPluginSettings>,
+    mut query: Query<(
+        Entity,
+        &mut PathfindTask,
+        Option<&AsyncPathfind>,
+        Has<Pathfind>,
+    )>,
     #[cfg(feature = "stats")] mut stats: ResMut<Stats>,
 ) {
-    let grid = grid.into_inner();
+    for (entity, mut pathfind_task, async_pathfind, has_pathfind) in &mut query {
+        if !has_pathfind {
+            commands
+                .entity(entity)
+                .remove::<(PathfindTask, PathfindingInProgress)>();
+            continue;
+        }
 
-    for (count, (entity, position, pathfind, path)) in query.iter_mut().enumerate() {
-        // TODO: This doesn't really tie in with the main pathfinding agent counts. This will stil help limit how many are rereouting for now.
-        // There's no point bridging it for the moment since this really needs to be reworked into an async system to really prevent stutters.
-        if count >= settings.max_pathfinding_agents_per_frame {
-            return;
+        if let Some(result) = block_on(future::poll_once(&mut pathfind_task.task)) {
+            #[cfg(feature = "stats")]
+            stats.add_pathfinding(
+                result.elapsed.as_secs_f64(),
+                result.path.as_ref().map_or(0.0, |path| path.cost() as f64),
+            );
+
+            commands
+                .entity(entity)
+                .remove::<(PathfindTask, PathfindingInProgress)>();
+            apply_path(&mut commands, entity, result);
+            continue;
         }
 
-        #[cfg(feature = "stats")]
-        let start = Instant::now();
+        let timeout = async_pathfind.and_then(|async_pathfind| async_pathfind.timeout);
+        if timeout.is_some_and(|timeout| pathfind_task.started.elapsed() >= timeout) {
+            commands
+                .entity(entity)
+                .remove::<(PathfindTask, PathfindingInProgress)>()
+                .insert(PathfindingFailed)
+                .remove::<NextPos>();
 
-        // Let's reroute the path
-        let refined = match pathfind.mode {
-            PathfindMode::Refined => true,
-            PathfindMode::Coarse => false,
-            PathfindMode::AStar => false, // A* is not supported for rerouting
-        };
+            commands.trigger_targets(
+                events::PathfindingFailed {
+                    entity,
+                    reason: PathError::TimedOut,
+                },
+                entity,
+            );
+        }
+    }
+}
 
-        let new_path = grid.reroute_path(path, position.0, pathfind.goal, &blocking.0, refined);
+// The goal of a `Pathfind::within` request, reached by any cell in range of one of its goals.
+// `None` if the request doesn't stop in range of its goals.
+fn within_goal<'a, N: Neighborhood>(
+    grid: &Grid<N>,
+    pathfind: &'a Pathfind,
+) -> Option<SearchGoal<'a>> {
+    let radius = pathfind.within.filter(|radius| *radius > 0)?;
+    if pathfind.region.is_some() {
+        return None;
+    }
 
-        if let Some(new_path) = new_path {
-            // if the last position in the path is not the goal...
-            if new_path.path().last().unwrap() != &pathfind.goal {
-                log::error!("WE HAVE A PARTIAL ROUTE ISSUE: {:?}", entity);
-            }
+    Some(SearchGoal::within(
+        grid.neighborhood(),
+        pathfind.goals(),
+        radius,
+    ))
+}
 
-            #[cfg(feature = "stats")]
-            let elapsed = start.elapsed().as_secs_f64();
-            #[cfg(feature = "stats")]
-            stats.add_collision(elapsed, new_path.cost() as f64);
+// The goals of a `Pathfind::stop_adjacent` request with every blocked goal replaced by the free cells next to it.
+// `None` if the request doesn't stop early, none of its goals are blocked, or every cell around them is blocked too.
+// The agent's own cell at `start` counts as free even if it's blocking.
+fn stand_in_goals<N: Neighborhood>(
+    grid: &Grid<N>,
+    pathfind: &Pathfind,
+    start: UVec3,
+    blocking: &HashMap<UVec3, Entity>,
+) -> Option<Vec<UVec3>> {
+    if pathfind.region.is_some() || pathfind.within.is_some_and(|radius| radius > 0) {
+        return None;
+    }
 
-            commands.entity(entity).insert(new_path);
-            commands.entity(entity).remove::<AvoidanceFailed>();
+    let free = |pos: UVec3| grid.is_passable(pos) && (pos == start || !blocking.contains_key(&pos));
+    if !pathfind.stop_adjacent || pathfind.goals().iter().all(|goal| free(*goal)) {
+        return None;
+    }
+
+    let mut goals = Vec::new();
+    for &goal in pathfind.goals() {
+        let cells = if free(goal) {
+            vec![goal]
         } else {
-            commands.entity(entity).insert(RerouteFailed);
-            commands.entity(entity).remove::<AvoidanceFailed>(); // Try again next frame
+            grid.adjacent_passable(goal)
+                .into_iter()
+                .filter(|cell| free(*cell))
+                .collect()
+        };
 
-            #[cfg(feature = "stats")]
-            let elapsed = start.elapsed().as_secs_f64();
-            #[cfg(feature = "stats")]
-            stats.add_collision(elapsed, 0.0);
+        for cell in cells {
+            if !goals.contains(&cell) {
+                goals.push(cell);
+            }
         }
     }
+
+    (!goals.is_empty()).then_some(goals)
 }
 
-fn update_blocking_map(
-    mut blocking_set: ResMut<BlockingMap>,
-    query: Query<(Entity, &AgentPos), With<Blocking>>,
-) {
-    blocking_set.0.clear();
+// Returns true if the agent at `pos` has completed the request, either on a goal, in range of a goal,
+// or next to a blocked goal.
+fn reached_goal<N: Neighborhood>(
+    grid: &Grid<N>,
+    pathfind: &Pathfind,
+    pos: UVec3,
+    blocking: &HashMap<UVec3, Entity>,
+) -> bool {
+    pathfind.is_goal(pos)
+        || within_goal(grid, pathfind).is_some_and(|goal| goal.is_goal(pos))
+        || stand_in_goals(grid, pathfind, pos, blocking).is_some_and(|goals| goals.contains(&pos))
+}
 
-    query.iter().for_each(|(entity, position)| {
-        blocking_set.0.insert(position.0, entity);
-    });
+// Single goal flow field requests are served from the `FlowFieldCache`.
+// Flow fields are shared by every agent so they ignore layers, and don't follow neighbor provider links.
+fn uses_flow_field<N: Neighborhood>(grid: &Grid<N>, pathfind: &Pathfind) -> bool {
+    pathfind.mode == PathfindMode::FlowField
+        && !pathfind.stop_adjacent
+        && pathfind.within.is_none()
+        && pathfind.goals().len() == 1
+        && pathfind.layers == ALL_NAV_LAYERS
+        && pathfind.corridor.is_none()
+        && !grid.has_neighbor_provider()
+}
+
+// The outcome of a single pathfinding request.
+struct PathResult {
+    path: Result<Path, PathError>,
+    #[cfg_attr(not(feature = "stats"), allow(dead_code))]
+    elapsed: Duration,
+    // The expanded cells if the search was traced.
+    trace: Option<SearchTrace>,
+}
+
+impl PathResult {
+    // The result of an identical request handed to another agent, see `CoalescePathRequests`.
+    fn shared(path: Result<Path, PathError>) -> Self {
+        Self {
+            path,
+            elapsed: Duration::ZERO,
+            trace: None,
+        }
+    }
+}
+
+// A request searched this frame and the agents with identical requests waiting for its result.
+struct CoalescedRequest<'a> {
+    leader: Entity,
+    pathfind: &'a Pathfind,
+    size: AgentSize,
+    ignore: Option<&'a IgnoreBlocking>,
+    followers: Vec<Entity>,
+    path: Option<Result<Path, PathError>>,
+}
+
+impl CoalescedRequest<'_> {
+    // Another agent at the same start asking for the same path, the start and goal are the key of the map.
+    fn matches(
+        &self,
+        pathfind: &Pathfind,
+        size: AgentSize,
+        ignore: Option<&IgnoreBlocking>,
+    ) -> bool {
+        self.pathfind == pathfind && self.size == size && self.ignore == ignore
+    }
+}
+
+// Computes the path for a single request and how long it took, recording the search if `trace` is set.
+// Fails with `PathError::ExpansionLimitReached` if the request's `max_expansions` cut the search short,
+// otherwise with the reason the grid gives for the missing path, see `Grid::path_error`.
+fn timed_path<N: Neighborhood>(
+    grid: &Grid<N>,
+    start: UVec3,
+    pathfind: &Pathfind,
+    size: AgentSize,
+    flow_field: Option<&FlowField>,
+    blocking: &HashMap<UVec3, Entity>,
+    trace: bool,
+) -> PathResult {
+    let start_time = bevy::platform::time::Instant::now();
+    let context = SearchContext::new(pathfind.max_expansions)
+        .prefer_straight(pathfind.prefer_straight)
+        .weight(pathfind.weight)
+        .corridor(pathfind.corridor.clone());
+    let context = if trace { context.traced() } else { context };
+
+    let adjacent = stand_in_goals(grid, pathfind, start, blocking);
+    let within = within_goal(grid, pathfind);
+    let goal = match (&adjacent, within) {
+        (Some(goals), _) => SearchGoal::from_slice(goals),
+        (None, Some(within)) => within,
+        (None, None) => pathfind.search_goal(),
+    };
+
+    // Async searches don't share the cache and search the grid directly
+    let path = if let Some(field) =
+        flow_field.filter(|_| !size.is_multi_cell() && uses_flow_field(grid, pathfind))
+    {
+        field.path_from(start)
+    } else {
+        grid.find_path_with_blocking(
+            start,
+            &goal,
+            pathfind.mode,
+            blocking,
+            pathfind.partial,
+            pathfind.heuristic,
+            pathfind.layers,
+            size.0,
+            &context,
+        )
+    };
+
+    // Index the goal in the order the request gave them, the goals in range of or next to blocked goals stand in for them
+    let path = path.map(|mut path| {
+        if let Some(&last) = path.path().last() {
+            let goals = pathfind.goals();
+            let index = goals.iter().position(|goal| *goal == last).or_else(|| {
+                if let Some(SearchGoal::Within { radius, .. }) = within {
+                    return goals
+                        .iter()
+                        .position(|goal| grid.step_distance(*goal, last) <= radius);
+                }
+                adjacent.as_ref()?;
+                goals
+                    .iter()
+                    .position(|goal| grid.adjacent_passable(*goal).contains(&last))
+            });
+            path.set_goal_index(index);
+        }
+        path
+    });
+
+    let path = path.ok_or_else(|| {
+        if context.reached() {
+            PathError::ExpansionLimitReached
+        } else {
+            grid.path_error(start, &goal, pathfind.partial)
+        }
+    });
+
+    PathResult {
+        path,
+        elapsed: start_time.elapsed(),
+        trace: context.into_trace(),
+    }
+}
+
+// The `next_position` system is responsible for popping the front of the path into a `NextPos` component.
+// If collision is enabled it will check for nearyby blocked paths and reroute the path if necessary.
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity)]
+fn next_position<N: Neighborhood + 'static>(
+    mut query: Query<
+        (
+            Entity,
+            &mut Path,
+            &AgentPos,
+            &Pathfind,
+            Has<Blocking>,
+            Option<&AvoidanceWait>,
+            Option<&PathPriority>,
+            Has<ManualAdvance>,
+            Option<&AgentSize>,
+        ),
+        (
+            WithoutPathingFailures,
+            Without<NextPos>,
+            Without<PathfindingPaused>,
+            Without<SpeedStalled>,
+        ),
+    >,
+    moving: Query<(Entity, &Path, Option<&PathPriority>), (With<Blocking>, With<NextPos>)>,
+    ignores: Query<&IgnoreBlocking>,
+    rerouted: Query<(), With<RerouteAttempts>>,
+    grid: Single<&Grid<N>>,
+    mut blocking: ResMut<BlockingMap>,
+    mut direction: ResMut<DirectionMap>,
+    mut commands: Commands,
+    settings: Res<NorthstarPluginSettings>,
+    cooperative: Option<Res<CooperativeConfig>>,
+    policy: Res<AvoidancePolicy>,
+    lookahead: Res<AvoidanceLookahead>,
+    smooth: Res<SmoothNextPos>,
+    last_dirs: Query<&NextDir>,
+    mut queue: Local<VecDeque<Entity>>,
+    #[cfg(feature = "stats")] mut stats: ResMut<Stats>,
+) {
+    let grid = grid.into_inner();
+
+    // Initialize the queue with all candidates once
+    if queue.is_empty() {
+        for (entity, ..) in query.iter() {
+            queue.push_back(entity);
+        }
+    }
+
+    // Higher priority agents claim their next cells first.
+    // The sort is stable so agents with the same priority keep taking turns.
+    queue.make_contiguous().sort_by_key(|entity| {
+        Reverse(query.get(*entity).map_or(0, |(.., priority, _, _)| {
+            priority.map_or(0, |priority| priority.0)
+        }))
+    });
+
+    // The cells the agents are going to move through over the next few steps
+    let lookahead_steps = (lookahead.0 as usize).min(grid.avoidance_distance() as usize);
+    let mut planned = HashMap::new();
+    if grid.collision() && lookahead_steps > 1 {
+        for (entity, path, _, _, is_blocking, _, priority, ..) in query.iter() {
+            if is_blocking {
+                plan_lookahead(&mut planned, entity, path, priority, 1, lookahead_steps);
+            }
+        }
+        // Agents still moving to their `NextPos` are a step further along
+        for (entity, path, priority) in moving.iter() {
+            plan_lookahead(&mut planned, entity, path, priority, 2, lookahead_steps);
+        }
+    }
+
+    let mut processed = 0;
+
+    for _ in 0..queue.len() {
+        if processed >= settings.max_collision_avoidance_agents_per_frame {
+            break;
+        }
+
+        let entity = queue.pop_front().unwrap();
+
+        // Two agents heading into each other's cells would both fail avoidance.
+        // The agent with the lower priority, or lower index, steps aside into a free cell
+        // and the other waits for it to clear the way.
+        let swap = if grid.collision() {
+            query
+                .get(entity)
+                .ok()
+                .filter(|(.., manual, size)| {
+                    !manual && size.is_none_or(|size| !size.is_multi_cell())
+                })
+                .and_then(|(_, path, position, pathfind, _, _, priority, ..)| {
+                    let next = path.next()?;
+                    let other = *blocking.0.get(&next).filter(|other| {
+                        **other != entity
+                            && !ignores
+                                .get(entity)
+                                .is_ok_and(|ignore| ignore.0.contains(*other))
+                    })?;
+                    let (
+                        _,
+                        other_path,
+                        other_position,
+                        other_pathfind,
+                        _,
+                        _,
+                        other_priority,
+                        manual,
+                        other_size,
+                    ) = query.get(other).ok()?;
+
+                    let other_size = other_size.copied().unwrap_or_default();
+                    if manual || other_size.is_multi_cell() || other_path.next() != Some(position.0)
+                    {
+                        return None;
+                    }
+
+                    // Both agents agree on who steps aside and where to
+                    let key = (priority.copied().unwrap_or_default(), entity.index());
+                    let other_key = (other_priority.copied().unwrap_or_default(), other.index());
+                    let (lower, lower_position, layers, avoid) = if key < other_key {
+                        (entity, position.0, pathfind.layers, other_path.peek(3))
+                    } else {
+                        (other, other_position.0, other_pathfind.layers, path.peek(3))
+                    };
+
+                    let side = side_step(grid, lower_position, avoid, &blocking.0, layers)?;
+                    Some((lower == entity, side))
+                })
+        } else {
+            None
+        };
+
+        // If the entity still exists and is valid
+        if let Ok((
+            entity,
+            mut path,
+            position,
+            pathfind,
+            is_blocking,
+            wait,
+            priority,
+            manual,
+            size,
+        )) = query.get_mut(entity)
+        {
+            let size = size.copied().unwrap_or_default();
+            let agent_blocking = without_ignored(&blocking.0, ignores.get(entity).ok());
+
+            if reached_goal(grid, pathfind, position.0, &agent_blocking) {
+                commands.entity(entity).remove::<Path>();
+                commands.entity(entity).remove::<Pathfind>();
+                commands.trigger_targets(
+                    PathCompleted {
+                        entity,
+                        goal: position.0,
+                    },
+                    entity,
+                );
+                continue;
+            }
+
+            // Moved by the user with `Path::advance` or by `cooperative_next_position` instead.
+            // The cooperative planner only plans single cell agents.
+            if manual || (cooperative.is_some() && is_blocking && !size.is_multi_cell()) {
+                continue;
+            }
+
+            if let Some((steps_aside, side)) = swap {
+                if steps_aside {
+                    // Step back onto the path once the other agent has passed
+                    path.push_front(position.0, step_cost(grid, side, position.0));
+                    path.push_front(side, step_cost(grid, position.0, side));
+
+                    direction
+                        .0
+                        .insert(entity, side.as_vec3() - position.0.as_vec3());
+                    blocking.0.remove(&position.0);
+                    blocking.0.insert(side, entity);
+                    commands.entity(entity).insert(NextPos(path.pop().unwrap()));
+                }
+
+                queue.push_back(entity);
+                continue;
+            }
+
+            // Without collision avoidance temporarily blocked cells are rerouted around here instead
+            if !grid.collision()
+                && path
+                    .next()
+                    .is_some_and(|next| grid.is_temporarily_blocked(next))
+            {
+                commands.entity(entity).insert(AvoidanceFailed);
+                continue;
+            }
+
+            if grid.collision() {
+                let waited = wait.map_or(0, |wait| wait.0);
+                let next_blocked = path
+                    .next()
+                    .is_some_and(|next| occupied(&agent_blocking, size, next, entity));
+
+                if next_blocked && waited < policy.wait_ticks {
+                    // Stand still for a step and hope the blocker moves on
+                    commands
+                        .entity(entity)
+                        .insert((NextPos(position.0), AvoidanceWait(waited + 1)));
+                    queue.push_back(entity);
+                    continue;
+                }
+
+                if wait.is_some() {
+                    commands.entity(entity).remove::<AvoidanceWait>();
+                }
+            }
+
+            let next = if grid.collision() {
+                #[cfg(feature = "stats")]
+                let start = Instant::now();
+
+                // Local avoidance only knows about single cells, larger agents reroute with their footprint instead
+                let success = if size.is_multi_cell() {
+                    !path
+                        .next()
+                        .is_some_and(|next| occupied(&agent_blocking, size, next, entity))
+                } else {
+                    let key = (priority.copied().unwrap_or_default(), entity.index());
+
+                    // Treat a conflict further down the path as blocked so it's rerouted around early,
+                    // falling back to the regular avoidance if there's no way around it
+                    lookahead_conflict(&planned, entity, key, &path, lookahead_steps).is_some_and(
+                        |(cell, other)| {
+                            let mut blocking = agent_blocking.clone().into_owned();
+                            blocking.insert(cell, other);
+                            avoidance(
+                                grid,
+                                entity,
+                                &mut path,
+                                pathfind,
+                                position.0,
+                                &blocking,
+                                &direction.0,
+                                grid.avoidance_distance() as usize,
+                            )
+                        },
+                    ) || avoidance(
+                        grid,
+                        entity,
+                        &mut path,
+                        pathfind,
+                        position.0,
+                        &agent_blocking,
+                        &direction.0,
+                        grid.avoidance_distance() as usize,
+                    )
+                };
+
+                if !success {
+                    commands.entity(entity).insert(AvoidanceFailed);
+                    continue;
+                }
+
+                #[cfg(feature = "stats")]
+                let elapsed = start.elapsed().as_secs_f64();
+                #[cfg(feature = "stats")]
+                stats.add_collision(elapsed, path.cost() as f64);
+
+                processed += 1;
+                path.pop()
+            } else {
+                path.pop()
+            };
+
+            if let Some(mut next) = next {
+                // The grid changed underneath the path, try to repair it from here before giving up
+                if !grid.is_passable(next) {
+                    if let Some(repaired) =
+                        repair_invalidated(grid, &mut path, pathfind, position.0, next, size)
+                    {
+                        next = repaired;
+                    }
+                }
+
+                if !grid.is_passable(next) {
+                    commands
+                        .entity(entity)
+                        .insert(PathfindingFailed)
+                        .remove::<Path>();
+                    commands.trigger_targets(
+                        events::PathfindingFailed {
+                            entity,
+                            reason: PathError::PathInvalidated,
+                        },
+                        entity,
+                    );
+                    continue;
+                }
+
+                if smooth.0 && !size.is_multi_cell() {
+                    let previous = last_dirs.get(entity).ok().and_then(|dir| {
+                        let previous = position.0.as_ivec3() - dir.0.offset();
+                        previous
+                            .cmpge(IVec3::ZERO)
+                            .all()
+                            .then(|| previous.as_uvec3())
+                    });
+
+                    if previous == Some(next) {
+                        if let Some(ahead) =
+                            skip_backtrack(grid, &mut path, position.0, &agent_blocking, entity)
+                        {
+                            next = ahead;
+                        }
+                    }
+                }
+
+                direction
+                    .0
+                    .insert(entity, next.as_vec3() - position.0.as_vec3());
+
+                if grid.collision() && occupied(&agent_blocking, size, next, entity) {
+                    // Someone beat us to it - requeue without inserting NextPos
+                    queue.push_back(entity);
+                    continue;
+                }
+
+                for cell in size.cells(position.0) {
+                    if blocking.0.get(&cell) == Some(&entity) {
+                        blocking.0.remove(&cell);
+                    }
+                }
+                for cell in size.cells(next) {
+                    blocking.0.insert(cell, entity);
+                }
+                commands.entity(entity).insert(NextPos(next));
+
+                // The agent is making progress again
+                if rerouted.contains(entity) {
+                    commands.entity(entity).remove::<RerouteAttempts>();
+                }
+
+                // Re-queue for next frame
+                queue.push_back(entity);
+            }
+        }
+    }
+}
+
+// Builds up the progress of the agents with an `AgentSpeed` waiting for their next cell,
+// holding back the ones that don't have enough for a whole cell yet.
+#[allow(clippy::type_complexity)]
+fn accumulate_agent_speed(
+    mut commands: Commands,
+    mut query: Query<
+        (
+            Entity,
+            &AgentSpeed,
+            Option<&mut SpeedProgress>,
+            Has<SpeedStalled>,
+        ),
+        (With<Path>, Without<NextPos>, Without<PathfindingPaused>),
+    >,
+    time: Res<Time>,
+) {
+    for (entity, speed, progress, stalled) in &mut query {
+        let gained = speed.0.max(0.0) * time.delta_secs();
+        let progress = match progress {
+            Some(mut progress) => {
+                progress.0 += gained;
+                progress.0
+            }
+            None => {
+                commands.entity(entity).insert(SpeedProgress(gained));
+                gained
+            }
+        };
+
+        if progress >= 1.0 && stalled {
+            commands.entity(entity).remove::<SpeedStalled>();
+        } else if progress < 1.0 && !stalled {
+            commands.entity(entity).insert(SpeedStalled);
+        }
+    }
+}
+
+// Moves the new `NextPos` of the agents with an `AgentSpeed` further down their path, one cell for each whole cell
+// of progress they've built up. Stops short of cells that are impassable or occupied, whole cells that couldn't be
+// taken are dropped and the fractional progress carries over.
+#[allow(clippy::type_complexity)]
+fn apply_agent_speed<N: Neighborhood + 'static>(
+    mut query: Query<
+        (
+            Entity,
+            &mut Path,
+            &AgentPos,
+            &mut NextPos,
+            &mut SpeedProgress,
+            Option<&AgentSize>,
+            Option<&IgnoreBlocking>,
+        ),
+        Added<NextPos>,
+    >,
+    grid: Single<&Grid<N>>,
+    mut blocking: ResMut<BlockingMap>,
+    mut direction: ResMut<DirectionMap>,
+) {
+    let grid = grid.into_inner();
+
+    for (entity, mut path, position, mut next, mut progress, size, ignore) in &mut query {
+        let steps = progress.0 as usize;
+        progress.0 = progress.0.fract();
+
+        // Waiting in place
+        if next.0 == position.0 {
+            continue;
+        }
+
+        let size = size.copied().unwrap_or_default();
+        let mut destination = next.0;
+        for _ in 1..steps {
+            let Some(ahead) = path.next() else {
+                break;
+            };
+
+            let blocked = grid.collision()
+                && occupied(&without_ignored(&blocking.0, ignore), size, ahead, entity);
+            if !grid.is_passable(ahead) || blocked {
+                break;
+            }
+
+            path.pop();
+            for cell in size.cells(destination) {
+                if blocking.0.get(&cell) == Some(&entity) {
+                    blocking.0.remove(&cell);
+                }
+            }
+            for cell in size.cells(ahead) {
+                blocking.0.insert(cell, entity);
+            }
+            destination = ahead;
+        }
+
+        if destination != next.0 {
+            direction
+                .0
+                .insert(entity, destination.as_vec3() - position.0.as_vec3());
+            next.0 = destination;
+        }
+    }
+}
+
+// Removes the `NextPos` of agents that have reached it when `AutoAdvance` is enabled,
+// the following systems then insert the next step as if the user had removed it.
+fn auto_advance(
+    mut commands: Commands,
+    auto_advance: Res<AutoAdvance>,
+    query: Query<(Entity, &AgentPos, &NextPos)>,
+) {
+    if !auto_advance.0 {
+        return;
+    }
+
+    for (entity, position, next) in &query {
+        if position.0 == next.0 {
+            commands.entity(entity).remove::<NextPos>();
+        }
+    }
+}
+
+// Inserts the direction of the move to every new `NextPos`, waiting in place keeps the previous direction.
+fn update_next_dir(
+    mut commands: Commands,
+    query: Query<(Entity, &AgentPos, &NextPos, Option<&NextDir>), Changed<NextPos>>,
+) {
+    for (entity, position, next, current) in &query {
+        if let Some(dir) = Dir::between(position.0, next.0) {
+            if current.is_none_or(|current| current.0 != dir) {
+                commands.entity(entity).insert(NextDir(dir));
+            }
+        }
+    }
+}
+
+// The blocking map as seen by an agent, without the cells of the entities in its `IgnoreBlocking`.
+fn without_ignored<'a>(
+    blocking: &'a HashMap<UVec3, Entity>,
+    ignore: Option<&IgnoreBlocking>,
+) -> Cow<'a, HashMap<UVec3, Entity>> {
+    match ignore {
+        Some(ignore) if !ignore.0.is_empty() => Cow::Owned(
+            blocking
+                .iter()
+                .filter(|(_, entity)| !ignore.0.contains(*entity))
+                .map(|(pos, entity)| (*pos, *entity))
+                .collect(),
+        ),
+        _ => Cow::Borrowed(blocking),
+    }
+}
+
+// Returns true if another entity occupies any cell covered by an agent of `size` at `pos`.
+fn occupied(
+    blocking: &HashMap<UVec3, Entity>,
+    size: AgentSize,
+    pos: UVec3,
+    entity: Entity,
+) -> bool {
+    size.cells(pos)
+        .any(|cell| blocking.get(&cell).is_some_and(|other| *other != entity))
+}
+
+// How many cells along the path `skip_backtrack` looks for a cell to step to instead of backtracking.
+const BACKTRACK_WINDOW: usize = 3;
+
+// Pops the path up to the furthest of the next few cells the agent at `position` can step to directly and returns it,
+// used to skip a step back to the cell the agent just left. `None` if the backtrack can't be skipped.
+fn skip_backtrack<N: Neighborhood>(
+    grid: &Grid<N>,
+    path: &mut Path,
+    position: UVec3,
+    blocking: &HashMap<UVec3, Entity>,
+    entity: Entity,
+) -> Option<UVec3> {
+    let skip = path.peek(BACKTRACK_WINDOW).iter().rposition(|pos| {
+        grid.is_edge_passable(position, *pos)
+            && !occupied(blocking, AgentSize::default(), *pos, entity)
+    })?;
+
+    for _ in 0..skip {
+        path.pop();
+    }
+    path.pop()
+}
+
+// The step an agent reaches a cell at and its priority key, used to break ties between conflicting agents.
+type PlannedCell = (Entity, usize, (PathPriority, u32));
+
+// Records the first `steps` cells of an agent's path, `first_step` being the step it reaches the front of its path at.
+// The highest priority agent to plan a cell keeps it.
+fn plan_lookahead(
+    planned: &mut HashMap<UVec3, PlannedCell>,
+    entity: Entity,
+    path: &Path,
+    priority: Option<&PathPriority>,
+    first_step: usize,
+    steps: usize,
+) {
+    let key = (priority.copied().unwrap_or_default(), entity.index());
+    for (i, pos) in path.path.iter().take(steps).enumerate() {
+        let cell = (entity, first_step + i, key);
+        planned
+            .entry(*pos)
+            .and_modify(|planned: &mut PlannedCell| {
+                if key > planned.2 {
+                    *planned = cell;
+                }
+            })
+            .or_insert(cell);
+    }
+}
+
+// Returns the first cell past the next position that a higher priority agent is going to reach
+// within a step of this agent, along with that agent.
+fn lookahead_conflict(
+    planned: &HashMap<UVec3, PlannedCell>,
+    entity: Entity,
+    key: (PathPriority, u32),
+    path: &Path,
+    steps: usize,
+) -> Option<(UVec3, Entity)> {
+    path.path
+        .iter()
+        .take(steps)
+        .enumerate()
+        .skip(1)
+        .find_map(|(i, pos)| {
+            let (other, step, other_key) = planned.get(pos)?;
+            (*other != entity && step.abs_diff(i + 1) <= 1 && *other_key > key)
+                .then_some((*pos, *other))
+        })
+}
+
+// Finds a free cell next to `pos` that an agent can step aside into, skipping the cells in `avoid`.
+fn side_step<N: Neighborhood>(
+    grid: &Grid<N>,
+    pos: UVec3,
+    avoid: &[UVec3],
+    blocking: &HashMap<UVec3, Entity>,
+    layers: NavLayers,
+) -> Option<UVec3> {
+    grid.neighbor_positions(pos).into_iter().find(|cell| {
+        grid.navcell(*cell).is_passable_for(layers)
+            && !blocking.contains_key(cell)
+            && !avoid.contains(cell)
+    })
+}
+
+// Repairs the path of an agent whose `next` step was made impassable by a grid change, reusing the rest of
+// the path where it's still walkable. Returns the new next step, `None` if the path couldn't be repaired
+// or the agent moves in ways the hierarchy can't reroute.
+fn repair_invalidated<N: Neighborhood>(
+    grid: &Grid<N>,
+    path: &mut Path,
+    pathfind: &Pathfind,
+    position: UVec3,
+    next: UVec3,
+    size: AgentSize,
+) -> Option<UVec3> {
+    if pathfind.layers != ALL_NAV_LAYERS
+        || grid.has_neighbor_provider()
+        || size.is_multi_cell()
+        || pathfind.corridor.is_some()
+    {
+        return None;
+    }
+
+    let goal = path.path().last().copied().unwrap_or(next);
+    path.push_front(next, 0);
+
+    // Other agents are left to collision avoidance, only the grid change is planned around
+    let mut repaired = grid.repath_from(position, goal, path, &HashMap::new())?;
+    repaired.set_goal_index(path.goal_index());
+    *path = repaired;
+    path.pop()
+}
+
+// The cost of stepping from `from` onto `pos`, including any cost override.
+fn step_cost<N: Neighborhood>(grid: &Grid<N>, from: UVec3, pos: UVec3) -> MovementCost {
+    grid.neighborhood()
+        .step_cost(from, pos, grid.nav_cost(pos).unwrap_or_default())
+        .saturating_add(grid.extra_cost(pos).unwrap_or_default())
+}
+
+// The `cooperative_next_position` system moves agents with `Blocking` when the `CooperativeConfig` resource is present.
+// Agents that are moving, idle, or failed reserve their cells first. Agents ready for their next move are then planned
+// in priority, then entity, order with a windowed space-time A* to a position further along their path, avoiding the cells reserved before them.
+// If an agent can't reach that position within the window it waits in place this frame.
+#[allow(clippy::type_complexity)]
+fn cooperative_next_position<N: Neighborhood + 'static>(
+    mut query: Query<
+        (
+            Entity,
+            &AgentPos,
+            Option<&mut Path>,
+            Option<&Pathfind>,
+            Option<&NextPos>,
+            Has<AvoidanceFailed>,
+            Has<RerouteFailed>,
+            Option<&PathPriority>,
+            Has<ManualAdvance>,
+            Has<PathfindingPaused>,
+            Option<&AgentSize>,
+        ),
+        With<Blocking>,
+    >,
+    grid: Single<&Grid<N>>,
+    config: Res<CooperativeConfig>,
+    mut blocking: ResMut<BlockingMap>,
+    mut direction: ResMut<DirectionMap>,
+    mut commands: Commands,
+) {
+    let grid = grid.into_inner();
+    let window = config.window.max(1);
+
+    let mut reservations = ReservationTable::new(window);
+    let mut ready = Vec::new();
+
+    for (
+        entity,
+        position,
+        path,
+        pathfind,
+        next,
+        avoidance_failed,
+        reroute_failed,
+        priority,
+        manual,
+        paused,
+        size,
+    ) in query.iter()
+    {
+        let position = position.0;
+
+        // Larger agents are moved by `next_position`, keep the planned agents out of every cell they cover
+        if let Some(size) = size.filter(|size| size.is_multi_cell()) {
+            for cell in size.cells(position) {
+                reservations.reserve(cell, 0, entity);
+                reservations.reserve(cell, 1, entity);
+            }
+            if let Some(next) = next {
+                for cell in size.cells(next.0) {
+                    reservations.reserve(cell, 0, entity);
+                    reservations.reserve(cell, 1, entity);
+                }
+            }
+            continue;
+        }
+
+        if manual {
+            // Moving on its own, expect it to follow its path one cell per step
+            reservations.reserve(position, 0, entity);
+            if let Some(path) = path {
+                for (step, pos) in path.iter().enumerate().take(window as usize) {
+                    reservations.reserve(*pos, step as u32 + 1, entity);
+                }
+            }
+            continue;
+        }
+
+        if let Some(next) = next {
+            // Still moving, it's expected to follow the rest of its path afterwards
+            reservations.reserve(position, 0, entity);
+            reservations.reserve(next.0, 0, entity);
+            reservations.reserve(next.0, 1, entity);
+
+            if let Some(path) = path {
+                for (step, pos) in path.iter().enumerate().take(window as usize) {
+                    reservations.reserve(*pos, step as u32 + 2, entity);
+                }
+            }
+            continue;
+        }
+
+        let can_move = !paused
+            && !avoidance_failed
+            && !reroute_failed
+            && path.is_some_and(|path| !path.is_empty())
+            && pathfind.is_some_and(|pathfind| !pathfind.is_goal(position));
+
+        if can_move {
+            reservations.reserve(position, 0, entity);
+            ready.push((Reverse(priority.copied().unwrap_or_default()), entity));
+        } else {
+            reservations.reserve_from(position, 0, entity);
+        }
+    }
+
+    // Higher priorities plan first, a fixed order keeps the priorities stable between frames
+    ready.sort_unstable();
+
+    for (_, entity) in ready {
+        let Ok((entity, position, Some(mut path), pathfind, ..)) = query.get_mut(entity) else {
+            continue;
+        };
+        let position = position.0;
+        let layers = pathfind.map_or(ALL_NAV_LAYERS, |pathfind| pathfind.layers);
+
+        // Plan towards the furthest position along the path the window can reach
+        let target_index = path.len().min(window as usize) - 1;
+        let target = path.path[target_index];
+
+        let Some(plan) = plan_window(grid, &reservations, entity, position, target, layers) else {
+            if !grid.is_passable(target) {
+                // The grid changed underneath the path
+                commands
+                    .entity(entity)
+                    .insert(PathfindingFailed)
+                    .remove::<Path>();
+                commands.trigger_targets(
+                    events::PathfindingFailed {
+                        entity,
+                        reason: PathError::PathInvalidated,
+                    },
+                    entity,
+                );
+            }
+
+            // Wait in place
+            reservations.reserve_from(position, 1, entity);
+            continue;
+        };
+
+        for (step, pos) in plan.iter().enumerate() {
+            reservations.reserve(*pos, step as u32 + 1, entity);
+        }
+
+        if target_index == path.len() - 1 {
+            // Stays at the goal once it arrives
+            reservations.reserve_from(target, plan.len() as u32 + 1, entity);
+        }
+
+        let Some(&next) = plan.first() else {
+            // Already standing on the target
+            path.path.drain(..=target_index);
+            continue;
+        };
+
+        if next == position || blocking.0.contains_key(&next) {
+            // Waiting this step, the plan is made again next frame
+            continue;
+        }
+
+        // Replace the path up to the target with the planned moves
+        path.path.drain(..=target_index);
+        let mut previous = position;
+        let moves = plan
+            .into_iter()
+            .filter(|pos| std::mem::replace(&mut previous, *pos) != *pos)
+            .collect::<Vec<_>>();
+        for pos in moves.into_iter().rev() {
+            path.path.push_front(pos);
+        }
+        path.path.make_contiguous();
+
+        path.pop();
+
+        direction
+            .0
+            .insert(entity, next.as_vec3() - position.as_vec3());
+
+        blocking.0.remove(&position);
+        blocking.0.insert(next, entity);
+        commands.entity(entity).insert(NextPos(next));
+    }
+}
+
+// The `avoidance` function does a lookahead on the path, if any blocking entities are found
+// that are moving in the opposite relateive direciton it will attempt a short astar reroute.
+#[allow(clippy::too_many_arguments)]
+fn avoidance<N: Neighborhood + 'static>(
+    grid: &Grid<N>,
+    entity: Entity,
+    path: &mut Path,
+    pathfind: &Pathfind,
+    position: UVec3,
+    blocking: &HashMap<UVec3, Entity>,
+    direction: &HashMap<Entity, Vec3>,
+    avoidance_distance: usize,
+) -> bool {
+    if path.is_empty() {
+        log::error!("Path is empty for entity: {:?}", entity);
+        return false;
+    }
+
+    // Check if the next few positions are blocked
+    let count = if path.path().len() > avoidance_distance {
+        avoidance_distance
+    } else {
+        path.path().len()
+    };
+
+    // Precompute the dot product for the current position
+    let next_position = path.path.front().unwrap();
+
+    if path.path.len() == 1 && blocking.contains_key(next_position) {
+        return false;
+    }
+
+    let difference = next_position.as_vec3() - position.as_vec3();
+
+    let unblocked_pos: Vec<UVec3> = path
+        .path
+        .iter()
+        .take(count)
+        .filter(|pos| {
+            if let Some(blocking_entity) = blocking.get(*pos) {
+                // If the blocking entity is the same as the current entity, skip it
+                if *blocking_entity == entity {
+                    return true;
+                }
+
+                // Too risky to move to the next position regardless of the direction
+                if *pos == next_position {
+                    return false;
+                }
+
+                if let Some(blocking_dir) = direction.get(blocking_entity) {
+                    let dot = difference.dot(*blocking_dir);
+                    if dot <= 0.0 {
+                        return false;
+                    }
+                } else {
+                    // They have no direction
+                    return false;
+                }
+            }
+            true
+        })
+        .cloned()
+        .collect();
+
+    // If we have a blocked position in the path, repath
+    if unblocked_pos.len() < count {
+        let avoidance_goal = {
+            let _ = info_span!("avoidance_goal", name = "avoidance_goal").entered();
+
+            // Get the first unlocked position AFTER skipping the count, we don't care about the direction
+            path.path
+                .iter()
+                .skip(count)
+                .find(|pos| !blocking.contains_key(&**pos))
+        };
+
+        // If we have an avoidance goal, astar path to that
+        if let Some(avoidance_goal) = avoidance_goal {
+            // Calculate a good radius from the current position to the avoidance goal
+            let radius = position.as_vec3().distance(avoidance_goal.as_vec3()) as u32
+                + grid.avoidance_distance();
+
+            //let new_path = grid.pathfind_astar(position, *avoidance_goal, blocking, false);
+            let new_path =
+                grid.pathfind_astar_radius(position, *avoidance_goal, radius, blocking, false);
+
+            // The local search ignores layers, don't take a detour the agent can't move through
+            let new_path = new_path.filter(|new_path| {
+                new_path.path().iter().all(|pos| {
+                    grid.nav_layers(*pos)
+                        .is_some_and(|layers| layers & pathfind.layers != 0)
+                })
+            });
+
+            // Replace the first few positions of path until the avoidance goal
+            if let Some(new_path) = new_path {
+                // Get every position AFTER the avoidance goal in the old path
+                let old_path = path
+                    .path
+                    .iter()
+                    .skip_while(|pos| *pos != avoidance_goal)
+                    .skip(1)
+                    .cloned()
+                    .collect::<Vec<UVec3>>();
+
+                let combined_cost = std::iter::once(*avoidance_goal)
+                    .chain(old_path.iter().copied())
+                    .collect::<Vec<_>>()
+                    .windows(2)
+                    .map(|step| step_cost(grid, step[0], step[1]))
+                    .fold(new_path.cost(), MovementCost::saturating_add);
+
+                // Combine the new path with the old path
+                let mut combined_path = new_path.path().to_vec();
+                combined_path.extend(old_path);
+
+                if combined_path.is_empty() {
+                    log::error!("Combined path is empty for entity: {:?}", entity);
+                    return false;
+                }
+
+                let graph_path = path.graph_path.clone();
+                let goal_index = path.goal_index();
+
+                // Replace the path with the combined path
+                *path = Path::from_slice(&combined_path, combined_cost);
+                path.graph_path = graph_path;
+                path.set_goal_index(goal_index);
+            } else {
+                return false;
+            }
+        } else {
+            // No easy avoidance astar path to the next entrance
+            return false;
+        }
+    }
+
+    // DELETE THIS
+    // We must have gotten to this point because of partial paths
+    if path.path.is_empty() {
+        // if goal is in blocking
+        if blocking.contains_key(&pathfind.goal) {
+            return false;
+        }
+
+        let new_path = grid.find_path_with_blocking(
+            position,
+            &SearchGoal::Position(pathfind.goal),
+            PathfindMode::Refined,
+            blocking,
+            false,
+            None,
+            pathfind.layers,
+            UVec2::ONE,
+            &SearchContext::new(pathfind.max_expansions)
+                .prefer_straight(pathfind.prefer_straight)
+                .weight(pathfind.weight)
+                .corridor(pathfind.corridor.clone()),
+        );
+
+        if let Some(new_path) = new_path {
+            *path = new_path;
+            return true;
+        } else {
+            return false;
+        }
+    }
+
+    true
+}
+
+// The `reroute_path` system is responsible for rerouting the path if the `AvoidanceFailed` component is present.
+// It will attempt to find a new full path to the goal position and insert it into the entity.
+// If the reroute fails, it will insert a `RerouteFailed` component to the entity.
+// Once an entity has a reroute failure, no pathfinding will be attempted until the user handles reinserts the `Pathfind` component.
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity)]
+fn reroute_path<N: Neighborhood + 'static>(
+    mut query: Query<
+        (
+            Entity,
+            &AgentPos,
+            &Pathfind,
+            &mut Path,
+            Option<&AgentSize>,
+            Option<&IgnoreBlocking>,
+            Option<&mut RerouteAttempts>,
+        ),
+        (With<AvoidanceFailed>, Without<PathfindingPaused>),
+    >,
+    grid: Single<&Grid<N>>,
+    mut blocking_map: ResMut<BlockingMap>,
+    mut direction: ResMut<DirectionMap>,
+    mut commands: Commands,
+    settings: Res<NorthstarPluginSettings>,
+    config: Res<RerouteConfig>,
+    keep_stale: Res<KeepStalePathOnFailure>,
+    mut frame: Local<u32>,
+    #[cfg(feature = "stats")] mut stats: ResMut<Stats>,
+) {
+    let grid = grid.into_inner();
+    *frame = frame.wrapping_add(1);
+
+    for (count, (entity, position, pathfind, mut path, size, ignore, attempts)) in
+        query.iter_mut().enumerate()
+    {
+        // TODO: This doesn't really tie in with the main pathfinding agent counts. This will stil help limit how many are rereouting for now.
+        // There's no point bridging it for the moment since this really needs to be reworked into an async system to really prevent stutters.
+        if count >= settings.max_pathfinding_agents_per_frame {
+            return;
+        }
+
+        // Wait in place until the cooldown from the last reroute has passed
+        if attempts
+            .as_ref()
+            .is_some_and(|attempts| *frame <= attempts.ready_at)
+        {
+            continue;
+        }
+
+        let attempted = attempts.as_ref().map_or(0, |attempts| attempts.attempts);
+        if config.max_attempts.is_some_and(|max| attempted >= max) {
+            commands
+                .entity(entity)
+                .insert(RerouteFailed)
+                .remove::<(AvoidanceFailed, RerouteAttempts)>();
+            reroute_failed(&mut commands, entity);
+            continue;
+        }
+
+        let next_attempt = RerouteAttempts {
+            attempts: attempted + 1,
+            ready_at: frame.wrapping_add(config.cooldown_ticks),
+        };
+        match attempts {
+            Some(mut attempts) => *attempts = next_attempt,
+            None => {
+                commands.entity(entity).insert(next_attempt);
+            }
+        }
+
+        #[cfg(feature = "stats")]
+        let start = Instant::now();
+
+        // Let's reroute the path
+        let refined = match pathfind.mode {
+            PathfindMode::Refined => true,
+            PathfindMode::Coarse => false,
+            PathfindMode::AStar => false, // A* is not supported for rerouting
+            PathfindMode::Jps => false,
+            PathfindMode::FlowField => false,
+            PathfindMode::Theta => false,
+        };
+
+        // Reroute to the goal the current path was heading to
+        let goal = path
+            .path()
+            .last()
+            .copied()
+            .filter(|pos| {
+                pathfind.is_goal(*pos) || pathfind.stop_adjacent || pathfind.within.is_some()
+            })
+            .unwrap_or(pathfind.goal);
+
+        let size = size.copied().unwrap_or_default();
+        let blocking = without_ignored(&blocking_map.0, ignore);
+
+        // Rerouting through the hierarchy ignores layers, provider links, footprints and corridors, search the whole path again instead
+        let new_path = if pathfind.layers == ALL_NAV_LAYERS
+            && !grid.has_neighbor_provider()
+            && !size.is_multi_cell()
+            && pathfind.corridor.is_none()
+        {
+            // Replan only the blocked stretch when the rest of the path is still walkable
+            repair_path(grid, &path, position.0, goal, &blocking)
+                .or_else(|| grid.reroute_path(&path, position.0, goal, &blocking, refined))
+        } else {
+            grid.find_path_with_blocking(
+                position.0,
+                &SearchGoal::Position(goal),
+                PathfindMode::AStar,
+                &blocking,
+                false,
+                pathfind.heuristic,
+                pathfind.layers,
+                size.0,
+                &SearchContext::new(pathfind.max_expansions)
+                    .prefer_straight(pathfind.prefer_straight)
+                    .weight(pathfind.weight)
+                    .corridor(pathfind.corridor.clone()),
+            )
+        };
+
+        if let Some(mut new_path) = new_path {
+            // The reroute leads to the same goal
+            new_path.set_goal_index(path.goal_index());
+
+            // if the last position in the path is not the goal...
+            if new_path.path().last().unwrap() != &goal {
+                log::error!("WE HAVE A PARTIAL ROUTE ISSUE: {:?}", entity);
+            }
+
+            #[cfg(feature = "stats")]
+            let elapsed = start.elapsed().as_secs_f64();
+            #[cfg(feature = "stats")]
+            stats.add_collision(elapsed, new_path.cost() as f64);
+
+            commands.entity(entity).insert(new_path);
+            commands.entity(entity).remove::<AvoidanceFailed>();
+        } else if keep_stale.0 {
+            // Keep following the old path while its next cell is free and reroute again later
+            let next = path.next().filter(|next| {
+                grid.is_passable(*next) && !occupied(&blocking, size, *next, entity)
+            });
+            commands.entity(entity).remove::<AvoidanceFailed>();
+
+            if let Some(next) = next {
+                path.pop();
+
+                for cell in size.cells(position.0) {
+                    if blocking_map.0.get(&cell) == Some(&entity) {
+                        blocking_map.0.remove(&cell);
+                    }
+                }
+                for cell in size.cells(next) {
+                    blocking_map.0.insert(cell, entity);
+                }
+                direction
+                    .0
+                    .insert(entity, next.as_vec3() - position.0.as_vec3());
+                commands
+                    .entity(entity)
+                    .insert(NextPos(next))
+                    .remove::<RerouteAttempts>();
+            }
+
+            #[cfg(feature = "stats")]
+            let elapsed = start.elapsed().as_secs_f64();
+            #[cfg(feature = "stats")]
+            stats.add_collision(elapsed, 0.0);
+        } else {
+            commands.entity(entity).insert(RerouteFailed);
+            commands
+                .entity(entity)
+                .remove::<(AvoidanceFailed, RerouteAttempts)>(); // Try again next frame
+            reroute_failed(&mut commands, entity);
+
+            #[cfg(feature = "stats")]
+            let elapsed = start.elapsed().as_secs_f64();
+            #[cfg(feature = "stats")]
+            stats.add_collision(elapsed, 0.0);
+        }
+    }
+}
+
+// Local avoidance failing only becomes a pathfinding failure once the reroute around the blocking agents fails too.
+fn reroute_failed(commands: &mut Commands, entity: Entity) {
+    commands.trigger_targets(events::RerouteFailed { entity }, entity);
+    commands.trigger_targets(
+        events::PathfindingFailed {
+            entity,
+            reason: PathError::AvoidanceFailed,
+        },
+        entity,
+    );
+}
+
+fn update_blocking_map<N: Neighborhood + 'static>(
+    mut blocking_set: ResMut<BlockingMap>,
+    query: Query<(Entity, &AgentPos, Option<&AgentSize>), With<Blocking>>,
+    grids: Query<&Grid<N>>,
+) {
+    blocking_set.0.clear();
+
+    query.iter().for_each(|(entity, position, size)| {
+        for cell in size.copied().unwrap_or_default().cells(position.0) {
+            blocking_set.0.insert(cell, entity);
+        }
+    });
+
+    // Cells blocked with `Grid::block_temporarily` aren't occupied by an entity
+    for grid in &grids {
+        for (pos, _) in grid.temporary_blocks() {
+            blocking_set.0.entry(pos).or_insert(Entity::PLACEHOLDER);
+        }
+    }
+}
+
+// Decays the `TrailField`, deposits the trail of every agent following a path and hands the rounded costs to the grids.
+// The grid is only marked as changed when a rounded cost changes, so async searches pick up a fresh snapshot.
+#[allow(clippy::type_complexity)]
+fn update_trails<N: Neighborhood + 'static>(
+    agents: Query<(&AgentPos, Option<&AgentSize>), (With<Path>, Without<PathfindingPaused>)>,
+    mut grids: Query<&mut Grid<N>>,
+    mut field: ResMut<TrailField>,
+    config: Res<TrailConfig>,
+) {
+    let decay = config.decay.clamp(0.0, 1.0);
+    field.0.retain(|_, trail| {
+        *trail *= decay;
+        *trail >= 0.5
+    });
+
+    for (position, size) in &agents {
+        for cell in size.copied().unwrap_or_default().cells(position.0) {
+            *field.0.entry(cell).or_default() += config.deposit;
+        }
+    }
+
+    for mut grid in &mut grids {
+        let changed = grid.bypass_change_detection().set_trail_costs(
+            field
+                .0
+                .iter()
+                .map(|(pos, trail)| (*pos, trail.round() as MovementCost)),
+        );
+
+        if changed {
+            grid.set_changed();
+        }
+    }
+}
+
+// Clears the `TrailField` and the grids' trail costs once the `TrailConfig` is removed.
+fn clear_trails<N: Neighborhood + 'static>(
+    mut grids: Query<&mut Grid<N>>,
+    mut field: ResMut<TrailField>,
+) {
+    field.0.clear();
+
+    for mut grid in &mut grids {
+        if grid.bypass_change_detection().set_trail_costs([]) {
+            grid.set_changed();
+        }
+    }
+}
+
+// Counts down the cells blocked with `Grid::block_temporarily` and clears the expired ones.
+// Ticking doesn't mark the grid as changed, only clearing a block does.
+fn tick_temporary_blocks<N: Neighborhood + 'static>(
+    mut grids: Query<&mut Grid<N>>,
+    time: Res<Time>,
+) {
+    for mut grid in &mut grids {
+        if grid.temporary_blocks().next().is_none() {
+            continue;
+        }
+
+        let expired = grid
+            .bypass_change_detection()
+            .tick_temporary_blocks(time.delta());
+
+        if !expired.is_empty() {
+            grid.set_changed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{prelude::*, time::TimeUpdateStrategy};
+
+    use super::{AvoidanceWait, NeedsPathfinding, PathfindTask};
+    use crate::prelude::*;
+
+    // Spawns hundreds of agents requesting paths in the same frame and returns each agent's start and path cost.
+    fn run_agents(parallel: bool) -> Vec<([u32; 3], Option<u32>)> {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<OrdinalNeighborhood>::default(),
+        ))
+        .insert_resource(ParallelPathfinding(parallel))
+        .insert_resource(PathfindingBudget {
+            max_paths: Some(usize::MAX),
+            ..Default::default()
+        });
+
+        let settings = GridSettingsBuilder::new_2d(64, 64).chunk_size(16).build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+        for y in 0..56 {
+            grid.set_nav(UVec3::new(32, y, 0), Nav::Impassable);
+        }
+        grid.build();
+        app.world_mut().spawn(grid);
+
+        let modes = [
+            PathfindMode::Refined,
+            PathfindMode::AStar,
+            PathfindMode::Jps,
+            PathfindMode::FlowField,
+            PathfindMode::Theta,
+        ];
+
+        for i in 0..500u32 {
+            let start = UVec3::new(i % 30, (i / 30) % 64, 0);
+            let goal = UVec3::new(63 - i % 20, 63 - i % 50, 0);
+            app.world_mut().spawn((
+                AgentPos(start),
+                Pathfind::new(goal).mode(modes[i as usize % modes.len()]),
+            ));
+        }
+
+        app.update();
+
+        let mut pending = app
+            .world_mut()
+            .query_filtered::<Entity, With<NeedsPathfinding>>();
+        assert_eq!(pending.iter(app.world()).count(), 0);
+
+        let mut query = app.world_mut().query::<(&AgentPos, Option<&Path>)>();
+        let mut results = query
+            .iter(app.world())
+            .map(|(pos, path)| (pos.0.to_array(), path.map(|path| path.cost())))
+            .collect::<Vec<_>>();
+        results.sort_unstable();
+
+        results
+    }
+
+    #[test]
+    fn test_parallel_pathfinding_stress() {
+        let parallel = run_agents(true);
+        let serial = run_agents(false);
+
+        assert_eq!(parallel.len(), 500);
+        assert!(parallel.iter().all(|(_, cost)| cost.is_some()));
+        assert_eq!(parallel, serial);
+    }
+
+    #[test]
+    fn test_flow_field_cache_per_grid() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ));
+
+        let settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut open: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        open.build();
+        let open = app.world_mut().spawn(open).id();
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(15, 0, 0);
+        let cost = |app: &mut App| {
+            let agent = app
+                .world_mut()
+                .spawn((
+                    AgentPos(start),
+                    Pathfind::new(goal).mode(PathfindMode::FlowField),
+                ))
+                .id();
+            app.update();
+            app.world().get::<Path>(agent).unwrap().cost()
+        };
+        assert_eq!(cost(&mut app), 15);
+
+        // A new map built the same number of times has the same chunk versions, but not the same field
+        app.world_mut().despawn(open);
+        let mut walled: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for y in 0..15 {
+            walled.set_nav(UVec3::new(8, y, 0), Nav::Impassable);
+        }
+        walled.build();
+        let walled = app.world_mut().spawn(walled).id();
+
+        assert_eq!(cost(&mut app), 45);
+        let cache = app.world().resource::<FlowFieldCache>();
+        assert!(!cache.0.contains_key(&(open, goal)));
+        assert!(cache.0.contains_key(&(walled, goal)));
+
+        // The field is dropped once no agent is heading to its goal
+        let agents = app
+            .world_mut()
+            .query_filtered::<Entity, With<Pathfind>>()
+            .iter(app.world())
+            .collect::<Vec<_>>();
+        for agent in agents {
+            app.world_mut().entity_mut(agent).remove::<Pathfind>();
+        }
+        app.update();
+        assert!(app.world().resource::<FlowFieldCache>().0.is_empty());
+    }
+
+    #[test]
+    fn test_async_pathfind() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ));
+
+        let settings = GridSettingsBuilder::new_2d(64, 64).chunk_size(16).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for y in 0..56 {
+            grid.set_nav(UVec3::new(32, y, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(63, 0, 0);
+        let expected = grid
+            .find_path(start, goal, PathfindMode::Refined, false)
+            .unwrap();
+        app.world_mut().spawn(grid);
+
+        let agent = app
+            .world_mut()
+            .spawn((
+                AgentPos(start),
+                Pathfind::new(goal),
+                AsyncPathfind::default(),
+            ))
+            .id();
+
+        for _ in 0..1000 {
+            app.update();
+            if app.world().get::<Path>(agent).is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        let path = app.world().get::<Path>(agent).unwrap();
+        assert_eq!(path.cost(), expected.cost());
+        assert!(app.world().get::<PathfindingInProgress>(agent).is_none());
+        assert!(app.world().get::<PathfindTask>(agent).is_none());
+
+        // Removing the request cancels the search
+        app.world_mut().entity_mut(agent).remove::<Path>();
+        app.world_mut()
+            .entity_mut(agent)
+            .insert(Pathfind::new(UVec3::new(63, 63, 0)));
+        app.update();
+        app.world_mut().entity_mut(agent).remove::<Pathfind>();
+        app.update();
+
+        assert!(app.world().get::<PathfindingInProgress>(agent).is_none());
+        assert!(app.world().get::<PathfindTask>(agent).is_none());
+    }
+
+    // Moves every agent to its `NextPos` at the end of the frame
+    fn move_agents(mut commands: Commands, mut query: Query<(Entity, &mut AgentPos, &NextPos)>) {
+        for (entity, mut position, next) in query.iter_mut() {
+            position.0 = next.0;
+            commands.entity(entity).remove::<NextPos>();
+        }
+    }
+
+    #[test]
+    fn test_cooperative_corridor_crossing() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ))
+        .insert_resource(CooperativeConfig { window: 16 })
+        .add_systems(Update, move_agents.after(PathingSet));
+
+        // Open areas on either side of a 1-wide corridor along y = 1
+        let settings = GridSettingsBuilder::new_2d(12, 3)
+            .chunk_size(4)
+            .enable_collision()
+            .build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for x in 3..9 {
+            grid.set_nav(UVec3::new(x, 0, 0), Nav::Impassable);
+            grid.set_nav(UVec3::new(x, 2, 0), Nav::Impassable);
+        }
+        grid.build();
+        app.world_mut().spawn(grid);
+
+        let first_goal = UVec3::new(11, 1, 0);
+        let second_goal = UVec3::new(0, 0, 0);
+
+        let first = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::new(0, 1, 0)),
+                Blocking,
+                Pathfind::new(first_goal),
+            ))
+            .id();
+        let second = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::new(11, 0, 0)),
+                Blocking,
+                Pathfind::new(second_goal),
+            ))
+            .id();
+
+        for _ in 0..64 {
+            app.update();
+
+            let first_pos = app.world().get::<AgentPos>(first).unwrap().0;
+            let second_pos = app.world().get::<AgentPos>(second).unwrap().0;
+            assert_ne!(first_pos, second_pos);
+        }
+
+        // Both made it through, one waited for the other to clear the corridor
+        assert_eq!(app.world().get::<AgentPos>(first).unwrap().0, first_goal);
+        assert_eq!(app.world().get::<AgentPos>(second).unwrap().0, second_goal);
+        assert!(app.world().get::<Pathfind>(first).is_none());
+        assert!(app.world().get::<Pathfind>(second).is_none());
+        assert!(app.world().get::<PathfindingFailed>(first).is_none());
+        assert!(app.world().get::<PathfindingFailed>(second).is_none());
+    }
+
+    #[test]
+    fn test_head_on_swap() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ))
+        .add_systems(Update, move_agents.after(PathingSet));
+
+        // A 2-wide corridor where both agents prefer the bottom lane
+        let settings = GridSettingsBuilder::new_2d(12, 3)
+            .chunk_size(4)
+            .enable_collision()
+            .build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        for x in 0..12 {
+            grid.set_nav(UVec3::new(x, 1, 0), Nav::Passable(3));
+            grid.set_nav(UVec3::new(x, 2, 0), Nav::Impassable);
+        }
+        grid.build();
+        app.world_mut().spawn(grid);
+
+        let first_goal = UVec3::new(11, 0, 0);
+        let second_goal = UVec3::new(0, 0, 0);
+
+        let first = app
+            .world_mut()
+            .spawn((AgentPos(second_goal), Blocking, Pathfind::new(first_goal)))
+            .id();
+        let second = app
+            .world_mut()
+            .spawn((AgentPos(first_goal), Blocking, Pathfind::new(second_goal)))
+            .id();
+
+        for _ in 0..64 {
+            app.update();
+
+            let first_pos = app.world().get::<AgentPos>(first).unwrap().0;
+            let second_pos = app.world().get::<AgentPos>(second).unwrap().0;
+            assert_ne!(first_pos, second_pos);
+
+            for agent in [first, second] {
+                assert!(app.world().get::<RerouteFailed>(agent).is_none());
+                assert!(app.world().get::<PathfindingFailed>(agent).is_none());
+            }
+        }
+
+        // They passed each other instead of erroring
+        assert_eq!(app.world().get::<AgentPos>(first).unwrap().0, first_goal);
+        assert_eq!(app.world().get::<AgentPos>(second).unwrap().0, second_goal);
+        assert!(app.world().get::<Pathfind>(first).is_none());
+        assert!(app.world().get::<Pathfind>(second).is_none());
+    }
+
+    #[test]
+    fn test_path_priority() {
+        for priority_first in [false, true] {
+            let mut app = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                NorthstarPlugin::<CardinalNeighborhood>::default(),
+            ));
+
+            let settings = GridSettingsBuilder::new_2d(3, 3)
+                .chunk_size(3)
+                .enable_collision()
+                .build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            grid.build();
+            app.world_mut().spawn(grid);
+
+            // Both agents want to move into the center cell
+            let center = UVec3::new(1, 1, 0);
+            let first = app
+                .world_mut()
+                .spawn((
+                    AgentPos(UVec3::new(0, 1, 0)),
+                    Blocking,
+                    Pathfind::new(UVec3::new(2, 1, 0)),
+                ))
+                .id();
+            let second = app
+                .world_mut()
+                .spawn((
+                    AgentPos(UVec3::new(1, 0, 0)),
+                    Blocking,
+                    Pathfind::new(UVec3::new(1, 2, 0)),
+                ))
+                .id();
+
+            let (winner, loser) = if priority_first {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            app.world_mut().entity_mut(winner).insert(PathPriority(5));
+            app.world_mut().entity_mut(loser).insert(PathPriority(1));
+
+            app.update();
+
+            assert_eq!(app.world().get::<NextPos>(winner).unwrap().0, center);
+            assert_ne!(
+                app.world().get::<NextPos>(loser).map(|next| next.0),
+                Some(center)
+            );
+        }
+    }
+
+    #[test]
+    fn test_manual_advance() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ));
+
+        let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+        app.world_mut().spawn(grid);
+
+        let goal = UVec3::new(5, 0, 0);
+        let agent = app
+            .world_mut()
+            .spawn((AgentPos(UVec3::ZERO), ManualAdvance, Pathfind::new(goal)))
+            .id();
+
+        app.update();
+        app.update();
+
+        // The whole path is left for the user to consume
+        assert!(app.world().get::<NextPos>(agent).is_none());
+        assert_eq!(app.world().get::<Path>(agent).unwrap().len(), 5);
+
+        let mut entity = app.world_mut().entity_mut(agent);
+        assert!(entity
+            .get_mut::<Path>()
+            .unwrap()
+            .advance(UVec3::new(2, 0, 0)));
+        entity.get_mut::<AgentPos>().unwrap().0 = UVec3::new(2, 0, 0);
+        app.update();
+
+        assert_eq!(app.world().get::<Path>(agent).unwrap().len(), 3);
+        assert!(app.world().get::<NextPos>(agent).is_none());
+
+        let mut entity = app.world_mut().entity_mut(agent);
+        assert!(entity.get_mut::<Path>().unwrap().advance(goal));
+        entity.get_mut::<AgentPos>().unwrap().0 = goal;
+        app.update();
+
+        assert!(app.world().get::<Path>(agent).is_none());
+        assert!(app.world().get::<Pathfind>(agent).is_none());
+    }
+
+    #[test]
+    fn test_pathfinding_paused() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ));
+
+        let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+        app.world_mut().spawn(grid);
+
+        let goal = UVec3::new(5, 0, 0);
+        let agent = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::ZERO),
+                PathfindingPaused,
+                Pathfind::new(goal),
+            ))
+            .id();
+
+        app.update();
+        app.update();
+
+        // The path is found but the agent holds its position
+        assert!(app.world().get::<NextPos>(agent).is_none());
+        assert_eq!(app.world().get::<Path>(agent).unwrap().len(), 5);
+        assert!(app.world().get::<Pathfind>(agent).is_some());
+
+        app.world_mut()
+            .entity_mut(agent)
+            .remove::<PathfindingPaused>();
+        app.update();
+
+        assert_eq!(
+            app.world().get::<NextPos>(agent).unwrap().0,
+            UVec3::new(1, 0, 0)
+        );
+        assert_eq!(app.world().get::<Path>(agent).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_trails() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ))
+        .insert_resource(TrailConfig {
+            deposit: 4.0,
+            decay: 0.5,
+        });
+
+        let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+        let grid_entity = app.world_mut().spawn(grid).id();
+
+        let agent = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::ZERO),
+                ManualAdvance,
+                Pathfind::new(UVec3::new(5, 0, 0)),
+            ))
+            .id();
+
+        for _ in 0..3 {
+            app.update();
+        }
+        assert!(app.world().get::<Path>(agent).is_some());
+
+        // The agent stands still on its first cell, the trail builds up towards 8
+        let trail = app.world().resource::<TrailField>().get(UVec3::ZERO);
+        assert!((4.0..8.0).contains(&trail));
+        assert_eq!(
+            app.world()
+                .get::<Grid<CardinalNeighborhood>>(grid_entity)
+                .unwrap()
+                .trail_cost(UVec3::ZERO),
+            Some(trail.round() as MovementCost)
+        );
+
+        // Paused agents don't leave a trail
+        app.world_mut().entity_mut(agent).insert(PathfindingPaused);
+        for _ in 0..6 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<TrailField>().get(UVec3::ZERO), 0.0);
+
+        app.world_mut()
+            .entity_mut(agent)
+            .remove::<PathfindingPaused>();
+        app.update();
+        assert_eq!(app.world().resource::<TrailField>().get(UVec3::ZERO), 4.0);
+
+        // Removing the config clears the trails
+        app.world_mut().remove_resource::<TrailConfig>();
+        app.update();
+        assert!(app.world().resource::<TrailField>().0.is_empty());
+        assert_eq!(
+            app.world()
+                .get::<Grid<CardinalNeighborhood>>(grid_entity)
+                .unwrap()
+                .trail_cost(UVec3::ZERO),
+            None
+        );
+
+        app.world_mut().insert_resource(TrailConfig {
+            deposit: 4.0,
+            decay: 0.5,
+        });
+        app.update();
+        assert_eq!(app.world().resource::<TrailField>().get(UVec3::ZERO), 4.0);
+
+        // Agents without a path don't leave a trail and the old one fades away
+        app.world_mut()
+            .entity_mut(agent)
+            .remove::<(Path, Pathfind)>();
+        for _ in 0..6 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<TrailField>().get(UVec3::ZERO), 0.0);
+        assert_eq!(
+            app.world()
+                .get::<Grid<CardinalNeighborhood>>(grid_entity)
+                .unwrap()
+                .trail_cost(UVec3::ZERO),
+            None
+        );
+    }
+
+    #[test]
+    fn test_smooth_next_pos() {
+        // The agent steps east from (0, 0) to (1, 0), then gets a path leading back through (0, 0)
+        let next_after_backtrack = |steps: Vec<UVec3>| {
+            let mut app = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                NorthstarPlugin::<CardinalNeighborhood>::default(),
+            ))
+            .insert_resource(SmoothNextPos(true));
+
+            let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            grid.build();
+            app.world_mut().spawn(grid);
+
+            let agent = app
+                .world_mut()
+                .spawn((AgentPos(UVec3::ZERO), Pathfind::new(UVec3::new(5, 0, 0))))
+                .id();
+
+            for _ in 0..3 {
+                app.update();
+            }
+            assert_eq!(
+                app.world().get::<NextPos>(agent).unwrap().0,
+                UVec3::new(1, 0, 0)
+            );
+
+            let mut entity = app.world_mut().entity_mut(agent);
+            entity.get_mut::<AgentPos>().unwrap().0 = UVec3::new(1, 0, 0);
+            entity.remove::<NextPos>();
+            *entity.get_mut::<Path>().unwrap() = Path::new(steps, 0);
+            app.update();
+
+            app.world().get::<NextPos>(agent).unwrap().0
+        };
+
+        // Skips ahead to (1, 1) instead of stepping back
+        let next = next_after_backtrack(vec![
+            UVec3::new(0, 0, 0),
+            UVec3::new(0, 1, 0),
+            UVec3::new(1, 1, 0),
+            UVec3::new(2, 1, 0),
+        ]);
+        assert_eq!(next, UVec3::new(1, 1, 0));
+
+        // None of the next cells can be stepped to directly, so the backtrack is kept
+        let next = next_after_backtrack(vec![
+            UVec3::new(0, 0, 0),
+            UVec3::new(0, 1, 0),
+            UVec3::new(0, 2, 0),
+            UVec3::new(1, 2, 0),
+        ]);
+        assert_eq!(next, UVec3::ZERO);
+    }
+
+    #[test]
+    fn test_auto_advance() {
+        // Moves every agent onto its `NextPos` without removing it
+        fn step_agents(mut query: Query<(&mut AgentPos, &NextPos)>) {
+            for (mut position, next) in &mut query {
+                position.0 = next.0;
+            }
+        }
+
+        let final_position = |auto_advance: bool| {
+            let mut app = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                NorthstarPlugin::<CardinalNeighborhood>::default(),
+            ))
+            .insert_resource(AutoAdvance(auto_advance))
+            .add_systems(Update, step_agents.after(PathingSet));
+
+            let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            grid.build();
+            app.world_mut().spawn(grid);
+
+            let agent = app
+                .world_mut()
+                .spawn((AgentPos(UVec3::ZERO), Pathfind::new(UVec3::new(5, 0, 0))))
+                .id();
+
+            for _ in 0..12 {
+                app.update();
+            }
+
+            app.world().get::<AgentPos>(agent).unwrap().0
+        };
+
+        // Without it the agent stays on its first step until `NextPos` is removed
+        assert_eq!(final_position(false), UVec3::new(1, 0, 0));
+        assert_eq!(final_position(true), UVec3::new(5, 0, 0));
+    }
+
+    #[test]
+    fn test_reflect_registration() {
+        use std::any::TypeId;
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ));
+
+        let agent = app
+            .world_mut()
+            .spawn((AgentPos(UVec3::new(2, 3, 0)), Blocking, AvoidanceFailed))
+            .id();
+
+        let registry = app.world().resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+
+        for component in [
+            TypeId::of::<AgentPos>(),
+            TypeId::of::<Blocking>(),
+            TypeId::of::<IgnoreBlocking>(),
+            TypeId::of::<AvoidanceFailed>(),
+            TypeId::of::<PathfindingFailed>(),
+            TypeId::of::<RerouteFailed>(),
+            TypeId::of::<Path>(),
+        ] {
+            assert!(registry
+                .get_type_data::<ReflectComponent>(component)
+                .is_some());
+        }
+        for resource in [
+            TypeId::of::<PathfindingBudget>(),
+            TypeId::of::<RerouteConfig>(),
+            TypeId::of::<AutoAdvance>(),
+            TypeId::of::<Stats>(),
+        ] {
+            assert!(registry
+                .get_type_data::<ReflectResource>(resource)
+                .is_some());
+        }
+
+        // Components are read and edited through reflection, like an inspector would, including sparse set ones
+        let entity = app.world().entity(agent);
+        let failed = registry
+            .get_type_data::<ReflectComponent>(TypeId::of::<AvoidanceFailed>())
+            .unwrap();
+        assert!(failed.reflect(entity).is_some());
+
+        let reflect_pos = registry
+            .get_type_data::<ReflectComponent>(TypeId::of::<AgentPos>())
+            .unwrap();
+        reflect_pos.apply(
+            app.world_mut().entity_mut(agent),
+            &AgentPos(UVec3::new(4, 4, 0)),
+        );
+        assert_eq!(
+            app.world().get::<AgentPos>(agent).unwrap().0,
+            UVec3::new(4, 4, 0)
+        );
+    }
+
+    #[test]
+    fn test_agent_speed() {
+        // Returns the cells the agent's `NextPos` lands on until it reaches the goal
+        let moves = |speed: Option<f32>, blocker: Option<UVec3>| {
+            let frame = Duration::from_millis(100);
+            let mut app = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                NorthstarPlugin::<CardinalNeighborhood>::default(),
+            ))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(frame));
+
+            let settings = GridSettingsBuilder::new_2d(12, 4)
+                .chunk_size(4)
+                .enable_collision()
+                .build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            grid.build();
+            app.world_mut().spawn(grid);
+
+            if let Some(blocker) = blocker {
+                app.world_mut().spawn((AgentPos(blocker), Blocking));
+            }
+
+            let agent = app
+                .world_mut()
+                .spawn((AgentPos(UVec3::ZERO), Pathfind::new(UVec3::new(9, 0, 0))))
+                .id();
+            if let Some(speed) = speed {
+                app.world_mut().entity_mut(agent).insert(AgentSpeed(speed));
+            }
+
+            let mut moves = Vec::new();
+            for _ in 0..60 {
+                app.update();
+
+                let mut entity = app.world_mut().entity_mut(agent);
+                if let Some(next) = entity.take::<NextPos>() {
+                    entity.get_mut::<AgentPos>().unwrap().0 = next.0;
+                    moves.push(next.0);
+                }
+
+                if !entity.contains::<Pathfind>() {
+                    break;
+                }
+            }
+
+            moves
+        };
+
+        let row = |xs: &[u32]| xs.iter().map(|x| UVec3::new(*x, 0, 0)).collect::<Vec<_>>();
+
+        // One cell every time without a speed
+        assert_eq!(moves(None, None), row(&[1, 2, 3, 4, 5, 6, 7, 8, 9]));
+
+        // 25 cells per second covers 2.5 cells every 100ms frame, the half cells add up to an extra cell
+        assert_eq!(moves(Some(25.0), None), row(&[2, 5, 7, 9]));
+
+        // 5 cells per second only moves every other frame
+        assert_eq!(moves(Some(5.0), None), row(&[1, 2, 3, 4, 5, 6, 7, 8, 9]));
+
+        // Fast agents never land on an agent standing in the way
+        let blocker = UVec3::new(5, 0, 0);
+        let blocked = moves(Some(25.0), Some(blocker));
+        assert!(!blocked.contains(&blocker));
+        assert_eq!(blocked.last(), Some(&UVec3::new(9, 0, 0)));
+    }
+
+    #[test]
+    fn test_goal_index() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ));
+
+        let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+        let grid_entity = app.world_mut().spawn(grid).id();
+
+        let objective = UVec3::new(5, 0, 0);
+        let a = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::ZERO),
+                AgentOfGrid(grid_entity),
+                Pathfind::new(objective),
+            ))
+            .id();
+        let b = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::new(0, 1, 0)),
+                Pathfind::new_multi(vec![objective, UVec3::new(6, 0, 0)]),
+            ))
+            .id();
+        let c = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::new(0, 2, 0)),
+                Pathfind::new_region(UVec3::new(4, 4, 0), UVec3::new(6, 6, 0)),
+            ))
+            .id();
+
+        app.update();
+
+        let index = app.world().resource::<GoalIndex>();
+        assert_eq!(index.agents_to(objective), vec![a, b]);
+        assert_eq!(index.count_to(UVec3::new(6, 0, 0)), 1);
+        assert_eq!(index.agents_to(UVec3::new(5, 5, 0)), vec![c]);
+        assert!(index.agents_to(UVec3::new(7, 7, 0)).is_empty());
+        assert_eq!(
+            index.agents_to_region(UVec3::new(4, 0, 0), UVec3::new(6, 4, 0)),
+            vec![a, b, c]
+        );
+        assert_eq!(index.grid_agents_to(grid_entity, objective), vec![a]);
+
+        // Changed and removed goals are reindexed
+        app.world_mut()
+            .entity_mut(a)
+            .insert(Pathfind::new(UVec3::new(7, 7, 0)));
+        app.world_mut().entity_mut(b).remove::<Pathfind>();
+        app.update();
+
+        let index = app.world().resource::<GoalIndex>();
+        assert!(index.agents_to(objective).is_empty());
+        assert_eq!(index.agents_to(UVec3::new(7, 7, 0)), vec![a]);
+        assert_eq!(index.agents_to(UVec3::new(6, 6, 0)), vec![c]);
+    }
+
+    #[test]
+    fn test_agent_size_blocks_footprint() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ));
+
+        let settings = GridSettingsBuilder::new_2d(8, 8)
+            .chunk_size(4)
+            .enable_collision()
+            .build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+        app.world_mut().spawn(grid);
+
+        let vehicle = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::new(2, 2, 0)),
+                AgentSize(UVec2::new(2, 2)),
+                Blocking,
+            ))
+            .id();
+
+        // A single cell agent can't path through the vehicle
+        let agent = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::new(3, 0, 0)),
+                Blocking,
+                Pathfind::new(UVec3::new(3, 6, 0)).mode(PathfindMode::AStar),
+            ))
+            .id();
+
+        app.update();
+
+        let blocking = app.world().resource::<BlockingMap>();
+        for cell in AgentSize(UVec2::new(2, 2)).cells(UVec3::new(2, 2, 0)) {
+            assert_eq!(blocking.0.get(&cell), Some(&vehicle));
+        }
+
+        let path = app.world().get::<Path>(agent).unwrap();
+        assert!(path
+            .path()
+            .iter()
+            .all(|pos| !(2..4).contains(&pos.x) || !(2..4).contains(&pos.y)));
+    }
+
+    #[test]
+    fn test_target_entity() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ));
+
+        let settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+        app.world_mut().spawn(grid);
+
+        let target = app.world_mut().spawn(AgentPos(UVec3::new(10, 10, 0))).id();
+        let chaser = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::new(0, 0, 0)),
+                Pathfind::target_entity(target)
+                    .mode(PathfindMode::AStar)
+                    .repath_threshold(2),
+            ))
+            .id();
+
+        app.update();
+
+        let path = app.world().get::<Path>(chaser).unwrap();
+        assert_eq!(path.path().last(), Some(&UVec3::new(10, 10, 0)));
+
+        // Small moves within the threshold keep the current path
+        app.world_mut().get_mut::<AgentPos>(target).unwrap().0 = UVec3::new(11, 10, 0);
+        app.update();
+        assert_eq!(
+            app.world().get::<Pathfind>(chaser).unwrap().goal,
+            UVec3::new(10, 10, 0)
+        );
+
+        app.world_mut().get_mut::<AgentPos>(target).unwrap().0 = UVec3::new(13, 10, 0);
+        app.update();
+        let path = app.world().get::<Path>(chaser).unwrap();
+        assert_eq!(path.path().last(), Some(&UVec3::new(13, 10, 0)));
+
+        // Losing the target fails the request
+        app.world_mut().despawn(target);
+        app.update();
+        assert!(app.world().get::<Pathfind>(chaser).is_none());
+        assert!(app.world().get::<PathfindingFailed>(chaser).is_some());
+    }
+
+    #[test]
+    fn test_max_expansions() {
+        #[derive(Resource, Default)]
+        struct Failures(Vec<PathError>);
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ))
+        .init_resource::<Failures>()
+        .add_observer(
+            |trigger: Trigger<events::PathfindingFailed>, mut failures: ResMut<Failures>| {
+                failures.0.push(trigger.event().reason);
+            },
+        );
+
+        let settings = GridSettingsBuilder::new_2d(64, 64).chunk_size(16).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+        app.world_mut().spawn(grid);
+
+        let goal = UVec3::new(63, 63, 0);
+
+        let capped = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::ZERO),
+                Pathfind::new(goal)
+                    .mode(PathfindMode::AStar)
+                    .max_expansions(10),
+            ))
+            .id();
+
+        let partial = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::ZERO),
+                Pathfind::new(goal)
+                    .mode(PathfindMode::AStar)
+                    .max_expansions(10)
+                    .partial(),
+            ))
+            .id();
+
+        app.update();
+
+        assert!(app.world().get::<Path>(capped).is_none());
+        assert!(app.world().get::<PathfindingFailed>(capped).is_some());
+        assert_eq!(
+            app.world().resource::<Failures>().0,
+            vec![PathError::ExpansionLimitReached]
+        );
+
+        // The partial request settles for the closest node it reached
+        let path = app.world().get::<Path>(partial).unwrap();
+        assert!(!path.is_empty());
+        assert_ne!(path.path().last(), Some(&goal));
+    }
+
+    #[test]
+    fn test_avoidance_policy_waits() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<OrdinalNeighborhood>::default(),
+        ))
+        .insert_resource(AvoidancePolicy { wait_ticks: 3 });
+
+        let settings = GridSettingsBuilder::new_2d(8, 8)
+            .chunk_size(4)
+            .enable_collision()
+            .build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+        app.world_mut().spawn(grid);
+
+        let agent = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::new(0, 0, 0)),
+                Blocking,
+                Pathfind::new(UVec3::new(6, 0, 0)),
+            ))
+            .id();
+
+        // Moves the agent to its `NextPos` and returns where it went
+        let step = |app: &mut App| {
+            app.update();
+            let next = app.world().get::<NextPos>(agent).unwrap().0;
+            app.world_mut()
+                .entity_mut(agent)
+                .insert(AgentPos(next))
+                .remove::<NextPos>();
+            next
+        };
+
+        let start = step(&mut app);
+
+        // Step in front of the agent
+        let next = app.world().get::<Path>(agent).unwrap().next().unwrap();
+        app.world_mut().spawn((AgentPos(next), Blocking));
+
+        for _ in 0..3 {
+            assert_eq!(step(&mut app), start);
+        }
+
+        // Out of patience, path around the blocker
+        let rerouted = step(&mut app);
+        assert_ne!(rerouted, start);
+        assert_ne!(rerouted, next);
+        assert!(app.world().get::<AvoidanceWait>(agent).is_none());
+    }
+
+    #[test]
+    fn test_avoidance_lookahead() {
+        let crossing = UVec3::new(3, 4, 0);
+
+        for steps in [0, 4] {
+            let mut app = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                NorthstarPlugin::<CardinalNeighborhood>::default(),
+            ))
+            .insert_resource(AvoidanceLookahead(steps));
+
+            let settings = GridSettingsBuilder::new_2d(10, 10)
+                .chunk_size(5)
+                .enable_collision()
+                .avoidance_distance(4)
+                .build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            grid.build();
+            app.world_mut().spawn(grid);
+
+            // Both agents reach the crossing on their third step
+            let low = app
+                .world_mut()
+                .spawn((
+                    AgentPos(UVec3::new(0, 4, 0)),
+                    Blocking,
+                    Pathfind::new(UVec3::new(9, 4, 0)).mode(PathfindMode::AStar),
+                    PathPriority(1),
+                ))
+                .id();
+            let high = app
+                .world_mut()
+                .spawn((
+                    AgentPos(UVec3::new(3, 1, 0)),
+                    Blocking,
+                    Pathfind::new(UVec3::new(3, 9, 0)).mode(PathfindMode::AStar),
+                    PathPriority(5),
+                ))
+                .id();
+
+            app.update();
+
+            assert_eq!(
+                app.world().get::<NextPos>(high).unwrap().0,
+                UVec3::new(3, 2, 0)
+            );
+            assert!(app.world().get::<NextPos>(low).is_some());
+
+            // Only looking ahead reroutes the lower priority agent before they meet
+            assert_eq!(
+                app.world().get::<Path>(low).unwrap().contains(crossing),
+                steps == 0
+            );
+        }
+    }
+
+    #[test]
+    fn test_next_dir() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<OrdinalNeighborhood>::default(),
+        ))
+        .add_systems(Update, move_agents.after(PathingSet));
+
+        let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+        app.world_mut().spawn(grid);
+
+        let agent = app
+            .world_mut()
+            .spawn((
+                AgentPos(UVec3::new(0, 0, 0)),
+                Pathfind::new(UVec3::new(3, 3, 0)).mode(PathfindMode::AStar),
+            ))
+            .id();
+
+        for _ in 0..8 {
+            app.update();
+        }
+
+        // The agent kept facing the way it moved after reaching its goal
+        assert_eq!(
+            app.world().get::<AgentPos>(agent).unwrap().0,
+            UVec3::new(3, 3, 0)
+        );
+        assert_eq!(app.world().get::<NextDir>(agent).unwrap().0, Dir::NorthEast);
+    }
+
+    #[test]
+    fn test_stop_adjacent() {
+        let run = |mode: PathfindMode, wall: bool| {
+            let mut app = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                NorthstarPlugin::<CardinalNeighborhood>::default(),
+            ))
+            .add_systems(Update, move_agents.after(PathingSet));
+
+            let settings = GridSettingsBuilder::new_2d(8, 8)
+                .chunk_size(4)
+                .enable_collision()
+                .build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            if wall {
+                grid.set_nav(UVec3::new(5, 3, 0), Nav::Impassable);
+            }
+            grid.build();
+            app.world_mut().spawn(grid);
+
+            if !wall {
+                // An enemy standing on the goal
+                app.world_mut()
+                    .spawn((AgentPos(UVec3::new(5, 3, 0)), Blocking));
+            }
+
+            let agent = app
+                .world_mut()
+                .spawn((
+                    AgentPos(UVec3::new(0, 3, 0)),
+                    Blocking,
+                    Pathfind::new(UVec3::new(5, 3, 0))
+                        .mode(mode)
+                        .stop_adjacent(),
+                ))
+                .id();
+
+            for _ in 0..12 {
+                app.update();
+            }
+
+            // The cheapest cell next to the goal is on the near side
+            assert_eq!(
+                app.world().get::<AgentPos>(agent).unwrap().0,
+                UVec3::new(4, 3, 0),
+                "{mode:?}"
+            );
+            assert!(app.world().get::<Pathfind>(agent).is_none(), "{mode:?}");
+            assert!(app.world().get::<PathfindingFailed>(agent).is_none());
+        };
+
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::AStar,
+            PathfindMode::FlowField,
+        ] {
+            run(mode, true);
+        }
+        run(PathfindMode::Refined, false);
+    }
+
+    #[test]
+    fn test_within() {
+        let run = |mode: PathfindMode| {
+            let mut app = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                NorthstarPlugin::<CardinalNeighborhood>::default(),
+            ))
+            .add_systems(Update, move_agents.after(PathingSet));
+
+            let settings = GridSettingsBuilder::new_2d(12, 12)
+                .chunk_size(4)
+                .enable_collision()
+                .build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            grid.build();
+            app.world_mut().spawn(grid);
+
+            let agent = app
+                .world_mut()
+                .spawn((
+                    AgentPos(UVec3::new(0, 3, 0)),
+                    Blocking,
+                    Pathfind::new(UVec3::new(10, 3, 0)).mode(mode).within(3),
+                ))
+                .id();
+
+            for _ in 0..16 {
+                app.update();
+            }
+
+            // Stops three cells out from the goal
+            assert_eq!(
+                app.world().get::<AgentPos>(agent).unwrap().0,
+                UVec3::new(7, 3, 0),
+                "{mode:?}"
+            );
+            assert!(app.world().get::<Pathfind>(agent).is_none(), "{mode:?}");
+            assert!(app.world().get::<PathfindingFailed>(agent).is_none());
+        };
+
+        for mode in [
+            PathfindMode::Refined,
+            PathfindMode::Coarse,
+            PathfindMode::AStar,
+            PathfindMode::FlowField,
+        ] {
+            run(mode);
+        }
+    }
+
+    #[test]
+    fn test_ignore_blocking() {
+        let run = |ignore: bool| {
+            let mut app = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                NorthstarPlugin::<CardinalNeighborhood>::default(),
+            ))
+            .add_systems(Update, move_agents.after(PathingSet));
+
+            // A wall with a single gap, a friendly unit stands in the gap
+            let settings = GridSettingsBuilder::new_2d(8, 8)
+                .chunk_size(4)
+                .enable_collision()
+                .build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            for y in (0..8).filter(|y| *y != 3) {
+                grid.set_nav(UVec3::new(4, y, 0), Nav::Impassable);
+            }
+            grid.build();
+            app.world_mut().spawn(grid);
+
+            let friend = app
+                .world_mut()
+                .spawn((AgentPos(UVec3::new(4, 3, 0)), Blocking))
+                .id();
+
+            let agent = app
+                .world_mut()
+                .spawn((
+                    AgentPos(UVec3::new(0, 3, 0)),
+                    Blocking,
+                    Pathfind::new(UVec3::new(7, 3, 0)).mode(PathfindMode::AStar),
+                ))
+                .id();
+            if ignore {
+                app.world_mut()
+                    .entity_mut(agent)
+                    .insert(IgnoreBlocking::from_iter([friend]));
+            }
+
+            for _ in 0..16 {
+                app.update();
+            }
+
+            app.world().get::<AgentPos>(agent).unwrap().0
+        };
+
+        assert_eq!(run(true), UVec3::new(7, 3, 0));
+        assert_ne!(run(false), UVec3::new(7, 3, 0));
+    }
+
+    #[test]
+    fn test_reroute_config() {
+        #[derive(Resource, Default)]
+        struct Failures(Vec<PathError>);
+
+        let run = |config: RerouteConfig| {
+            let mut app = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                NorthstarPlugin::<CardinalNeighborhood>::default(),
+            ))
+            .insert_resource(config)
+            .init_resource::<Failures>()
+            .add_observer(
+                |trigger: Trigger<events::PathfindingFailed>, mut failures: ResMut<Failures>| {
+                    failures.0.push(trigger.event().reason);
+                },
+            )
+            .add_systems(Update, move_agents.after(PathingSet));
+
+            // A wall with gaps at both ends, too long for local avoidance to get around
+            let settings = GridSettingsBuilder::new_2d(8, 8)
+                .chunk_size(4)
+                .enable_collision()
+                .avoidance_distance(1)
+                .build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            for y in 1..7 {
+                grid.set_nav(UVec3::new(4, y, 0), Nav::Impassable);
+            }
+            grid.build();
+            app.world_mut().spawn(grid);
+
+            let agent = app
+                .world_mut()
+                .spawn((
+                    AgentPos(UVec3::new(0, 0, 0)),
+                    Blocking,
+                    Pathfind::new(UVec3::new(7, 0, 0)).mode(PathfindMode::AStar),
+                ))
+                .id();
+            app.update();
+
+            // Block the near gap once the agent is on its way, only a reroute through the far gap gets around it
+            app.world_mut()
+                .spawn((AgentPos(UVec3::new(4, 0, 0)), Blocking));
+
+            for _ in 0..40 {
+                app.update();
+            }
+
+            (
+                app.world().get::<AgentPos>(agent).unwrap().0,
+                app.world().get::<RerouteFailed>(agent).is_some(),
+                app.world_mut().remove_resource::<Failures>().unwrap().0,
+            )
+        };
+
+        // Avoidance failing isn't reported as a failure when the reroute gets around the blocker
+        assert_eq!(
+            run(RerouteConfig::default()),
+            (UVec3::new(7, 0, 0), false, Vec::new())
+        );
+
+        let (position, failed, failures) = run(RerouteConfig {
+            max_attempts: Some(0),
+            cooldown_ticks: 0,
+        });
+        assert!(failed);
+        assert_ne!(position, UVec3::new(7, 0, 0));
+        assert_eq!(failures, vec![PathError::AvoidanceFailed]);
+    }
+
+    #[test]
+    fn test_keep_stale_path_on_failure() {
+        let run = |keep_stale: bool| {
+            let mut app = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                NorthstarPlugin::<CardinalNeighborhood>::default(),
+            ))
+            .insert_resource(KeepStalePathOnFailure(keep_stale))
+            .add_systems(Update, move_agents.after(PathingSet));
+
+            // A dead end corridor along y = 0
+            let settings = GridSettingsBuilder::new_2d(8, 4)
+                .chunk_size(4)
+                .enable_collision()
+                .build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            for x in 0..8 {
+                for y in 1..4 {
+                    grid.set_nav(UVec3::new(x, y, 0), Nav::Impassable);
+                }
+            }
+            grid.build();
+            app.world_mut().spawn(grid);
+
+            let agent = app
+                .world_mut()
+                .spawn((
+                    AgentPos(UVec3::new(0, 0, 0)),
+                    Blocking,
+                    Pathfind::new(UVec3::new(7, 0, 0)).mode(PathfindMode::AStar),
+                ))
+                .id();
+            app.update();
+
+            // There's no way around the blocker, every reroute fails
+            let blocker = app
+                .world_mut()
+                .spawn((AgentPos(UVec3::new(5, 0, 0)), Blocking))
+                .id();
+
+            for _ in 0..20 {
+                app.update();
+            }
+
+            let stalled = (
+                app.world().get::<AgentPos>(agent).unwrap().0,
+                app.world().get::<RerouteFailed>(agent).is_some(),
+            );
+
+            // Once the way clears the agent carries on along the old path
+            app.world_mut().despawn(blocker);
+            for _ in 0..10 {
+                app.update();
+            }
+
+            (stalled, app.world().get::<AgentPos>(agent).unwrap().0)
+        };
+
+        let ((position, failed), _) = run(false);
+        assert!(failed);
+        assert!(position.x < 4);
+
+        let (stalled, position) = run(true);
+        assert_eq!(stalled, (UVec3::new(4, 0, 0), false));
+        assert_eq!(position, UVec3::new(7, 0, 0));
+    }
+
+    #[test]
+    fn test_coalesce_path_requests() {
+        let run = |coalesce: bool| {
+            let mut app = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                NorthstarPlugin::<CardinalNeighborhood>::default(),
+            ))
+            .insert_resource(CoalescePathRequests(coalesce))
+            .insert_resource(PathfindingBudget {
+                max_paths: Some(1),
+                ..Default::default()
+            });
+
+            let settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+            let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+            grid.build();
+            app.world_mut().spawn(grid);
+
+            let start = UVec3::new(0, 0, 0);
+            let goal = UVec3::new(15, 15, 0);
+            let squad = (0..3)
+                .map(|_| {
+                    app.world_mut()
+                        .spawn((AgentPos(start), Pathfind::new(goal)))
+                        .id()
+                })
+                .collect::<Vec<_>>();
+            // The same start and goal restricted to other layers is a different request
+            let other = app
+                .world_mut()
+                .spawn((AgentPos(start), Pathfind::new(goal).layers(1)))
+                .id();
+
+            app.update();
+
+            let paths = squad
+                .iter()
+                .filter_map(|agent| app.world().get::<Path>(*agent))
+                .map(|path| path.path().to_vec())
+                .collect::<Vec<_>>();
+            (paths, app.world().get::<Path>(other).is_some())
+        };
+
+        // Only the first request fits in the budget
+        let (paths, other) = run(false);
+        assert_eq!(paths.len(), 1);
+        assert!(!other);
+
+        // The squad shares a single search
+        let (paths, other) = run(true);
+        assert_eq!(paths.len(), 3);
+        assert!(paths.iter().all(|path| *path == paths[0]));
+        assert!(!other);
+    }
+
+    #[test]
+    fn test_pathing_stages() {
+        #[derive(Resource, Default)]
+        struct Seen {
+            planned: bool,
+            advanced: bool,
+        }
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ))
+        .init_resource::<Seen>()
+        .add_systems(
+            Update,
+            (
+                (|query: Query<(Has<Path>, Has<NextPos>)>, mut seen: ResMut<Seen>| {
+                    seen.planned = query.iter().any(|(path, next)| path && !next);
+                })
+                .after(PathingStage::Plan)
+                .before(PathingStage::Advance),
+                (|query: Query<&NextPos>, mut seen: ResMut<Seen>| {
+                    seen.advanced = !query.is_empty();
+                })
+                .after(PathingStage::Advance)
+                .before(PathingStage::Reroute),
+            ),
+        );
+
+        let settings = GridSettingsBuilder::new_2d(8, 8).chunk_size(4).build();
+        let mut grid: Grid<CardinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+        app.world_mut().spawn(grid);
+        app.world_mut()
+            .spawn((AgentPos(UVec3::new(0, 0, 0)), Pathfind::new_2d(5, 5)));
+
+        // The path is planned and the first step taken in the same frame
+        app.update();
+        let seen = app.world().resource::<Seen>();
+        assert!(seen.planned);
+        assert!(seen.advanced);
+    }
+
+    #[test]
+    #[cfg(feature = "rng")]
+    fn test_northstar_rng() {
+        use rand::RngCore;
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NorthstarPlugin::<CardinalNeighborhood>::default(),
+        ));
+        assert_eq!(app.world().resource::<NorthstarRng>().seed(), 0);
+
+        // The reference SplitMix64 sequence, the same on every platform
+        let mut rng = NorthstarRng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+
+        let settings = GridSettingsBuilder::new_2d(16, 16).build();
+        let grid = Grid::<CardinalNeighborhood>::new(&settings);
+        let cells = |rng: &mut NorthstarRng| {
+            (0..8)
+                .map(|_| grid.random_passable_cell(rng).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // The same seed replays the same sequence
+        app.insert_resource(NorthstarRng::new(42));
+        let first = cells(&mut app.world_mut().resource_mut::<NorthstarRng>());
+        assert_eq!(first, cells(&mut NorthstarRng::new(42)));
+        assert_ne!(first, cells(&mut NorthstarRng::new(7)));
+
+        let mut rng = app.world_mut().resource_mut::<NorthstarRng>();
+        rng.reseed(42);
+        assert_eq!(rng.seed(), 42);
+        assert_eq!(cells(&mut rng), first);
+    }
 }
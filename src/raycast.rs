@@ -312,6 +312,27 @@ pub(crate) fn bresenham_path(
     ordinal: bool,
 ) -> Option<Vec<UVec3>> {
     let mut path = Vec::new();
+
+    bresenham_walk(grid, start, goal, ordinal, |pos| {
+        path.push(pos);
+        // The goal itself isn't checked
+        pos == goal || !grid[[pos.x as usize, pos.y as usize, pos.z as usize]].is_impassable()
+    })
+    .ok()?;
+
+    Some(path)
+}
+
+// Step through the Bresenham line from start to goal calling `visit` on every cell, including both ends.
+// Returns the cell the walk stopped at if `visit` returns false, the cell is out of bounds,
+// or the line can't make any more progress towards the goal.
+pub(crate) fn bresenham_walk(
+    grid: &ArrayView3<NavCell>,
+    start: UVec3,
+    goal: UVec3,
+    ordinal: bool,
+    mut visit: impl FnMut(UVec3) -> bool,
+) -> Result<(), UVec3> {
     let mut current = start;
 
     let (width, height, depth) = (
@@ -336,16 +357,19 @@ pub(crate) fn bresenham_path(
     let mut err_xy = dx - dy;
     let mut err_xz = dx - dz;
 
-    while current != goal {
+    // Moving a single axis at a time is the longest a line can take
+    for _ in 0..=(dx + dy + dz) {
         // Bounds check
         if current.x >= width || current.y >= height || current.z >= depth {
-            return None;
+            return Err(current);
         }
 
-        path.push(current);
+        if !visit(current) {
+            return Err(current);
+        }
 
-        if grid[[current.x as usize, current.y as usize, current.z as usize]].is_impassable() {
-            return None;
+        if current == goal {
+            return Ok(());
         }
 
         // Error-based stepping
@@ -385,14 +409,12 @@ pub(crate) fn bresenham_path(
         }
     }
 
-    path.push(goal);
-
-    Some(path)
+    Err(current)
 }
 
 #[cfg(test)]
 mod tests {
-    use bevy::math::UVec3;
+    use bevy::math::{BVec3, UVec3};
     use ndarray::Array3;
 
     use crate::{
@@ -408,8 +430,7 @@ mod tests {
     const GRID_SETTINGS: GridSettings = GridSettings(GridInternalSettings {
         dimensions: UVec3::new(12, 12, 1),
         chunk_settings: ChunkSettings {
-            size: 4,
-            depth: 1,
+            size: UVec3::new(4, 4, 1),
             diagonal_connections: false,
         },
         cost_settings: NavSettings {
@@ -422,7 +443,13 @@ mod tests {
         },
         neighborhood_settings: NeighborhoodSettings {
             filters: Vec::new(),
+            heuristic: None,
+            diagonal_cost: None,
+            provider: None,
         },
+        wrap: BVec3::FALSE,
+        height_cost: HeightCost(0),
+        diagonal_movement: DiagonalMovement::Always,
     });
 
     #[test]
@@ -480,4 +507,45 @@ mod tests {
 
         assert!(path.is_none());
     }
+
+    #[test]
+    fn test_grid_raycast() {
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&GRID_SETTINGS);
+
+        grid.set_nav(UVec3::new(5, 5, 0), Nav::Impassable);
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+
+        assert_eq!(
+            grid.raycast(start, UVec3::new(10, 10, 0)),
+            Some(UVec3::new(5, 5, 0))
+        );
+        assert!(!grid.line_of_sight(start, UVec3::new(10, 10, 0)));
+
+        assert_eq!(grid.raycast(start, UVec3::new(10, 0, 0)), None);
+        assert!(grid.line_of_sight(start, UVec3::new(10, 0, 0)));
+
+        // Out of bounds cells block the line
+        assert_eq!(
+            grid.raycast(start, UVec3::new(20, 0, 0)),
+            Some(UVec3::new(12, 0, 0))
+        );
+
+        // The z-axis is walked in 3D grids
+        let settings = GridSettingsBuilder::new_3d(8, 8, 8)
+            .chunk_size(4)
+            .chunk_depth(4)
+            .build();
+        let mut grid: Grid<OrdinalNeighborhood3d> = Grid::new(&settings);
+
+        grid.set_nav(UVec3::new(4, 4, 4), Nav::Impassable);
+        grid.build();
+
+        assert_eq!(
+            grid.raycast(UVec3::new(0, 0, 0), UVec3::new(7, 7, 7)),
+            Some(UVec3::new(4, 4, 4))
+        );
+        assert!(grid.line_of_sight(UVec3::new(0, 0, 0), UVec3::new(7, 7, 0)));
+    }
 }
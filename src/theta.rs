@@ -0,0 +1,331 @@
+//! Theta* any-angle search used by [`crate::components::PathfindMode::Theta`].
+use bevy::{log, math::UVec3, platform::collections::HashMap, prelude::Entity};
+use indexmap::map::Entry::{Occupied, Vacant};
+use ndarray::ArrayView3;
+use std::collections::BinaryHeap;
+
+use crate::{
+    astar::SearchContext, goal::SearchGoal, in_bounds_3d, nav::NavCell, neighbor::Neighborhood,
+    path::Path, raycast::bresenham_path_filtered, FxIndexMap, MovementCost, SmallestCostHolder,
+};
+
+/// Theta* search for a [`crate::grid::Grid`] of [`crate::nav::NavCell`]s.
+///
+/// Works like A* but when a neighbor is visible from the current cell's parent, the neighbor is linked
+/// directly to that parent. The returned [`Path`] only contains the waypoints where the path changes direction.
+/// Line of sight is traced with the same neighbor rules as HPA* path refinement and only within a single z-plane.
+///
+/// # Arguments
+/// * `neighborhood` - Reference to the [`Neighborhood`] to use.
+/// * `grid` - A reference to a 3D array representing the grid, as an [`ndarray::ArrayView3`] of [`NavCell`].
+/// * `start` - The start position as [`bevy::math::UVec3`].
+/// * `goal` - The [`SearchGoal`] to reach.
+/// * `size_hint` - A hint for the size of the binary heap.
+/// * `partial` - If `true`, the algorithm will return the closest node if the goal is not reachable.
+/// * `blocking` - Pass [`crate::plugin::BlockingMap`] or a new `HashMap<UVec3, Entity>` to indicate which positions are blocked by entities.
+/// * `cost_overrides` - Extra movement cost added on top of the [`NavCell`] cost when entering a position.
+/// * `context` - Caps the node expansions of the search and records them when tracing.
+///
+/// # Returns
+/// * [`Option<Path>`] - An optional path of waypoints. If a path is found, it returns `Some(Path)`, otherwise it returns `None`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn theta_grid<N: Neighborhood>(
+    neighborhood: &N,
+    grid: &ArrayView3<NavCell>,
+    start: UVec3,
+    goal: &SearchGoal,
+    size_hint: usize,
+    partial: bool,
+    blocking: &HashMap<UVec3, Entity>,
+    cost_overrides: &HashMap<UVec3, MovementCost>,
+    context: &SearchContext,
+) -> Option<Path> {
+    let mut to_visit = BinaryHeap::with_capacity(size_hint / 2);
+    to_visit.push(SmallestCostHolder {
+        estimated_cost: 0,
+        cost: 0,
+        index: 0,
+        tie_break: 0,
+    });
+
+    let mut visited: FxIndexMap<UVec3, (usize, u32)> = FxIndexMap::default();
+    visited.insert(start, (usize::MAX, 0));
+
+    let mut closest_node = start;
+    let mut closest_distance = goal.heuristic(neighborhood, start);
+
+    let shape = grid.shape();
+    let min = UVec3::new(0, 0, 0);
+    let max = UVec3::new(shape[0] as u32, shape[1] as u32, shape[2] as u32);
+
+    while let Some(SmallestCostHolder { cost, index, .. }) = to_visit.pop() {
+        let (current_pos, &(parent_index, current_cost)) = visited.get_index(index).unwrap();
+        let current_pos = *current_pos;
+        let current_distance = goal.heuristic(neighborhood, current_pos);
+
+        // Update the closest node if this node is closer
+        if current_distance < closest_distance {
+            closest_node = current_pos;
+            closest_distance = current_distance;
+        }
+
+        if goal.is_goal(current_pos) {
+            return Some(reconstruct_path(&visited, index, current_cost));
+        }
+
+        if cost > current_cost {
+            continue;
+        }
+
+        if !context.expand(current_pos, current_cost, current_distance) {
+            break;
+        }
+
+        // The start node has no parent to trace from
+        let parent = visited
+            .get_index(parent_index)
+            .map(|(pos, &(_, cost))| (*pos, cost));
+
+        let cell = &grid[[
+            current_pos.x as usize,
+            current_pos.y as usize,
+            current_pos.z as usize,
+        ]];
+
+        for neighbor in cell.neighbor_iter(current_pos) {
+            if !in_bounds_3d(neighbor, min, max) {
+                continue;
+            }
+
+            let neighbor_cell = &grid[[
+                neighbor.x as usize,
+                neighbor.y as usize,
+                neighbor.z as usize,
+            ]];
+
+            if neighbor_cell.is_impassable() {
+                continue;
+            }
+
+            if blocking.contains_key(&neighbor) {
+                continue;
+            }
+
+            // Step from the current cell like A*
+            let mut new_parent = index;
+            let extra_cost = cost_overrides.get(&neighbor).copied().unwrap_or(0);
+            let mut new_cost = cost
+                .saturating_add(neighborhood.step_cost(
+                    current_pos,
+                    neighbor,
+                    cell.move_cost(current_pos, neighbor, neighbor_cell),
+                ))
+                .saturating_add(extra_cost);
+
+            // Or go straight from the current cell's parent if the neighbor is visible from it.
+            // Portals are always taken as a step.
+            if let Some((parent_pos, parent_cost)) = parent {
+                let same_plane = parent_pos.z == neighbor.z && current_pos.z == neighbor.z;
+
+                if same_plane && !cell.special_neighbors.contains(&neighbor) {
+                    if let Some(line_cost) = line_cost(
+                        neighborhood,
+                        grid,
+                        parent_pos,
+                        neighbor,
+                        blocking,
+                        cost_overrides,
+                    ) {
+                        // Prefer the straight line on ties for fewer waypoints
+                        if parent_cost + line_cost <= new_cost {
+                            new_parent = parent_index;
+                            new_cost = parent_cost + line_cost;
+                        }
+                    }
+                }
+            }
+
+            let h;
+            let n;
+            match visited.entry(neighbor) {
+                Vacant(e) => {
+                    h = goal.heuristic(neighborhood, neighbor);
+                    n = e.index();
+                    e.insert((new_parent, new_cost));
+                }
+                Occupied(mut e) => {
+                    if e.get().1 > new_cost {
+                        h = goal.heuristic(neighborhood, neighbor);
+                        n = e.index();
+                        e.insert((new_parent, new_cost));
+                    } else {
+                        continue;
+                    }
+                }
+            }
+
+            to_visit.push(SmallestCostHolder {
+                estimated_cost: context.weighted(h),
+                cost: new_cost,
+                index: n,
+                tie_break: 0,
+            });
+        }
+    }
+
+    if partial {
+        // If the goal is not reached, return the path to the closest node, but if the closest node is the start return None
+        if closest_node == start {
+            return None;
+        }
+
+        let Some(index) = visited.get_index_of(&closest_node) else {
+            log::error!("Closest node is missing from the visited nodes");
+            return None;
+        };
+
+        Some(reconstruct_path(&visited, index, visited[&closest_node].1))
+    } else {
+        None
+    }
+}
+
+// Movement cost of the straight line from `from` to `to`, not including `from`.
+// Returns `None` if the line breaks the neighbor rules or crosses a blocked position.
+fn line_cost<N: Neighborhood>(
+    neighborhood: &N,
+    grid: &ArrayView3<NavCell>,
+    from: UVec3,
+    to: UVec3,
+    blocking: &HashMap<UVec3, Entity>,
+    cost_overrides: &HashMap<UVec3, MovementCost>,
+) -> Option<u32> {
+    let line = bresenham_path_filtered(grid, from, to, neighborhood.is_ordinal())?;
+
+    line.windows(2).try_fold(0u32, |total, step| {
+        let pos = step[1];
+        if blocking.contains_key(&pos) {
+            return None;
+        }
+
+        let from_cell = &grid[[step[0].x as usize, step[0].y as usize, step[0].z as usize]];
+        let cell = &grid[[pos.x as usize, pos.y as usize, pos.z as usize]];
+        let cost = neighborhood.step_cost(
+            step[0],
+            pos,
+            cell.cost.saturating_add(from_cell.climb_cost(cell)),
+        );
+        let extra_cost = cost_overrides.get(&pos).copied().unwrap_or(0);
+        Some(total.saturating_add(cost).saturating_add(extra_cost))
+    })
+}
+
+// Walks the parents back from `index` to the start and returns the waypoints in order.
+fn reconstruct_path(visited: &FxIndexMap<UVec3, (usize, u32)>, index: usize, cost: u32) -> Path {
+    let mut current = index;
+    let mut steps = vec![];
+
+    while let Some((pos, &(parent, _))) = visited.get_index(current) {
+        steps.push(*pos);
+        current = parent;
+    }
+
+    steps.reverse();
+    Path::new(steps, cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{math::UVec3, platform::collections::HashMap};
+
+    use crate::{
+        astar::{astar_grid, SearchContext},
+        goal::SearchGoal,
+        grid::{Grid, GridSettingsBuilder},
+        nav::Nav,
+        neighbor::OrdinalNeighborhood,
+        theta::theta_grid,
+    };
+
+    #[test]
+    fn test_theta_open_grid_is_straight() {
+        let settings = GridSettingsBuilder::new_2d(10, 10).chunk_size(5).build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+        grid.build();
+
+        let path = theta_grid(
+            grid.neighborhood(),
+            &grid.view(),
+            UVec3::new(0, 0, 0),
+            &SearchGoal::Position(UVec3::new(9, 3, 0)),
+            64,
+            false,
+            &HashMap::new(),
+            &HashMap::new(),
+            &SearchContext::default(),
+        )
+        .unwrap();
+
+        // Only the start and goal are needed in the open
+        assert_eq!(path.path(), &[UVec3::new(0, 0, 0), UVec3::new(9, 3, 0)]);
+        assert_eq!(path.cost(), 9);
+    }
+
+    #[test]
+    fn test_theta_fewer_waypoints_than_astar() {
+        let settings = GridSettingsBuilder::new_2d(16, 16).chunk_size(4).build();
+        let mut grid: Grid<OrdinalNeighborhood> = Grid::new(&settings);
+
+        for y in 0..12 {
+            grid.set_nav(UVec3::new(8, y, 0), Nav::Impassable);
+        }
+        grid.build();
+
+        let start = UVec3::new(0, 0, 0);
+        let goal = UVec3::new(15, 0, 0);
+
+        let theta = theta_grid(
+            grid.neighborhood(),
+            &grid.view(),
+            start,
+            &SearchGoal::Position(goal),
+            64,
+            false,
+            &HashMap::new(),
+            &HashMap::new(),
+            &SearchContext::default(),
+        )
+        .unwrap();
+
+        let astar = astar_grid(
+            grid.neighborhood(),
+            &grid.view(),
+            start,
+            goal,
+            64,
+            false,
+            &HashMap::new(),
+            &HashMap::new(),
+        )
+        .unwrap();
+
+        assert_eq!(theta.path().last(), Some(&goal));
+        assert!(theta.len() < astar.len());
+
+        // Every waypoint must be a step or visible from the one before it
+        for pair in theta.path().windows(2) {
+            assert!(
+                grid.navcell(pair[0])
+                    .neighbor_iter(pair[0])
+                    .any(|pos| pos == pair[1])
+                    || crate::raycast::bresenham_path_filtered(
+                        &grid.view(),
+                        pair[0],
+                        pair[1],
+                        true
+                    )
+                    .is_some()
+            );
+        }
+    }
+}
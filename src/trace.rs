@@ -0,0 +1,60 @@
+//! Recording of the cells a search expands, for visualizing and debugging searches.
+use bevy::{
+    ecs::{component::Component, reflect::ReflectComponent},
+    math::UVec3,
+    reflect::Reflect,
+};
+
+use crate::MovementCost;
+
+/// A cell expanded by a search along with its scores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct TracedNode {
+    /// The position of the expanded cell.
+    pub pos: UVec3,
+    /// The cost of the cheapest known path from the start to the cell, the g-score.
+    pub g: MovementCost,
+    /// The heuristic estimate of the remaining cost to the goal, the h-score.
+    pub h: u32,
+}
+
+/// The cells expanded by a search in the order they were expanded, the closed set of the search.
+///
+/// Record one for a single query with [`crate::grid::Grid::find_path_traced`].
+/// With the `gui-debug` feature, agents with the `DebugSearch` component get a [`SearchTrace`] component
+/// recorded by the [`crate::plugin::NorthstarPlugin`] which `DebugGrid` draws as a heatmap.
+///
+/// Only the grid searches of [`crate::components::PathfindMode::AStar`], [`crate::components::PathfindMode::Jps`],
+/// and [`crate::components::PathfindMode::Theta`] are recorded, along with requests that fall back to A*.
+/// The HPA* modes leave the trace empty.
+#[derive(Component, Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct SearchTrace {
+    nodes: Vec<TracedNode>,
+}
+
+impl SearchTrace {
+    pub(crate) fn push(&mut self, node: TracedNode) {
+        self.nodes.push(node);
+    }
+
+    /// Returns the expanded cells in the order they were expanded.
+    pub fn nodes(&self) -> &[TracedNode] {
+        &self.nodes
+    }
+
+    /// Returns the number of expanded cells.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if the search didn't expand any cells.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the highest g-score of the expanded cells, useful for normalizing a color gradient.
+    pub fn max_g(&self) -> MovementCost {
+        self.nodes.iter().map(|node| node.g).max().unwrap_or(0)
+    }
+}